@@ -5,4 +5,4 @@
 
 mod storage;
 
-pub use storage::{load_baseline, save_baseline, Baseline};
+pub use storage::{load_baseline, load_baseline_dir, save_baseline, Baseline};
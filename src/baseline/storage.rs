@@ -9,7 +9,10 @@ use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
 /// Current baseline format version
-const BASELINE_VERSION: u32 = 1;
+///
+/// Version 2 hashes both sides of a block, so version 1 content hashes
+/// no longer match.
+const BASELINE_VERSION: u32 = 2;
 
 /// A single baseline entry representing a known duplicate
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -22,6 +25,12 @@ pub struct BaselineEntry {
     pub content_hash: u64,
     /// Number of duplicate lines
     pub line_count: usize,
+    /// Source line range of the block in `file1` (e.g. "10-14"), for review only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file1_lines: Option<String>,
+    /// Source line range of the block in `file2`, for review only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file2_lines: Option<String>,
 }
 
 impl BaselineEntry {
@@ -38,9 +47,19 @@ impl BaselineEntry {
             file2: f2,
             content_hash,
             line_count,
+            file1_lines: None,
+            file2_lines: None,
         }
     }
 
+    /// Attach the line ranges of both sides (in the entry's file order);
+    /// they are never used for matching
+    pub fn with_lines(mut self, file1_lines: String, file2_lines: String) -> Self {
+        self.file1_lines = Some(file1_lines);
+        self.file2_lines = Some(file2_lines);
+        self
+    }
+
     /// Create a matching key (file pair only, ignoring content for broad matching)
     #[allow(dead_code)]
     fn file_pair_key(&self) -> (&str, &str) {
@@ -55,46 +74,100 @@ pub struct Baseline {
     pub version: u32,
     /// Hash of detection configuration (for warning about config changes)
     pub config_hash: u64,
+    /// Whether content hashes ignore the order of lines within a block
+    #[serde(default)]
+    pub order_insensitive: bool,
     /// All baseline entries
     pub entries: Vec<BaselineEntry>,
 }
 
 impl Baseline {
     /// Create a new baseline from detection results
+    ///
+    /// With `order_insensitive`, a block still matches after lines within it
+    /// are reordered.
     pub fn from_results(
         result: &DuploResult,
         source_files: &[SourceFile],
         config_hash: u64,
+        order_insensitive: bool,
     ) -> Self {
         let entries: Vec<BaselineEntry> = result
             .blocks
             .iter()
             .map(|block| {
-                let file1 = source_files[block.source1_idx].filename().to_string();
-                let file2 = source_files[block.source2_idx].filename().to_string();
-                let content_hash = compute_block_hash(block, source_files);
-                BaselineEntry::new(file1, file2, content_hash, block.count)
+                let source1 = &source_files[block.source1_idx];
+                let source2 = &source_files[block.source2_idx];
+                // Sort the sides up front so each range stays with its file
+                let mut sides = [
+                    (
+                        source1.filename(),
+                        line_range(source1, block.line1, block.count),
+                    ),
+                    (
+                        source2.filename(),
+                        line_range(source2, block.line2, block.count),
+                    ),
+                ];
+                sides.sort();
+                let [(file1, lines1), (file2, lines2)] = sides;
+
+                let content_hash = compute_block_hash(block, source_files, order_insensitive);
+                BaselineEntry::new(
+                    file1.to_string(),
+                    file2.to_string(),
+                    content_hash,
+                    block.count,
+                )
+                .with_lines(lines1, lines2)
             })
             .collect();
 
         Self {
             version: BASELINE_VERSION,
             config_hash,
+            order_insensitive,
             entries,
         }
     }
 
     /// Get the set of baseline entries for fast lookup
-    #[allow(dead_code)]
     pub fn entry_set(&self) -> HashSet<BaselineEntry> {
         self.entries.iter().cloned().collect()
     }
 
+    /// Add the entries of `other` that this baseline doesn't have yet
+    ///
+    /// Entries are only comparable under one set of detection settings and
+    /// one line order setting, so baselines differing in either are rejected.
+    pub fn merge(&mut self, other: Baseline) -> Result<()> {
+        if other.config_hash != self.config_hash {
+            return Err(DuploError::BaselineError(format!(
+                "baselines were created with different detection settings \
+                 (config hash {} and {})",
+                self.config_hash, other.config_hash
+            )));
+        }
+        if other.order_insensitive != self.order_insensitive {
+            return Err(DuploError::BaselineError(
+                "baselines differ in --baseline-order-insensitive".to_string(),
+            ));
+        }
+
+        let mut known = self.entry_set();
+        for entry in other.entries {
+            if known.insert(entry.clone()) {
+                self.entries.push(entry);
+            }
+        }
+        Ok(())
+    }
+
     /// Check if a block matches any baseline entry
     pub fn contains(&self, block: &Block, source_files: &[SourceFile]) -> bool {
         let file1 = source_files[block.source1_idx].filename();
         let file2 = source_files[block.source2_idx].filename();
-        let content_hash = compute_block_hash(block, source_files);
+        let content_hash = compute_block_hash(block, source_files, self.order_insensitive);
 
         // Normalize file order
         let (f1, f2) = if file1 <= file2 {
@@ -112,39 +185,58 @@ impl Baseline {
     /// Filter results to only NEW duplicates (not in baseline)
     pub fn filter_new_duplicates(
         &self,
-        result: DuploResult,
+        mut result: DuploResult,
         source_files: &[SourceFile],
     ) -> DuploResult {
-        let new_blocks: Vec<Block> = result
-            .blocks
-            .into_iter()
-            .filter(|block| !self.contains(block, source_files))
-            .collect();
-
-        let duplicate_lines: usize = new_blocks.iter().map(|b| b.count).sum();
-        let duplicate_blocks = new_blocks.len();
+        result.retain_blocks(|block| !self.contains(block, source_files));
+        result
+    }
 
-        DuploResult {
-            blocks: new_blocks,
-            files_analyzed: result.files_analyzed,
-            total_lines: result.total_lines,
-            duplicate_lines,
-            duplicate_blocks,
-        }
+    /// Filter results to only duplicates that are in the baseline and still exist
+    pub fn still_present(
+        &self,
+        mut result: DuploResult,
+        source_files: &[SourceFile],
+    ) -> DuploResult {
+        result.retain_blocks(|block| self.contains(block, source_files));
+        result
     }
 }
 
+/// Original line numbers spanned by `count` cleaned lines from `start` (e.g. "10-14")
+fn line_range(source: &SourceFile, start: usize, count: usize) -> String {
+    format!(
+        "{}-{}",
+        source.get_line(start).line_number(),
+        source.get_line(start + count - 1).end_line_number()
+    )
+}
+
 /// Compute a hash of the block's content for fuzzy matching
-fn compute_block_hash(block: &Block, source_files: &[SourceFile]) -> u64 {
+///
+/// Both sides are hashed: with gaps allowed the two copies can differ, and
+/// two clones sharing one side must not be mistaken for each other. The
+/// sides are taken in file name order, as in the stored entry, so the hash
+/// doesn't change when a run reports the files the other way round. With
+/// `order_insensitive`, each side's line hashes are sorted first, so the
+/// hash covers the multiset of lines rather than their sequence.
+fn compute_block_hash(block: &Block, source_files: &[SourceFile], order_insensitive: bool) -> u64 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
-    let source = &source_files[block.source1_idx];
     let mut hasher = DefaultHasher::new();
 
-    // Hash all line hashes in the block
-    for i in 0..block.count {
-        source.get_line(block.line1 + i).hash().hash(&mut hasher);
+    let mut sides = [
+        (block.source1_idx, block.line1),
+        (block.source2_idx, block.line2),
+    ];
+    sides.sort_by_key(|&(idx, start)| (source_files[idx].filename(), start));
+    for (idx, start) in sides {
+        let mut hashes: Vec<u32> = source_files[idx].line_hashes(start, block.count).collect();
+        if order_insensitive {
+            hashes.sort_unstable();
+        }
+        hashes.hash(&mut hasher);
     }
 
     hasher.finish()
@@ -192,6 +284,36 @@ pub fn load_baseline(path: &Path) -> Result<Baseline> {
     Ok(baseline)
 }
 
+/// Load every `*.json` baseline in `dir` and merge them into one
+///
+/// Files are merged in name order; the directory must hold at least one.
+pub fn load_baseline_dir(dir: &Path) -> Result<Baseline> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        DuploError::BaselineError(format!(
+            "Failed to read baseline directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut paths = paths.into_iter();
+    let first = paths.next().ok_or_else(|| {
+        DuploError::BaselineError(format!("No baseline files (*.json) in '{}'", dir.display()))
+    })?;
+    let mut merged = load_baseline(&first)?;
+    for path in paths {
+        merged.merge(load_baseline(&path)?).map_err(|e| {
+            DuploError::BaselineError(format!("Can't merge '{}': {}", path.display(), e))
+        })?;
+    }
+    Ok(merged)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,15 +352,9 @@ mod tests {
     #[test]
     fn test_baseline_from_results() {
         let source_files = create_test_source_files();
-        let result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 3)],
-            files_analyzed: 2,
-            total_lines: 6,
-            duplicate_lines: 3,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 3)]);
 
-        let baseline = Baseline::from_results(&result, &source_files, 12345);
+        let baseline = Baseline::from_results(&result, &source_files, 12345, false);
 
         assert_eq!(baseline.version, BASELINE_VERSION);
         assert_eq!(baseline.config_hash, 12345);
@@ -251,15 +367,9 @@ mod tests {
         let baseline_path = temp.path().join("baseline.json");
 
         let source_files = create_test_source_files();
-        let result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 3)],
-            files_analyzed: 2,
-            total_lines: 6,
-            duplicate_lines: 3,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 3)]);
 
-        let baseline = Baseline::from_results(&result, &source_files, 12345);
+        let baseline = Baseline::from_results(&result, &source_files, 12345, false);
         save_baseline(&baseline, &baseline_path).unwrap();
 
         let loaded = load_baseline(&baseline_path).unwrap();
@@ -268,20 +378,52 @@ mod tests {
         assert_eq!(loaded.entries.len(), baseline.entries.len());
     }
 
+    #[test]
+    fn test_merge_takes_union_of_entries() {
+        let entry = |file: &str| BaselineEntry::new(file.to_string(), "z.c".to_string(), 1, 4);
+        let baseline = |files: &[&str], config_hash: u64| Baseline {
+            version: BASELINE_VERSION,
+            config_hash,
+            order_insensitive: false,
+            entries: files.iter().map(|f| entry(f)).collect(),
+        };
+
+        let mut merged = baseline(&["a.c", "b.c"], 7);
+        merged.merge(baseline(&["b.c", "c.c"], 7)).unwrap();
+        assert_eq!(
+            merged.entries,
+            vec![entry("a.c"), entry("b.c"), entry("c.c")]
+        );
+
+        assert!(merged.merge(baseline(&["d.c"], 8)).is_err());
+        assert_eq!(merged.entries.len(), 3);
+    }
+
+    #[test]
+    fn test_load_baseline_dir() {
+        let temp = TempDir::new().unwrap();
+        assert!(load_baseline_dir(temp.path()).is_err());
+
+        let source_files = create_test_source_files();
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 3)]);
+        let baseline = Baseline::from_results(&result, &source_files, 12345, false);
+        save_baseline(&baseline, &temp.path().join("team-a.json")).unwrap();
+        save_baseline(&baseline, &temp.path().join("team-b.json")).unwrap();
+        std::fs::write(temp.path().join("README.md"), "not a baseline").unwrap();
+
+        let merged = load_baseline_dir(temp.path()).unwrap();
+        assert_eq!(merged.config_hash, 12345);
+        assert_eq!(merged.entries, baseline.entries);
+    }
+
     #[test]
     fn test_baseline_contains() {
         let source_files = create_test_source_files();
         let block = Block::new(0, 1, 0, 0, 3);
 
-        let result = DuploResult {
-            blocks: vec![block.clone()],
-            files_analyzed: 2,
-            total_lines: 6,
-            duplicate_lines: 3,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![block.clone()]);
 
-        let baseline = Baseline::from_results(&result, &source_files, 12345);
+        let baseline = Baseline::from_results(&result, &source_files, 12345, false);
 
         // Same block should be found in baseline
         assert!(baseline.contains(&block, &source_files));
@@ -291,35 +433,185 @@ mod tests {
         assert!(!baseline.contains(&different_block, &source_files));
     }
 
+    #[test]
+    fn test_block_hash_includes_second_file() {
+        let mut source_files = create_test_source_files();
+        source_files.push(SourceFile::from_lines(
+            "c.c".to_string(),
+            vec![
+                SourceLine::new("int x = 5;".to_string(), 1),
+                SourceLine::new("int z = 99;".to_string(), 2),
+                SourceLine::new("return x + y;".to_string(), 3),
+            ],
+        ));
+
+        // Same file1 content, different file2 content
+        let with_b = Block::new(0, 1, 0, 0, 3).with_gaps(1);
+        let with_c = Block::new(0, 2, 0, 0, 3).with_gaps(1);
+        assert_ne!(
+            compute_block_hash(&with_b, &source_files, false),
+            compute_block_hash(&with_c, &source_files, false)
+        );
+    }
+
+    #[test]
+    fn test_block_hash_ignores_file_order() {
+        let mut source_files = create_test_source_files();
+        source_files.push(SourceFile::from_lines(
+            "c.c".to_string(),
+            vec![
+                SourceLine::new("int x = 5;".to_string(), 1),
+                SourceLine::new("int z = 99;".to_string(), 2),
+                SourceLine::new("return x + y;".to_string(), 3),
+            ],
+        ));
+
+        // A gapped block whose two sides differ, reported both ways round
+        let forward = Block::new(0, 2, 0, 0, 3).with_gaps(1);
+        let backward = Block::new(2, 0, 0, 0, 3).with_gaps(1);
+        assert_eq!(
+            compute_block_hash(&forward, &source_files, false),
+            compute_block_hash(&backward, &source_files, false)
+        );
+
+        let result = DuploResult::from_blocks(vec![forward]);
+        let baseline = Baseline::from_results(&result, &source_files, 0, false);
+        assert!(baseline.contains(&backward, &source_files));
+    }
+
+    #[test]
+    fn test_order_insensitive_matches_swapped_lines() {
+        let source_files = create_test_source_files();
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 3)]);
+
+        // The same block after two independent lines were swapped in both files
+        let swapped = |name: &str| {
+            SourceFile::from_lines(
+                name.to_string(),
+                vec![
+                    SourceLine::new("int y = 10;".to_string(), 1),
+                    SourceLine::new("int x = 5;".to_string(), 2),
+                    SourceLine::new("return x + y;".to_string(), 3),
+                ],
+            )
+        };
+        let reordered_files = vec![swapped("a.c"), swapped("b.c")];
+        let block = Block::new(0, 1, 0, 0, 3);
+
+        let strict = Baseline::from_results(&result, &source_files, 12345, false);
+        assert!(!strict.contains(&block, &reordered_files));
+
+        let relaxed = Baseline::from_results(&result, &source_files, 12345, true);
+        assert!(relaxed.contains(&block, &reordered_files));
+        let filtered = relaxed.filter_new_duplicates(
+            DuploResult {
+                blocks: vec![block],
+                ..result
+            },
+            &reordered_files,
+        );
+        assert_eq!(filtered.duplicate_blocks, 0);
+    }
+
+    #[test]
+    fn test_entries_carry_line_ranges() {
+        let source_files = create_test_source_files();
+        // Reported with the files swapped: ranges must follow their file
+        let result = DuploResult::from_blocks(vec![Block::new(1, 0, 0, 1, 2)]);
+
+        let baseline = Baseline::from_results(&result, &source_files, 12345, false);
+        let entry = &baseline.entries[0];
+        assert_eq!(entry.file1, "a.c");
+        assert_eq!(entry.file1_lines.as_deref(), Some("2-3"));
+        assert_eq!(entry.file2, "b.c");
+        assert_eq!(entry.file2_lines.as_deref(), Some("1-2"));
+
+        let json = serde_json::to_value(&baseline).unwrap();
+        assert_eq!(json["entries"][0]["file1_lines"], "2-3");
+    }
+
+    #[test]
+    fn test_load_entries_without_line_ranges() {
+        let temp = TempDir::new().unwrap();
+        let baseline_path = temp.path().join("baseline.json");
+        let source_files = create_test_source_files();
+        let block = Block::new(0, 1, 0, 0, 3);
+        let content_hash = compute_block_hash(&block, &source_files, false);
+        std::fs::write(
+            &baseline_path,
+            format!(
+                r#"{{"version": {}, "config_hash": 0, "entries": [
+                    {{"file1": "a.c", "file2": "b.c", "content_hash": {}, "line_count": 3}}
+                ]}}"#,
+                BASELINE_VERSION, content_hash
+            ),
+        )
+        .unwrap();
+
+        let baseline = load_baseline(&baseline_path).unwrap();
+        assert!(baseline.entries[0].file1_lines.is_none());
+        // Matching only looks at the files and the hash
+        assert!(baseline.contains(&block, &source_files));
+    }
+
+    #[test]
+    fn test_load_rejects_old_version() {
+        let temp = TempDir::new().unwrap();
+        let baseline_path = temp.path().join("baseline.json");
+        std::fs::write(
+            &baseline_path,
+            r#"{"version": 1, "config_hash": 0, "entries": []}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            load_baseline(&baseline_path),
+            Err(DuploError::BaselineVersionMismatch {
+                found: 1,
+                expected: BASELINE_VERSION
+            })
+        ));
+    }
+
     #[test]
     fn test_filter_new_duplicates() {
         let source_files = create_test_source_files();
 
         // Create baseline with one block
-        let baseline_result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 2)],
-            files_analyzed: 2,
-            total_lines: 6,
-            duplicate_lines: 2,
-            duplicate_blocks: 1,
-        };
-        let baseline = Baseline::from_results(&baseline_result, &source_files, 12345);
+        let baseline_result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 2)]);
+        let baseline = Baseline::from_results(&baseline_result, &source_files, 12345, false);
 
         // Create new result with two blocks (one existing, one new)
-        let new_result = DuploResult {
-            blocks: vec![
-                Block::new(0, 1, 0, 0, 2), // Same as baseline
-                Block::new(0, 1, 1, 1, 2), // New duplicate
-            ],
-            files_analyzed: 2,
-            total_lines: 6,
-            duplicate_lines: 4,
-            duplicate_blocks: 2,
-        };
+        let new_result = DuploResult::from_blocks(vec![
+            Block::new(0, 1, 0, 0, 2), // Same as baseline
+            Block::new(0, 1, 1, 1, 2), // New duplicate
+        ]);
 
         let filtered = baseline.filter_new_duplicates(new_result, &source_files);
 
         // Should only have the new block
         assert_eq!(filtered.duplicate_blocks, 1);
     }
+
+    #[test]
+    fn test_still_present() {
+        let source_files = create_test_source_files();
+
+        // Baseline with two known duplicates
+        let baseline_result =
+            DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 2), Block::new(0, 1, 1, 1, 2)]);
+        let baseline = Baseline::from_results(&baseline_result, &source_files, 12345, false);
+
+        // Current run: the first duplicate remains, the second was removed,
+        // and an unrelated new one appeared
+        let current =
+            DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 2), Block::new(0, 1, 0, 0, 3)]);
+
+        let present = baseline.still_present(current, &source_files);
+
+        assert_eq!(present.duplicate_blocks, 1);
+        assert_eq!(present.duplicate_lines, 2);
+        assert_eq!(present.blocks[0].count, 2);
+        assert_eq!(present.blocks[0].line1, 0);
+    }
 }
@@ -2,8 +2,11 @@
 //!
 //! This module provides caching of processed source lines to speed up
 //! repeated runs on the same codebase. Files are cached based on their
-//! content hash and the cleaning configuration.
+//! content hash and the cleaning configuration. An optional pair cache
+//! additionally stores the blocks found for each compared file pair.
 
+mod pair_cache;
 mod storage;
 
+pub use pair_cache::{PairCache, PairKey};
 pub use storage::{clear_cache, FileCache};
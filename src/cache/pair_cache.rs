@@ -0,0 +1,217 @@
+//! Second-level cache of detection results per file pair
+//!
+//! Stores the blocks found for a pair of files keyed on both files' content
+//! hashes and the detection configuration, so unchanged pairs can skip the
+//! comparison matrix entirely on the next run.
+
+use crate::core::Block;
+use crate::error::{DuploError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Current pair cache format version
+const PAIR_CACHE_VERSION: u32 = 2;
+
+/// Name of the pair cache file inside the cache directory
+const PAIR_CACHE_FILE: &str = "pairs.cache";
+
+/// Identifies a compared pair of files by content rather than by path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PairKey {
+    /// Content hash of the first file
+    pub content_hash1: u64,
+    /// Content hash of the second file
+    pub content_hash2: u64,
+    /// Hash of the detection configuration
+    pub config_hash: u64,
+}
+
+/// A block stored without file indices, which differ between runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CachedBlock {
+    line1: usize,
+    line2: usize,
+    count: usize,
+    #[serde(default)]
+    gaps: usize,
+}
+
+/// A single pair cache entry
+#[derive(Debug, Serialize, Deserialize)]
+struct PairEntry {
+    key: PairKey,
+    blocks: Vec<CachedBlock>,
+}
+
+/// On-disk pair cache format
+#[derive(Debug, Serialize, Deserialize)]
+struct PairCacheFile {
+    version: u32,
+    entries: Vec<PairEntry>,
+}
+
+/// Pair cache shared across worker threads
+pub struct PairCache {
+    /// File the cache is loaded from and saved to
+    path: PathBuf,
+    /// Entries loaded from the previous run
+    previous: HashMap<PairKey, Vec<CachedBlock>>,
+    /// Entries used or added during this run (the only ones saved)
+    current: Mutex<HashMap<PairKey, Vec<CachedBlock>>>,
+    /// Number of lookups answered from the cache
+    hits: AtomicUsize,
+}
+
+impl PairCache {
+    /// Load the pair cache from a cache directory
+    ///
+    /// A missing, unreadable or outdated cache file yields an empty cache.
+    pub fn load(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(PAIR_CACHE_FILE);
+
+        let previous = File::open(&path)
+            .ok()
+            .and_then(|f| serde_json::from_reader::<_, PairCacheFile>(BufReader::new(f)).ok())
+            .filter(|cf| cf.version == PAIR_CACHE_VERSION)
+            .map(|cf| cf.entries.into_iter().map(|e| (e.key, e.blocks)).collect())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            previous,
+            current: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Look up the blocks for a pair, filling in the given file indices
+    pub fn get(&self, key: &PairKey, source1_idx: usize, source2_idx: usize) -> Option<Vec<Block>> {
+        let cached = self.previous.get(key)?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.current.lock().unwrap().insert(*key, cached.clone());
+
+        Some(
+            cached
+                .iter()
+                .map(|b| {
+                    Block::new(source1_idx, source2_idx, b.line1, b.line2, b.count)
+                        .with_gaps(b.gaps)
+                })
+                .collect(),
+        )
+    }
+
+    /// Record the blocks found for a pair
+    pub fn put(&self, key: PairKey, blocks: &[Block]) {
+        let cached = blocks
+            .iter()
+            .map(|b| CachedBlock {
+                line1: b.line1,
+                line2: b.line2,
+                count: b.count,
+                gaps: b.gaps,
+            })
+            .collect();
+        self.current.lock().unwrap().insert(key, cached);
+    }
+
+    /// Number of lookups answered from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Write the entries used during this run back to disk
+    ///
+    /// Pairs whose files changed or disappeared are dropped, so the cache
+    /// doesn't grow without bound.
+    pub fn save(&self) -> Result<()> {
+        let current = self.current.lock().unwrap();
+        let mut entries: Vec<PairEntry> = current
+            .iter()
+            .map(|(key, blocks)| PairEntry {
+                key: *key,
+                blocks: blocks.clone(),
+            })
+            .collect();
+        // Stable file contents across runs
+        entries.sort_by_key(|e| (e.key.content_hash1, e.key.content_hash2, e.key.config_hash));
+
+        let file = File::create(&self.path).map_err(|e| {
+            DuploError::CacheError(format!(
+                "Failed to create pair cache '{}': {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+
+        let cache_file = PairCacheFile {
+            version: PAIR_CACHE_VERSION,
+            entries,
+        };
+        serde_json::to_writer(BufWriter::new(file), &cache_file)
+            .map_err(|e| DuploError::CacheError(format!("Failed to write pair cache: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn key(content_hash1: u64, content_hash2: u64) -> PairKey {
+        PairKey {
+            content_hash1,
+            content_hash2,
+            config_hash: 7,
+        }
+    }
+
+    #[test]
+    fn test_pair_cache_roundtrip() {
+        let temp = TempDir::new().unwrap();
+
+        let cache = PairCache::load(temp.path());
+        assert!(cache.get(&key(1, 2), 0, 1).is_none());
+        cache.put(key(1, 2), &[Block::new(0, 1, 3, 5, 4)]);
+        cache.save().unwrap();
+
+        // Indices are taken from the lookup, positions from the cache
+        let cache = PairCache::load(temp.path());
+        let blocks = cache.get(&key(1, 2), 4, 9).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].source1_idx, blocks[0].source2_idx), (4, 9));
+        assert_eq!(
+            (blocks[0].line1, blocks[0].line2, blocks[0].count),
+            (3, 5, 4)
+        );
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_pair_cache_invalidated_by_content_change() {
+        let temp = TempDir::new().unwrap();
+
+        let cache = PairCache::load(temp.path());
+        cache.put(key(1, 2), &[Block::new(0, 1, 0, 0, 4)]);
+        cache.put(key(5, 6), &[Block::new(0, 1, 0, 0, 4)]);
+        cache.save().unwrap();
+
+        // The second file changed: its new content hash misses
+        let cache = PairCache::load(temp.path());
+        assert!(cache.get(&key(1, 3), 0, 1).is_none());
+        assert!(cache.get(&key(1, 2), 0, 1).is_some());
+
+        // Only pairs seen in this run survive the next save
+        cache.save().unwrap();
+        let cache = PairCache::load(temp.path());
+        assert!(cache.get(&key(1, 2), 0, 1).is_some());
+        assert!(cache.get(&key(5, 6), 0, 1).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 //! Cache storage implementation
 
+use crate::cache::PairCache;
 use crate::config::Config;
 use crate::core::SourceLine;
 use crate::error::{DuploError, Result};
@@ -11,7 +12,26 @@ use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
 
 /// Current cache format version
-const CACHE_VERSION: u32 = 1;
+const CACHE_VERSION: u32 = 4;
+
+/// Name of the default cache directory
+const DEFAULT_CACHE_DIR: &str = ".duplo-cache";
+
+/// Resolve the cache directory: `--cache-dir` if given, else `.duplo-cache`
+/// at the repository root in git mode (so runs from subdirectories share
+/// one cache), else in the current directory
+fn resolve_cache_dir(config: &Config) -> PathBuf {
+    if let Some(ref dir) = config.cache_dir {
+        return dir.clone();
+    }
+
+    let base = if config.git_mode {
+        crate::git::get_repo_root().ok()
+    } else {
+        None
+    };
+    base.unwrap_or_default().join(DEFAULT_CACHE_DIR)
+}
 
 /// Cached source line data
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,8 +40,12 @@ struct CachedLine {
     line: String,
     /// Original line number in the source file
     line_number: usize,
+    /// Last original line of a joined statement
+    end_line_number: usize,
     /// Precomputed hash of the line
     hash: u32,
+    /// Precomputed secondary hash of the line
+    hash64: u64,
 }
 
 /// Cache entry for a single source file
@@ -43,6 +67,8 @@ pub struct FileCache {
     cache_dir: PathBuf,
     /// Cleaning config hash (for cache invalidation)
     config_hash: u64,
+    /// Per-pair detection results (only with --pair-cache)
+    pair_cache: Option<PairCache>,
 }
 
 impl FileCache {
@@ -54,10 +80,7 @@ impl FileCache {
     /// # Returns
     /// A FileCache instance, or an error if the cache directory cannot be created
     pub fn new(config: &Config) -> Result<Self> {
-        let cache_dir = config
-            .cache_dir
-            .clone()
-            .unwrap_or_else(|| PathBuf::from(".duplo-cache"));
+        let cache_dir = resolve_cache_dir(config);
 
         // Create cache directory if it doesn't exist
         if !cache_dir.exists() {
@@ -71,13 +94,22 @@ impl FileCache {
         }
 
         let config_hash = config.cleaning_config_hash();
+        let pair_cache = config
+            .pair_cache_enabled
+            .then(|| PairCache::load(&cache_dir));
 
         Ok(Self {
             cache_dir,
             config_hash,
+            pair_cache,
         })
     }
 
+    /// Get the pair cache, if enabled
+    pub fn pair_cache(&self) -> Option<&PairCache> {
+        self.pair_cache.as_ref()
+    }
+
     /// Get the cache file path for a source file
     fn cache_path(&self, source_path: &str) -> PathBuf {
         // Create a hash-based filename to avoid path length issues
@@ -88,7 +120,10 @@ impl FileCache {
     }
 
     /// Compute content hash of a file
-    fn compute_content_hash(path: &str) -> Result<u64> {
+    ///
+    /// Hashes the raw bytes, so it changes with any edit, even one the
+    /// cleaned lines don't see.
+    pub fn compute_content_hash(path: &str) -> Result<u64> {
         let content = fs::read(path).map_err(|e| DuploError::FileNotFound {
             path: path.to_string(),
             reason: e.to_string(),
@@ -135,7 +170,10 @@ impl FileCache {
         let lines: Vec<SourceLine> = entry
             .lines
             .into_iter()
-            .map(|cl| SourceLine::from_cached(cl.line, cl.line_number, cl.hash))
+            .map(|cl| {
+                SourceLine::from_cached(cl.line, cl.line_number, cl.hash, cl.hash64)
+                    .with_end_line(cl.end_line_number)
+            })
             .collect();
 
         Some(lines)
@@ -151,7 +189,9 @@ impl FileCache {
             .map(|sl| CachedLine {
                 line: sl.line().to_string(),
                 line_number: sl.line_number(),
+                end_line_number: sl.end_line_number(),
                 hash: sl.hash(),
+                hash64: sl.hash64(),
             })
             .collect();
 
@@ -180,10 +220,7 @@ impl FileCache {
 
 /// Clear the cache directory
 pub fn clear_cache(config: &Config) -> Result<()> {
-    let cache_dir = config
-        .cache_dir
-        .clone()
-        .unwrap_or_else(|| PathBuf::from(".duplo-cache"));
+    let cache_dir = resolve_cache_dir(config);
 
     if cache_dir.exists() {
         // Remove all .cache files in the directory
@@ -222,10 +259,11 @@ mod tests {
     use tempfile::TempDir;
 
     fn create_test_config(cache_dir: &Path) -> Config {
-        let mut config = Config::default();
-        config.cache_enabled = true;
-        config.cache_dir = Some(cache_dir.to_path_buf());
-        config
+        Config {
+            cache_enabled: true,
+            cache_dir: Some(cache_dir.to_path_buf()),
+            ..Default::default()
+        }
     }
 
     #[test]
@@ -1,9 +1,37 @@
 //! CLI argument parsing using clap
 
-use crate::config::{Config, OutputFormat};
+use crate::config::{
+    default_base_branch_candidates, default_generated_markers, default_num_threads, ChangedScope,
+    Config, ConfigFile, CrossLanguage, FileSelection, Granularity, HashAlgorithm, OutputFormat,
+    Sample,
+};
 use crate::error::{DuploError, Result};
-use clap::Parser;
+use clap::{ArgAction, Parser};
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Value of `-j/--threads`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Threads {
+    /// Use `RAYON_NUM_THREADS` or the available parallelism
+    Auto,
+    /// Use exactly this many threads
+    Count(usize),
+}
+
+impl FromStr for Threads {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Threads::Auto);
+        }
+        s.parse()
+            .map(Threads::Count)
+            .map_err(|_| format!("expected a number or 'auto', got '{}'", s))
+    }
+}
 
 /// Code duplication detection tool
 #[derive(Parser, Debug)]
@@ -17,7 +45,8 @@ pub struct Cli {
     #[arg(value_name = "FILE_LIST")]
     pub file_list: Option<String>,
 
-    /// Output file for results (use "-" for stdout)
+    /// Output file for results (use "-" for stdout). `{format}`, `{timestamp}`
+    /// and `{branch}` in the name are expanded, e.g. "report-{format}.out"
     #[arg(value_name = "OUTPUT", default_value = "-")]
     pub output: String,
 
@@ -25,26 +54,177 @@ pub struct Cli {
     #[arg(short = 'm', long = "min-lines", value_name = "N", default_value = "4")]
     pub min_lines: u32,
 
-    /// Block percentage threshold (0-100)
+    /// Block percentage threshold (0-100): below 100, blocks must also span at
+    /// least this percentage of the smaller file
     #[arg(short = 'p', long = "percent", value_name = "N", default_value = "100")]
     pub percent: u8,
 
+    /// Consecutive non-matching lines allowed inside a block before it is split
+    #[arg(long = "max-gap", value_name = "N", default_value = "0")]
+    pub max_gap: usize,
+
+    /// Drop blocks whose ratio of distinct lines to total lines is below
+    /// RATIO (0-1), e.g. runs of repeated `});` or `end`
+    #[arg(long = "min-block-entropy", value_name = "RATIO", default_value = "0")]
+    pub min_block_entropy: f64,
+
     /// Minimum characters per line
     #[arg(short = 'c', long = "min-chars", value_name = "N", default_value = "3")]
     pub min_chars: u32,
 
-    /// Analyze only the first N files
+    /// Compare only N files against every other file, skipping pairs in
+    /// which neither file is one of the N
     #[arg(short = 'n', long = "num-files", value_name = "N")]
     pub num_files: Option<usize>,
 
-    /// Number of threads for parallel processing
+    /// Which N files --num-files checks: the first in list order, a seeded
+    /// random choice, or the ones with the most lines
+    #[arg(
+        long = "select",
+        value_name = "MODE",
+        value_enum,
+        default_value_t = FileSelection::First,
+        requires = "num_files"
+    )]
+    pub select: FileSelection,
+
+    /// Seed for --select random
+    #[arg(long = "select-seed", value_name = "SEED", default_value_t = 0)]
+    pub select_seed: u64,
+
+    /// Skip files larger than this many bytes without reading them
+    #[arg(long = "max-file-bytes", value_name = "BYTES")]
+    pub max_file_bytes: Option<u64>,
+
+    /// Analyze only a sample of the discovered files: a fraction such as
+    /// 0.25, or a file count. The same files are picked on every run, and
+    /// the reported percentages are estimates.
+    #[arg(
+        long = "sample",
+        value_name = "FRACTION|COUNT",
+        conflicts_with = "compare_two"
+    )]
+    pub sample: Option<Sample>,
+
+    /// Number of threads for parallel processing, or "auto" (default).
+    /// Auto honors RAYON_NUM_THREADS and container CPU limits.
     #[arg(short = 'j', long = "threads", value_name = "N")]
-    pub threads: Option<usize>,
+    pub threads: Option<Threads>,
 
     /// Ignore file pairs with the same filename
     #[arg(short = 'd', long = "ignore-same-name")]
     pub ignore_same_name: bool,
 
+    /// Ignore file pairs whose names match once version and variant suffixes
+    /// (`.v2`, `.dev`, `.prod`, `-copy`) are stripped
+    #[arg(long = "ignore-same-name-fuzzy")]
+    pub ignore_same_name_fuzzy: bool,
+
+    /// Compare files of different languages with each other (default: forbid)
+    #[arg(
+        long = "cross-language",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = CrossLanguage::Forbid
+    )]
+    pub cross_language: CrossLanguage,
+
+    /// Unit of comparison: physical lines, or logical statements joined
+    /// across continued lines (experimental, C-family languages only)
+    #[arg(
+        long = "granularity",
+        value_name = "MODE",
+        value_enum,
+        default_value_t = Granularity::Line
+    )]
+    pub granularity: Granularity,
+
+    /// Experimental: report whole functions whose cleaned lines match
+    /// instead of line blocks (brace languages only)
+    #[arg(long = "function-granularity", conflicts_with = "cache")]
+    pub function_granularity: bool,
+
+    /// Skip code inside `#if 0` / `#ifdef NEVER` regions (C/C++ only)
+    #[arg(long = "skip-disabled-code")]
+    pub skip_disabled_code: bool,
+
+    /// Split minified files (a few lines of 500+ characters) into statements
+    /// after `;`, `{` and `}` so duplicated chunks inside them are found
+    #[arg(long = "split-minified")]
+    pub split_minified: bool,
+
+    /// Keep lines that are only structural delimiters (`{`, `}`, `});`,
+    /// `begin`, `end`) regardless of --min-chars, so they don't split blocks
+    #[arg(long = "keep-structural-lines")]
+    pub keep_structural_lines: bool,
+
+    /// Keep lines without any letter (e.g. `255, 0, 17,` in data tables),
+    /// which are otherwise dropped whatever --min-chars is
+    #[arg(long = "include-nonalphabetic")]
+    pub include_nonalphabetic: bool,
+
+    /// Replace the contents of string literals with "STR" before comparing,
+    /// so lines differing only in their strings match
+    #[arg(long = "normalize-string-literals")]
+    pub normalize_string_literals: bool,
+
+    /// Hash function applied to cleaned lines: fnv1a (default, compatible
+    /// with C++ Duplo), xxhash or fnv64
+    #[arg(long = "hash", value_name = "ALGORITHM", value_enum, default_value_t = HashAlgorithm::Fnv1a)]
+    pub hash: HashAlgorithm,
+
+    /// Drop cleaned lines matching this regular expression before hashing
+    /// (repeatable, e.g. '^return;?$')
+    #[arg(long = "ignore-line-pattern", value_name = "REGEX")]
+    pub ignore_line_patterns: Vec<String>,
+
+    /// Drop import, package and module directives (default: true; pass
+    /// --ignore-imports=false to compare them)
+    #[arg(
+        long = "ignore-imports",
+        value_name = "BOOL",
+        default_value_t = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        action = ArgAction::Set
+    )]
+    pub ignore_imports: bool,
+
+    /// Drop annotations, attributes and decorators (default: true)
+    #[arg(
+        long = "ignore-annotations",
+        value_name = "BOOL",
+        default_value_t = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        action = ArgAction::Set
+    )]
+    pub ignore_annotations: bool,
+
+    /// Drop function and method signatures (default: true)
+    #[arg(
+        long = "ignore-signatures",
+        value_name = "BOOL",
+        default_value_t = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        action = ArgAction::Set
+    )]
+    pub ignore_signatures: bool,
+
+    /// Skip generated files, detected by a marker such as "DO NOT EDIT" or
+    /// "@generated" in their first lines (markers configurable via --config)
+    #[arg(long = "ignore-generated")]
+    pub ignore_generated: bool,
+
+    /// TOML config file (supports a [file_types] table mapping extra extensions
+    /// to built-in ones, e.g. ino = "c", and a generated_markers list)
+    #[arg(long = "config", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     /// Output in JSON format
     #[arg(long = "json")]
     pub json: bool,
@@ -53,25 +233,96 @@ pub struct Cli {
     #[arg(long = "xml")]
     pub xml: bool,
 
+    /// Output each duplicate as a unified diff between the cleaned lines of its two ranges
+    #[arg(long = "diff")]
+    pub diff: bool,
+
+    /// Output a Markdown summary with the top duplicates (e.g. for PR comments)
+    #[arg(long = "markdown")]
+    pub markdown: bool,
+
+    /// Output in YAML format, structured like the JSON output
+    #[cfg(feature = "yaml")]
+    #[arg(long = "yaml")]
+    pub yaml: bool,
+
     // === Git Integration ===
     /// Discover files from git (tracked files via git ls-files)
     #[arg(long = "git")]
     pub git: bool,
 
+    /// Discover only files with these extensions (comma-separated, e.g. "c,h"),
+    /// replacing the built-in list of supported extensions
+    #[arg(long = "only-extensions", value_name = "EXTS", value_delimiter = ',')]
+    pub only_extensions: Option<Vec<String>>,
+
+    /// File listing paths (one per line, like FILE_LIST) to remove from the
+    /// discovered files
+    #[arg(long = "exclude-file-list", value_name = "FILE")]
+    pub exclude_file_list: Option<String>,
+
     /// Only analyze files changed vs base branch (requires --git)
     #[arg(long = "changed-only", requires = "git")]
     pub changed_only: bool,
 
-    /// Base branch for --changed-only comparison (auto-detected if not specified)
-    #[arg(long = "base-branch", value_name = "BRANCH", requires = "changed_only")]
+    /// Base branch for --changed-only comparison (auto-detected if not specified).
+    /// Implies --changed-only when given on its own.
+    #[arg(long = "base-branch", value_name = "BRANCH", requires = "git")]
     pub base_branch: Option<String>,
 
+    /// Comma-separated branch names tried, in order, when auto-detecting the base branch
+    #[arg(
+        long = "base-branch-candidates",
+        value_name = "BRANCHES",
+        value_delimiter = ',',
+        requires = "changed_only"
+    )]
+    pub base_branch_candidates: Option<Vec<String>>,
+
+    /// Only report duplicates involving files changed since a date or revision (requires --git)
+    #[arg(
+        long = "since",
+        value_name = "DATE_OR_REV",
+        requires = "git",
+        conflicts_with_all = ["changed_only", "base_branch"]
+    )]
+    pub since: Option<String>,
+
+    /// Experimental: compare each changed file with its version at the merge
+    /// base and report code it deleted and then reintroduced. Implies --changed-only
+    #[arg(
+        long = "self-history",
+        requires = "git",
+        conflicts_with_all = ["since", "compare_two", "baseline_against_ref"]
+    )]
+    pub self_history: bool,
+
+    /// With a changed set, compare all file pairs (all), or skip pairs of two
+    /// unchanged files (changed), which finds the same duplicates faster
+    #[arg(
+        long = "changed-scope",
+        value_name = "SCOPE",
+        value_enum,
+        default_value_t = ChangedScope::All
+    )]
+    pub changed_scope: ChangedScope,
+
+    /// Report all duplicates, but only exit nonzero for those involving changed files
+    #[arg(long = "fail-on-new", requires = "git")]
+    pub fail_on_new: bool,
+
+    /// Exit with an error (code 2) when no files could be analyzed, e.g.
+    /// because of an empty file list or over-eager filters
+    #[arg(long = "fail-on-empty")]
+    pub fail_on_empty: bool,
+
     // === Incremental Cache ===
     /// Enable incremental caching of processed files
     #[arg(long = "cache")]
     pub cache: bool,
 
-    /// Cache directory (default: .duplo-cache in repository root)
+    /// Cache directory (default: .duplo-cache in the repository root with --git,
+    /// otherwise in the current directory)
     #[arg(long = "cache-dir", value_name = "DIR")]
     pub cache_dir: Option<PathBuf>,
 
@@ -79,62 +330,340 @@ pub struct Cli {
     #[arg(long = "clear-cache")]
     pub clear_cache: bool,
 
+    /// Also cache detected blocks per file pair so unchanged pairs are skipped (requires --cache)
+    #[arg(long = "pair-cache", requires = "cache")]
+    pub pair_cache: bool,
+
+    /// Load every file into the cache and exit without comparing files or
+    /// writing output, so a later run is served from the cache (requires --cache)
+    #[arg(long = "warm-cache", requires = "cache")]
+    pub warm_cache: bool,
+
     // === Baseline Mode ===
-    /// Compare against baseline file, only report NEW duplicates
-    #[arg(long = "baseline", value_name = "FILE")]
+    /// Compare against baseline file, only report NEW duplicates. A
+    /// directory's *.json baselines are merged into one
+    #[arg(long = "baseline", value_name = "PATH")]
     pub baseline: Option<PathBuf>,
 
     /// Save current results as a baseline file
     #[arg(long = "save-baseline", value_name = "FILE")]
     pub save_baseline: Option<PathBuf>,
+
+    /// Compare against FILE like --baseline, or create it from the current
+    /// results and exit 0 when it doesn't exist yet
+    #[arg(
+        long = "baseline-auto",
+        value_name = "FILE",
+        conflicts_with_all = ["baseline", "save_baseline", "baseline_against_ref"]
+    )]
+    pub baseline_auto: Option<PathBuf>,
+
+    /// Use the duplicates found in the files tracked at REF as the baseline,
+    /// computed on the fly instead of read from a file (requires --git)
+    #[arg(
+        long = "baseline-against-ref",
+        value_name = "REF",
+        requires = "git",
+        conflicts_with = "baseline"
+    )]
+    pub baseline_against_ref: Option<String>,
+
+    /// Report baseline duplicates that still exist instead of new ones (requires --baseline)
+    #[arg(long = "report-unchanged-duplicates", requires = "baseline")]
+    pub report_unchanged_duplicates: bool,
+
+    /// Match baseline blocks regardless of the order of lines within them,
+    /// so reordering independent lines doesn't make a known duplicate new
+    #[arg(long = "baseline-order-insensitive")]
+    pub baseline_order_insensitive: bool,
+
+    /// Fail instead of warning when the baseline was saved with different
+    /// detection settings (requires --baseline)
+    #[arg(long = "baseline-strict", requires = "baseline")]
+    pub baseline_strict: bool,
+
+    /// Compare exactly two files directly, without a file list (output goes to stdout)
+    #[arg(
+        long = "compare-two",
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["file_list", "git", "cache"]
+    )]
+    pub compare_two: Option<Vec<String>>,
+
+    /// Compare two directory trees, reporting only duplicates with one side
+    /// in each (e.g. a vendored copy against its upstream)
+    #[arg(
+        long = "compare-dirs",
+        num_args = 2,
+        value_names = ["DIR_A", "DIR_B"],
+        conflicts_with_all = ["file_list", "git", "compare_two"]
+    )]
+    pub compare_dirs: Option<Vec<PathBuf>>,
+
+    /// Print the files that would be analyzed, one absolute path and its
+    /// language per line (tab-separated), and exit
+    #[arg(long = "list-files")]
+    pub list_files: bool,
+
+    /// Print the supported languages and their file extensions, and exit
+    #[arg(long = "list-languages")]
+    pub list_languages: bool,
+
+    /// Print the effective configuration as JSON and exit without running detection
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// Print the cleaning (cache) and detection (baseline) config hashes and exit
+    #[arg(long = "config-hash-only")]
+    pub config_hash_only: bool,
+
+    // === Reporting ===
+    /// Attach git blame authors of the duplicated lines to each block (JSON output)
+    #[arg(long = "blame")]
+    pub blame: bool,
+
+    /// Emit at most N lines of each duplicate's snippet (JSON and XML output)
+    #[arg(long = "max-snippet-lines", value_name = "N")]
+    pub max_snippet_lines: Option<usize>,
+
+    /// Emit snippet lines as written in the source, indentation included,
+    /// instead of cleaned (JSON and XML output)
+    #[arg(long = "preserve-indentation")]
+    pub preserve_indentation: bool,
+
+    /// Group console output under one header per file pair
+    #[arg(long = "group-by-pair")]
+    pub group_by_pair: bool,
+
+    /// Print each console block as grep-style `path:line` lines, one per
+    /// file, which IDE terminals turn into links
+    #[arg(long = "console-clickable")]
+    pub console_clickable: bool,
+
+    /// Never color console output. Without it, output is colored only when
+    /// written to a terminal and NO_COLOR is unset.
+    #[arg(long = "no-color")]
+    pub no_color: bool,
+
+    /// Roll up duplication by the first DEPTH directory components (JSON and console output)
+    #[arg(long = "group-report", value_name = "DEPTH")]
+    pub group_report: Option<usize>,
+
+    /// List files dropped for having no analyzable lines (JSON and console output)
+    #[arg(long = "report-skipped")]
+    pub report_skipped: bool,
+
+    /// Group files linked by duplicates, directly or through other files,
+    /// into clone families (JSON and console output)
+    #[arg(long = "clone-families")]
+    pub clone_families: bool,
+
+    /// Exponent applied to each block's line count in the duplication debt
+    /// score (sum of line_count^EXP over all blocks)
+    #[arg(long = "debt-exponent", value_name = "EXP", default_value = "1.5")]
+    pub debt_exponent: f64,
+
+    /// Append a line with the timestamp, commit and summary of this run to
+    /// FILE (JSON Lines), for tracking duplication over time
+    #[arg(long = "history", value_name = "FILE")]
+    pub history: Option<PathBuf>,
+
+    /// Add the alphabetically first file of each duplicate's clone class to
+    /// JSON output, as a stable name for the class
+    #[arg(long = "class-representative")]
+    pub class_representative: bool,
+
+    /// Write a JSON manifest mapping each analyzed file to its content hash
+    /// and cleaned line count to FILE
+    #[arg(long = "manifest", value_name = "FILE")]
+    pub manifest: Option<PathBuf>,
+
+    /// Report how many line hashes are shared by lines with different text,
+    /// which can make unrelated lines match
+    #[arg(long = "collision-report")]
+    pub collision_report: bool,
+
+    /// Also exit with code 1 if some file has more than PERCENT of its lines
+    /// duplicated, even when the overall duplication is low
+    #[arg(long = "file-fail-threshold", value_name = "PERCENT")]
+    pub file_fail_threshold: Option<f64>,
+
+    /// Report file pairs sharing at least RATIO (0-1) of their distinct
+    /// lines in any order (Jaccard similarity), e.g. files with reordered
+    /// functions
+    #[arg(long = "similarity-threshold", value_name = "RATIO")]
+    pub similarity_threshold: Option<f64>,
+
+    /// Report the number of blocks in each size range (4-9, 10-24, 25-49
+    /// and 50+ lines by default), to help choose --min-lines
+    #[arg(long = "histogram")]
+    pub histogram: bool,
+
+    /// Store identical cleaned lines once across all files, saving memory on
+    /// large repositories with many shared lines
+    #[arg(long = "intern-lines")]
+    pub intern_lines: bool,
+
+    /// Print pair, matrix and timing counters of the detection to stderr
+    #[arg(long = "algorithm-stats")]
+    pub algorithm_stats: bool,
+
+    /// Print the wall-clock time of each phase (discovery, detection, export, ...) to stderr
+    #[arg(long = "profile")]
+    pub profile: bool,
 }
 
 impl Cli {
     /// Parse command line arguments into a Config
     pub fn into_config(self) -> Result<Config> {
         // Check for conflicting output format options
-        if self.json && self.xml {
+        let formats = [
+            (self.json, OutputFormat::Json),
+            (self.xml, OutputFormat::Xml),
+            (self.diff, OutputFormat::Diff),
+            (self.markdown, OutputFormat::Markdown),
+            #[cfg(feature = "yaml")]
+            (self.yaml, OutputFormat::Yaml),
+        ];
+        let mut requested = formats.into_iter().filter(|&(on, _)| on).map(|(_, f)| f);
+        let output_format = requested.next().unwrap_or(OutputFormat::Console);
+        if requested.next().is_some() {
             return Err(DuploError::OutputFormatConflict);
         }
 
-        // Validate: file_list required unless --git is used
-        if self.file_list.is_none() && !self.git {
+        // Validate: file_list required unless --git or --compare-two is used
+        if self.file_list.is_none()
+            && !self.git
+            && self.compare_two.is_none()
+            && self.compare_dirs.is_none()
+        {
             return Err(DuploError::InvalidConfig(
-                "FILE_LIST is required unless --git is specified".to_string(),
+                "FILE_LIST is required unless --git, --compare-two or --compare-dirs is specified"
+                    .to_string(),
             ));
         }
 
-        let output_format = if self.json {
-            OutputFormat::Json
-        } else if self.xml {
-            OutputFormat::Xml
-        } else {
-            OutputFormat::Console
+        let config_file = match self.config {
+            Some(ref path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
         };
 
-        Ok(Config {
+        let color = !self.no_color && color_supported(&self.output);
+
+        let config = Config {
             min_chars: self.min_chars,
             min_block_size: self.min_lines,
             block_percent_threshold: self.percent,
+            max_gap: self.max_gap,
+            min_block_entropy: self.min_block_entropy,
             files_to_check: self.num_files.unwrap_or(0),
-            num_threads: self.threads.unwrap_or_else(num_cpus::get),
+            file_selection: self.select,
+            selection_seed: self.select_seed,
+            max_file_bytes: self.max_file_bytes,
+            sample: self.sample,
+            num_threads: match self.threads {
+                Some(Threads::Count(n)) => n,
+                Some(Threads::Auto) | None => default_num_threads(),
+            },
+            reuse_thread_pool: false,
+            cancel: None,
+            focus_files: None,
             output_format,
             ignore_same_filename: self.ignore_same_name,
+            ignore_same_filename_fuzzy: self.ignore_same_name_fuzzy,
+            cross_language: self.cross_language,
+            granularity: self.granularity,
+            function_granularity: self.function_granularity,
+            skip_disabled_code: self.skip_disabled_code,
+            split_minified: self.split_minified,
+            keep_structural_lines: self.keep_structural_lines,
+            include_nonalphabetic: self.include_nonalphabetic,
+            normalize_string_literals: self.normalize_string_literals,
+            hash_algorithm: self.hash,
+            ignore_line_patterns: self.ignore_line_patterns,
+            ignore_imports: self.ignore_imports,
+            ignore_annotations: self.ignore_annotations,
+            ignore_signatures: self.ignore_signatures,
+            extension_map: config_file.extension_map(),
+            ignore_generated: self.ignore_generated,
+            generated_markers: config_file
+                .generated_markers
+                .clone()
+                .unwrap_or_else(default_generated_markers),
             list_filename: self.file_list,
+            exclude_list_filename: self.exclude_file_list,
             output_filename: self.output,
             // Git integration
             git_mode: self.git,
-            changed_only: self.changed_only,
+            only_extensions: self.only_extensions.map(|exts| {
+                exts.iter()
+                    .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|ext| !ext.is_empty())
+                    .collect()
+            }),
+            // A base branch is only meaningful for the changed set
+            changed_only: self.changed_only || self.base_branch.is_some() || self.self_history,
             base_branch: self.base_branch,
+            base_branch_candidates: self
+                .base_branch_candidates
+                .unwrap_or_else(default_base_branch_candidates),
+            since: self.since,
+            self_history: self.self_history,
+            changed_scope: self.changed_scope,
             // Caching
             cache_enabled: self.cache,
             cache_dir: self.cache_dir,
             clear_cache: self.clear_cache,
+            pair_cache_enabled: self.pair_cache,
             // Baseline
             baseline_path: self.baseline,
             save_baseline_path: self.save_baseline,
-        })
+            baseline_auto: self.baseline_auto,
+            baseline_against_ref: self.baseline_against_ref,
+            report_unchanged_duplicates: self.report_unchanged_duplicates,
+            baseline_order_insensitive: self.baseline_order_insensitive,
+            baseline_strict: self.baseline_strict,
+            fail_on_new: self.fail_on_new,
+            fail_on_empty: self.fail_on_empty,
+            // Reporting
+            blame: self.blame,
+            max_snippet_lines: self.max_snippet_lines,
+            preserve_indentation: self.preserve_indentation,
+            group_by_pair: self.group_by_pair,
+            console_clickable: self.console_clickable,
+            color,
+            group_report_depth: self.group_report,
+            report_skipped: self.report_skipped,
+            clone_families: self.clone_families,
+            debt_exponent: self.debt_exponent,
+            algorithm_stats: self.algorithm_stats,
+            history_path: self.history,
+            class_representative: self.class_representative,
+            manifest_path: self.manifest,
+            collision_report: self.collision_report,
+            file_fail_threshold: self.file_fail_threshold,
+            similarity_threshold: self.similarity_threshold,
+            histogram: self.histogram,
+            intern_lines: self.intern_lines,
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Whether console output written to `output` may be colored
+///
+/// Follows the NO_COLOR and CLICOLOR_FORCE conventions; otherwise only
+/// stdout attached to a terminal is colored.
+fn color_supported(output: &str) -> bool {
+    let env_set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+    if output != "-" || env_set("NO_COLOR") {
+        return false;
     }
+    let forced = std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+    forced || std::io::stdout().is_terminal()
 }
 
 #[cfg(test)]
@@ -169,6 +698,47 @@ mod tests {
         assert_eq!(config.output_format, OutputFormat::Xml);
     }
 
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_cli_yaml_output() {
+        let cli = Cli::parse_from(["duplo", "--yaml", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().output_format, OutputFormat::Yaml);
+
+        let cli = Cli::parse_from(["duplo", "--yaml", "--json", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::OutputFormatConflict)
+        ));
+    }
+
+    #[test]
+    fn test_cli_markdown_output() {
+        let cli = Cli::parse_from(["duplo", "--markdown", "files.txt"]);
+        let config = cli.into_config().unwrap();
+
+        assert_eq!(config.output_format, OutputFormat::Markdown);
+
+        let cli = Cli::parse_from(["duplo", "--markdown", "--xml", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::OutputFormatConflict)
+        ));
+    }
+
+    #[test]
+    fn test_cli_diff_output() {
+        let cli = Cli::parse_from(["duplo", "--diff", "files.txt"]);
+        let config = cli.into_config().unwrap();
+
+        assert_eq!(config.output_format, OutputFormat::Diff);
+
+        let cli = Cli::parse_from(["duplo", "--diff", "--json", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::OutputFormatConflict)
+        ));
+    }
+
     #[test]
     fn test_cli_conflicting_output() {
         let cli = Cli::parse_from(["duplo", "--json", "--xml", "files.txt"]);
@@ -219,6 +789,30 @@ mod tests {
         assert!(config.list_filename.is_none());
     }
 
+    #[test]
+    fn test_cli_only_extensions() {
+        let cli = Cli::parse_from(["duplo", "--git"]);
+        assert!(cli.into_config().unwrap().only_extensions.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--git", "--only-extensions", "h,.HPP, c"]);
+        assert_eq!(
+            cli.into_config().unwrap().only_extensions,
+            Some(vec!["h".to_string(), "hpp".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_cli_exclude_file_list() {
+        let cli = Cli::parse_from(["duplo", "--git"]);
+        assert!(cli.into_config().unwrap().exclude_list_filename.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--git", "--exclude-file-list", ".duploignore"]);
+        assert_eq!(
+            cli.into_config().unwrap().exclude_list_filename.as_deref(),
+            Some(".duploignore")
+        );
+    }
+
     #[test]
     fn test_cli_git_changed_only() {
         let cli = Cli::parse_from(["duplo", "--git", "--changed-only"]);
@@ -274,13 +868,34 @@ mod tests {
 
     #[test]
     fn test_cli_baseline_options() {
-        let cli = Cli::parse_from(["duplo", "--git", "--baseline", "baseline.json"]);
+        let baseline = tempfile::NamedTempFile::new().unwrap();
+        let baseline_path = baseline.path().to_str().unwrap();
+        let cli = Cli::parse_from(["duplo", "--git", "--baseline", baseline_path]);
         let config = cli.into_config().unwrap();
 
-        assert_eq!(config.baseline_path, Some(PathBuf::from("baseline.json")));
+        assert_eq!(config.baseline_path, Some(PathBuf::from(baseline_path)));
         assert!(config.save_baseline_path.is_none());
     }
 
+    #[test]
+    fn test_cli_baseline_against_ref() {
+        let cli = Cli::parse_from(["duplo", "--git", "--baseline-against-ref", "main"]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.baseline_against_ref.as_deref(), Some("main"));
+
+        // Requires --git, and replaces a baseline file
+        assert!(Cli::try_parse_from(["duplo", "--baseline-against-ref", "main", "f.txt"]).is_err());
+        assert!(Cli::try_parse_from([
+            "duplo",
+            "--git",
+            "--baseline-against-ref",
+            "main",
+            "--baseline",
+            "b.json"
+        ])
+        .is_err());
+    }
+
     #[test]
     fn test_cli_save_baseline() {
         let cli = Cli::parse_from(["duplo", "--git", "--save-baseline", "new-baseline.json"]);
@@ -295,17 +910,761 @@ mod tests {
 
     #[test]
     fn test_cli_baseline_and_save_baseline_together() {
+        let baseline = tempfile::NamedTempFile::new().unwrap();
+        let baseline_path = baseline.path().to_str().unwrap();
         let cli = Cli::parse_from([
             "duplo",
             "--git",
             "--baseline",
-            "old.json",
+            baseline_path,
             "--save-baseline",
             "new.json",
         ]);
         let config = cli.into_config().unwrap();
 
-        assert_eq!(config.baseline_path, Some(PathBuf::from("old.json")));
+        assert_eq!(config.baseline_path, Some(PathBuf::from(baseline_path)));
         assert_eq!(config.save_baseline_path, Some(PathBuf::from("new.json")));
     }
+
+    #[test]
+    fn test_cli_blame() {
+        let cli = Cli::parse_from(["duplo", "--git", "--json", "--blame"]);
+        let config = cli.into_config().unwrap();
+
+        assert!(config.blame);
+    }
+
+    #[test]
+    fn test_cli_base_branch_candidates() {
+        let cli = Cli::parse_from([
+            "duplo",
+            "--git",
+            "--changed-only",
+            "--base-branch-candidates",
+            "trunk,release",
+        ]);
+        let config = cli.into_config().unwrap();
+
+        assert_eq!(
+            config.base_branch_candidates,
+            vec!["trunk".to_string(), "release".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_cli_base_branch_candidates_default() {
+        let cli = Cli::parse_from(["duplo", "--git", "--changed-only"]);
+        let config = cli.into_config().unwrap();
+
+        assert_eq!(
+            config.base_branch_candidates,
+            vec!["main", "master", "develop"]
+        );
+    }
+
+    #[test]
+    fn test_cli_since() {
+        let cli = Cli::parse_from(["duplo", "--git", "--since", "2024-01-01"]);
+        let config = cli.into_config().unwrap();
+
+        assert_eq!(config.since, Some("2024-01-01".to_string()));
+        assert!(!config.changed_only);
+    }
+
+    #[test]
+    fn test_cli_since_conflicts_with_changed_only() {
+        let result =
+            Cli::try_parse_from(["duplo", "--git", "--changed-only", "--since", "2024-01-01"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_self_history() {
+        let cli = Cli::parse_from(["duplo", "--git", "--self-history"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.self_history);
+        assert!(config.changed_only);
+        assert!(config.validate().is_ok());
+
+        assert!(Cli::try_parse_from(["duplo", "--self-history", "files.txt"]).is_err());
+        assert!(
+            Cli::try_parse_from(["duplo", "--git", "--self-history", "--since", "HEAD~3"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_max_gap() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().max_gap, 0);
+
+        let cli = Cli::parse_from(["duplo", "--max-gap", "2", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().max_gap, 2);
+    }
+
+    #[test]
+    fn test_cli_granularity() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().granularity, Granularity::Line);
+
+        let cli = Cli::parse_from(["duplo", "--granularity", "statement", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().granularity,
+            Granularity::Statement
+        );
+    }
+
+    #[test]
+    fn test_cli_function_granularity() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(!config.function_granularity);
+        assert!(!config.file_type_options().functions);
+
+        let cli = Cli::parse_from(["duplo", "--function-granularity", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.function_granularity);
+        assert!(config.file_type_options().functions);
+
+        // Cached files don't record where their functions are
+        assert!(
+            Cli::try_parse_from(["duplo", "--function-granularity", "--cache", "files.txt"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_changed_scope() {
+        let cli = Cli::parse_from(["duplo", "--git", "--changed-only"]);
+        assert_eq!(cli.into_config().unwrap().changed_scope, ChangedScope::All);
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "--git",
+            "--changed-only",
+            "--changed-scope",
+            "changed",
+        ]);
+        assert_eq!(
+            cli.into_config().unwrap().changed_scope,
+            ChangedScope::Changed
+        );
+
+        // Nothing to scope to without a changed set
+        let cli = Cli::parse_from(["duplo", "--changed-scope", "changed", "files.txt"]);
+        assert!(cli.into_config().is_err());
+    }
+
+    #[test]
+    fn test_cli_cross_language() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().cross_language,
+            CrossLanguage::Forbid
+        );
+
+        let cli = Cli::parse_from(["duplo", "--cross-language", "allow", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().cross_language,
+            CrossLanguage::Allow
+        );
+    }
+
+    #[test]
+    fn test_cli_normalize_string_literals() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().normalize_string_literals);
+
+        let cli = Cli::parse_from(["duplo", "--normalize-string-literals", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.normalize_string_literals);
+        assert!(config.line_filter().normalize_strings);
+    }
+
+    #[test]
+    fn test_cli_ignore_same_name_fuzzy() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().ignore_same_filename_fuzzy);
+
+        let cli = Cli::parse_from(["duplo", "--ignore-same-name-fuzzy", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.ignore_same_filename_fuzzy);
+        assert!(!config.ignore_same_filename);
+    }
+
+    #[test]
+    fn test_cli_hash() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        let default = cli.into_config().unwrap();
+        assert_eq!(default.hash_algorithm, HashAlgorithm::Fnv1a);
+
+        let cli = Cli::parse_from(["duplo", "--hash", "xxhash", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.hash_algorithm, HashAlgorithm::Xxhash);
+        assert_eq!(config.line_filter().hash, HashAlgorithm::Xxhash);
+        // Cached lines hashed differently are invalidated
+        assert_ne!(
+            config.cleaning_config_hash(),
+            default.cleaning_config_hash()
+        );
+
+        let cli = Cli::parse_from(["duplo", "--hash", "fnv64", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().hash_algorithm,
+            HashAlgorithm::Fnv64
+        );
+
+        assert!(Cli::try_parse_from(["duplo", "--hash", "md5", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_ignore_line_pattern() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        let default = cli.into_config().unwrap();
+        assert!(default.ignore_line_patterns.is_empty());
+        assert!(default.line_filter().ignore_patterns.is_none());
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "--ignore-line-pattern",
+            "^return;?$",
+            "--ignore-line-pattern",
+            "^break;$",
+            "files.txt",
+        ]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.ignore_line_patterns, vec!["^return;?$", "^break;$"]);
+        assert_eq!(config.line_filter().ignore_patterns.unwrap().len(), 2);
+        assert_ne!(
+            config.cleaning_config_hash(),
+            default.cleaning_config_hash()
+        );
+    }
+
+    #[test]
+    fn test_cli_ignore_declaration_toggles() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        let default = cli.into_config().unwrap();
+        assert!(default.ignore_imports && default.ignore_annotations && default.ignore_signatures);
+
+        let cli = Cli::parse_from(["duplo", "--ignore-annotations=false", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.ignore_imports);
+        assert!(!config.ignore_annotations);
+        assert!(config.ignore_signatures);
+        assert!(!config.line_filter().skip.annotations);
+        assert_ne!(
+            config.cleaning_config_hash(),
+            default.cleaning_config_hash()
+        );
+
+        // A bare flag keeps the default, and doesn't swallow the file list
+        let cli = Cli::parse_from(["duplo", "--ignore-imports", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.ignore_imports);
+        assert_eq!(config.list_filename.as_deref(), Some("files.txt"));
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "--ignore-imports=false",
+            "--ignore-signatures=false",
+            "files.txt",
+        ]);
+        let config = cli.into_config().unwrap();
+        assert!(!config.ignore_imports && !config.ignore_signatures);
+        assert!(config.ignore_annotations);
+    }
+
+    #[test]
+    fn test_cli_include_nonalphabetic() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().include_nonalphabetic);
+
+        let cli = Cli::parse_from(["duplo", "--include-nonalphabetic", "-c", "0", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.include_nonalphabetic);
+        assert!(config.line_filter().include_nonalphabetic);
+        assert_ne!(
+            config.cleaning_config_hash(),
+            Config {
+                include_nonalphabetic: false,
+                ..config.clone()
+            }
+            .cleaning_config_hash()
+        );
+    }
+
+    #[test]
+    fn test_cli_keep_structural_lines() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().keep_structural_lines);
+
+        let cli = Cli::parse_from(["duplo", "--keep-structural-lines", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(config.keep_structural_lines);
+        assert!(config.line_filter().keep_structural);
+    }
+
+    #[test]
+    fn test_cli_split_minified() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().split_minified);
+
+        let cli = Cli::parse_from(["duplo", "--split-minified", "files.txt"]);
+        assert!(cli.into_config().unwrap().split_minified);
+    }
+
+    #[test]
+    fn test_cli_skip_disabled_code() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().skip_disabled_code);
+
+        let cli = Cli::parse_from(["duplo", "--skip-disabled-code", "files.txt"]);
+        assert!(cli.into_config().unwrap().skip_disabled_code);
+    }
+
+    #[test]
+    fn test_cli_report_unchanged_duplicates() {
+        let baseline = tempfile::NamedTempFile::new().unwrap();
+        let cli = Cli::parse_from([
+            "duplo",
+            "--baseline",
+            baseline.path().to_str().unwrap(),
+            "--report-unchanged-duplicates",
+            "files.txt",
+        ]);
+        assert!(cli.into_config().unwrap().report_unchanged_duplicates);
+
+        // Requires --baseline
+        let err = Cli::try_parse_from(["duplo", "--report-unchanged-duplicates", "files.txt"]);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_cli_min_block_entropy() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().min_block_entropy, 0.0);
+
+        let cli = Cli::parse_from(["duplo", "--min-block-entropy", "0.5", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().min_block_entropy, 0.5);
+
+        let cli = Cli::parse_from(["duplo", "--min-block-entropy", "1.5", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_cli_compare_two() {
+        let cli = Cli::parse_from(["duplo", "--compare-two", "a.c", "b.c"]);
+        assert_eq!(
+            cli.compare_two,
+            Some(vec!["a.c".to_string(), "b.c".to_string()])
+        );
+        assert!(cli.into_config().is_ok());
+
+        // Exactly two files, and no other file source
+        assert!(Cli::try_parse_from(["duplo", "--compare-two", "a.c"]).is_err());
+        assert!(
+            Cli::try_parse_from(["duplo", "--compare-two", "a.c", "b.c", "files.txt"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["duplo", "--git", "--compare-two", "a.c", "b.c"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_compare_dirs() {
+        let cli = Cli::parse_from(["duplo", "--compare-dirs", "vendor/lib", "upstream"]);
+        assert_eq!(
+            cli.compare_dirs,
+            Some(vec![PathBuf::from("vendor/lib"), PathBuf::from("upstream")])
+        );
+        assert!(cli.into_config().is_ok());
+
+        assert!(Cli::try_parse_from(["duplo", "--compare-dirs", "a"]).is_err());
+        assert!(Cli::try_parse_from(["duplo", "--compare-dirs", "a", "b", "files.txt"]).is_err());
+        assert!(Cli::try_parse_from(["duplo", "--git", "--compare-dirs", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_baseline_order_insensitive() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().baseline_order_insensitive);
+
+        let cli = Cli::parse_from(["duplo", "--baseline-order-insensitive", "files.txt"]);
+        assert!(cli.into_config().unwrap().baseline_order_insensitive);
+    }
+
+    #[test]
+    fn test_cli_rejects_invalid_values() {
+        let cli = Cli::parse_from(["duplo", "-j", "0", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::InvalidConfig(msg)) if msg == "--threads must be at least 1"
+        ));
+
+        let cli = Cli::parse_from(["duplo", "--percent", "150", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::InvalidConfig(msg)) if msg.starts_with("--percent")
+        ));
+    }
+
+    #[test]
+    fn test_cli_base_branch_implies_changed_only() {
+        let cli = Cli::parse_from(["duplo", "--git", "--base-branch", "develop"]);
+        let config = cli.into_config().unwrap();
+
+        assert!(config.changed_only);
+        assert_eq!(config.base_branch, Some("develop".to_string()));
+
+        // Still requires --git
+        assert!(Cli::try_parse_from(["duplo", "--base-branch", "develop", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_pair_cache() {
+        let cli = Cli::parse_from(["duplo", "--cache", "--pair-cache", "files.txt"]);
+        assert!(cli.into_config().unwrap().pair_cache_enabled);
+
+        // Requires --cache
+        assert!(Cli::try_parse_from(["duplo", "--pair-cache", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_warm_cache() {
+        let cli = Cli::parse_from(["duplo", "--cache", "--warm-cache", "files.txt"]);
+        assert!(cli.warm_cache);
+
+        // Requires --cache
+        assert!(Cli::try_parse_from(["duplo", "--warm-cache", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_threads() {
+        let cli = Cli::parse_from(["duplo", "-j", "3", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().num_threads, 3);
+
+        let cli = Cli::parse_from(["duplo", "--threads", "auto", "files.txt"]);
+        assert!(cli.into_config().unwrap().num_threads >= 1);
+
+        assert!(Cli::try_parse_from(["duplo", "-j", "lots", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_max_snippet_lines() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().max_snippet_lines, None);
+
+        let cli = Cli::parse_from(["duplo", "--json", "--max-snippet-lines", "5", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().max_snippet_lines, Some(5));
+    }
+
+    #[test]
+    fn test_cli_group_by_pair() {
+        let cli = Cli::parse_from(["duplo", "--group-by-pair", "files.txt"]);
+        assert!(cli.into_config().unwrap().group_by_pair);
+    }
+
+    #[test]
+    fn test_cli_no_color() {
+        let cli = Cli::parse_from(["duplo", "--no-color", "files.txt"]);
+        assert!(!cli.into_config().unwrap().color);
+
+        // Output written to a file is never colored
+        let cli = Cli::parse_from(["duplo", "files.txt", "report.txt"]);
+        assert!(!cli.into_config().unwrap().color);
+    }
+
+    #[test]
+    fn test_cli_console_clickable() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().console_clickable);
+
+        let cli = Cli::parse_from(["duplo", "--console-clickable", "files.txt"]);
+        assert!(cli.into_config().unwrap().console_clickable);
+    }
+
+    #[test]
+    fn test_cli_algorithm_stats() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().algorithm_stats);
+
+        let cli = Cli::parse_from(["duplo", "--algorithm-stats", "files.txt"]);
+        assert!(cli.into_config().unwrap().algorithm_stats);
+    }
+
+    #[test]
+    fn test_cli_baseline_strict() {
+        let cli = Cli::parse_from(["duplo", "--baseline", "b.json", "files.txt"]);
+        assert!(!cli.baseline_strict);
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "--baseline",
+            "b.json",
+            "--baseline-strict",
+            "files.txt",
+        ]);
+        assert!(cli.baseline_strict);
+
+        assert!(Cli::try_parse_from(["duplo", "--baseline-strict", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_select() {
+        let cli = Cli::parse_from(["duplo", "-n", "10", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.file_selection, FileSelection::First);
+        assert_eq!(config.selection_seed, 0);
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "-n",
+            "10",
+            "--select",
+            "random",
+            "--select-seed",
+            "42",
+            "files.txt",
+        ]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.file_selection, FileSelection::Random);
+        assert_eq!(config.selection_seed, 42);
+
+        let cli = Cli::parse_from(["duplo", "-n", "3", "--select", "largest", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().file_selection,
+            FileSelection::Largest
+        );
+
+        // Without a limit there is nothing to select
+        assert!(Cli::try_parse_from(["duplo", "--select", "largest", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_sample() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().sample, None);
+
+        let cli = Cli::parse_from(["duplo", "--sample", "0.5", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().sample,
+            Some(Sample::Fraction(0.5))
+        );
+
+        let cli = Cli::parse_from(["duplo", "--sample", "200", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().sample, Some(Sample::Count(200)));
+
+        for invalid in ["0", "0.0", "1.5"] {
+            let cli = Cli::parse_from(["duplo", "--sample", invalid, "files.txt"]);
+            assert!(matches!(
+                cli.into_config(),
+                Err(DuploError::InvalidConfig(_))
+            ));
+        }
+        assert!(Cli::try_parse_from(["duplo", "--sample", "half", "files.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_baseline_auto() {
+        let cli = Cli::parse_from(["duplo", "--baseline-auto", "base.json", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(config.baseline_auto, Some(PathBuf::from("base.json")));
+        // The file is only looked at when the run starts
+        assert_eq!(config.baseline_path, None);
+
+        assert!(Cli::try_parse_from([
+            "duplo",
+            "--baseline-auto",
+            "a.json",
+            "--save-baseline",
+            "b.json",
+            "files.txt"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_history() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(cli.into_config().unwrap().history_path.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--history", "trend.jsonl", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().history_path,
+            Some(PathBuf::from("trend.jsonl"))
+        );
+    }
+
+    #[test]
+    fn test_cli_class_representative() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().class_representative);
+
+        let cli = Cli::parse_from(["duplo", "--json", "--class-representative", "files.txt"]);
+        assert!(cli.into_config().unwrap().class_representative);
+    }
+
+    #[test]
+    fn test_cli_manifest() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(cli.into_config().unwrap().manifest_path.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--manifest", "analyzed.json", "files.txt"]);
+        assert_eq!(
+            cli.into_config().unwrap().manifest_path,
+            Some(PathBuf::from("analyzed.json"))
+        );
+    }
+
+    #[test]
+    fn test_cli_collision_report() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().collision_report);
+
+        let cli = Cli::parse_from(["duplo", "--collision-report", "files.txt"]);
+        assert!(cli.into_config().unwrap().collision_report);
+    }
+
+    #[test]
+    fn test_cli_file_fail_threshold() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(cli.into_config().unwrap().file_fail_threshold.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--file-fail-threshold", "25.5", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().file_fail_threshold, Some(25.5));
+
+        let cli = Cli::parse_from(["duplo", "--file-fail-threshold", "101", "files.txt"]);
+        assert!(cli.into_config().is_err());
+    }
+
+    #[test]
+    fn test_cli_similarity_threshold() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(cli.into_config().unwrap().similarity_threshold.is_none());
+
+        let cli = Cli::parse_from(["duplo", "--similarity-threshold", "0.8", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().similarity_threshold, Some(0.8));
+
+        let cli = Cli::parse_from(["duplo", "--similarity-threshold", "80", "files.txt"]);
+        assert!(cli.into_config().is_err());
+    }
+
+    #[test]
+    fn test_cli_histogram() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().histogram);
+
+        let cli = Cli::parse_from(["duplo", "--histogram", "files.txt"]);
+        assert!(cli.into_config().unwrap().histogram);
+    }
+
+    #[test]
+    fn test_cli_intern_lines() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().intern_lines);
+
+        let cli = Cli::parse_from(["duplo", "--intern-lines", "files.txt"]);
+        assert!(cli.into_config().unwrap().intern_lines);
+    }
+
+    #[test]
+    fn test_cli_debt_exponent() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().debt_exponent, 1.5);
+
+        let cli = Cli::parse_from(["duplo", "--debt-exponent", "2", "files.txt"]);
+        assert_eq!(cli.into_config().unwrap().debt_exponent, 2.0);
+
+        let cli = Cli::parse_from(["duplo", "--debt-exponent", "0", "files.txt"]);
+        assert!(matches!(
+            cli.into_config(),
+            Err(DuploError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_cli_fail_on_empty() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().fail_on_empty);
+
+        let cli = Cli::parse_from(["duplo", "--fail-on-empty", "files.txt"]);
+        assert!(cli.into_config().unwrap().fail_on_empty);
+    }
+
+    #[test]
+    fn test_cli_preserve_indentation() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().preserve_indentation);
+
+        let cli = Cli::parse_from(["duplo", "--json", "--preserve-indentation", "files.txt"]);
+        assert!(cli.into_config().unwrap().preserve_indentation);
+    }
+
+    #[test]
+    fn test_cli_profile() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.profile);
+
+        let cli = Cli::parse_from(["duplo", "--profile", "files.txt"]);
+        assert!(cli.profile);
+    }
+
+    #[test]
+    fn test_cli_clone_families() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().clone_families);
+
+        let cli = Cli::parse_from(["duplo", "--clone-families", "files.txt"]);
+        assert!(cli.into_config().unwrap().clone_families);
+    }
+
+    #[test]
+    fn test_cli_report_skipped() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        assert!(!cli.into_config().unwrap().report_skipped);
+
+        let cli = Cli::parse_from(["duplo", "--report-skipped", "files.txt"]);
+        assert!(cli.into_config().unwrap().report_skipped);
+    }
+
+    #[test]
+    fn test_cli_ignore_generated() {
+        let cli = Cli::parse_from(["duplo", "files.txt"]);
+        let config = cli.into_config().unwrap();
+        assert!(!config.ignore_generated);
+        assert_eq!(config.generated_markers, default_generated_markers());
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"generated_markers = [\"@autogen\"]\n").unwrap();
+        let cli = Cli::parse_from([
+            "duplo",
+            "--ignore-generated",
+            "--config",
+            file.path().to_str().unwrap(),
+            "files.txt",
+        ]);
+        let config = cli.into_config().unwrap();
+        assert!(config.ignore_generated);
+        assert_eq!(config.generated_markers, vec!["@autogen".to_string()]);
+    }
+
+    #[test]
+    fn test_cli_config_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"[file_types]\nino = \"c\"\n").unwrap();
+
+        let cli = Cli::parse_from([
+            "duplo",
+            "--config",
+            file.path().to_str().unwrap(),
+            "files.txt",
+        ]);
+        let config = cli.into_config().unwrap();
+        assert_eq!(
+            config.extension_map.get("ino").map(String::as_str),
+            Some("c")
+        );
+    }
 }
@@ -1,10 +1,18 @@
 //! Configuration types for lucidshark-duplo
 
+use crate::error::{DuploError, Result};
+use crate::filetype::{FileTypeOptions, LineFilter, SkipRules};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// Output format for duplicate detection results
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     /// Human-readable console output
     #[default]
@@ -13,10 +21,131 @@ pub enum OutputFormat {
     Json,
     /// XML output for tool integration
     Xml,
+    /// Unified diff between the two ranges of each duplicate
+    Diff,
+    /// GitHub-flavored Markdown summary for PR comments
+    Markdown,
+    /// YAML output, structured like the JSON output
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl OutputFormat {
+    /// Lowercase name of the format (e.g. "json")
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Console => "console",
+            OutputFormat::Json => "json",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Diff => "diff",
+            OutputFormat::Markdown => "markdown",
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => "yaml",
+        }
+    }
+}
+
+/// Unit of comparison for duplicate detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// Compare physical source lines
+    #[default]
+    Line,
+    /// Compare logical statements, joining continued lines (C-family languages only)
+    Statement,
+}
+
+/// Hash function applied to cleaned lines before comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// 32-bit FNV-1a, compatible with C++ Duplo (64-bit FNV-1a as the secondary hash)
+    #[default]
+    Fnv1a,
+    /// xxHash (XXH32, with XXH64 as the secondary hash)
+    Xxhash,
+    /// 64-bit FNV-1a, folded to 32 bits for the primary hash
+    Fnv64,
+}
+
+/// Whether files of different languages are compared with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossLanguage {
+    /// Compare every pair of files regardless of language
+    Allow,
+    /// Only compare files with the same file type
+    #[default]
+    Forbid,
+}
+
+/// Which file pairs are compared when only duplicates involving changed
+/// files are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangedScope {
+    /// Compare every pair, then keep the blocks touching a changed file
+    #[default]
+    All,
+    /// Skip pairs of two unchanged files, whose blocks would be dropped
+    Changed,
+}
+
+/// Which files are checked against all others when `files_to_check` limits
+/// the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSelection {
+    /// The first files in list order
+    #[default]
+    First,
+    /// A seeded pseudo-random choice, the same for the same seed and files
+    Random,
+    /// The files with the most analyzable lines
+    Largest,
+}
+
+/// Subset of the discovered files to analyze (`--sample`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sample {
+    /// This fraction of the files, between 0 (exclusive) and 1
+    Fraction(f64),
+    /// At most this many files
+    Count(usize),
+}
+
+impl Sample {
+    /// Number of files sampled out of `total` (at least one when a fraction
+    /// of a non-empty list is requested)
+    pub fn size(&self, total: usize) -> usize {
+        match *self {
+            Sample::Fraction(fraction) => ((total as f64 * fraction).ceil() as usize).min(total),
+            Sample::Count(count) => count.min(total),
+        }
+    }
+}
+
+impl std::str::FromStr for Sample {
+    type Err = String;
+
+    /// A value with a decimal point is a fraction, anything else a count
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.contains('.') {
+            s.parse()
+                .map(Sample::Fraction)
+                .map_err(|_| format!("expected a fraction or a file count, got '{}'", s))
+        } else {
+            s.parse()
+                .map(Sample::Count)
+                .map_err(|_| format!("expected a fraction or a file count, got '{}'", s))
+        }
+    }
 }
 
 /// Configuration options for Duplo
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Config {
     /// Minimum number of characters in a line to be considered (default: 3)
     /// Lines with fewer characters are ignored
@@ -26,26 +155,118 @@ pub struct Config {
     /// Duplicate blocks smaller than this are ignored
     pub min_block_size: u32,
 
-    /// Block percentage threshold (default: 100)
-    /// When set below 100, also considers blocks that represent
-    /// at least this percentage of the smaller file
+    /// Block percentage threshold (default: 100, disabled)
+    /// When set below 100, a block must also span at least this
+    /// percentage of the smaller file's lines to be reported
     pub block_percent_threshold: u8,
 
-    /// Maximum number of files to analyze (0 = all files)
+    /// Consecutive non-matching lines allowed inside a block (default: 0)
+    pub max_gap: usize,
+
+    /// Minimum ratio of distinct lines to total lines in a reported block
+    /// (default: 0.0, disabled). Drops blocks of repeated boilerplate like `});`
+    pub min_block_entropy: f64,
+
+    /// Number of files compared against every other file (0 = all files).
+    /// Pairs of two unchecked files are never compared.
     pub files_to_check: usize,
 
-    /// Number of threads for parallel processing (default: num_cpus)
+    /// How the `files_to_check` files are chosen
+    pub file_selection: FileSelection,
+
+    /// Seed for `FileSelection::Random`
+    pub selection_seed: u64,
+
+    /// Skip files larger than this many bytes before reading them (None = no limit)
+    pub max_file_bytes: Option<u64>,
+
+    /// Analyze only a deterministic sample of the discovered files
+    pub sample: Option<Sample>,
+
+    /// Number of threads for parallel processing (default: see `default_num_threads`)
     pub num_threads: usize,
 
-    /// Output format (console, json, or xml)
+    /// Keep the worker thread pool alive for later runs with the same number
+    /// of threads (for callers that detect repeatedly)
+    #[serde(skip)]
+    pub reuse_thread_pool: bool,
+
+    /// Flag that stops detection early when set (e.g. from a Ctrl-C
+    /// handler); files already compared are still reported
+    #[serde(skip)]
+    pub cancel: Option<Arc<AtomicBool>>,
+
+    /// With `ChangedScope::Changed`, the changed files; pairs of two files
+    /// outside it aren't compared
+    #[serde(skip)]
+    pub focus_files: Option<Arc<HashSet<String>>>,
+
+    /// Output format (console, json, xml, diff, or markdown)
     pub output_format: OutputFormat,
 
     /// Ignore file pairs with the same filename (different paths)
     pub ignore_same_filename: bool,
 
+    /// Also ignore file pairs whose names differ only by a version or variant
+    /// suffix (`Foo.v2.java`, `config.dev.yaml`, `util-copy.c`)
+    pub ignore_same_filename_fuzzy: bool,
+
+    /// Whether file pairs of different languages are compared (default: forbid)
+    pub cross_language: CrossLanguage,
+
+    /// Unit of comparison (experimental statement mode for C-family languages)
+    pub granularity: Granularity,
+
+    /// Experimental: report only whole functions of brace languages whose
+    /// cleaned lines match, instead of line blocks
+    pub function_granularity: bool,
+
+    /// Skip code inside `#if 0` / `#ifdef NEVER` regions (C/C++ only)
+    pub skip_disabled_code: bool,
+
+    /// Split the few, very long lines of minified files into statements
+    pub split_minified: bool,
+
+    /// Keep structural delimiter lines (`{`, `});`, `end`) regardless of `min_chars`
+    pub keep_structural_lines: bool,
+
+    /// Keep lines with no alphabetic character (numeric tables, enum values)
+    pub include_nonalphabetic: bool,
+
+    /// Replace the contents of string literals with a placeholder before hashing
+    pub normalize_string_literals: bool,
+
+    /// Hash function applied to cleaned lines
+    pub hash_algorithm: HashAlgorithm,
+
+    /// Regular expressions for cleaned lines to drop before hashing (e.g. `^return;?$`)
+    pub ignore_line_patterns: Vec<String>,
+
+    /// Drop import, package and module directives (default: true)
+    pub ignore_imports: bool,
+
+    /// Drop annotations, attributes and decorators (default: true)
+    pub ignore_annotations: bool,
+
+    /// Drop function and method signatures (default: true)
+    pub ignore_signatures: bool,
+
+    /// Extra extensions mapped to built-in ones (e.g. `ino` -> `c`), from the config file
+    pub extension_map: BTreeMap<String, String>,
+
+    /// Skip files whose first lines contain a generator marker
+    pub ignore_generated: bool,
+
+    /// Markers identifying generated files (default: `DEFAULT_GENERATED_MARKERS`)
+    pub generated_markers: Vec<String>,
+
     /// Path to input file list (or "-" for stdin). None when using --git mode.
     pub list_filename: Option<String>,
 
+    /// File listing paths to leave out of the analysis, whichever way the
+    /// files were discovered
+    pub exclude_list_filename: Option<String>,
+
     /// Path to output file (or "-" for stdout)
     pub output_filename: String,
 
@@ -53,28 +274,221 @@ pub struct Config {
     /// Use git to discover files
     pub git_mode: bool,
 
+    /// Discover only files with these extensions (lowercase, no dot),
+    /// replacing the built-in supported list
+    pub only_extensions: Option<Vec<String>>,
+
     /// Only analyze files changed vs base branch (requires git_mode)
     pub changed_only: bool,
 
     /// Base branch for --changed-only comparison (auto-detected if None)
     pub base_branch: Option<String>,
 
+    /// Branch names tried, in order, when auto-detecting the base branch
+    pub base_branch_candidates: Vec<String>,
+
+    /// Only report duplicates involving files changed since this date or revision
+    pub since: Option<String>,
+
+    /// Compare each changed file with its version at the merge base instead
+    /// of with other files (experimental, requires changed_only)
+    pub self_history: bool,
+
+    /// Which pairs are compared when a changed set limits the report
+    pub changed_scope: ChangedScope,
+
     // === Incremental Cache ===
     /// Enable incremental caching
     pub cache_enabled: bool,
 
-    /// Cache directory (default: .duplo-cache in repo root)
+    /// Cache directory (default: .duplo-cache in repo root in git mode, else in CWD)
     pub cache_dir: Option<PathBuf>,
 
     /// Clear the cache before running
     pub clear_cache: bool,
 
+    /// Also cache detected blocks per file pair (requires cache_enabled)
+    pub pair_cache_enabled: bool,
+
     // === Baseline Mode ===
     /// Path to baseline file to compare against
     pub baseline_path: Option<PathBuf>,
 
     /// Path to save current results as baseline
     pub save_baseline_path: Option<PathBuf>,
+
+    /// Baseline compared against when it exists, and created otherwise
+    pub baseline_auto: Option<PathBuf>,
+
+    /// Git ref whose tracked files are analyzed on the fly to form the baseline
+    pub baseline_against_ref: Option<String>,
+
+    /// Report baseline duplicates that still exist instead of new ones
+    pub report_unchanged_duplicates: bool,
+
+    /// Match baseline blocks regardless of the order of lines within them
+    pub baseline_order_insensitive: bool,
+
+    /// Fail when the baseline's detection config hash differs from ours
+    pub baseline_strict: bool,
+
+    /// Report all duplicates but only fail on those involving changed files
+    /// (requires a changed set: --changed-only, --base-branch or --since)
+    pub fail_on_new: bool,
+
+    /// Exit with an error when no files could be analyzed
+    pub fail_on_empty: bool,
+
+    // === Reporting ===
+    /// Attach git blame authors to each duplicate block (JSON output)
+    pub blame: bool,
+
+    /// Truncate the lines emitted per block to this many (JSON and XML output)
+    pub max_snippet_lines: Option<usize>,
+
+    /// Emit snippet lines with their original indentation (JSON and XML output)
+    pub preserve_indentation: bool,
+
+    /// Group console output under one header per file pair
+    pub group_by_pair: bool,
+
+    /// Print console block headers as `path:line`, one file per line
+    pub console_clickable: bool,
+
+    /// Highlight file names, line ranges and the summary in console output
+    /// with ANSI escapes
+    pub color: bool,
+
+    /// Roll up duplication by the first N directory components (JSON and console output)
+    pub group_report_depth: Option<usize>,
+
+    /// List files dropped for having no analyzable lines (JSON and console output)
+    pub report_skipped: bool,
+
+    /// Report files transitively linked by duplicates as clone families
+    /// (JSON and console output)
+    pub clone_families: bool,
+
+    /// Exponent applied to each block's line count in the debt score
+    /// (default: 1.5), so larger clones weigh more than several small ones
+    pub debt_exponent: f64,
+
+    /// Print pair, matrix and timing counters of the detection to stderr
+    pub algorithm_stats: bool,
+
+    /// JSON Lines file that gets one summary line appended per run
+    pub history_path: Option<PathBuf>,
+
+    /// Name the alphabetically first file of each clone class in JSON output
+    pub class_representative: bool,
+
+    /// File that gets a JSON listing of the analyzed files and their hashes
+    pub manifest_path: Option<PathBuf>,
+
+    /// Report hash values shared by lines with different text
+    pub collision_report: bool,
+
+    /// Also fail when some file is more than this percentage duplicated
+    pub file_fail_threshold: Option<f64>,
+
+    /// Report file pairs whose line sets are at least this similar (0-1)
+    pub similarity_threshold: Option<f64>,
+
+    /// Report how many blocks fall into each size range
+    pub histogram: bool,
+
+    /// Store identical cleaned lines once across files, to save memory
+    pub intern_lines: bool,
+}
+
+/// Settings read from a `--config` TOML file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Extension mappings, e.g. `[file_types]` with `ino = "c"`
+    #[serde(default)]
+    pub file_types: BTreeMap<String, String>,
+
+    /// Markers identifying generated files for `--ignore-generated`,
+    /// replacing the defaults
+    #[serde(default)]
+    pub generated_markers: Option<Vec<String>>,
+}
+
+impl ConfigFile {
+    /// Read and validate a config file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DuploError::InvalidConfig(format!(
+                "cannot read config file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let file: ConfigFile = toml::from_str(&content).map_err(|e| {
+            DuploError::InvalidConfig(format!("invalid config file '{}': {}", path.display(), e))
+        })?;
+
+        for (extension, target) in &file.file_types {
+            if !crate::filetype::is_builtin_extension(target) {
+                return Err(DuploError::InvalidConfig(format!(
+                    "[file_types] maps '{}' to '{}', which is not a built-in file type extension",
+                    extension, target
+                )));
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Extension mappings normalized for lookup (lowercase, no leading dot)
+    pub fn extension_map(&self) -> BTreeMap<String, String> {
+        self.file_types
+            .iter()
+            .map(|(ext, target)| {
+                (
+                    ext.trim_start_matches('.').to_lowercase(),
+                    target.trim_start_matches('.').to_lowercase(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Default markers identifying generated files
+pub fn default_generated_markers() -> Vec<String> {
+    crate::core::generated::DEFAULT_GENERATED_MARKERS
+        .iter()
+        .map(|m| m.to_string())
+        .collect()
+}
+
+/// Default branch names tried when auto-detecting the base branch
+pub fn default_base_branch_candidates() -> Vec<String> {
+    crate::git::DEFAULT_BASE_BRANCH_CANDIDATES
+        .iter()
+        .map(|b| b.to_string())
+        .collect()
+}
+
+/// Default thread count when `-j` is not given or is `auto`
+///
+/// Honors `RAYON_NUM_THREADS` if set to a positive number, otherwise uses
+/// `available_parallelism`, which respects cgroup CPU quotas in containers.
+pub fn default_num_threads() -> usize {
+    threads_from_env(std::env::var("RAYON_NUM_THREADS").ok().as_deref())
+}
+
+/// Resolve the default thread count from a `RAYON_NUM_THREADS` value
+fn threads_from_env(rayon_num_threads: Option<&str>) -> usize {
+    rayon_num_threads
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
 }
 
 impl Default for Config {
@@ -83,23 +497,83 @@ impl Default for Config {
             min_chars: 3,
             min_block_size: 4,
             block_percent_threshold: 100,
+            max_gap: 0,
+            min_block_entropy: 0.0,
             files_to_check: 0,
-            num_threads: num_cpus::get(),
+            file_selection: FileSelection::First,
+            selection_seed: 0,
+            max_file_bytes: None,
+            sample: None,
+            num_threads: default_num_threads(),
+            reuse_thread_pool: false,
+            cancel: None,
+            focus_files: None,
             output_format: OutputFormat::Console,
             ignore_same_filename: false,
+            ignore_same_filename_fuzzy: false,
+            cross_language: CrossLanguage::Forbid,
+            granularity: Granularity::Line,
+            function_granularity: false,
+            skip_disabled_code: false,
+            split_minified: false,
+            keep_structural_lines: false,
+            include_nonalphabetic: false,
+            normalize_string_literals: false,
+            hash_algorithm: HashAlgorithm::Fnv1a,
+            ignore_line_patterns: Vec::new(),
+            ignore_imports: true,
+            ignore_annotations: true,
+            ignore_signatures: true,
+            extension_map: BTreeMap::new(),
+            ignore_generated: false,
+            generated_markers: default_generated_markers(),
             list_filename: None,
+            exclude_list_filename: None,
             output_filename: String::from("-"),
             // Git integration
             git_mode: false,
+            only_extensions: None,
             changed_only: false,
             base_branch: None,
+            base_branch_candidates: default_base_branch_candidates(),
+            since: None,
+            self_history: false,
+            changed_scope: ChangedScope::All,
             // Caching
             cache_enabled: false,
             cache_dir: None,
             clear_cache: false,
+            pair_cache_enabled: false,
             // Baseline
             baseline_path: None,
             save_baseline_path: None,
+            baseline_auto: None,
+            baseline_against_ref: None,
+            report_unchanged_duplicates: false,
+            baseline_order_insensitive: false,
+            baseline_strict: false,
+            fail_on_new: false,
+            fail_on_empty: false,
+            // Reporting
+            blame: false,
+            max_snippet_lines: None,
+            preserve_indentation: false,
+            group_by_pair: false,
+            console_clickable: false,
+            color: false,
+            group_report_depth: None,
+            report_skipped: false,
+            clone_families: false,
+            debt_exponent: 1.5,
+            algorithm_stats: false,
+            history_path: None,
+            class_representative: false,
+            manifest_path: None,
+            collision_report: false,
+            file_fail_threshold: None,
+            similarity_threshold: None,
+            histogram: false,
+            intern_lines: false,
         }
     }
 }
@@ -115,23 +589,188 @@ impl Config {
         }
     }
 
+    /// Whether the cancellation flag has been raised
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Reject nonsensical option combinations with an actionable message
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(DuploError::InvalidConfig(msg));
+
+        if self.block_percent_threshold > 100 {
+            return invalid(format!(
+                "--percent must be between 0 and 100 (got {})",
+                self.block_percent_threshold
+            ));
+        }
+        if self.min_block_size == 0 {
+            return invalid("--min-lines must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.min_block_entropy) {
+            return invalid(format!(
+                "--min-block-entropy must be between 0 and 1 (got {})",
+                self.min_block_entropy
+            ));
+        }
+        if let Err(e) = RegexSet::new(&self.ignore_line_patterns) {
+            return invalid(format!("invalid --ignore-line-pattern: {}", e));
+        }
+        if self.num_threads == 0 {
+            return invalid("--threads must be at least 1".to_string());
+        }
+        if self.changed_only && !self.git_mode {
+            return invalid("--changed-only requires --git".to_string());
+        }
+        if self.self_history && !self.changed_only {
+            return invalid("--self-history requires --changed-only".to_string());
+        }
+        // Cached files don't carry their function boundaries
+        if self.function_granularity && self.cache_enabled {
+            return invalid("--function-granularity can't be combined with --cache".to_string());
+        }
+        if self.fail_on_new && !self.changed_only && self.since.is_none() {
+            return invalid("--fail-on-new requires --changed-only or --since".to_string());
+        }
+        if self.changed_scope == ChangedScope::Changed {
+            if !self.changed_only && self.since.is_none() {
+                return invalid(
+                    "--changed-scope changed requires --changed-only or --since".to_string(),
+                );
+            }
+            // Every duplicate is reported, so no pair can be skipped
+            if self.fail_on_new {
+                return invalid(
+                    "--changed-scope changed can't be combined with --fail-on-new".to_string(),
+                );
+            }
+        }
+        if !(self.debt_exponent.is_finite() && self.debt_exponent > 0.0) {
+            return invalid(format!(
+                "--debt-exponent must be greater than 0 (got {})",
+                self.debt_exponent
+            ));
+        }
+        if let Some(percent) = self.file_fail_threshold {
+            if !(0.0..=100.0).contains(&percent) {
+                return invalid(format!(
+                    "--file-fail-threshold must be between 0 and 100 (got {})",
+                    percent
+                ));
+            }
+        }
+        if let Some(ratio) = self.similarity_threshold {
+            if !(0.0..=1.0).contains(&ratio) {
+                return invalid(format!(
+                    "--similarity-threshold must be between 0 and 1 (got {})",
+                    ratio
+                ));
+            }
+        }
+        match self.sample {
+            Some(Sample::Fraction(f)) if !(f > 0.0 && f <= 1.0) => {
+                return invalid(format!(
+                    "--sample fraction must be greater than 0 and at most 1 (got {})",
+                    f
+                ));
+            }
+            Some(Sample::Count(0)) => {
+                return invalid("--sample must select at least 1 file".to_string());
+            }
+            _ => {}
+        }
+        if self.group_report_depth == Some(0) {
+            return invalid("--group-report depth must be at least 1".to_string());
+        }
+        if let Some(ref path) = self.baseline_path {
+            if !path.exists() {
+                return invalid(format!(
+                    "baseline file '{}' does not exist (create one with --save-baseline)",
+                    path.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rules the file types use to decide which cleaned lines are kept
+    ///
+    /// Compiles `ignore_line_patterns`, so build it once per run rather than
+    /// per file. Patterns that don't compile (rejected by `validate()`) are
+    /// left out.
+    pub fn line_filter(&self) -> LineFilter {
+        let ignore_patterns = if self.ignore_line_patterns.is_empty() {
+            None
+        } else {
+            RegexSet::new(&self.ignore_line_patterns).ok().map(Arc::new)
+        };
+        LineFilter {
+            min_chars: self.min_chars,
+            keep_structural: self.keep_structural_lines,
+            include_nonalphabetic: self.include_nonalphabetic,
+            normalize_strings: self.normalize_string_literals,
+            hash: self.hash_algorithm,
+            ignore_patterns,
+            skip: SkipRules {
+                imports: self.ignore_imports,
+                annotations: self.ignore_annotations,
+                signatures: self.ignore_signatures,
+            },
+        }
+    }
+
+    /// Options for turning each file into comparable lines
+    ///
+    /// Builds the [`LineFilter`] via [`Config::line_filter`], so call it once
+    /// per run as well.
+    pub fn file_type_options(&self) -> FileTypeOptions {
+        FileTypeOptions {
+            filter: self.line_filter(),
+            granularity: self.granularity,
+            skip_disabled_code: self.skip_disabled_code,
+            split_minified: self.split_minified,
+            functions: self.function_granularity,
+            keep_original: self.preserve_indentation,
+        }
+    }
+
     /// Compute a hash of config options that affect source line cleaning.
     /// Used for cache invalidation - if this changes, cached lines are invalid.
     pub fn cleaning_config_hash(&self) -> u64 {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        // Only min_chars affects the cleaning process
         self.min_chars.hash(&mut hasher);
+        self.granularity.hash(&mut hasher);
+        self.skip_disabled_code.hash(&mut hasher);
+        self.split_minified.hash(&mut hasher);
+        self.keep_structural_lines.hash(&mut hasher);
+        self.include_nonalphabetic.hash(&mut hasher);
+        self.normalize_string_literals.hash(&mut hasher);
+        self.hash_algorithm.hash(&mut hasher);
+        self.ignore_line_patterns.hash(&mut hasher);
+        self.ignore_imports.hash(&mut hasher);
+        self.ignore_annotations.hash(&mut hasher);
+        self.ignore_signatures.hash(&mut hasher);
+        self.extension_map.hash(&mut hasher);
         hasher.finish()
     }
 
     /// Compute a hash of config options that affect duplicate detection.
     /// Used for baseline comparison - warns if detection parameters differ.
+    ///
+    /// Includes every cleaning option, since those decide which lines exist
+    /// and so the block hashes stored in a baseline.
     pub fn detection_config_hash(&self) -> u64 {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        self.min_chars.hash(&mut hasher);
+        self.cleaning_config_hash().hash(&mut hasher);
         self.min_block_size.hash(&mut hasher);
         self.block_percent_threshold.hash(&mut hasher);
+        self.max_gap.hash(&mut hasher);
         self.ignore_same_filename.hash(&mut hasher);
+        self.ignore_same_filename_fuzzy.hash(&mut hasher);
+        self.cross_language.hash(&mut hasher);
         hasher.finish()
     }
 }
@@ -152,11 +791,15 @@ mod tests {
 
     #[test]
     fn test_cleaning_config_hash_changes_with_min_chars() {
-        let mut config1 = Config::default();
-        config1.min_chars = 3;
+        let config1 = Config {
+            min_chars: 3,
+            ..Default::default()
+        };
 
-        let mut config2 = Config::default();
-        config2.min_chars = 5;
+        let config2 = Config {
+            min_chars: 5,
+            ..Default::default()
+        };
 
         assert_ne!(
             config1.cleaning_config_hash(),
@@ -164,13 +807,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cleaning_config_hash_changes_with_granularity() {
+        let config1 = Config::default();
+        let config2 = Config {
+            granularity: Granularity::Statement,
+            ..Default::default()
+        };
+
+        assert_ne!(
+            config1.cleaning_config_hash(),
+            config2.cleaning_config_hash()
+        );
+    }
+
+    #[test]
+    fn test_cleaning_config_hash_changes_with_ignore_line_patterns() {
+        let config1 = Config::default();
+        let config2 = Config {
+            ignore_line_patterns: vec!["^return;?$".to_string()],
+            ..Default::default()
+        };
+
+        assert_ne!(
+            config1.cleaning_config_hash(),
+            config2.cleaning_config_hash()
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_ignore_line_pattern() {
+        let config = Config {
+            ignore_line_patterns: vec!["(unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(DuploError::InvalidConfig(msg)) if msg.contains("--ignore-line-pattern")
+        ));
+    }
+
     #[test]
     fn test_cleaning_config_hash_unchanged_by_min_block_size() {
-        let mut config1 = Config::default();
-        config1.min_block_size = 4;
+        let config1 = Config {
+            min_block_size: 4,
+            ..Default::default()
+        };
 
-        let mut config2 = Config::default();
-        config2.min_block_size = 10;
+        let config2 = Config {
+            min_block_size: 10,
+            ..Default::default()
+        };
 
         // min_block_size doesn't affect cleaning, only detection
         assert_eq!(
@@ -191,11 +878,15 @@ mod tests {
 
     #[test]
     fn test_detection_config_hash_changes_with_min_block_size() {
-        let mut config1 = Config::default();
-        config1.min_block_size = 4;
+        let config1 = Config {
+            min_block_size: 4,
+            ..Default::default()
+        };
 
-        let mut config2 = Config::default();
-        config2.min_block_size = 10;
+        let config2 = Config {
+            min_block_size: 10,
+            ..Default::default()
+        };
 
         assert_ne!(
             config1.detection_config_hash(),
@@ -205,15 +896,210 @@ mod tests {
 
     #[test]
     fn test_detection_config_hash_changes_with_threshold() {
-        let mut config1 = Config::default();
-        config1.block_percent_threshold = 100;
+        let config1 = Config {
+            block_percent_threshold: 100,
+            ..Default::default()
+        };
+
+        let config2 = Config {
+            block_percent_threshold: 50,
+            ..Default::default()
+        };
+
+        assert_ne!(
+            config1.detection_config_hash(),
+            config2.detection_config_hash()
+        );
+    }
 
-        let mut config2 = Config::default();
-        config2.block_percent_threshold = 50;
+    #[test]
+    fn test_detection_config_hash_changes_with_hash_algorithm() {
+        let config1 = Config::default();
+        let config2 = Config {
+            hash_algorithm: HashAlgorithm::Xxhash,
+            ..Default::default()
+        };
 
         assert_ne!(
             config1.detection_config_hash(),
             config2.detection_config_hash()
         );
     }
+
+    #[test]
+    fn test_detection_config_hash_changes_with_line_shaping_options() {
+        let default_hash = Config::default().detection_config_hash();
+        let variants = [
+            Config {
+                normalize_string_literals: true,
+                ..Default::default()
+            },
+            Config {
+                ignore_line_patterns: vec!["^#".to_string()],
+                ..Default::default()
+            },
+            Config {
+                ignore_imports: false,
+                ..Default::default()
+            },
+            Config {
+                ignore_annotations: false,
+                ..Default::default()
+            },
+            Config {
+                ignore_signatures: false,
+                ..Default::default()
+            },
+            Config {
+                keep_structural_lines: true,
+                ..Default::default()
+            },
+            Config {
+                include_nonalphabetic: true,
+                ..Default::default()
+            },
+            Config {
+                granularity: Granularity::Statement,
+                ..Default::default()
+            },
+        ];
+
+        for config in variants {
+            assert_ne!(config.detection_config_hash(), default_hash);
+        }
+    }
+
+    fn validation_message(config: Config) -> String {
+        match config.validate() {
+            Err(DuploError::InvalidConfig(msg)) => msg,
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_default_ok() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_percent_out_of_range() {
+        let config = Config {
+            block_percent_threshold: 101,
+            ..Default::default()
+        };
+        assert_eq!(
+            validation_message(config),
+            "--percent must be between 0 and 100 (got 101)"
+        );
+    }
+
+    #[test]
+    fn test_validate_zero_min_lines() {
+        let config = Config {
+            min_block_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(validation_message(config), "--min-lines must be at least 1");
+    }
+
+    #[test]
+    fn test_validate_zero_threads() {
+        let config = Config {
+            num_threads: 0,
+            ..Default::default()
+        };
+        assert_eq!(validation_message(config), "--threads must be at least 1");
+    }
+
+    #[test]
+    fn test_validate_changed_only_without_git() {
+        let config = Config {
+            changed_only: true,
+            git_mode: false,
+            ..Default::default()
+        };
+        assert_eq!(validation_message(config), "--changed-only requires --git");
+    }
+
+    #[test]
+    fn test_validate_missing_baseline() {
+        let config = Config {
+            baseline_path: Some(PathBuf::from("/nonexistent/baseline.json")),
+            ..Default::default()
+        };
+        assert_eq!(
+            validation_message(config),
+            "baseline file '/nonexistent/baseline.json' does not exist \
+             (create one with --save-baseline)"
+        );
+    }
+
+    #[test]
+    fn test_threads_from_env() {
+        assert_eq!(threads_from_env(Some("3")), 3);
+
+        // Unset, zero or garbage fall back to available parallelism
+        let available = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        assert_eq!(threads_from_env(None), available);
+        assert_eq!(threads_from_env(Some("0")), available);
+        assert_eq!(threads_from_env(Some("many")), available);
+    }
+
+    #[test]
+    fn test_validate_fail_on_new_without_changed_set() {
+        let config = Config {
+            git_mode: true,
+            fail_on_new: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            validation_message(config),
+            "--fail-on-new requires --changed-only or --since"
+        );
+    }
+
+    #[test]
+    fn test_config_file_file_types() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"[file_types]\n\".INO\" = \"c\"\npde = \"java\"\n",
+        )
+        .unwrap();
+
+        let config_file = ConfigFile::load(file.path()).unwrap();
+        let map = config_file.extension_map();
+        assert_eq!(map.get("ino").map(String::as_str), Some("c"));
+        assert_eq!(map.get("pde").map(String::as_str), Some("java"));
+    }
+
+    #[test]
+    fn test_config_file_generated_markers() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"generated_markers = [\"Generated by MyTool\"]\n",
+        )
+        .unwrap();
+
+        let config_file = ConfigFile::load(file.path()).unwrap();
+        assert_eq!(
+            config_file.generated_markers,
+            Some(vec!["Generated by MyTool".to_string()])
+        );
+        assert!(ConfigFile::default().generated_markers.is_none());
+    }
+
+    #[test]
+    fn test_config_file_rejects_unknown_target() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"[file_types]\nino = \"arduino\"\n").unwrap();
+
+        match ConfigFile::load(file.path()) {
+            Err(DuploError::InvalidConfig(msg)) => assert!(msg.contains("'arduino'")),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+    }
 }
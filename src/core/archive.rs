@@ -0,0 +1,191 @@
+//! Reading source files from `.tar`/`.tar.gz` archives without extraction
+//!
+//! Entries with supported source extensions are read into memory and
+//! processed via `SourceFile::from_source`. Each entry is reported as
+//! `<archive>!/<entry path>`.
+
+use crate::config::Config;
+use crate::core::generated::has_generated_marker;
+use crate::core::{SourceFile, Warning, WarningKind};
+use crate::error::{DuploError, Result};
+use crate::git::is_discoverable;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+/// Check if a path names a supported archive
+pub fn is_archive(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".tar") || lower.ends_with(".tar.gz") || lower.ends_with(".tgz")
+}
+
+/// Load all supported source entries from an archive
+///
+/// Entries that are over `--max-file-bytes` or can't be read are skipped
+/// and reported in the returned warnings.
+pub fn load_archive(path: &str, config: &Config) -> Result<(Vec<SourceFile>, Vec<Warning>)> {
+    let file = File::open(path).map_err(|e| DuploError::FileNotFound {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let lower = path.to_lowercase();
+    let reader: Box<dyn Read> = if lower.ends_with(".tar") {
+        Box::new(BufReader::new(file))
+    } else {
+        Box::new(GzDecoder::new(BufReader::new(file)))
+    };
+
+    let archive_error = |e: std::io::Error| DuploError::FileNotFound {
+        path: path.to_string(),
+        reason: format!("Failed to read archive: {}", e),
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    let mut source_files = Vec::new();
+    let mut warnings = Vec::new();
+    let options = config.file_type_options();
+
+    for entry in archive.entries().map_err(archive_error)? {
+        let mut entry = entry.map_err(archive_error)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let entry_path = entry.path().map_err(archive_error)?;
+        let entry_name = entry_path.to_string_lossy().to_string();
+        if !is_discoverable(&entry_name, config) {
+            continue;
+        }
+        let name = format!("{}!/{}", path, entry_name);
+
+        // Checked before reading, like files on disk
+        if let Some(limit) = config.max_file_bytes {
+            let size = entry.header().size().map_err(archive_error)?;
+            if size > limit {
+                warnings.push(
+                    Warning::new(
+                        WarningKind::FileTooLarge,
+                        format!(
+                            "Skipping '{}': {} bytes exceeds --max-file-bytes {}",
+                            name, size, limit
+                        ),
+                    )
+                    .with_path(&name),
+                );
+                continue;
+            }
+        }
+
+        let mut content = String::new();
+        if let Err(e) = entry.read_to_string(&mut content) {
+            warnings.push(
+                Warning::new(
+                    WarningKind::FileUnreadable,
+                    format!("Skipping '{}': {}", name, e),
+                )
+                .with_path(&name),
+            );
+            continue;
+        }
+
+        if config.ignore_generated
+            && has_generated_marker(content.lines(), &config.generated_markers)
+        {
+            continue;
+        }
+
+        source_files.push(SourceFile::from_source(
+            name,
+            &content,
+            options.clone(),
+            &config.extension_map,
+        ));
+    }
+
+    Ok((source_files, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::process_files_with_list;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    fn append_file(builder: &mut tar::Builder<GzEncoder<File>>, name: &str, content: &str) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, content.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive("vendor.tar"));
+        assert!(is_archive("vendor.tar.gz"));
+        assert!(is_archive("VENDOR.TGZ"));
+        assert!(!is_archive("main.c"));
+        assert!(!is_archive("data.gz"));
+    }
+
+    #[test]
+    fn test_detects_clone_inside_tarball() {
+        let temp = TempDir::new().unwrap();
+        let archive_path = temp.path().join("snapshot.tar.gz");
+
+        let code = "int alpha = 1;\nint beta = 2;\nint gamma = 3;\nint delta = 4;\n";
+        let file = File::create(&archive_path).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        append_file(&mut builder, "src/a.c", code);
+        append_file(&mut builder, "src/b.c", code);
+        append_file(&mut builder, "README.md", "not source code at all\n");
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let archive = archive_path.to_string_lossy().to_string();
+        let config = Config::default();
+        let (result, source_files) =
+            process_files_with_list(std::slice::from_ref(&archive), &config, |_| {}).unwrap();
+
+        assert_eq!(result.files_analyzed, 2);
+        assert_eq!(result.duplicate_blocks, 1);
+        assert_eq!(source_files[0].filename(), format!("{}!/src/a.c", archive));
+    }
+
+    #[test]
+    fn test_entries_over_max_file_bytes_skipped() {
+        let temp = TempDir::new().unwrap();
+        let archive_path = temp.path().join("snapshot.tar.gz");
+
+        let code = "int alpha = 1;\nint beta = 2;\nint gamma = 3;\nint delta = 4;\n";
+        let file = File::create(&archive_path).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        append_file(&mut builder, "src/a.c", code);
+        append_file(&mut builder, "src/big.c", &code.repeat(100));
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let archive = archive_path.to_string_lossy().to_string();
+        let config = Config {
+            max_file_bytes: Some(1000),
+            ..Default::default()
+        };
+        // The compressed archive itself is under the limit
+        assert!(std::fs::metadata(&archive_path).unwrap().len() < 1000);
+
+        let (result, source_files) =
+            process_files_with_list(std::slice::from_ref(&archive), &config, |_| {}).unwrap();
+
+        assert_eq!(source_files.len(), 1);
+        assert_eq!(source_files[0].filename(), format!("{}!/src/a.c", archive));
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].kind, WarningKind::FileTooLarge);
+        assert_eq!(
+            result.warnings[0].path.as_deref(),
+            Some(format!("{}!/src/big.c", archive).as_str())
+        );
+    }
+}
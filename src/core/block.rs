@@ -1,7 +1,22 @@
 //! Duplicate block representation
 
+use super::SourceFile;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// Whether the two copies of a block are textually identical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    /// Every line of one copy is byte-equal to its counterpart
+    Exact,
+    /// Some lines only match by hash: they differ in whitespace, collide,
+    /// or were bridged as gaps
+    Approximate,
+}
+
 /// Represents a detected duplicate code block between two files
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[allow(dead_code)]
 pub struct Block {
     /// Index of the first source file in the file list
@@ -14,6 +29,8 @@ pub struct Block {
     pub line2: usize,
     /// Number of consecutive matching lines
     pub count: usize,
+    /// Non-matching lines bridged inside the block (see `--max-gap`), included in `count`
+    pub gaps: usize,
 }
 
 #[allow(dead_code)]
@@ -32,9 +49,16 @@ impl Block {
             line1,
             line2,
             count,
+            gaps: 0,
         }
     }
 
+    /// Record the number of non-matching lines bridged inside the block
+    pub fn with_gaps(mut self, gaps: usize) -> Self {
+        self.gaps = gaps;
+        self
+    }
+
     /// Check if this is a self-duplicate (within the same file)
     pub fn is_self_duplicate(&self) -> bool {
         self.source1_idx == self.source2_idx
@@ -49,6 +73,42 @@ impl Block {
     pub fn end2(&self) -> usize {
         self.line2 + self.count
     }
+
+    /// Compare the cleaned text of both copies line by line
+    ///
+    /// Detection only compares hashes, which ignore whitespace, so this is
+    /// where a block learns whether its copies are really the same text.
+    pub fn match_kind(&self, source_files: &[SourceFile]) -> MatchKind {
+        let lines1 = &source_files[self.source1_idx].lines_slice()[self.line1..self.end1()];
+        let lines2 = &source_files[self.source2_idx].lines_slice()[self.line2..self.end2()];
+        if self.gaps == 0 && lines1.iter().zip(lines2).all(|(a, b)| a.line() == b.line()) {
+            MatchKind::Exact
+        } else {
+            MatchKind::Approximate
+        }
+    }
+}
+
+/// Canonical ordering: largest blocks first, then by position
+/// (`source1_idx`, `source2_idx`, `line1`, `line2`), with `gaps` as the last
+/// tiebreaker so the order agrees with `Eq`
+impl Ord for Block {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .count
+            .cmp(&self.count)
+            .then(self.source1_idx.cmp(&other.source1_idx))
+            .then(self.source2_idx.cmp(&other.source2_idx))
+            .then(self.line1.cmp(&other.line1))
+            .then(self.line2.cmp(&other.line2))
+            .then(self.gaps.cmp(&other.gaps))
+    }
+}
+
+impl PartialOrd for Block {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[cfg(test)]
@@ -74,10 +134,124 @@ mod tests {
         assert!(!cross_dup.is_self_duplicate());
     }
 
+    #[test]
+    fn test_match_kind() {
+        use crate::core::SourceLine;
+
+        let file = |name: &str, lines: &[&str]| {
+            SourceFile::from_lines(
+                name.to_string(),
+                lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, l)| SourceLine::new(l.to_string(), i + 1))
+                    .collect(),
+            )
+        };
+        let files = vec![
+            file("a.c", &["int a = 1;", "int b = 2;", "return a + b;"]),
+            file("b.c", &["int a = 1;", "int b = 2;", "return a + b;"]),
+            // Same hashes, as whitespace is ignored, but different text
+            file("c.c", &["int a = 1;", "int b=2;", "return a + b;"]),
+        ];
+        assert_eq!(files[0].get_line(1), files[2].get_line(1));
+
+        assert_eq!(
+            Block::new(0, 1, 0, 0, 3).match_kind(&files),
+            MatchKind::Exact
+        );
+        assert_eq!(
+            Block::new(0, 2, 0, 0, 3).match_kind(&files),
+            MatchKind::Approximate
+        );
+
+        // Two hash-colliding lines with different text
+        let collision = SourceFile::from_lines(
+            "d.c".to_string(),
+            vec![
+                SourceLine::new("int a = 1;".to_string(), 1),
+                SourceLine::from_cached(
+                    "x();".to_string(),
+                    2,
+                    files[0].get_line(1).hash(),
+                    files[0].get_line(1).hash64(),
+                ),
+                SourceLine::new("return a + b;".to_string(), 3),
+            ],
+        );
+        let files = vec![
+            file("a.c", &["int a = 1;", "int b = 2;", "return a + b;"]),
+            collision,
+        ];
+        assert_eq!(
+            Block::new(0, 1, 0, 0, 3).match_kind(&files),
+            MatchKind::Approximate
+        );
+        // Bridged gaps never count as exact
+        assert_eq!(
+            Block::new(0, 0, 0, 0, 3).with_gaps(1).match_kind(&files),
+            MatchKind::Approximate
+        );
+    }
+
     #[test]
     fn test_end_indices() {
         let block = Block::new(0, 1, 10, 20, 5);
         assert_eq!(block.end1(), 15);
         assert_eq!(block.end2(), 25);
     }
+
+    #[test]
+    fn test_ordering_largest_first() {
+        let mut blocks = [
+            Block::new(0, 1, 0, 0, 4),
+            Block::new(2, 3, 5, 5, 9),
+            Block::new(0, 1, 8, 8, 6),
+        ];
+        blocks.sort();
+        let counts: Vec<usize> = blocks.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![9, 6, 4]);
+    }
+
+    #[test]
+    fn test_ordering_equal_sizes_by_position() {
+        let mut blocks = vec![
+            Block::new(1, 2, 0, 0, 5),
+            Block::new(0, 2, 7, 3, 5),
+            Block::new(0, 1, 4, 9, 5),
+            Block::new(0, 1, 4, 2, 5),
+            Block::new(0, 1, 2, 6, 5),
+        ];
+        blocks.sort();
+        let positions: Vec<(usize, usize, usize, usize)> = blocks
+            .iter()
+            .map(|b| (b.source1_idx, b.source2_idx, b.line1, b.line2))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (0, 1, 2, 6),
+                (0, 1, 4, 2),
+                (0, 1, 4, 9),
+                (0, 2, 7, 3),
+                (1, 2, 0, 0)
+            ]
+        );
+
+        // Sorting any permutation yields the same order
+        let mut reversed: Vec<Block> = blocks.iter().rev().cloned().collect();
+        reversed.sort();
+        assert_eq!(reversed, blocks);
+    }
+
+    #[test]
+    fn test_ordering_consistent_with_eq() {
+        let plain = Block::new(0, 1, 0, 0, 5);
+        let gapped = Block::new(0, 1, 0, 0, 5).with_gaps(1);
+
+        assert_eq!(plain.cmp(&plain.clone()), Ordering::Equal);
+        assert_ne!(plain, gapped);
+        assert_ne!(plain.cmp(&gapped), Ordering::Equal);
+        assert_eq!(plain.cmp(&gapped), gapped.cmp(&plain).reverse());
+    }
 }
@@ -0,0 +1,116 @@
+//! Detection of generated source files by their header comments
+//!
+//! Code generators conventionally stamp a marker such as `DO NOT EDIT` near
+//! the top of their output. Only the first few lines are scanned, so a
+//! marker mentioned deep inside hand-written code doesn't exclude the file.
+
+use super::source_file::open_file;
+use std::io::BufRead;
+
+/// Number of leading lines scanned for a generator marker
+pub const GENERATED_SCAN_LINES: usize = 20;
+
+/// Markers used when the config file doesn't override them
+pub const DEFAULT_GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "DO NOT EDIT",
+    "Code generated by",
+    "autogenerated",
+];
+
+/// Check whether any of the leading lines contains a marker (case-insensitive)
+pub fn has_generated_marker<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    markers: &[String],
+) -> bool {
+    let markers: Vec<String> = markers.iter().map(|m| m.to_lowercase()).collect();
+    lines
+        .into_iter()
+        .take(GENERATED_SCAN_LINES)
+        .map(|line| line.to_lowercase())
+        .any(|line| markers.iter().any(|m| line.contains(m.as_str())))
+}
+
+/// Check whether the file at `path` looks generated
+///
+/// The file is read like any other source (`.gz` files decompressed with
+/// the `gzip` feature). Unreadable files are reported as not generated,
+/// leaving the error to the regular loading path.
+pub fn is_generated_file(path: &str, markers: &[String]) -> bool {
+    let Ok(reader) = open_file(path) else {
+        return false;
+    };
+    let head: Vec<String> = reader
+        .lines()
+        .take(GENERATED_SCAN_LINES)
+        .map_while(|l| l.ok())
+        .collect();
+    has_generated_marker(head.iter().map(String::as_str), markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_markers() -> Vec<String> {
+        DEFAULT_GENERATED_MARKERS
+            .iter()
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_markers() {
+        let markers = default_markers();
+        assert!(has_generated_marker(
+            ["// Code generated by protoc. DO NOT EDIT."],
+            &markers
+        ));
+        assert!(has_generated_marker(
+            ["package api;", "/* This file is AUTOGENERATED */"],
+            &markers
+        ));
+        assert!(!has_generated_marker(
+            ["int main() {", "    return 0;", "}"],
+            &markers
+        ));
+    }
+
+    #[test]
+    fn test_marker_past_scan_window_ignored() {
+        let mut lines = vec!["int x = 1;"; GENERATED_SCAN_LINES];
+        lines.push("// @generated");
+        assert!(!has_generated_marker(lines, &default_markers()));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_generated_gzip_file_detected() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("api.pb.c.gz");
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            Compression::default(),
+        );
+        encoder
+            .write_all(b"// Code generated by protoc. DO NOT EDIT.\nint x = 1;\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        assert!(is_generated_file(
+            path.to_str().unwrap(),
+            &default_markers()
+        ));
+    }
+
+    #[test]
+    fn test_custom_markers() {
+        let markers = vec!["Generated by MyTool".to_string()];
+        assert!(has_generated_marker(["# generated by mytool v2"], &markers));
+        assert!(!has_generated_marker(["// DO NOT EDIT"], &markers));
+    }
+}
@@ -0,0 +1,93 @@
+//! Transparent reading of gzip-compressed file lists and sources
+//!
+//! A `.gz` suffix is all that marks a compressed file. Sources are typed by
+//! the name inside it, so `util.c.gz` is cleaned as C.
+
+use flate2::read::GzDecoder;
+use std::fs::File;
+
+/// Suffix of gzip-compressed files
+const GZIP_SUFFIX: &str = ".gz";
+
+/// Check if a path names a gzip-compressed file
+pub fn is_gzip(path: &str) -> bool {
+    path.to_lowercase().ends_with(GZIP_SUFFIX)
+}
+
+/// `path` without its `.gz` suffix, if it has one
+pub fn inner_name(path: &str) -> Option<&str> {
+    is_gzip(path).then(|| &path[..path.len() - GZIP_SUFFIX.len()])
+}
+
+/// Reader decompressing `file` as it is read
+pub fn decoder(file: File) -> GzDecoder<File> {
+    GzDecoder::new(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::core::source_file::open_file;
+    use crate::core::{load_file_list, process_files_with_list};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{BufRead, Write};
+    use std::path::Path;
+
+    fn write_gzip(path: &Path, content: &str) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_inner_name() {
+        assert_eq!(inner_name("src/util.c.gz"), Some("src/util.c"));
+        assert_eq!(inner_name("FILES.TXT.GZ"), Some("FILES.TXT"));
+        assert_eq!(inner_name("src/util.c"), None);
+    }
+
+    #[test]
+    fn test_open_file_decompresses_only_gz() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let plain = temp.path().join("a.txt");
+        std::fs::write(&plain, "first\nsecond\n").unwrap();
+        let compressed = temp.path().join("a.txt.gz");
+        write_gzip(&compressed, "first\nsecond\n");
+
+        for path in [plain, compressed] {
+            let lines: Vec<String> = open_file(path.to_str().unwrap())
+                .unwrap()
+                .lines()
+                .collect::<std::io::Result<_>>()
+                .unwrap();
+            assert_eq!(lines, vec!["first", "second"]);
+        }
+    }
+
+    #[test]
+    fn test_gzipped_file_list_and_source() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int alpha = 1;\nint beta = 2;\n// typed as C, so dropped\nint gamma = 3;\nint delta = 4;\n";
+        let compressed = temp.path().join("a.c.gz");
+        write_gzip(&compressed, code);
+        let plain = temp.path().join("b.c");
+        std::fs::write(&plain, code.replace("typed as C", "also C")).unwrap();
+        let list = temp.path().join("files.txt.gz");
+        write_gzip(
+            &list,
+            &format!("{}\n{}\n", compressed.display(), plain.display()),
+        );
+
+        let files = load_file_list(list.to_str().unwrap()).unwrap();
+        assert_eq!(files.len(), 2);
+        let (result, source_files) =
+            process_files_with_list(&files, &Config::default(), |_| {}).unwrap();
+
+        assert_eq!(source_files[0].language(), "C/C++");
+        assert_eq!(source_files[0].num_lines(), 4);
+        assert_eq!(result.duplicate_blocks, 1);
+        assert_eq!(result.blocks[0].count, 4);
+    }
+}
@@ -2,7 +2,11 @@
 //!
 //! This module implements the Fowler-Noll-Vo hash function (FNV-1a variant)
 //! with the same parameters as the original C++ Duplo implementation to ensure
-//! hash compatibility.
+//! hash compatibility. xxHash and plain 64-bit FNV-1a can be selected instead
+//! with `--hash`.
+
+use crate::config::HashAlgorithm;
+use xxhash_rust::{xxh32::xxh32, xxh64::xxh64};
 
 /// FNV-1a offset basis (32-bit)
 const FNV_OFFSET_BASIS: u32 = 2_166_136_261;
@@ -10,6 +14,12 @@ const FNV_OFFSET_BASIS: u32 = 2_166_136_261;
 /// FNV-1a prime (32-bit)
 const FNV_PRIME: u32 = 16_777_619;
 
+/// FNV-1a offset basis (64-bit)
+const FNV64_OFFSET_BASIS: u64 = 14_695_981_039_346_656_037;
+
+/// FNV-1a prime (64-bit)
+const FNV64_PRIME: u64 = 1_099_511_628_211;
+
 /// Compute FNV-1a hash for a byte slice
 ///
 /// This implementation matches the C++ Duplo HashUtil::Hash function exactly.
@@ -29,6 +39,19 @@ pub fn fnv1a_hash(data: &[u8]) -> u32 {
     hash
 }
 
+/// Compute 64-bit FNV-1a hash for a byte slice
+///
+/// Used as a secondary hash to rule out 32-bit collisions.
+#[inline]
+pub fn fnv1a_hash64(data: &[u8]) -> u64 {
+    let mut hash = FNV64_OFFSET_BASIS;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV64_PRIME);
+    }
+    hash
+}
+
 /// Compute hash for a source line with whitespace normalization
 ///
 /// This function filters out whitespace and control characters before hashing,
@@ -44,6 +67,28 @@ pub fn hash_line(line: &str) -> u32 {
     fnv1a_hash(&clean)
 }
 
+/// Compute the secondary 64-bit hash for a source line
+///
+/// Uses the same whitespace normalization as `hash_line`.
+pub fn hash_line64(line: &str) -> u64 {
+    let clean: Vec<u8> = line.bytes().filter(|&b| b > b' ').collect();
+    fnv1a_hash64(&clean)
+}
+
+/// Compute the primary and secondary hashes of a source line with the given
+/// algorithm, using the same whitespace normalization as `hash_line`
+pub fn hash_line_with(line: &str, algorithm: HashAlgorithm) -> (u32, u64) {
+    let clean: Vec<u8> = line.bytes().filter(|&b| b > b' ').collect();
+    match algorithm {
+        HashAlgorithm::Fnv1a => (fnv1a_hash(&clean), fnv1a_hash64(&clean)),
+        HashAlgorithm::Xxhash => (xxh32(&clean, 0), xxh64(&clean, 0)),
+        HashAlgorithm::Fnv64 => {
+            let hash = fnv1a_hash64(&clean);
+            ((hash ^ (hash >> 32)) as u32, hash)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +130,15 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn test_hash_line64() {
+        assert_eq!(fnv1a_hash64(&[]), FNV64_OFFSET_BASIS);
+        // Known FNV-1a 64-bit test vector
+        assert_eq!(fnv1a_hash64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash_line64("int x = 5;"), hash_line64("  int  x=5;"));
+        assert_ne!(hash_line64("int x = 5;"), hash_line64("int y = 5;"));
+    }
+
     #[test]
     fn test_hash_line_empty() {
         // Empty line (all whitespace) should hash to offset basis
@@ -92,4 +146,41 @@ mod tests {
         assert_eq!(hash_line("   "), FNV_OFFSET_BASIS);
         assert_eq!(hash_line("\t\t"), FNV_OFFSET_BASIS);
     }
+
+    #[test]
+    fn test_hash_line_with_algorithms() {
+        let algorithms = [
+            HashAlgorithm::Fnv1a,
+            HashAlgorithm::Xxhash,
+            HashAlgorithm::Fnv64,
+        ];
+
+        // The default matches the C++ Duplo compatible hashes
+        assert_eq!(
+            hash_line_with("int x = 5;", HashAlgorithm::Fnv1a),
+            (hash_line("int x = 5;"), hash_line64("int x = 5;"))
+        );
+        // Known XXH32 / XXH64 test vectors (seed 0)
+        assert_eq!(
+            hash_line_with("", HashAlgorithm::Xxhash),
+            (0x02cc_5d05, 0xef46_db37_51d8_e999)
+        );
+
+        for algorithm in algorithms {
+            let hash = hash_line_with("int x = 5;", algorithm);
+            // Consistent, and still blind to whitespace
+            assert_eq!(hash, hash_line_with("int x = 5;", algorithm));
+            assert_eq!(hash, hash_line_with("  int  x\t=5;", algorithm));
+            assert_ne!(hash, hash_line_with("int y = 5;", algorithm));
+        }
+
+        // Each algorithm fingerprints lines differently
+        let primaries: Vec<u32> = algorithms
+            .iter()
+            .map(|&a| hash_line_with("int x = 5;", a).0)
+            .collect();
+        assert_ne!(primaries[0], primaries[1]);
+        assert_ne!(primaries[0], primaries[2]);
+        assert_ne!(primaries[1], primaries[2]);
+    }
 }
@@ -0,0 +1,73 @@
+//! Sub-line tokenization of minified sources
+//!
+//! Minified JS/CSS packs a whole file into a handful of enormous lines, so a
+//! shared chunk shows up as a single matching line and never reaches the
+//! minimum block size. Splitting such lines after statement separators turns
+//! the chunk back into a run of comparable lines.
+
+/// A line at least this long marks a file as minified
+pub const MINIFIED_LINE_LEN: usize = 500;
+
+/// Files with more lines than this are never treated as minified
+pub const MINIFIED_MAX_LINES: usize = 10;
+
+/// Characters after which a minified line is split
+const STATEMENT_SEPARATORS: &[char] = &[';', '{', '}'];
+
+/// Check whether the raw lines look minified: very few lines, one very long
+pub fn is_minified(raw_lines: &[String]) -> bool {
+    raw_lines.len() <= MINIFIED_MAX_LINES && raw_lines.iter().any(|l| l.len() >= MINIFIED_LINE_LEN)
+}
+
+/// Split each line after statement separators
+///
+/// Returns the pieces and, for each piece, the 1-indexed line it came from.
+pub fn split_statements(raw_lines: &[String]) -> (Vec<String>, Vec<usize>) {
+    let mut pieces = Vec::new();
+    let mut origins = Vec::new();
+
+    for (line_num, line) in raw_lines.iter().enumerate() {
+        let mut rest = line.as_str();
+        while let Some(pos) = rest.find(STATEMENT_SEPARATORS) {
+            let (piece, tail) = rest.split_at(pos + 1);
+            pieces.push(piece.to_string());
+            origins.push(line_num + 1);
+            rest = tail;
+        }
+        if !rest.trim().is_empty() {
+            pieces.push(rest.to_string());
+            origins.push(line_num + 1);
+        }
+    }
+
+    (pieces, origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_minified() {
+        let long = "a=1;".repeat(200);
+        assert!(is_minified(std::slice::from_ref(&long)));
+        assert!(!is_minified(&["int x = 1;".to_string()]));
+
+        let many: Vec<String> = (0..=MINIFIED_MAX_LINES).map(|_| long.clone()).collect();
+        assert!(!is_minified(&many));
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let lines = vec![
+            "function f(){var a=1;return a}g();".to_string(),
+            "tail".to_string(),
+        ];
+        let (pieces, origins) = split_statements(&lines);
+        assert_eq!(
+            pieces,
+            vec!["function f(){", "var a=1;", "return a}", "g();", "tail"]
+        );
+        assert_eq!(origins, vec![1, 1, 1, 1, 2]);
+    }
+}
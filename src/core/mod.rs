@@ -1,19 +1,30 @@
 //! Core data structures and algorithms for duplicate detection
 
+#[cfg(feature = "archives")]
+pub mod archive;
 pub mod block;
+pub mod generated;
+#[cfg(feature = "gzip")]
+pub mod gzip;
 pub mod hash;
+pub mod minified;
 pub mod processor;
 pub mod source_file;
 pub mod source_line;
+pub mod stats;
+pub mod warning;
 
-pub use block::Block;
+pub use block::{Block, MatchKind};
 // hash_line is used in tests
 #[allow(unused_imports)]
 pub use hash::hash_line;
 // Keep all processor functions in public API even if not all are used in main
 #[allow(unused_imports)]
 pub use processor::{
-    load_file_list, process_files, process_files_with_cache, process_files_with_list, DuploResult,
+    compare_source_files, compare_two_files, detect_streaming, file_list_iter, load_file_list,
+    process_files, process_files_with_cache, process_files_with_list, process_source_files,
+    warm_cache, DuploResult,
 };
 pub use source_file::SourceFile;
-pub use source_line::SourceLine;
+pub use source_line::{LineInterner, SourceLine};
+pub use warning::{Warning, WarningKind};
@@ -3,9 +3,12 @@
 //! This module implements the LCS-based matrix algorithm for detecting
 //! code duplicates, ported from the C++ Duplo implementation.
 
-use crate::cache::FileCache;
-use crate::config::Config;
-use crate::core::{Block, SourceFile};
+use crate::cache::{FileCache, PairCache, PairKey};
+use crate::config::{Config, CrossLanguage, FileSelection};
+use crate::core::generated::is_generated_file;
+use crate::core::hash::fnv1a_hash64;
+use crate::core::source_file::open_file;
+use crate::core::{Block, LineInterner, SourceFile, Warning, WarningKind};
 
 #[cfg(test)]
 use crate::core::SourceLine;
@@ -13,16 +16,28 @@ use crate::error::{DuploError, Result};
 use bitvec::prelude::*;
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// Thread pools kept alive for `Config::reuse_thread_pool`, by thread count
+static SHARED_POOLS: OnceLock<Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>> = OnceLock::new();
 
 /// Result of duplicate detection
 #[derive(Debug)]
 pub struct DuploResult {
     /// All detected duplicate blocks
     pub blocks: Vec<Block>,
+    /// Number of files in the input list
+    pub files_provided: usize,
     /// Total number of files analyzed
     pub files_analyzed: usize,
+    /// Files dropped because they had no analyzable lines (e.g. comment-only)
+    pub skipped_files: Vec<String>,
+    /// Non-fatal problems encountered along the way
+    pub warnings: Vec<Warning>,
     /// Total lines of code analyzed
     pub total_lines: usize,
     /// Total duplicate lines found
@@ -31,19 +46,100 @@ pub struct DuploResult {
     pub duplicate_blocks: usize,
 }
 
+impl DuploResult {
+    /// Create a result holding `blocks`, counting the files they name and
+    /// twice their lines as analyzed (for testing)
+    #[cfg(test)]
+    pub fn from_blocks(blocks: Vec<Block>) -> Self {
+        let duplicate_lines = blocks.iter().map(|b| b.count).sum();
+        let files = blocks
+            .iter()
+            .map(|b| b.source1_idx.max(b.source2_idx) + 1)
+            .max()
+            .unwrap_or(0);
+        Self {
+            duplicate_blocks: blocks.len(),
+            blocks,
+            files_provided: files,
+            files_analyzed: files,
+            skipped_files: Vec::new(),
+            warnings: Vec::new(),
+            total_lines: 2 * duplicate_lines,
+            duplicate_lines,
+        }
+    }
+
+    /// Whether detection was interrupted before every file was compared
+    pub fn is_partial(&self) -> bool {
+        self.warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Interrupted)
+    }
+
+    /// Keep only the blocks matching `keep`, updating the duplicate counts
+    ///
+    /// Every filter applied after detection goes through here, so the
+    /// summary always agrees with the blocks that are left.
+    pub fn retain_blocks(&mut self, mut keep: impl FnMut(&Block) -> bool) {
+        self.blocks.retain(|b| keep(b));
+        self.duplicate_blocks = self.blocks.len();
+        self.duplicate_lines = self.blocks.iter().map(|b| b.count).sum();
+    }
+
+    /// Check that every block lies within the files of `source_files`
+    ///
+    /// Filtering drops blocks but never touches the files, so the indices
+    /// found by detection stay valid. This catches a step that breaks that,
+    /// with an error instead of a panic in whatever indexes the files next.
+    pub fn check_indices(&self, source_files: &[SourceFile]) -> Result<()> {
+        for block in &self.blocks {
+            let sides = [
+                (block.source1_idx, block.line1),
+                (block.source2_idx, block.line2),
+            ];
+            for (idx, start) in sides {
+                let Some(file) = source_files.get(idx) else {
+                    return Err(DuploError::InconsistentResult(format!(
+                        "a duplicate refers to file #{} of {} loaded files",
+                        idx,
+                        source_files.len()
+                    )));
+                };
+                if block.count == 0 || start + block.count > file.num_lines() {
+                    return Err(DuploError::InconsistentResult(format!(
+                        "a duplicate spans lines {}..{} of '{}', which has {} lines",
+                        start,
+                        start + block.count,
+                        file.filename(),
+                        file.num_lines()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Maps line hashes to file indices that contain that line
 type HashToFiles = HashMap<u32, Vec<usize>>;
 
+/// Maps the line hashes of a function body to the (file index, first line)
+/// of each function with that body
+type FunctionBodies = HashMap<Vec<(u32, u64)>, Vec<(usize, usize)>>;
+
 /// Thread-local context for processing
 struct ThreadContext {
     /// Reusable boolean matrix for line comparison
     matrix: BitVec,
+    /// Matrix cells computed by this thread since the last flush
+    cells: u64,
 }
 
 impl ThreadContext {
-    fn new(max_lines: usize) -> Self {
+    fn new(max_cells: usize) -> Self {
         Self {
-            matrix: bitvec![0; max_lines * max_lines],
+            matrix: bitvec![0; max_cells],
+            cells: 0,
         }
     }
 
@@ -52,26 +148,112 @@ impl ThreadContext {
         // Only clear the portion we'll use
         let size = m * n;
         self.matrix[..size].fill(false);
+        self.cells += size as u64;
+    }
+}
+
+/// Counters for `--algorithm-stats`, shared by the worker threads
+#[derive(Default)]
+struct AlgorithmStats {
+    /// Distinct file pairs considered (self-comparisons excluded)
+    candidate_pairs: AtomicUsize,
+    /// Pairs that were compared (including pair cache hits)
+    pairs_compared: AtomicUsize,
+    /// Pairs skipped because the hash index found no shared line
+    skipped_no_shared_lines: AtomicUsize,
+    /// Pairs skipped by --ignore-same-filename
+    skipped_same_name: AtomicUsize,
+    /// Pairs skipped for being different languages
+    skipped_cross_language: AtomicUsize,
+    /// Pairs of two unchanged files skipped by --changed-scope changed
+    skipped_unchanged: AtomicUsize,
+    /// Comparison matrix cells computed
+    matrix_cells: AtomicU64,
+}
+
+impl AlgorithmStats {
+    fn add(counter: &AtomicUsize, n: usize) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Report the counters through `progress`
+    fn report(&self, detection_ms: u128, progress: &impl Fn(&str)) {
+        let get = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        progress("Algorithm stats:");
+        progress(&format!(
+            "  Candidate pairs: {}",
+            get(&self.candidate_pairs)
+        ));
+        progress(&format!("  Pairs compared: {}", get(&self.pairs_compared)));
+        progress(&format!(
+            "  Pairs skipped (no shared lines): {}",
+            get(&self.skipped_no_shared_lines)
+        ));
+        progress(&format!(
+            "  Pairs skipped (same filename): {}",
+            get(&self.skipped_same_name)
+        ));
+        progress(&format!(
+            "  Pairs skipped (cross-language): {}",
+            get(&self.skipped_cross_language)
+        ));
+        progress(&format!(
+            "  Pairs skipped (both unchanged): {}",
+            get(&self.skipped_unchanged)
+        ));
+        progress(&format!(
+            "  Matrix cells computed: {}",
+            self.matrix_cells.load(Ordering::Relaxed)
+        ));
+        progress(&format!("  Detection time: {} ms", detection_ms));
     }
 }
 
 /// Load file list from path (or stdin if "-")
 pub fn load_file_list(path: &str) -> Result<Vec<String>> {
-    let lines = if path == "-" {
-        let stdin = std::io::stdin();
-        stdin.lock().lines().collect::<std::io::Result<Vec<_>>>()?
+    file_list_iter(path).collect()
+}
+
+/// Stream a file list from path (or stdin if "-") one entry at a time
+///
+/// Applies the same filtering as `load_file_list`. If the list can't be
+/// opened, the iterator yields that error as its only item.
+pub fn file_list_iter(path: &str) -> impl Iterator<Item = Result<String>> {
+    let reader: Result<Box<dyn BufRead>> = if path == "-" {
+        Ok(Box::new(BufReader::new(std::io::stdin())))
     } else {
-        let file = File::open(path).map_err(|e| DuploError::FileNotFound {
+        open_file(path).map_err(|e| DuploError::FileNotFound {
             path: path.to_string(),
             reason: e.to_string(),
-        })?;
-        BufReader::new(file)
-            .lines()
-            .collect::<std::io::Result<Vec<_>>>()?
+        })
+    };
+
+    let (reader, open_error) = match reader {
+        Ok(reader) => (Some(reader), None),
+        Err(e) => (None, Some(e)),
     };
 
-    // Filter out short lines and whitespace-only lines
-    Ok(lines.into_iter().filter(|l| l.trim().len() > 5).collect())
+    open_error.into_iter().map(Err).chain(
+        reader
+            .into_iter()
+            .flat_map(|r| r.lines())
+            .map(|line| line.map_err(DuploError::from))
+            // Filter out short lines and whitespace-only lines
+            .filter(|line| line.as_ref().map_or(true, |l| l.trim().len() > 5)),
+    )
+}
+
+/// Source files loaded from a file list, ready for detection
+struct LoadedFiles {
+    source_files: Vec<SourceFile>,
+    /// Matrix cells needed for the largest pair of distinct files
+    max_cells: usize,
+    /// Number of entries in the file list
+    files_provided: usize,
+    /// Files dropped for having no analyzable lines
+    skipped_files: Vec<String>,
+    /// Files that could not be loaded or cached, and why
+    warnings: Vec<Warning>,
 }
 
 /// Load all source files from the file list (without caching)
@@ -80,58 +262,156 @@ fn load_source_files(
     file_list: &[String],
     config: &Config,
     progress: &impl Fn(&str),
-) -> Result<(Vec<SourceFile>, usize)> {
-    load_source_files_with_cache(file_list, config, None, progress)
+) -> Result<LoadedFiles> {
+    load_source_files_with_cache(file_list.iter().cloned().map(Ok), config, None, progress)
 }
 
-/// Load all source files from the file list with optional caching
+/// Load all source files from a (possibly streamed) file list with optional caching
 fn load_source_files_with_cache(
-    file_list: &[String],
+    file_list: impl IntoIterator<Item = Result<String>>,
     config: &Config,
     cache: Option<&FileCache>,
     progress: &impl Fn(&str),
-) -> Result<(Vec<SourceFile>, usize)> {
+) -> Result<LoadedFiles> {
     let mut source_files = Vec::new();
-    let mut max_lines = 0usize;
+    let mut skipped_files = Vec::new();
+    let mut warnings = Vec::new();
+    let mut warn = |warning: Warning| {
+        progress(&format!("Warning: {}", warning));
+        warnings.push(warning);
+    };
     let mut cache_hits = 0usize;
+    let mut generated_skipped = 0usize;
+    let mut duplicate_entries = 0usize;
+    let mut files_provided = 0usize;
+    // Canonical paths already listed, so a file listed twice (e.g. in
+    // concatenated lists) isn't compared against itself as two files
+    let mut seen = HashSet::new();
+    let options = config.file_type_options();
+    // Each file is interned as it is loaded, so its own copies of shared
+    // lines are freed right away
+    let mut interner = config.intern_lines.then(LineInterner::default);
+    let mut keep = |mut sf: SourceFile, source_files: &mut Vec<SourceFile>| {
+        if let Some(interner) = interner.as_mut() {
+            sf.intern_lines(interner);
+        }
+        source_files.push(sf);
+    };
+
+    for entry in file_list {
+        let path = &entry?;
+        files_provided += 1;
+
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        if !seen.insert(canonical) {
+            duplicate_entries += 1;
+            continue;
+        }
+
+        // Check the size before reading so huge files can't exhaust memory
+        if let Some(limit) = config.max_file_bytes {
+            if let Ok(metadata) = std::fs::metadata(path) {
+                if metadata.len() > limit {
+                    warn(
+                        Warning::new(
+                            WarningKind::FileTooLarge,
+                            format!(
+                                "Skipping '{}': {} bytes exceeds --max-file-bytes {}",
+                                path,
+                                metadata.len(),
+                                limit
+                            ),
+                        )
+                        .with_path(path),
+                    );
+                    continue;
+                }
+            }
+        }
 
-    for path in file_list {
-        // Try to load from cache first
-        if let Some(cache) = cache {
+        // Read archive entries in memory (not cached)
+        #[cfg(feature = "archives")]
+        if super::archive::is_archive(path) {
+            match super::archive::load_archive(path, config) {
+                Ok((files, entry_warnings)) => {
+                    entry_warnings.into_iter().for_each(&mut warn);
+                    for sf in files {
+                        if sf.num_lines() > 0 {
+                            keep(sf, &mut source_files);
+                        } else {
+                            skipped_files.push(sf.filename().to_string());
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn(Warning::new(WarningKind::FileUnreadable, e.to_string()).with_path(path))
+                }
+            }
+            continue;
+        }
+
+        // Skip generated files before touching the cache
+        if config.ignore_generated && is_generated_file(path, &config.generated_markers) {
+            generated_skipped += 1;
+            continue;
+        }
+
+        // Try to load from cache first (cached lines carry no original text,
+        // which --preserve-indentation snippets need)
+        if let Some(cache) = cache.filter(|_| !config.preserve_indentation) {
             if let Some(lines) = cache.get(path) {
-                let sf = SourceFile::from_cached_lines(path.clone(), lines);
+                let sf = SourceFile::from_cached_lines(path.clone(), lines, &config.extension_map);
                 let num_lines = sf.num_lines();
                 if num_lines > 0 {
-                    max_lines = max_lines.max(num_lines);
-                    source_files.push(sf);
+                    keep(sf, &mut source_files);
                     cache_hits += 1;
+                } else {
+                    skipped_files.push(path.clone());
                 }
                 continue;
             }
         }
 
         // Load from disk
-        match SourceFile::load(path, config.min_chars) {
+        match SourceFile::load(path, options.clone(), &config.extension_map) {
             Ok(sf) => {
                 let num_lines = sf.num_lines();
                 if num_lines > 0 {
                     // Save to cache if enabled
                     if let Some(cache) = cache {
                         if let Err(e) = cache.put(path, sf.lines_slice()) {
-                            progress(&format!("Warning: Failed to cache '{}': {}", path, e));
+                            warn(
+                                Warning::new(
+                                    WarningKind::Cache,
+                                    format!("Failed to cache '{}': {}", path, e),
+                                )
+                                .with_path(path),
+                            );
                         }
                     }
-                    max_lines = max_lines.max(num_lines);
-                    source_files.push(sf);
+                    keep(sf, &mut source_files);
+                } else {
+                    skipped_files.push(path.clone());
                 }
             }
             Err(e) => {
-                // Log warning but continue
-                progress(&format!("Warning: {}", e));
+                // Record the warning but continue
+                warn(Warning::new(WarningKind::FileUnreadable, e.to_string()).with_path(path));
             }
         }
     }
 
+    if generated_skipped > 0 {
+        progress(&format!("Skipped {} generated files", generated_skipped));
+    }
+
+    if duplicate_entries > 0 {
+        progress(&format!(
+            "Skipped {} duplicate file list entries",
+            duplicate_entries
+        ));
+    }
+
     if cache.is_some() && cache_hits > 0 {
         progress(&format!(
             "Cache: {} hits, {} misses",
@@ -140,25 +420,78 @@ fn load_source_files_with_cache(
         ));
     }
 
-    // Validate memory requirements
+    let max_cells = check_matrix_size(&source_files, config)?;
+
+    Ok(LoadedFiles {
+        source_files,
+        max_cells,
+        files_provided,
+        skipped_files,
+        warnings,
+    })
+}
+
+/// Validate memory requirements of the per-thread comparison matrix,
+/// returning the number of cells each thread needs
+///
+/// Only pairs of distinct files use the matrix (see `self_blocks`), so the
+/// largest one is the two longest files, and a single huge file on its own
+/// never trips the limit.
+fn check_matrix_size(source_files: &[SourceFile], config: &Config) -> Result<usize> {
     // Limit to ~1GB of matrix memory per thread (8 billion bits = 1GB)
     const MAX_BITS_PER_THREAD: usize = 8_000_000_000;
     let max_matrix_size = MAX_BITS_PER_THREAD;
-    let required_size = max_lines * max_lines;
-    if required_size > max_matrix_size {
-        // Find the longest files for the error message
-        let mut sorted: Vec<_> = source_files.iter().collect();
-        sorted.sort_by_key(|f| std::cmp::Reverse(f.num_lines()));
 
+    // Find the longest files, for the size and the error message
+    let mut sorted: Vec<_> = source_files.iter().collect();
+    sorted.sort_by_key(|f| std::cmp::Reverse(f.num_lines()));
+    let required_size = match sorted[..] {
+        [first, second, ..] => first.num_lines() * second.num_lines(),
+        _ => 0,
+    };
+    if required_size > max_matrix_size {
         return Err(DuploError::FileTooLarge {
             path: sorted[0].filename().to_string(),
-            lines: max_lines,
+            lines: sorted[0].num_lines(),
             threads: config.num_threads,
             max_lines: ((max_matrix_size / config.num_threads) as f64).sqrt() as usize,
         });
     }
 
-    Ok((source_files, max_lines))
+    Ok(required_size)
+}
+
+/// Indices, in ascending order, of the `count` files checked against every
+/// other file
+fn select_files(
+    source_files: &[SourceFile],
+    count: usize,
+    selection: FileSelection,
+    seed: u64,
+) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..source_files.len()).collect();
+    if count >= indices.len() {
+        return indices;
+    }
+
+    match selection {
+        FileSelection::First => {}
+        FileSelection::Largest => {
+            indices.sort_by_key(|&i| std::cmp::Reverse(source_files[i].num_lines()));
+        }
+        FileSelection::Random => {
+            // Ranked by an FNV-1a hash of the path, so the choice doesn't
+            // depend on list order or the build
+            indices.sort_by_cached_key(|&i| {
+                let mut bytes = seed.to_le_bytes().to_vec();
+                bytes.extend_from_slice(source_files[i].filename().as_bytes());
+                (fnv1a_hash64(&bytes), i)
+            });
+        }
+    }
+    indices.truncate(count);
+    indices.sort_unstable();
+    indices
 }
 
 /// Build hash-to-files index for optimization
@@ -187,18 +520,117 @@ fn get_matching_files(source_file: &SourceFile, hash_index: &HashToFiles) -> Has
     matching
 }
 
-/// Calculate the effective minimum block size
+/// Calculate the effective minimum block size for a pair of files
+///
+/// Below 100, the percent threshold raises the minimum to that percentage of
+/// the smaller file (rounded up), never lowering it below `min_block_size`.
 fn calc_min_block_size(config: &Config, m: usize, n: usize) -> usize {
-    let min_from_threshold = if config.block_percent_threshold > 0 {
-        (m.max(n) * 100) / config.block_percent_threshold as usize
-    } else {
-        0
-    };
+    let min_block_size = config.min_block_size as usize;
+    if config.block_percent_threshold >= 100 {
+        return min_block_size;
+    }
+
+    let from_threshold = (m.min(n) * config.block_percent_threshold as usize).div_ceil(100);
+    min_block_size.max(from_threshold)
+}
+
+/// Find runs of matching lines along one diagonal of the comparison matrix
+///
+/// Up to `max_gap` consecutive non-matching lines are bridged and counted
+/// toward the run. Returns `(offset, count, gaps)` for each run of at least
+/// `min_len` lines, where `gaps` is the number of bridged lines.
+fn diagonal_runs(
+    len: usize,
+    max_gap: usize,
+    min_len: usize,
+    is_match: impl Fn(usize) -> bool,
+) -> Vec<(usize, usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start: Option<usize> = None;
+    // One past the last matching line of the current run
+    let mut end = 0usize;
+    let mut gaps = 0usize;
+
+    for k in 0..len {
+        if is_match(k) {
+            match start {
+                Some(_) => gaps += k - end,
+                None => {
+                    start = Some(k);
+                    gaps = 0;
+                }
+            }
+            end = k + 1;
+        } else if let Some(s) = start {
+            // Too many consecutive mismatches: close the run at its last match
+            if k + 1 - end > max_gap {
+                if end - s >= min_len {
+                    runs.push((s, end - s, gaps));
+                }
+                start = None;
+            }
+        }
+    }
+
+    if let Some(s) = start {
+        if end - s >= min_len {
+            runs.push((s, end - s, gaps));
+        }
+    }
 
-    (config.min_block_size as usize).max((config.min_block_size as usize).min(min_from_threshold))
+    runs
+}
+
+/// Whole functions with identical cleaned lines (`--function-granularity`)
+///
+/// Every pair of copies becomes one block spanning the whole function, as
+/// long as one of its files is checked. Functions shorter than the minimum
+/// block size are ignored, as are pairs the name and language rules skip.
+fn function_clones(
+    source_files: &[SourceFile],
+    is_checked: &[bool],
+    config: &Config,
+) -> Vec<Block> {
+    // Functions in file order, grouped by body
+    let mut bodies = FunctionBodies::new();
+    for (i, file) in source_files.iter().enumerate() {
+        for function in file.functions() {
+            if function.len() < config.min_block_size as usize {
+                continue;
+            }
+            let body = file.lines_slice()[function.clone()]
+                .iter()
+                .map(|l| (l.hash(), l.hash64()))
+                .collect();
+            bodies.entry(body).or_default().push((i, function.start));
+        }
+    }
+
+    let mut blocks = Vec::new();
+    for (body, copies) in &bodies {
+        for (n, &(i, line1)) in copies.iter().enumerate() {
+            for &(j, line2) in &copies[n + 1..] {
+                let (source1, source2) = (&source_files[i], &source_files[j]);
+                let skipped = !(is_checked[i] || is_checked[j])
+                    || (i != j
+                        && ((config.ignore_same_filename && source1.has_same_basename(source2))
+                            || (config.ignore_same_filename_fuzzy
+                                && source1.has_similar_basename(source2))
+                            || (config.cross_language == CrossLanguage::Forbid
+                                && source1.language() != source2.language())));
+                if !skipped {
+                    blocks.push(Block::new(i, j, line1, line2, body.len()));
+                }
+            }
+        }
+    }
+    blocks
 }
 
 /// Process a pair of files and find duplicates
+///
+/// A file compared with itself (equal indices) goes to `self_blocks`, which
+/// needs no matrix.
 fn process_file_pair(
     source1: &SourceFile,
     source2: &SourceFile,
@@ -207,6 +639,10 @@ fn process_file_pair(
     config: &Config,
     context: &mut ThreadContext,
 ) -> Vec<Block> {
+    if source1_idx == source2_idx {
+        return self_blocks(source1, source1_idx, config);
+    }
+
     let m = source1.num_lines();
     let n = source2.num_lines();
 
@@ -229,111 +665,217 @@ fn process_file_pair(
     let min_block_size = calc_min_block_size(config, m, n);
     let mut blocks = Vec::new();
 
-    let is_same_file = source1_idx == source2_idx;
-
     // Vertical diagonal scan
     for y in 0..m {
-        let mut seq_len = 0usize;
-        let max_x = n.min(m - y);
+        let len = n.min(m - y);
+        let runs = diagonal_runs(len, config.max_gap, min_block_size, |k| {
+            context.matrix[k + n * (y + k)]
+        });
+        for (offset, count, gaps) in runs {
+            blocks.push(
+                Block::new(source1_idx, source2_idx, y + offset, offset, count).with_gaps(gaps),
+            );
+        }
+    }
 
-        for x in 0..max_x {
-            if context.matrix[x + n * (y + x)] {
-                seq_len += 1;
-            } else {
-                if seq_len >= min_block_size {
-                    let line1 = y + x - seq_len;
-                    let line2 = x - seq_len;
-                    // For self-comparison, only report if positions differ
-                    if !is_same_file || line1 != line2 {
-                        blocks.push(Block::new(source1_idx, source2_idx, line1, line2, seq_len));
-                    }
+    // Horizontal diagonal scan
+    for x in 1..n {
+        let len = m.min(n - x);
+        let runs = diagonal_runs(len, config.max_gap, min_block_size, |k| {
+            context.matrix[x + k + n * k]
+        });
+        for (offset, count, gaps) in runs {
+            blocks.push(
+                Block::new(source1_idx, source2_idx, offset, x + offset, count).with_gaps(gaps),
+            );
+        }
+    }
+
+    blocks
+}
+
+/// Most line pairs `self_blocks` collects before it walks the diagonals
+/// instead (8 bytes each)
+const MAX_SELF_MATCHES: usize = 1 << 24;
+
+/// Find the blocks a file repeats within itself
+///
+/// Comparing a file with itself through the matrix takes `m * m` bits,
+/// which for one very long file is the largest allocation of a run. Here a
+/// hash index of the lines yields every pair of equal lines instead, as a
+/// match on the diagonal at their distance; only those diagonals are
+/// scanned. A file that repeats a few lines very often (`}`, `end`) has too
+/// many pairs for that, and every diagonal is walked comparing lines
+/// directly. Either way no memory grows with `m * m`.
+///
+/// The main diagonal matches every line, and the upper triangle mirrors the
+/// lower one, so only the diagonals below the main one are considered.
+fn self_blocks(source: &SourceFile, source_idx: usize, config: &Config) -> Vec<Block> {
+    let m = source.num_lines();
+    let min_block_size = calc_min_block_size(config, m, m);
+
+    // Positions of each distinct line
+    let mut positions: HashMap<(u32, u64), Vec<u32>> = HashMap::new();
+    for k in 0..m {
+        let line = source.get_line(k);
+        positions
+            .entry((line.hash(), line.hash64()))
+            .or_default()
+            .push(k as u32);
+    }
+    let pairs: usize = positions
+        .values()
+        .map(|p| p.len() * (p.len() - 1) / 2)
+        .sum();
+
+    let mut blocks = Vec::new();
+    let mut add_runs = |y: usize, runs: Vec<(usize, usize, usize)>| {
+        let is_match = |k: usize| source.get_line(y + k) == source.get_line(k);
+        for (offset, count, gaps) in runs {
+            // A run longer than its distance from the main diagonal overlaps
+            // its own copy (e.g. a stretch of repeated lines); keep only the
+            // part before the overlap
+            let (count, gaps) = if count > y {
+                let mut count = y;
+                while !is_match(offset + count - 1) {
+                    count -= 1;
                 }
-                seq_len = 0;
-            }
+                if count < min_block_size {
+                    continue;
+                }
+                let gaps = (offset..offset + count).filter(|&k| !is_match(k)).count();
+                (count, gaps)
+            } else {
+                (count, gaps)
+            };
+            blocks.push(
+                Block::new(source_idx, source_idx, y + offset, offset, count).with_gaps(gaps),
+            );
         }
+    };
 
-        // Check for sequence at end
-        if seq_len >= min_block_size {
-            let line1 = m - seq_len;
-            let line2 = n.min(m - y) - seq_len;
-            if !is_same_file || line1 != line2 {
-                blocks.push(Block::new(source1_idx, source2_idx, line1, line2, seq_len));
+    if pairs <= MAX_SELF_MATCHES && m <= u32::MAX as usize {
+        // (distance, position of the earlier line), sorted by diagonal
+        let mut matches: Vec<(u32, u32)> = Vec::with_capacity(pairs);
+        for p in positions.values() {
+            for (i, &a) in p.iter().enumerate() {
+                matches.extend(p[i + 1..].iter().map(|&b| (b - a, a)));
             }
         }
+        matches.sort_unstable();
+        for diagonal in matches.chunk_by(|a, b| a.0 == b.0) {
+            let offsets = diagonal.iter().map(|&(_, k)| k as usize);
+            add_runs(
+                diagonal[0].0 as usize,
+                sparse_runs(offsets, config.max_gap, min_block_size),
+            );
+        }
+    } else {
+        for y in 1..m {
+            add_runs(
+                y,
+                diagonal_runs(m - y, config.max_gap, min_block_size, |k| {
+                    source.get_line(y + k) == source.get_line(k)
+                }),
+            );
+        }
     }
+    blocks
+}
 
-    // Horizontal diagonal scan (only for different files)
-    if !is_same_file {
-        for x in 1..n {
-            let mut seq_len = 0usize;
-            let max_y = m.min(n - x);
+/// `diagonal_runs` for a diagonal given as the ascending offsets of its
+/// matches rather than a predicate over every cell
+fn sparse_runs(
+    offsets: impl IntoIterator<Item = usize>,
+    max_gap: usize,
+    min_len: usize,
+) -> Vec<(usize, usize, usize)> {
+    let mut runs = Vec::new();
+    // Start, one past the last match, and number of matches of the current run
+    let mut current: Option<(usize, usize, usize)> = None;
+    let close = |runs: &mut Vec<_>, (start, end, matched): (usize, usize, usize)| {
+        if end - start >= min_len {
+            runs.push((start, end - start, end - start - matched));
+        }
+    };
 
-            for y in 0..max_y {
-                if context.matrix[x + y + n * y] {
-                    seq_len += 1;
-                } else {
-                    if seq_len >= min_block_size {
-                        blocks.push(Block::new(
-                            source1_idx,
-                            source2_idx,
-                            y - seq_len,
-                            x + y - seq_len,
-                            seq_len,
-                        ));
-                    }
-                    seq_len = 0;
-                }
+    for k in offsets {
+        current = match current {
+            Some((start, end, matched)) if k - end <= max_gap => Some((start, k + 1, matched + 1)),
+            Some(run) => {
+                close(&mut runs, run);
+                Some((k, k + 1, 1))
             }
+            None => Some((k, k + 1, 1)),
+        };
+    }
+    if let Some(run) = current {
+        close(&mut runs, run);
+    }
 
-            if seq_len >= min_block_size {
-                blocks.push(Block::new(
-                    source1_idx,
-                    source2_idx,
-                    m - seq_len,
-                    n - seq_len,
-                    seq_len,
-                ));
-            }
-        }
+    runs
+}
+
+/// Compare files `i` and `j`, reusing cached blocks when both are unchanged
+fn compare_with_pair_cache(
+    source_files: &[SourceFile],
+    i: usize,
+    j: usize,
+    config: &Config,
+    context: &mut ThreadContext,
+    pair_cache: Option<(&PairCache, &[u64], u64)>,
+) -> Vec<Block> {
+    let Some((pair_cache, content_hashes, config_hash)) = pair_cache else {
+        return process_file_pair(&source_files[i], &source_files[j], i, j, config, context);
+    };
+
+    let key = PairKey {
+        content_hash1: content_hashes[i],
+        content_hash2: content_hashes[j],
+        config_hash,
+    };
+    if let Some(blocks) = pair_cache.get(&key, i, j) {
+        return blocks;
     }
 
+    let blocks = process_file_pair(&source_files[i], &source_files[j], i, j, config, context);
+    pair_cache.put(key, &blocks);
     blocks
 }
 
 /// Main entry point for processing files from a file list path.
 /// For git-based discovery, use `process_files_with_list` instead.
-#[allow(dead_code)]
 pub fn process_files(
     config: &Config,
     progress: impl Fn(&str) + Send + Sync,
 ) -> Result<(DuploResult, Vec<SourceFile>)> {
-    let file_list = match &config.list_filename {
-        Some(path) => load_file_list(path)?,
-        None => {
-            return Err(DuploError::InvalidConfig(
-                "No file list provided. Use --git or provide a file list.".to_string(),
-            ))
-        }
+    let Some(path) = &config.list_filename else {
+        return Err(DuploError::InvalidConfig(
+            "No file list provided. Use --git or provide a file list.".to_string(),
+        ));
     };
 
-    process_files_with_list(&file_list, config, progress)
+    // Stream the list rather than holding every entry in memory
+    process_files_with_cache(file_list_iter(path), config, None, progress)
 }
 
 /// Process files from a pre-resolved file list.
 /// This is the main processing function that handles duplicate detection.
-#[allow(dead_code)]
 pub fn process_files_with_list(
     file_list: &[String],
     config: &Config,
     progress: impl Fn(&str) + Send + Sync,
 ) -> Result<(DuploResult, Vec<SourceFile>)> {
-    process_files_with_cache(file_list, config, None, progress)
+    process_files_with_cache(file_list.iter().cloned().map(Ok), config, None, progress)
 }
 
-/// Process files from a pre-resolved file list with optional caching.
-/// This is the main processing function that handles duplicate detection.
+/// Process files from a file list with optional caching.
+///
+/// Entries are loaded as they arrive, so a list streamed with
+/// `file_list_iter` is never held in memory as a whole; an `Err` entry
+/// (e.g. an unreadable list) aborts loading.
 pub fn process_files_with_cache(
-    file_list: &[String],
+    file_list: impl IntoIterator<Item = Result<String>>,
     config: &Config,
     cache: Option<&FileCache>,
     progress: impl Fn(&str) + Send + Sync,
@@ -341,20 +883,121 @@ pub fn process_files_with_cache(
     progress("Loading and hashing files...");
 
     // Load source files (with optional cache)
-    let (source_files, max_lines) =
-        load_source_files_with_cache(file_list, config, cache, &progress)?;
+    let load_start = Instant::now();
+    let loaded = load_source_files_with_cache(file_list, config, cache, &progress)?;
+    if config.algorithm_stats {
+        progress(&format!(
+            "Load time: {} ms",
+            load_start.elapsed().as_millis()
+        ));
+    }
+    process_loaded(loaded, config, cache, &progress)
+}
+
+/// Load every listed file through the cache without comparing them
+///
+/// Pre-populates the cache (for --warm-cache) so a later run reads every
+/// unchanged file from it. Returns the number of files with analyzable lines.
+pub fn warm_cache(
+    file_list: impl IntoIterator<Item = Result<String>>,
+    config: &Config,
+    cache: &FileCache,
+    progress: impl Fn(&str) + Send + Sync,
+) -> Result<usize> {
+    progress("Loading and hashing files...");
+    let loaded = load_source_files_with_cache(file_list, config, Some(cache), &progress)?;
+    Ok(loaded.source_files.len())
+}
+
+/// Compare exactly two files with a single `process_file_pair` call
+///
+/// Skips the hash index and thread pool, and doesn't compare either file
+/// with itself.
+pub fn compare_two_files(
+    path1: &str,
+    path2: &str,
+    config: &Config,
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let options = config.file_type_options();
+    let load = |path: &str| SourceFile::load(path, options.clone(), &config.extension_map);
+    compare_source_files(load(path1)?, load(path2)?, config)
+}
+
+/// Compare two files already held in memory, like `compare_two_files`
+///
+/// Blocks refer to `first` as source 0 and `second` as source 1.
+pub fn compare_source_files(
+    first: SourceFile,
+    second: SourceFile,
+    config: &Config,
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let source_files = vec![first, second];
+    let skipped_files: Vec<String> = source_files
+        .iter()
+        .filter(|sf| sf.num_lines() == 0)
+        .map(|sf| sf.filename().to_string())
+        .collect();
+
+    let max_cells = check_matrix_size(&source_files, config)?;
+
+    let mut blocks = if config.function_granularity {
+        // Only the clones between the two files, as in line mode
+        function_clones(&source_files, &[true, true], config)
+            .into_iter()
+            .filter(|b| b.source1_idx != b.source2_idx)
+            .collect()
+    } else {
+        let mut context = ThreadContext::new(max_cells);
+        process_file_pair(
+            &source_files[0],
+            &source_files[1],
+            0,
+            1,
+            config,
+            &mut context,
+        )
+    };
+    dedup_blocks(&mut blocks);
+    blocks.sort_unstable();
+
+    let duplicate_lines: usize = blocks.iter().map(|b| b.count).sum();
+    let duplicate_blocks = blocks.len();
+    Ok((
+        DuploResult {
+            blocks,
+            files_provided: 2,
+            files_analyzed: 2 - skipped_files.len(),
+            skipped_files,
+            warnings: Vec::new(),
+            total_lines: source_files.iter().map(|f| f.num_lines()).sum(),
+            duplicate_lines,
+            duplicate_blocks,
+        },
+        source_files,
+    ))
+}
+
+/// Detect duplicates among the files loaded from a file list
+fn process_loaded(
+    loaded: LoadedFiles,
+    config: &Config,
+    cache: Option<&FileCache>,
+    progress: &(impl Fn(&str) + Sync),
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let LoadedFiles {
+        source_files,
+        max_cells,
+        files_provided,
+        skipped_files,
+        warnings,
+    } = loaded;
 
     if source_files.is_empty() {
-        return Ok((
-            DuploResult {
-                blocks: Vec::new(),
-                files_analyzed: 0,
-                total_lines: 0,
-                duplicate_lines: 0,
-                duplicate_blocks: 0,
-            },
-            source_files,
-        ));
+        let result = DuploResult {
+            warnings,
+            ..empty_result(files_provided, skipped_files)
+        };
+        return Ok((result, source_files));
     }
 
     progress(&format!(
@@ -363,63 +1006,84 @@ pub fn process_files_with_cache(
         source_files.iter().map(|f| f.num_lines()).sum::<usize>()
     ));
 
-    // Build hash index
-    let hash_index = build_hash_index(&source_files);
-
-    // Determine how many files to check
-    let files_to_check = config.effective_files_to_check().min(source_files.len());
+    let (result, source_files) = detect_all(
+        source_files,
+        max_cells,
+        files_provided,
+        skipped_files,
+        config,
+        cache,
+        progress,
+    )?;
+    Ok((DuploResult { warnings, ..result }, source_files))
+}
 
-    // Set up thread pool
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(config.num_threads)
-        .build()
-        .map_err(|e| DuploError::Other(format!("Failed to create thread pool: {}", e)))?;
-
-    // Process files in parallel
-    let results: Vec<Vec<Block>> = pool.install(|| {
-        (0..files_to_check)
-            .into_par_iter()
-            .map(|i| {
-                let source1 = &source_files[i];
-                let matching = get_matching_files(source1, &hash_index);
-                let mut context = ThreadContext::new(max_lines);
-                let mut all_blocks = Vec::new();
-
-                // Compare with self
-                let self_blocks = process_file_pair(source1, source1, i, i, config, &mut context);
-                all_blocks.extend(self_blocks);
-
-                // Compare with subsequent files
-                for (j, source2) in source_files.iter().enumerate().skip(i + 1) {
-                    // Skip if configured to ignore same filename
-                    if config.ignore_same_filename && source1.has_same_basename(source2) {
-                        continue;
-                    }
+/// Detect duplicates among files already held in memory (e.g. read from a git ref)
+pub fn process_source_files(
+    source_files: Vec<SourceFile>,
+    config: &Config,
+    progress: impl Fn(&str) + Send + Sync,
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let files_provided = source_files.len();
+    let (source_files, skipped): (Vec<_>, Vec<_>) =
+        source_files.into_iter().partition(|sf| sf.num_lines() > 0);
+    let skipped_files: Vec<String> = skipped.iter().map(|sf| sf.filename().to_string()).collect();
 
-                    // Skip if no matching lines
-                    if !matching.contains(&j) {
-                        continue;
-                    }
+    if source_files.is_empty() {
+        return Ok((empty_result(files_provided, skipped_files), source_files));
+    }
 
-                    let blocks = process_file_pair(source1, source2, i, j, config, &mut context);
-                    all_blocks.extend(blocks);
-                }
+    let max_cells = check_matrix_size(&source_files, config)?;
 
-                all_blocks
-            })
-            .collect()
-    });
+    detect_all(
+        source_files,
+        max_cells,
+        files_provided,
+        skipped_files,
+        config,
+        None,
+        &progress,
+    )
+}
 
-    // Aggregate results
-    let all_blocks: Vec<Block> = results.into_iter().flatten().collect();
+/// Run detection over loaded files and aggregate the blocks into a result
+fn detect_all(
+    source_files: Vec<SourceFile>,
+    max_cells: usize,
+    files_provided: usize,
+    skipped_files: Vec<String>,
+    config: &Config,
+    cache: Option<&FileCache>,
+    progress: &(impl Fn(&str) + Sync),
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let mut all_blocks = Vec::new();
+    let files_to_check = detect_blocks(
+        &source_files,
+        max_cells,
+        config,
+        cache,
+        progress,
+        |blocks| all_blocks.extend(blocks),
+    )?;
+
+    // Aggregate results in the canonical block order so output doesn't depend on num_threads
+    all_blocks.sort_unstable();
     let duplicate_lines: usize = all_blocks.iter().map(|b| b.count).sum();
     let duplicate_blocks = all_blocks.len();
     let total_lines: usize = source_files.iter().map(|f| f.num_lines()).sum();
 
+    let mut warnings = Vec::new();
+    if config.is_cancelled() {
+        warnings.push(interrupted_warning(duplicate_blocks));
+    }
+
     Ok((
         DuploResult {
             blocks: all_blocks,
+            files_provided,
             files_analyzed: files_to_check,
+            skipped_files,
+            warnings,
             total_lines,
             duplicate_lines,
             duplicate_blocks,
@@ -428,23 +1092,337 @@ pub fn process_files_with_cache(
     ))
 }
 
+/// Detect duplicates, invoking `on_block` for each block as file pairs
+/// complete instead of buffering them all.
+///
+/// Blocks arrive in completion order, not the canonical order of
+/// `process_files_with_cache`. The returned result carries the aggregate
+/// counts but an empty `blocks` list.
+#[allow(dead_code)]
+pub fn detect_streaming(
+    config: &Config,
+    file_list: &[String],
+    mut on_block: impl FnMut(&Block),
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let LoadedFiles {
+        source_files,
+        max_cells,
+        files_provided,
+        skipped_files,
+        mut warnings,
+    } = load_source_files_with_cache(
+        file_list.iter().cloned().map(Ok),
+        config,
+        None,
+        &|_: &str| {},
+    )?;
+
+    if source_files.is_empty() {
+        let result = DuploResult {
+            warnings,
+            ..empty_result(files_provided, skipped_files)
+        };
+        return Ok((result, source_files));
+    }
+
+    let mut duplicate_lines = 0usize;
+    let mut duplicate_blocks = 0usize;
+    let files_to_check = detect_blocks(
+        &source_files,
+        max_cells,
+        config,
+        None,
+        &|_: &str| {},
+        |blocks| {
+            for block in &blocks {
+                duplicate_lines += block.count;
+                duplicate_blocks += 1;
+                on_block(block);
+            }
+        },
+    )?;
+    let total_lines: usize = source_files.iter().map(|f| f.num_lines()).sum();
+
+    if config.is_cancelled() {
+        warnings.push(interrupted_warning(duplicate_blocks));
+    }
+
+    Ok((
+        DuploResult {
+            blocks: Vec::new(),
+            files_provided,
+            files_analyzed: files_to_check,
+            skipped_files,
+            warnings,
+            total_lines,
+            duplicate_lines,
+            duplicate_blocks,
+        },
+        source_files,
+    ))
+}
+
+/// Warning recording that detection stopped early with `found` blocks
+fn interrupted_warning(found: usize) -> Warning {
+    Warning::new(
+        WarningKind::Interrupted,
+        format!(
+            "Interrupted before all files were compared; reporting the {} blocks found so far",
+            found
+        ),
+    )
+}
+
+/// Result for a file list with nothing to analyze
+fn empty_result(files_provided: usize, skipped_files: Vec<String>) -> DuploResult {
+    DuploResult {
+        blocks: Vec::new(),
+        files_provided,
+        files_analyzed: 0,
+        skipped_files,
+        warnings: Vec::new(),
+        total_lines: 0,
+        duplicate_lines: 0,
+        duplicate_blocks: 0,
+    }
+}
+
+/// Thread pool for a detection run
+///
+/// A fresh pool is built unless `reuse_thread_pool` is set, in which case
+/// the first pool built for the thread count is kept and handed to every
+/// later run, so repeated detections don't pay for spawning workers.
+fn thread_pool(config: &Config) -> Result<Arc<rayon::ThreadPool>> {
+    let build = || {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(config.num_threads)
+            .build()
+            .map(Arc::new)
+            .map_err(|e| DuploError::Other(format!("Failed to create thread pool: {}", e)))
+    };
+    if !config.reuse_thread_pool {
+        return build();
+    }
+
+    let mut pools = SHARED_POOLS.get_or_init(Default::default).lock().unwrap();
+    if let Some(pool) = pools.get(&config.num_threads) {
+        return Ok(Arc::clone(pool));
+    }
+    let pool = build()?;
+    pools.insert(config.num_threads, Arc::clone(&pool));
+    Ok(pool)
+}
+
+/// Drop repeated blocks, keeping the first occurrence of each
+///
+/// The scans can report the same run more than once (e.g. a self-comparison
+/// and a diagonal both reaching it), which would double-count the summary.
+fn dedup_blocks(blocks: &mut Vec<Block>) {
+    let mut seen = HashSet::with_capacity(blocks.len());
+    blocks.retain(|b| seen.insert(b.clone()));
+}
+
+/// Compare all file pairs in parallel, handing the blocks found for each
+/// file to `on_blocks` on the calling thread as soon as it completes.
+///
+/// Returns the number of files checked.
+fn detect_blocks(
+    source_files: &[SourceFile],
+    max_cells: usize,
+    config: &Config,
+    cache: Option<&FileCache>,
+    progress: &(impl Fn(&str) + Sync),
+    mut on_blocks: impl FnMut(Vec<Block>),
+) -> Result<usize> {
+    // Determine which files to check
+    let checked = select_files(
+        source_files,
+        config.effective_files_to_check(),
+        config.file_selection,
+        config.selection_seed,
+    );
+    let files_to_check = checked.len();
+    let mut is_checked = vec![false; source_files.len()];
+    for &i in &checked {
+        is_checked[i] = true;
+    }
+
+    if config.function_granularity {
+        on_blocks(function_clones(source_files, &is_checked, config));
+        return Ok(files_to_check);
+    }
+
+    // Build hash index
+    let hash_index = build_hash_index(source_files);
+
+    // Files whose pairs are worth comparing; a block between two others
+    // would only be filtered out later
+    let is_focus: Vec<bool> = match config.focus_files {
+        Some(ref focus) => source_files
+            .iter()
+            .map(|f| focus.contains(f.filename()))
+            .collect(),
+        None => vec![true; source_files.len()],
+    };
+
+    // Content hashes identify unchanged pairs in the pair cache
+    let pair_cache = cache.and_then(|c| c.pair_cache());
+    let detection_hash = config.detection_config_hash();
+    let content_hashes: Vec<u64> = if pair_cache.is_some() {
+        source_files.iter().map(|f| f.content_hash()).collect()
+    } else {
+        Vec::new()
+    };
+    let compare = |i: usize, j: usize, context: &mut ThreadContext| {
+        compare_with_pair_cache(
+            source_files,
+            i,
+            j,
+            config,
+            context,
+            pair_cache.map(|pc| (pc, &content_hashes[..], detection_hash)),
+        )
+    };
+
+    let stats = AlgorithmStats::default();
+    let detection_start = Instant::now();
+
+    // Set up thread pool
+    progress(&format!("Using {} threads", config.num_threads));
+    let pool = thread_pool(config)?;
+
+    // Workers send each file's blocks to the calling thread; the bounded
+    // channel keeps them from running far ahead of a slow consumer
+    let (tx, rx) = std::sync::mpsc::sync_channel::<Vec<Block>>(config.num_threads);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            pool.install(|| {
+                checked.into_par_iter().for_each_with(tx, |tx, i| {
+                    // Once cancelled, queued files are drained without work
+                    if config.is_cancelled() {
+                        return;
+                    }
+                    let source1 = &source_files[i];
+                    let matching = get_matching_files(source1, &hash_index);
+                    let mut context = ThreadContext::new(max_cells);
+                    let mut all_blocks = Vec::new();
+
+                    // Compare with self
+                    if is_focus[i] {
+                        all_blocks.extend(compare(i, i, &mut context));
+                    }
+
+                    // Compare with the files not checked in a batch of
+                    // their own: every later file, and unchecked earlier ones
+                    let others = source_files
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j > i || (j < i && !is_checked[j]));
+                    for (j, source2) in others {
+                        if config.is_cancelled() {
+                            break;
+                        }
+                        AlgorithmStats::add(&stats.candidate_pairs, 1);
+                        if !is_focus[i] && !is_focus[j] {
+                            AlgorithmStats::add(&stats.skipped_unchanged, 1);
+                            continue;
+                        }
+                        // Skip if configured to ignore same filename
+                        if (config.ignore_same_filename && source1.has_same_basename(source2))
+                            || (config.ignore_same_filename_fuzzy
+                                && source1.has_similar_basename(source2))
+                        {
+                            AlgorithmStats::add(&stats.skipped_same_name, 1);
+                            continue;
+                        }
+
+                        // Skip pairs of different languages unless allowed
+                        if config.cross_language == CrossLanguage::Forbid
+                            && source1.language() != source2.language()
+                        {
+                            AlgorithmStats::add(&stats.skipped_cross_language, 1);
+                            continue;
+                        }
+
+                        // Skip if no matching lines
+                        if !matching.contains(&j) {
+                            AlgorithmStats::add(&stats.skipped_no_shared_lines, 1);
+                            continue;
+                        }
+
+                        AlgorithmStats::add(&stats.pairs_compared, 1);
+                        // Blocks keep the lower file index first
+                        let blocks = compare(i.min(j), i.max(j), &mut context);
+                        all_blocks.extend(blocks);
+                    }
+                    stats
+                        .matrix_cells
+                        .fetch_add(context.cells, Ordering::Relaxed);
+
+                    // Every block of a pair is found in this batch, so
+                    // repeats can be dropped before they are counted
+                    dedup_blocks(&mut all_blocks);
+
+                    // The receiver only goes away if the consumer panicked
+                    let _ = tx.send(all_blocks);
+                })
+            });
+        });
+
+        for blocks in rx {
+            on_blocks(blocks);
+        }
+    });
+
+    if let Some(pair_cache) = pair_cache {
+        progress(&format!("Pair cache: {} hits", pair_cache.hits()));
+        if let Err(e) = pair_cache.save() {
+            progress(&format!("Warning: Failed to save pair cache: {}", e));
+        }
+    }
+
+    if config.algorithm_stats {
+        stats.report(detection_start.elapsed().as_millis(), progress);
+    }
+
+    Ok(files_to_check)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::AtomicBool;
 
     #[test]
     fn test_calc_min_block_size() {
-        let mut config = Config::default();
-        config.min_block_size = 4;
-        config.block_percent_threshold = 100;
+        let mut config = Config {
+            min_block_size: 4,
+            block_percent_threshold: 100,
+            ..Default::default()
+        };
 
         // With 100% threshold, should just return min_block_size
         assert_eq!(calc_min_block_size(&config, 100, 100), 4);
+        assert_eq!(calc_min_block_size(&config, 10, 1000), 4);
 
-        // With lower threshold, might return larger value
+        // A lower threshold raises the minimum relative to the smaller file
         config.block_percent_threshold = 10;
-        let result = calc_min_block_size(&config, 100, 100);
-        assert!(result >= 4);
+        assert_eq!(calc_min_block_size(&config, 100, 100), 10);
+        assert_eq!(calc_min_block_size(&config, 1000, 200), 20);
+        assert_eq!(calc_min_block_size(&config, 200, 1000), 20);
+        // Rounded up
+        assert_eq!(calc_min_block_size(&config, 95, 95), 10);
+
+        config.block_percent_threshold = 50;
+        assert_eq!(calc_min_block_size(&config, 30, 40), 15);
+
+        // Never below min_block_size for small files
+        assert_eq!(calc_min_block_size(&config, 6, 6), 4);
+
+        config.block_percent_threshold = 0;
+        assert_eq!(calc_min_block_size(&config, 100, 100), 4);
     }
 
     #[test]
@@ -482,10 +1460,12 @@ mod tests {
         let sf1 = SourceFile::from_lines("a.c".to_string(), lines.clone());
         let sf2 = SourceFile::from_lines("b.c".to_string(), lines);
 
-        let mut config = Config::default();
-        config.min_block_size = 4;
+        let config = Config {
+            min_block_size: 4,
+            ..Default::default()
+        };
 
-        let mut context = ThreadContext::new(10);
+        let mut context = ThreadContext::new(10 * 10);
         let blocks = process_file_pair(&sf1, &sf2, 0, 1, &config, &mut context);
 
         // Should find one block of 5 lines
@@ -493,6 +1473,131 @@ mod tests {
         assert_eq!(blocks[0].count, 5);
     }
 
+    #[test]
+    fn test_self_comparison_single_repeat() {
+        // Four lines repeated once further down the same file
+        let texts = [
+            "alpha", "beta", "gamma", "delta", "other", "alpha", "beta", "gamma", "delta",
+        ];
+        let lines = texts
+            .iter()
+            .enumerate()
+            .map(|(i, t)| SourceLine::new(t.to_string(), i + 1))
+            .collect();
+        let sf = SourceFile::from_lines("a.c".to_string(), lines);
+
+        let config = Config {
+            min_block_size: 4,
+            ..Default::default()
+        };
+        let mut context = ThreadContext::new(10 * 10);
+        let blocks = process_file_pair(&sf, &sf, 0, 0, &config, &mut context);
+
+        let found: Vec<(usize, usize, usize)> =
+            blocks.iter().map(|b| (b.line1, b.line2, b.count)).collect();
+        assert_eq!(found, vec![(5, 0, 4)]);
+    }
+
+    #[test]
+    fn test_self_comparison_clips_overlapping_repeats() {
+        // A run of identical lines matches itself shifted by one; reporting
+        // lines 1-9 as a copy of lines 0-8 would count the same lines twice
+        let lines = (1..=10)
+            .map(|i| SourceLine::new("counter++;".to_string(), i))
+            .collect();
+        let sf = SourceFile::from_lines("a.c".to_string(), lines);
+
+        let config = Config {
+            min_block_size: 4,
+            ..Default::default()
+        };
+        let mut context = ThreadContext::new(10 * 10);
+        let blocks = process_file_pair(&sf, &sf, 0, 0, &config, &mut context);
+
+        for block in &blocks {
+            assert!(block.line2 + block.count <= block.line1, "{:?}", block);
+        }
+        let mut found: Vec<(usize, usize, usize)> =
+            blocks.iter().map(|b| (b.line1, b.line2, b.count)).collect();
+        found.sort();
+        assert_eq!(found, vec![(4, 0, 4), (5, 0, 5), (6, 0, 4)]);
+    }
+
+    #[test]
+    fn test_sparse_runs_match_diagonal_runs() {
+        let patterns = [
+            "1111000011",
+            "1011011100110",
+            "0000000000",
+            "1100101011111",
+            "1",
+        ];
+        for pattern in patterns {
+            let cells: Vec<bool> = pattern.chars().map(|c| c == '1').collect();
+            let offsets = || (0..cells.len()).filter(|&k| cells[k]);
+            for max_gap in 0..3 {
+                for min_len in 1..5 {
+                    assert_eq!(
+                        sparse_runs(offsets(), max_gap, min_len),
+                        diagonal_runs(cells.len(), max_gap, min_len, |k| cells[k]),
+                        "{} gap {} min {}",
+                        pattern,
+                        max_gap,
+                        min_len
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_self_comparison_of_large_file_needs_no_matrix() {
+        // 40,000 lines would take a 200 MB matrix compared with themselves
+        let mut lines: Vec<SourceLine> = (0..40_000)
+            .map(|i| SourceLine::new(format!("int value_{} = compute({});", i, i), i + 1))
+            .collect();
+        for k in 0..6 {
+            let copy = lines[100 + k].clone();
+            lines[30_000 + k] = SourceLine::new(copy.line().to_string(), 30_001 + k);
+        }
+        let sf = SourceFile::from_lines("huge.c".to_string(), lines);
+
+        // A zero-sized matrix would panic if it were used
+        let mut empty_context = ThreadContext::new(0);
+        let blocks = process_file_pair(&sf, &sf, 0, 0, &Config::default(), &mut empty_context);
+        let found: Vec<(usize, usize, usize)> =
+            blocks.iter().map(|b| (b.line1, b.line2, b.count)).collect();
+        assert_eq!(found, vec![(30_000, 100, 6)]);
+
+        // Only distinct files count toward the memory guard
+        assert_eq!(
+            check_matrix_size(std::slice::from_ref(&sf), &Config::default()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_self_comparison_of_repetitive_file() {
+        // Enough copies of one line to exceed MAX_SELF_MATCHES, so every
+        // diagonal is walked instead; runs are still clipped at the overlap
+        let lines = (1..=6_000)
+            .map(|i| SourceLine::new("counter++;".to_string(), i))
+            .collect();
+        let sf = SourceFile::from_lines("a.c".to_string(), lines);
+        let config = Config {
+            min_block_size: 2_000,
+            ..Default::default()
+        };
+        let mut empty_context = ThreadContext::new(0);
+        let blocks = process_file_pair(&sf, &sf, 0, 0, &config, &mut empty_context);
+
+        assert!(!blocks.is_empty());
+        for block in &blocks {
+            assert!(block.line2 + block.count <= block.line1, "{:?}", block);
+            assert!(block.count >= 2_000);
+        }
+    }
+
     #[test]
     fn test_process_no_duplicates() {
         let lines1 = vec![
@@ -507,9 +1612,784 @@ mod tests {
         let sf2 = SourceFile::from_lines("b.c".to_string(), lines2);
 
         let config = Config::default();
-        let mut context = ThreadContext::new(10);
+        let mut context = ThreadContext::new(10 * 10);
         let blocks = process_file_pair(&sf1, &sf2, 0, 1, &config, &mut context);
 
         assert!(blocks.is_empty());
     }
+
+    #[test]
+    fn test_pair_cache_hit_skips_detection() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let lines: Vec<SourceLine> = (1..=5)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let hashes: Vec<u64> = files.iter().map(|f| f.content_hash()).collect();
+        let config = Config::default();
+
+        // First run: detection runs and the result is recorded
+        let pair_cache = PairCache::load(temp.path());
+        let mut context = ThreadContext::new(10 * 10);
+        let fresh = compare_with_pair_cache(
+            &files,
+            0,
+            1,
+            &config,
+            &mut context,
+            Some((&pair_cache, &hashes, 42)),
+        );
+        assert_eq!(pair_cache.hits(), 0);
+        pair_cache.save().unwrap();
+
+        // Second run: a zero-sized matrix would panic if detection re-ran
+        let pair_cache = PairCache::load(temp.path());
+        let mut empty_context = ThreadContext::new(0);
+        let cached = compare_with_pair_cache(
+            &files,
+            0,
+            1,
+            &config,
+            &mut empty_context,
+            Some((&pair_cache, &hashes, 42)),
+        );
+        assert_eq!(pair_cache.hits(), 1);
+
+        let key = |b: &Block| (b.source1_idx, b.source2_idx, b.line1, b.line2, b.count);
+        assert_eq!(
+            fresh.iter().map(key).collect::<Vec<_>>(),
+            cached.iter().map(key).collect::<Vec<_>>()
+        );
+        assert_eq!(cached.len(), 1);
+    }
+
+    #[test]
+    fn test_detect_streaming_matches_batch() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\nint e = 5;\n";
+        let file_list: Vec<String> = ["a.c", "b.c", "c.c"]
+            .iter()
+            .map(|name| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let config = Config {
+            num_threads: 2,
+            ..Default::default()
+        };
+
+        let (batch, _) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+
+        let mut streamed = Vec::new();
+        let (summary, _) =
+            detect_streaming(&config, &file_list, |b| streamed.push(b.clone())).unwrap();
+
+        let key = |b: &Block| (b.source1_idx, b.source2_idx, b.line1, b.line2, b.count);
+        let mut streamed: Vec<_> = streamed.iter().map(key).collect();
+        streamed.sort_unstable();
+        assert_eq!(streamed, batch.blocks.iter().map(key).collect::<Vec<_>>());
+
+        assert!(summary.blocks.is_empty());
+        assert_eq!(summary.duplicate_blocks, batch.duplicate_blocks);
+        assert_eq!(summary.duplicate_lines, batch.duplicate_lines);
+        assert_eq!(summary.total_lines, batch.total_lines);
+        assert_eq!(summary.files_analyzed, batch.files_analyzed);
+    }
+
+    #[test]
+    fn test_intern_lines_finds_same_blocks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let shared = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\nint e = 5;\n";
+        let file_list: Vec<String> = ["a.c", "b.c", "c.c"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, format!("int own_{} = {};\n{}", i, i, shared)).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let run = |intern_lines: bool| {
+            let config = Config {
+                num_threads: 1,
+                intern_lines,
+                ..Default::default()
+            };
+            process_files_with_list(&file_list, &config, |_| {}).unwrap()
+        };
+
+        let (plain, _) = run(false);
+        let (interned, source_files) = run(true);
+        let key = |b: &Block| (b.source1_idx, b.source2_idx, b.line1, b.line2, b.count);
+        assert_eq!(
+            interned.blocks.iter().map(key).collect::<Vec<_>>(),
+            plain.blocks.iter().map(key).collect::<Vec<_>>()
+        );
+        assert_eq!(interned.duplicate_blocks, 3);
+        assert_eq!(source_files[2].get_line(1).line(), "int a = 1;");
+    }
+
+    #[test]
+    fn test_max_file_bytes_skips_large_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let small = temp.path().join("small.c");
+        let large = temp.path().join("large.c");
+        std::fs::write(&small, "int x = 1;\n").unwrap();
+        // Sparse file: large on disk metadata, never actually read
+        std::fs::File::create(&large)
+            .unwrap()
+            .set_len(10 * 1024 * 1024)
+            .unwrap();
+
+        let config = Config {
+            max_file_bytes: Some(1024),
+            ..Default::default()
+        };
+        let file_list = [
+            small.to_string_lossy().to_string(),
+            large.to_string_lossy().to_string(),
+        ];
+        let messages = std::sync::Mutex::new(Vec::new());
+        let files = load_source_files_with_cache(
+            file_list.iter().cloned().map(Ok),
+            &config,
+            None,
+            &|m: &str| messages.lock().unwrap().push(m.to_string()),
+        )
+        .unwrap()
+        .source_files;
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename(), file_list[0]);
+        let messages = messages.into_inner().unwrap();
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("large.c") && m.contains("--max-file-bytes 1024")));
+    }
+
+    #[test]
+    fn test_cross_language_pairs_skipped_by_default() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\nint e = 5;\n";
+        let file_list: Vec<String> = ["a.c", "B.java"]
+            .iter()
+            .map(|name| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let config = Config::default();
+        let (result, _) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 0);
+
+        let config = Config {
+            cross_language: CrossLanguage::Allow,
+            ..Default::default()
+        };
+        let (result, _) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 1);
+    }
+
+    #[test]
+    fn test_keep_structural_lines_counts_braces() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int add(int a, int b)\n{\n    int sum = a + b;\n    log_value(sum);\n    return sum;\n}\n";
+        let file_list: Vec<String> = ["a.c", "b.c"]
+            .iter()
+            .map(|name| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        // Four lines of code fall short of a five-line minimum on their own
+        let config = Config {
+            min_block_size: 5,
+            ..Default::default()
+        };
+        let (result, _) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 0);
+
+        // With the braces kept the whole function is one block
+        let config = Config {
+            min_block_size: 5,
+            keep_structural_lines: true,
+            ..Default::default()
+        };
+        let (result, _) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 1);
+        assert_eq!(result.blocks[0].count, 6);
+    }
+
+    #[test]
+    fn test_normalize_string_literals_matches_message_variants() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, action: &str, call: &str| {
+            let code = format!(
+                "{call}(\"{action} user\");\nint status = run(user_id);\nreport(status, \"{action}\");\nint done = finish(status);\n"
+            );
+            let path = temp.path().join(name);
+            std::fs::write(&path, code).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let saving = write("a.c", "saving", "log");
+        let deleting = write("b.c", "deleting", "log");
+        let other_call = write("c.c", "deleting", "trace");
+
+        let detect = |files: &[String], normalize_string_literals| {
+            let config = Config {
+                min_block_size: 4,
+                normalize_string_literals,
+                ..Default::default()
+            };
+            process_files_with_list(files, &config, |_| {}).unwrap().0
+        };
+
+        let pair = [saving.clone(), deleting];
+        assert_eq!(detect(&pair, false).duplicate_blocks, 0);
+        let result = detect(&pair, true);
+        assert_eq!(result.duplicate_blocks, 1);
+        assert_eq!(result.blocks[0].count, 4);
+
+        // A difference outside the strings still breaks the match
+        assert_eq!(detect(&[saving, other_call], true).duplicate_blocks, 0);
+    }
+
+    #[test]
+    fn test_thread_pool_reused_only_when_enabled() {
+        let fresh = Config {
+            num_threads: 2,
+            ..Default::default()
+        };
+        assert!(!Arc::ptr_eq(
+            &thread_pool(&fresh).unwrap(),
+            &thread_pool(&fresh).unwrap()
+        ));
+
+        let reused = Config {
+            reuse_thread_pool: true,
+            ..fresh.clone()
+        };
+        let pool = thread_pool(&reused).unwrap();
+        assert!(Arc::ptr_eq(&pool, &thread_pool(&reused).unwrap()));
+        assert_eq!(pool.current_num_threads(), 2);
+
+        // A different thread count gets a pool of its own
+        let wider = Config {
+            num_threads: 3,
+            ..reused
+        };
+        assert_eq!(thread_pool(&wider).unwrap().current_num_threads(), 3);
+    }
+
+    #[test]
+    fn test_ignore_line_pattern_drops_trivial_block() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, setup: &str| {
+            let code = format!(
+                "int {setup} = prepare();\nif (a) return;\nreturn;\nreturn;\nreturn\nreturn;\nint {setup}_done = 1;\n"
+            );
+            let path = temp.path().join(name);
+            std::fs::write(&path, code).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let files = [write("a.c", "left"), write("b.c", "right")];
+
+        let detect = |ignore_line_patterns: Vec<String>| {
+            let config = Config {
+                min_block_size: 4,
+                ignore_line_patterns,
+                ..Default::default()
+            };
+            process_files_with_list(&files, &config, |_| {}).unwrap().0
+        };
+
+        let result = detect(Vec::new());
+        assert_eq!(result.duplicate_blocks, 1);
+        assert_eq!(result.blocks[0].count, 5);
+
+        // Only the conditional return is left, too short for a block
+        let result = detect(vec!["^return;?$".to_string()]);
+        assert_eq!(result.duplicate_blocks, 0);
+        assert_eq!(result.total_lines, 6);
+    }
+
+    fn numbered_file(name: &str, lines: usize) -> SourceFile {
+        let source: String = (0..lines)
+            .map(|i| format!("int {}_{} = {};\n", name.replace('.', "_"), i, i))
+            .collect();
+        SourceFile::from_source(name.to_string(), &source, 3, &BTreeMap::new())
+    }
+
+    #[test]
+    fn test_select_files() {
+        let files = vec![
+            numbered_file("a.c", 2),
+            numbered_file("b.c", 8),
+            numbered_file("c.c", 5),
+            numbered_file("d.c", 1),
+        ];
+
+        assert_eq!(select_files(&files, 2, FileSelection::First, 0), vec![0, 1]);
+        assert_eq!(
+            select_files(&files, 2, FileSelection::Largest, 0),
+            vec![1, 2]
+        );
+        assert_eq!(
+            select_files(&files, 9, FileSelection::Largest, 0),
+            vec![0, 1, 2, 3]
+        );
+
+        let random = select_files(&files, 2, FileSelection::Random, 7);
+        assert_eq!(random.len(), 2);
+        assert!(random.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(random, select_files(&files, 2, FileSelection::Random, 7));
+    }
+
+    #[test]
+    fn test_random_selection_is_pinned_per_seed() {
+        let files = vec![
+            numbered_file("a.c", 2),
+            numbered_file("b.c", 8),
+            numbered_file("c.c", 5),
+            numbered_file("d.c", 1),
+        ];
+
+        // Fixed by the FNV-1a ranking, so these hold for every build
+        assert_eq!(
+            select_files(&files, 2, FileSelection::Random, 7),
+            vec![1, 3]
+        );
+        assert_eq!(
+            select_files(&files, 2, FileSelection::Random, 42),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn test_largest_selection_compares_earlier_files() {
+        let shared = "int first = compute(1);\nint second = compute(2);\nint third = compute(3);\nint fourth = compute(4);\n";
+        let extra: String = (0..6)
+            .map(|i| format!("int extra_{} = {};\n", i, i))
+            .collect();
+        let files = || {
+            vec![
+                numbered_file("a.c", 3),
+                SourceFile::from_source("b.c".to_string(), shared, 3, &BTreeMap::new()),
+                SourceFile::from_source(
+                    "c.c".to_string(),
+                    &format!("{}{}", shared, extra),
+                    3,
+                    &BTreeMap::new(),
+                ),
+            ]
+        };
+        let detect = |file_selection| {
+            let config = Config {
+                files_to_check: 1,
+                file_selection,
+                ..Default::default()
+            };
+            process_source_files(files(), &config, |_| {}).unwrap().0
+        };
+
+        // Only a.c is checked, and it shares nothing
+        assert_eq!(detect(FileSelection::First).duplicate_blocks, 0);
+
+        // c.c is checked, and reaches b.c before it in the list
+        let result = detect(FileSelection::Largest);
+        assert_eq!(result.files_analyzed, 1);
+        assert_eq!(result.duplicate_blocks, 1);
+        let block = &result.blocks[0];
+        assert_eq!((block.source1_idx, block.source2_idx), (1, 2));
+        assert_eq!(block.count, 4);
+    }
+
+    #[test]
+    fn test_cancellation_reports_partial_result() {
+        let shared = "int first = compute(1);\nint second = compute(2);\nint third = compute(3);\nint fourth = compute(4);\n";
+        let files = || {
+            vec![
+                SourceFile::from_source("a.c".to_string(), shared, 3, &BTreeMap::new()),
+                SourceFile::from_source("b.c".to_string(), shared, 3, &BTreeMap::new()),
+            ]
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let config = Config {
+            cancel: Some(Arc::clone(&cancel)),
+            ..Default::default()
+        };
+
+        let (result, _) = process_source_files(files(), &config, |_| {}).unwrap();
+        assert!(!result.is_partial());
+        assert_eq!(result.duplicate_blocks, 1);
+
+        // Raised before detection: nothing is compared, but the result still
+        // aggregates and says it is partial
+        cancel.store(true, Ordering::Relaxed);
+        let (result, _) = process_source_files(files(), &config, |_| {}).unwrap();
+        assert!(result.is_partial());
+        assert_eq!(result.duplicate_blocks, 0);
+        assert_eq!(result.total_lines, 8);
+        assert_eq!(result.warnings[0].kind, WarningKind::Interrupted);
+    }
+
+    #[test]
+    fn test_function_granularity_reports_whole_functions() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let shared = "    int total(int[] values) {\n        int sum = 0;\n        for (int v : values) {\n            sum += v;\n        }\n        return sum;\n    }\n";
+        // The tail of `scale` matches `total`, but not the whole function
+        let a = format!("class A {{\n{}\n    int scale(int f) {{\n        int sum = 0;\n        for (int v : values) {{\n            sum += v;\n        }}\n        return sum;\n    }}\n}}\n", shared);
+        let b = format!("class B {{\n    int offset = 10;\n{}}}\n", shared);
+        let files: Vec<String> = [("A.java", a), ("B.java", b)]
+            .iter()
+            .map(|(name, code)| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let config = Config {
+            min_block_size: 3,
+            function_granularity: true,
+            ..Default::default()
+        };
+        let (result, source_files) = process_files_with_list(&files, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 1);
+        let block = &result.blocks[0];
+        assert_eq!((block.source1_idx, block.source2_idx), (0, 1));
+        // Every cleaned line of `total`, in both files
+        assert_eq!(block.count, 5);
+        assert_eq!(source_files[0].get_line(block.line1).line_number(), 2);
+        assert_eq!(source_files[1].get_line(block.line2).line_number(), 3);
+
+        // Line mode also matches the tail of `scale` against `total`
+        let config = Config {
+            function_granularity: false,
+            ..config
+        };
+        let (result, _) = process_files_with_list(&files, &config, |_| {}).unwrap();
+        assert!(result.duplicate_blocks > 1);
+
+        // --compare-two honors the mode as well
+        let config = Config {
+            function_granularity: true,
+            ..config
+        };
+        let (result, _) = compare_two_files(&files[0], &files[1], &config).unwrap();
+        assert_eq!(result.duplicate_blocks, 1);
+    }
+
+    #[test]
+    fn test_ignore_same_name_fuzzy_skips_versioned_variants() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code =
+            "int a = compute(1);\nint b = compute(2);\nint c = compute(3);\nint d = compute(4);\n";
+        let file_list: Vec<String> = ["Parser.c", "Parser.v2.c"]
+            .iter()
+            .map(|name| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let detect = |config: &Config| {
+            process_files_with_list(&file_list, config, |_| {})
+                .unwrap()
+                .0
+                .duplicate_blocks
+        };
+
+        // Exact matching doesn't see the versioned copy as the same name
+        let exact = Config {
+            ignore_same_filename: true,
+            ..Default::default()
+        };
+        assert_eq!(detect(&exact), 1);
+
+        let fuzzy = Config {
+            ignore_same_filename_fuzzy: true,
+            ..Default::default()
+        };
+        assert_eq!(detect(&fuzzy), 0);
+    }
+
+    #[test]
+    fn test_duplicate_list_entries_loaded_once() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("a.c");
+        std::fs::write(&path, "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n").unwrap();
+        // The same file listed again, once verbatim and once via another spelling
+        let file_list = vec![
+            path.to_string_lossy().to_string(),
+            path.to_string_lossy().to_string(),
+            temp.path()
+                .join(".")
+                .join("a.c")
+                .to_string_lossy()
+                .to_string(),
+        ];
+
+        let (result, source_files) =
+            process_files_with_list(&file_list, &Config::default(), |_| {}).unwrap();
+        assert_eq!(result.files_provided, 3);
+        assert_eq!(result.files_analyzed, 1);
+        assert_eq!(source_files.len(), 1);
+        assert_eq!(result.duplicate_blocks, 0);
+    }
+
+    #[test]
+    fn test_comment_only_file_reported_as_skipped() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = temp.path().join("code.c");
+        let comments = temp.path().join("comments.c");
+        std::fs::write(&code, "int x = 1;\nint y = 2;\n").unwrap();
+        std::fs::write(&comments, "// only\n/* comments */\n").unwrap();
+        let file_list = vec![
+            code.to_string_lossy().to_string(),
+            comments.to_string_lossy().to_string(),
+        ];
+
+        let (result, _) = process_files_with_list(&file_list, &Config::default(), |_| {}).unwrap();
+        assert_eq!(result.files_provided, 2);
+        assert_eq!(result.files_analyzed, 1);
+        assert_eq!(result.skipped_files, vec![file_list[1].clone()]);
+    }
+
+    #[test]
+    fn test_max_gap_merges_runs() {
+        let lines = |texts: &[&str]| {
+            texts
+                .iter()
+                .enumerate()
+                .map(|(i, t)| SourceLine::new(t.to_string(), i + 1))
+                .collect::<Vec<_>>()
+        };
+        let sf1 = SourceFile::from_lines(
+            "a.c".to_string(),
+            lines(&[
+                "int a = 1;",
+                "int b = 2;",
+                "int c = 3;",
+                "int x = 0;",
+                "int d = 4;",
+                "int e = 5;",
+                "int f = 6;",
+            ]),
+        );
+        let sf2 = SourceFile::from_lines(
+            "b.c".to_string(),
+            lines(&[
+                "int a = 1;",
+                "int b = 2;",
+                "int c = 3;",
+                "int y = 9;",
+                "int d = 4;",
+                "int e = 5;",
+                "int f = 6;",
+            ]),
+        );
+        let mut context = ThreadContext::new(7 * 7);
+
+        // Without a gap allowance, neither 3-line run reaches --min-lines 4
+        let config = Config::default();
+        let blocks = process_file_pair(&sf1, &sf2, 0, 1, &config, &mut context);
+        assert!(blocks.is_empty());
+
+        let config = Config {
+            max_gap: 1,
+            ..Default::default()
+        };
+        let blocks = process_file_pair(&sf1, &sf2, 0, 1, &config, &mut context);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].line1, blocks[0].line2), (0, 0));
+        assert_eq!(blocks[0].count, 7);
+        assert_eq!(blocks[0].gaps, 1);
+    }
+
+    #[test]
+    fn test_diagonal_runs() {
+        let pattern = [true, true, false, false, true, true, true, false];
+        let is_match = |k: usize| pattern[k];
+
+        assert_eq!(diagonal_runs(8, 0, 2, is_match), vec![(0, 2, 0), (4, 3, 0)]);
+        assert_eq!(diagonal_runs(8, 1, 2, is_match), vec![(0, 2, 0), (4, 3, 0)]);
+        // Trailing mismatches are never part of a run
+        assert_eq!(diagonal_runs(8, 2, 2, is_match), vec![(0, 7, 2)]);
+    }
+
+    #[test]
+    fn test_split_minified_finds_shared_chunk() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let chunk = "function sum(items){var total=0;for(var i=0;i<items.length;i++){\
+                     total+=items[i].price;}return total;}";
+        let minified = |prefix: &str| {
+            let filler = |part: &str| -> String {
+                (0..40)
+                    .map(|i| format!("{}_{}{}=1;", prefix, part, i))
+                    .collect()
+            };
+            format!("{}{}{}\n", filler("head"), chunk, filler("tail"))
+        };
+        let a = temp.path().join("a.min.js");
+        let b = temp.path().join("b.min.js");
+        std::fs::write(&a, minified("alpha")).unwrap();
+        std::fs::write(&b, minified("beta")).unwrap();
+        let file_list = vec![
+            a.to_string_lossy().to_string(),
+            b.to_string_lossy().to_string(),
+        ];
+
+        // One huge line each: the shared chunk is invisible
+        let (result, _) = process_files_with_list(&file_list, &Config::default(), |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 0);
+
+        let config = Config {
+            split_minified: true,
+            ..Default::default()
+        };
+        let (result, files) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.duplicate_blocks, 1);
+        let block = &result.blocks[0];
+        assert!(block.count >= 4);
+        assert_eq!(files[0].get_line(block.line1).line(), "var total=0;");
+        assert_eq!(files[0].get_line(block.line1).line_number(), 1);
+    }
+
+    #[test]
+    fn test_ignore_generated_skips_marked_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n";
+        let handwritten = temp.path().join("a.c");
+        let generated = temp.path().join("b.pb.c");
+        std::fs::write(&handwritten, code).unwrap();
+        std::fs::write(
+            &generated,
+            format!("// Code generated by protoc. DO NOT EDIT.\n{}", code),
+        )
+        .unwrap();
+        let file_list = vec![
+            handwritten.to_string_lossy().to_string(),
+            generated.to_string_lossy().to_string(),
+        ];
+
+        let (result, _) = process_files_with_list(&file_list, &Config::default(), |_| {}).unwrap();
+        assert_eq!(result.files_analyzed, 2);
+        assert_eq!(result.duplicate_blocks, 1);
+
+        let config = Config {
+            ignore_generated: true,
+            ..Default::default()
+        };
+        let (result, files) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(result.files_analyzed, 1);
+        assert_eq!(files[0].filename(), file_list[0]);
+        assert_eq!(result.duplicate_blocks, 0);
+    }
+
+    #[test]
+    fn test_dedup_blocks() {
+        let mut blocks = vec![
+            Block::new(0, 1, 0, 0, 4),
+            Block::new(0, 0, 2, 8, 5),
+            Block::new(0, 1, 0, 0, 4),
+            Block::new(0, 1, 0, 0, 5),
+            Block::new(0, 0, 2, 8, 5),
+        ];
+        dedup_blocks(&mut blocks);
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::new(0, 1, 0, 0, 4),
+                Block::new(0, 0, 2, 8, 5),
+                Block::new(0, 1, 0, 0, 5),
+            ]
+        );
+        assert_eq!(blocks.iter().map(|b| b.count).sum::<usize>(), 14);
+    }
+
+    #[test]
+    fn test_file_list_iter_matches_load_file_list() {
+        use std::io::Write;
+
+        let mut list = tempfile::NamedTempFile::new().unwrap();
+        {
+            let mut writer = std::io::BufWriter::new(list.as_file_mut());
+            for i in 0..100_000 {
+                // Every tenth entry is too short and filtered out
+                if i % 10 == 0 {
+                    writeln!(writer, "a.c").unwrap();
+                } else {
+                    writeln!(writer, "src/file_{}.c", i).unwrap();
+                }
+            }
+        }
+        let path = list.path().to_str().unwrap();
+
+        // Entries are produced lazily, without reading the whole list
+        let first: Vec<String> = file_list_iter(path).take(2).map(|r| r.unwrap()).collect();
+        assert_eq!(first, vec!["src/file_1.c", "src/file_2.c"]);
+
+        let loaded = load_file_list(path).unwrap();
+        assert_eq!(loaded.len(), 90_000);
+        assert!(file_list_iter(path)
+            .map(|r| r.unwrap())
+            .eq(loaded.into_iter()));
+    }
+
+    #[test]
+    fn test_file_list_iter_missing_list() {
+        let mut iter = file_list_iter("/nonexistent/files.txt");
+        assert!(matches!(
+            iter.next(),
+            Some(Err(DuploError::FileNotFound { .. }))
+        ));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_retain_blocks_recounts() {
+        let mut result = DuploResult {
+            total_lines: 40,
+            ..DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4), Block::new(0, 1, 5, 5, 6)])
+        };
+        result.retain_blocks(|b| b.count > 4);
+        assert_eq!(result.blocks, vec![Block::new(0, 1, 5, 5, 6)]);
+        assert_eq!((result.duplicate_blocks, result.duplicate_lines), (1, 6));
+        assert_eq!(result.total_lines, 40);
+    }
+
+    #[test]
+    fn test_check_indices() {
+        let lines: Vec<SourceLine> = (1..=4)
+            .map(|i| SourceLine::new(format!("int v{} = {};", i, i), i))
+            .collect();
+        let files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = |block: Block| DuploResult::from_blocks(vec![block]);
+
+        assert!(result(Block::new(0, 1, 0, 0, 4))
+            .check_indices(&files)
+            .is_ok());
+        // A file that isn't there, and lines past the end of one that is
+        for block in [Block::new(0, 2, 0, 0, 4), Block::new(0, 1, 0, 1, 4)] {
+            assert!(matches!(
+                result(block).check_indices(&files),
+                Err(DuploError::InconsistentResult(_))
+            ));
+        }
+    }
 }
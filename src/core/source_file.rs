@@ -1,12 +1,42 @@
 //! Source file representation
 
-use crate::core::SourceLine;
+use crate::config::HashAlgorithm;
+use crate::core::minified::{is_minified, split_statements};
+use crate::core::{LineInterner, SourceLine};
 use crate::error::{DuploError, Result};
-use crate::filetype::create_file_type;
+use crate::filetype::{
+    create_file_type, file_type_name, find_functions, normalize_string_literals, FileTypeOptions,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader};
+use std::ops::{Range, RangeInclusive};
 use std::path::Path;
 
+/// Open `path` for reading line by line
+///
+/// With the `gzip` feature, `.gz` files are decompressed as they are read.
+pub(crate) fn open_file(path: &str) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    #[cfg(feature = "gzip")]
+    if super::gzip::is_gzip(path) {
+        return Ok(Box::new(BufReader::new(super::gzip::decoder(file))));
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Name whose extension selects the file type: with the `gzip` feature,
+/// `util.c.gz` is typed as `util.c`
+fn type_name(filename: &str) -> &str {
+    #[cfg(feature = "gzip")]
+    if let Some(inner) = super::gzip::inner_name(filename) {
+        return inner;
+    }
+    filename
+}
+
 /// Represents a loaded and processed source file
 #[derive(Debug)]
 pub struct SourceFile {
@@ -14,6 +44,13 @@ pub struct SourceFile {
     filename: String,
     /// Processed source lines (after cleaning/filtering)
     source_lines: Vec<SourceLine>,
+    /// Name of the file type the lines were cleaned with (e.g. "C/C++")
+    language: &'static str,
+    /// Cleaned lines of each function, for `--function-granularity`
+    functions: Vec<Range<usize>>,
+    /// Original text of every physical line, without trailing whitespace
+    /// (kept for `--preserve-indentation`)
+    original_lines: Option<Vec<String>>,
 }
 
 impl SourceFile {
@@ -21,17 +58,22 @@ impl SourceFile {
     ///
     /// # Arguments
     /// * `path` - Path to the source file
-    /// * `min_chars` - Minimum characters per line
+    /// * `options` - How lines are cleaned and kept (a bare `u32` is the
+    ///   minimum characters per line)
+    /// * `extension_map` - Extra extensions mapped to built-in ones
     ///
     /// # Returns
     /// A processed SourceFile, or an error if the file cannot be read
-    pub fn load(path: &str, min_chars: u32) -> Result<Self> {
-        let file = File::open(path).map_err(|e| DuploError::FileNotFound {
+    pub fn load(
+        path: &str,
+        options: impl Into<FileTypeOptions>,
+        extension_map: &BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let reader = open_file(path).map_err(|e| DuploError::FileNotFound {
             path: path.to_string(),
             reason: e.to_string(),
         })?;
 
-        let reader = BufReader::new(file);
         let raw_lines: Vec<String> = reader
             .lines()
             .collect::<std::io::Result<Vec<_>>>()
@@ -40,38 +82,138 @@ impl SourceFile {
                 reason: e.to_string(),
             })?;
 
-        let file_type = create_file_type(path, min_chars);
-        let source_lines = file_type.get_cleaned_source_lines(&raw_lines);
+        Ok(Self::from_raw_lines(
+            path.to_string(),
+            &raw_lines,
+            options.into(),
+            extension_map,
+        ))
+    }
+
+    /// Process in-memory source text (e.g. an archive entry) into a SourceFile
+    ///
+    /// # Arguments
+    /// * `filename` - Name used for reporting and to select the file type
+    /// * `source` - Raw file contents
+    /// * `options` - How lines are cleaned and kept (a bare `u32` is the
+    ///   minimum characters per line)
+    /// * `extension_map` - Extra extensions mapped to built-in ones
+    pub fn from_source(
+        filename: String,
+        source: &str,
+        options: impl Into<FileTypeOptions>,
+        extension_map: &BTreeMap<String, String>,
+    ) -> Self {
+        let raw_lines: Vec<String> = source.lines().map(|l| l.to_string()).collect();
+        Self::from_raw_lines(filename, &raw_lines, options.into(), extension_map)
+    }
+
+    /// Clean raw lines with the file type matching `filename`
+    ///
+    /// Minified files are split into statements first when `split_minified`
+    /// is set in `options`; the cleaned lines keep the numbers of the lines they came from.
+    /// String literals are collapsed last, so they don't affect which lines
+    /// are kept, and the kept lines are then hashed with the chosen algorithm.
+    fn from_raw_lines(
+        filename: String,
+        raw_lines: &[String],
+        options: FileTypeOptions,
+        extension_map: &BTreeMap<String, String>,
+    ) -> Self {
+        let file_type = create_file_type(type_name(&filename), options.clone(), extension_map);
+        let filter = options.filter;
+        let mut source_lines = if options.split_minified && is_minified(raw_lines) {
+            let (pieces, origins) = split_statements(raw_lines);
+            file_type
+                .get_cleaned_source_lines(&pieces)
+                .into_iter()
+                .map(|l| {
+                    SourceLine::new(l.line().to_string(), origins[l.line_number() - 1])
+                        .with_end_line(origins[l.end_line_number() - 1])
+                })
+                .collect()
+        } else {
+            file_type.get_cleaned_source_lines(raw_lines)
+        };
+        if filter.normalize_strings || filter.hash != HashAlgorithm::default() {
+            let quotes = file_type.string_quotes();
+            for line in &mut source_lines {
+                let text = if filter.normalize_strings {
+                    normalize_string_literals(line.line(), quotes)
+                } else {
+                    line.line().to_string()
+                };
+                *line = SourceLine::with_algorithm(text, line.line_number(), filter.hash)
+                    .with_end_line(line.end_line_number());
+            }
+        }
+
+        let functions = if options.functions && file_type.has_brace_functions() {
+            function_lines(&source_lines, &find_functions(raw_lines))
+        } else {
+            Vec::new()
+        };
+        let original_lines = options
+            .keep_original
+            .then(|| raw_lines.iter().map(|l| l.trim_end().to_string()).collect());
 
-        Ok(Self {
-            filename: path.to_string(),
+        Self {
+            filename,
             source_lines,
-        })
+            language: file_type.name(),
+            functions,
+            original_lines,
+        }
     }
 
     /// Create a SourceFile from already-processed lines (for testing)
     #[cfg(test)]
     pub fn from_lines(filename: String, source_lines: Vec<SourceLine>) -> Self {
+        let language = file_type_name(&filename, &BTreeMap::new());
         Self {
             filename,
             source_lines,
+            language,
+            functions: Vec::new(),
+            original_lines: None,
         }
     }
 
     /// Create a SourceFile from cached lines
-    pub fn from_cached_lines(filename: String, source_lines: Vec<SourceLine>) -> Self {
+    pub fn from_cached_lines(
+        filename: String,
+        source_lines: Vec<SourceLine>,
+        extension_map: &BTreeMap<String, String>,
+    ) -> Self {
+        let language = file_type_name(type_name(&filename), extension_map);
         Self {
             filename,
             source_lines,
+            language,
+            functions: Vec::new(),
+            original_lines: None,
         }
     }
 
+    /// Original text of the physical lines, if the file was loaded with
+    /// `keep_original` (line number `n` is at index `n - 1`)
+    #[inline]
+    pub fn original_lines(&self) -> Option<&[String]> {
+        self.original_lines.as_deref()
+    }
+
     /// Get the filename
     #[inline]
     pub fn filename(&self) -> &str {
         &self.filename
     }
 
+    /// Get the name of the file's language (its file type)
+    #[inline]
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
     /// Get just the file name without the directory path
     pub fn basename(&self) -> &str {
         Path::new(&self.filename)
@@ -100,20 +242,98 @@ impl SourceFile {
             .collect()
     }
 
+    /// Iterate over the hashes of `count` lines starting at `start`
+    pub fn line_hashes(&self, start: usize, count: usize) -> impl Iterator<Item = u32> + '_ {
+        self.source_lines[start..start + count]
+            .iter()
+            .map(|l| l.hash())
+    }
+
     /// Iterate over all source lines
     pub fn lines(&self) -> impl Iterator<Item = &SourceLine> {
         self.source_lines.iter()
     }
 
+    /// Share the text of each line with identical lines of other files
+    /// interned through `interner`
+    pub fn intern_lines(&mut self, interner: &mut LineInterner) {
+        for line in &mut self.source_lines {
+            interner.intern(line);
+        }
+    }
+
+    /// Ranges of cleaned lines making up each function, in file order
+    ///
+    /// Only recorded for brace languages with `--function-granularity`;
+    /// functions left without cleaned lines are omitted.
+    pub fn functions(&self) -> &[Range<usize>] {
+        &self.functions
+    }
+
     /// Get a slice of all source lines (for caching)
     pub fn lines_slice(&self) -> &[SourceLine] {
         &self.source_lines
     }
 
+    /// Hash of the cleaned lines, identifying the file's comparable content
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for line in &self.source_lines {
+            line.hash64().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     /// Check if two files have the same basename (for -d flag)
     pub fn has_same_basename(&self, other: &SourceFile) -> bool {
         self.basename() == other.basename()
     }
+
+    /// Check if two files are variants of the same name once version and
+    /// environment suffixes are stripped (for --ignore-same-name-fuzzy),
+    /// e.g. `Foo.java` and `Foo.v2.java`, or `config.dev.yaml` and
+    /// `config.prod.yaml`
+    pub fn has_similar_basename(&self, other: &SourceFile) -> bool {
+        variant_stem(self.basename()) == variant_stem(other.basename())
+    }
+}
+
+/// Map functions given as ranges of original line numbers to the ranges of
+/// the cleaned lines within them
+fn function_lines(lines: &[SourceLine], functions: &[RangeInclusive<usize>]) -> Vec<Range<usize>> {
+    functions
+        .iter()
+        .map(|f| {
+            let start = lines.partition_point(|l| l.line_number() < *f.start());
+            let end = lines.partition_point(|l| l.line_number() <= *f.end());
+            start..end
+        })
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// Strip variant suffixes (`.v2`, `.dev`, `.prod`, `-copy`) from the name
+/// part of a basename, keeping its extension
+fn variant_stem(basename: &str) -> (&str, &str) {
+    let (mut name, extension) = match basename.rsplit_once('.') {
+        Some((name, extension)) if !name.is_empty() => (name, extension),
+        _ => (basename, ""),
+    };
+
+    loop {
+        let stripped = name
+            .strip_suffix(".dev")
+            .or_else(|| name.strip_suffix(".prod"))
+            .or_else(|| name.strip_suffix("-copy"))
+            .or_else(|| {
+                let (rest, version) = name.rsplit_once(".v")?;
+                (!version.is_empty() && version.chars().all(|c| c.is_ascii_digit())).then_some(rest)
+            });
+        match stripped {
+            Some(rest) if !rest.is_empty() => name = rest,
+            _ => return (name, extension),
+        }
+    }
 }
 
 impl PartialEq for SourceFile {
@@ -127,6 +347,7 @@ impl Eq for SourceFile {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filetype::LineFilter;
 
     #[test]
     fn test_source_file_from_lines() {
@@ -141,6 +362,104 @@ mod tests {
         assert_eq!(sf.get_line(0).line(), "int x = 5;");
     }
 
+    #[test]
+    fn test_source_file_from_source() {
+        let source = "// comment\nint x = 5;\nint y = 10;\n";
+        let sf = SourceFile::from_source("mem.c".to_string(), source, 3, &BTreeMap::new());
+
+        assert_eq!(sf.filename(), "mem.c");
+        assert_eq!(sf.num_lines(), 2);
+        assert_eq!(sf.get_line(0).line(), "int x = 5;");
+        assert_eq!(sf.get_line(0).line_number(), 2);
+    }
+
+    #[test]
+    fn test_minified_source_split_into_statements() {
+        let chunk = "var total=0;for(var i=0;i<items.length;i++){total+=items[i].price;}";
+        let source = format!("{}{}\n", "x=1;".repeat(150), chunk);
+        let load = |split| {
+            SourceFile::from_source(
+                "app.min.js".to_string(),
+                &source,
+                FileTypeOptions {
+                    split_minified: split,
+                    ..3.into()
+                },
+                &BTreeMap::new(),
+            )
+        };
+
+        assert_eq!(load(false).num_lines(), 1);
+
+        let sf = load(true);
+        assert!(sf.num_lines() > 150);
+        // Every piece still points at the single physical line
+        assert!(sf.lines().all(|l| l.line_number() == 1));
+    }
+
+    #[test]
+    fn test_normalize_strings_per_language() {
+        let load = |name: &str, source: &str, normalize_strings| {
+            let filter = LineFilter {
+                normalize_strings,
+                ..3.into()
+            };
+            let sf = SourceFile::from_source(name.to_string(), source, filter, &BTreeMap::new());
+            sf.get_line(0).line().to_string()
+        };
+
+        let c = "log(\"saving\", 'x');\n";
+        assert_eq!(load("a.c", c, false), "log(\"saving\", 'x');");
+        assert_eq!(load("a.c", c, true), "log(\"STR\", 'x');");
+        // Single quotes delimit strings in Python
+        assert_eq!(load("a.py", c, true), "log(\"STR\", 'STR');");
+    }
+
+    #[test]
+    fn test_hash_algorithm_applied() {
+        let load = |hash| {
+            SourceFile::from_source(
+                "a.c".to_string(),
+                "int x = 5;\nint  x  = 5;\n",
+                LineFilter { hash, ..3.into() },
+                &BTreeMap::new(),
+            )
+        };
+
+        let fnv = load(HashAlgorithm::Fnv1a);
+        let xx = load(HashAlgorithm::Xxhash);
+        assert_eq!(xx.get_line(0), xx.get_line(1));
+        assert_ne!(fnv.get_line(0).hash(), xx.get_line(0).hash());
+        assert_eq!(
+            xx.get_line(0).hash(),
+            SourceLine::with_algorithm("int x = 5;".to_string(), 1, HashAlgorithm::Xxhash).hash()
+        );
+    }
+
+    #[test]
+    fn test_language() {
+        let sf = SourceFile::from_lines("a.c".to_string(), vec![]);
+        assert_eq!(sf.language(), "C/C++");
+
+        let mut extension_map = BTreeMap::new();
+        extension_map.insert("ino".to_string(), "c".to_string());
+        let sf = SourceFile::from_cached_lines("sketch.ino".to_string(), vec![], &extension_map);
+        assert_eq!(sf.language(), "C/C++");
+    }
+
+    #[test]
+    fn test_line_hashes() {
+        let lines = vec![
+            SourceLine::new("line1".to_string(), 1),
+            SourceLine::new("line2".to_string(), 2),
+            SourceLine::new("line3".to_string(), 3),
+        ];
+        let sf = SourceFile::from_lines("test.c".to_string(), lines);
+
+        let hashes: Vec<u32> = sf.line_hashes(1, 2).collect();
+        assert_eq!(hashes, vec![sf.get_line(1).hash(), sf.get_line(2).hash()]);
+    }
+
     #[test]
     fn test_basename() {
         let sf = SourceFile::from_lines("/path/to/test.c".to_string(), vec![]);
@@ -157,6 +476,25 @@ mod tests {
         assert!(!sf1.has_same_basename(&sf3));
     }
 
+    #[test]
+    fn test_similar_basename() {
+        let similar = |a: &str, b: &str| {
+            SourceFile::from_lines(a.to_string(), vec![])
+                .has_similar_basename(&SourceFile::from_lines(b.to_string(), vec![]))
+        };
+
+        assert!(similar("/a/Foo.java", "/b/Foo.v2.java"));
+        assert!(similar("/a/Foo.v1.java", "/b/Foo.v12.java"));
+        assert!(similar("config.dev.yaml", "config.prod.yaml"));
+        assert!(similar("util-copy.c", "util.c"));
+        assert!(similar("util.v2-copy.c", "util.c"));
+
+        assert!(!similar("Foo.java", "Bar.java"));
+        assert!(!similar("Foo.java", "Foo.v2.kt"));
+        assert!(!similar("Foo.vx.java", "Foo.java"));
+        assert!(!similar("config.test.yaml", "config.yaml"));
+    }
+
     #[test]
     fn test_get_lines() {
         let lines = vec![
@@ -1,16 +1,25 @@
 //! Source line representation with hash
 
-use super::hash::hash_line;
+use super::hash::hash_line_with;
+use crate::config::HashAlgorithm;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Represents a single processed source code line
 #[derive(Debug, Clone)]
 pub struct SourceLine {
-    /// The cleaned line text (after comment/preprocessor removal)
-    line: String,
+    /// The cleaned line text (after comment/preprocessor removal), shared
+    /// with identical lines once interned
+    line: Arc<str>,
     /// Original line number in the source file (1-indexed for display)
     line_number: usize,
-    /// FNV-1a hash of the whitespace-normalized line
+    /// Last original line the text spans: a statement joined from several
+    /// physical lines ends after it starts
+    end_line_number: usize,
+    /// Hash of the whitespace-normalized line (FNV-1a unless `--hash` says otherwise)
     hash: u32,
+    /// Secondary 64-bit hash, guards against 32-bit collisions
+    hash64: u64,
 }
 
 impl SourceLine {
@@ -20,25 +29,46 @@ impl SourceLine {
     /// * `line` - The cleaned line text
     /// * `line_number` - The 1-indexed original line number
     pub fn new(line: String, line_number: usize) -> Self {
-        let hash = hash_line(&line);
+        Self::with_algorithm(line, line_number, HashAlgorithm::default())
+    }
+
+    /// Create a new SourceLine hashed with the given algorithm
+    ///
+    /// # Arguments
+    /// * `line` - The cleaned line text
+    /// * `line_number` - The 1-indexed original line number
+    /// * `algorithm` - Hash function applied to the line
+    pub fn with_algorithm(line: String, line_number: usize, algorithm: HashAlgorithm) -> Self {
+        let (hash, hash64) = hash_line_with(&line, algorithm);
         Self {
-            line,
+            line: line.into(),
             line_number,
+            end_line_number: line_number,
             hash,
+            hash64,
         }
     }
 
+    /// Mark the line as spanning original lines up to `end_line_number`
+    pub fn with_end_line(mut self, end_line_number: usize) -> Self {
+        self.end_line_number = end_line_number.max(self.line_number);
+        self
+    }
+
     /// Create a SourceLine from cached data (with pre-computed hash)
     ///
     /// # Arguments
     /// * `line` - The cleaned line text
     /// * `line_number` - The 1-indexed original line number
     /// * `hash` - The pre-computed hash value
-    pub fn from_cached(line: String, line_number: usize, hash: u32) -> Self {
+    /// * `hash64` - The pre-computed secondary hash value
+    pub fn from_cached(line: String, line_number: usize, hash: u32, hash64: u64) -> Self {
         Self {
-            line,
+            line: line.into(),
             line_number,
+            end_line_number: line_number,
             hash,
+            hash64,
         }
     }
 
@@ -54,23 +84,65 @@ impl SourceLine {
         self.line_number
     }
 
+    /// Get the last original line number the line spans (1-indexed)
+    #[inline]
+    pub fn end_line_number(&self) -> usize {
+        self.end_line_number
+    }
+
     /// Get the hash value
     #[inline]
     pub fn hash(&self) -> u32 {
         self.hash
     }
+
+    /// Get the secondary 64-bit hash value
+    #[inline]
+    pub fn hash64(&self) -> u64 {
+        self.hash64
+    }
 }
 
 impl PartialEq for SourceLine {
-    /// Two source lines are equal if their hashes match
+    /// Two source lines are equal if both their hashes match
     /// This provides fast comparison for duplicate detection
     fn eq(&self, other: &Self) -> bool {
-        self.hash == other.hash
+        self.hash == other.hash && self.hash64 == other.hash64
     }
 }
 
 impl Eq for SourceLine {}
 
+/// Table of the cleaned line texts seen so far, so that identical lines
+/// across files (license headers, common idioms) share one allocation
+#[derive(Debug, Default)]
+pub struct LineInterner {
+    /// Distinct texts by line hashes; lines differing only in whitespace
+    /// share the hashes but keep their own text
+    texts: HashMap<(u32, u64), Vec<Arc<str>>>,
+}
+
+impl LineInterner {
+    /// Point `line` at the stored copy of its text, storing it if new
+    pub fn intern(&mut self, line: &mut SourceLine) {
+        let texts = self.texts.entry((line.hash, line.hash64)).or_default();
+        match texts.iter().find(|text| **text == line.line) {
+            Some(text) => line.line = Arc::clone(text),
+            None => texts.push(Arc::clone(&line.line)),
+        }
+    }
+
+    /// Number of distinct texts stored
+    pub fn len(&self) -> usize {
+        self.texts.values().map(Vec::len).sum()
+    }
+
+    /// Whether no text has been stored yet
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,4 +175,61 @@ mod tests {
         // Whitespace differences should be normalized in hash
         assert_eq!(line1, line2);
     }
+
+    #[test]
+    fn test_interned_files_share_line_storage() {
+        use crate::core::SourceFile;
+        use std::collections::BTreeMap;
+
+        let header: String = (1..=20)
+            .map(|i| format!("int shared_{} = {};\n", i, i))
+            .collect();
+        let load = |name: &str, body: &str| {
+            SourceFile::from_source(
+                name.to_string(),
+                &format!("{}{}", header, body),
+                3,
+                &BTreeMap::new(),
+            )
+        };
+        let mut a = load("a.c", "int only_a = 1;\n");
+        let mut b = load("b.c", "int  shared_1  =  1;\n");
+        let hashes = |f: &SourceFile| {
+            f.lines()
+                .map(|l| (l.hash(), l.hash64()))
+                .collect::<Vec<_>>()
+        };
+        let before = (hashes(&a), hashes(&b));
+
+        let mut interner = LineInterner::default();
+        a.intern_lines(&mut interner);
+        b.intern_lines(&mut interner);
+
+        // The 20 shared lines are stored once
+        assert_eq!(interner.len(), 20 + 2);
+        for (line_a, line_b) in a.lines().zip(b.lines()).take(20) {
+            assert!(Arc::ptr_eq(&line_a.line, &line_b.line));
+        }
+        // Same hashes, so the same equality as before; a whitespace variant
+        // equals its original but keeps its own text
+        assert_eq!((hashes(&a), hashes(&b)), before);
+        assert_eq!(b.get_line(20), a.get_line(0));
+        assert_eq!(b.get_line(20).line(), "int  shared_1  =  1;");
+        assert!(!Arc::ptr_eq(&b.get_line(20).line, &a.get_line(0).line));
+    }
+
+    #[test]
+    fn test_source_line_32bit_collision_not_equal() {
+        // Contrived: same 32-bit hash, different content
+        let line1 = SourceLine::new("int x = 5;".to_string(), 1);
+        let line2 = SourceLine::from_cached(
+            "int y = 6;".to_string(),
+            2,
+            line1.hash(),
+            crate::core::hash::hash_line64("int y = 6;"),
+        );
+
+        assert_eq!(line1.hash(), line2.hash());
+        assert_ne!(line1, line2);
+    }
 }
@@ -0,0 +1,680 @@
+//! Per-file and per-directory duplication statistics
+
+use crate::core::hash::fnv1a_hash64;
+use crate::core::{Block, DuploResult, SourceFile};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path};
+
+/// Duplication statistics for a single file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileStats {
+    /// Number of analyzable (cleaned) lines
+    pub total_lines: usize,
+    /// Number of those lines covered by at least one duplicate block
+    pub duplicate_lines: usize,
+}
+
+/// Duplication statistics rolled up by directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupStats {
+    /// Directory prefix the files share (e.g. "src/a")
+    pub group: String,
+    /// Number of files in the group
+    pub files: usize,
+    /// Number of analyzable lines in the group
+    pub total_lines: usize,
+    /// Number of duplicated lines in the group
+    pub duplicate_lines: usize,
+}
+
+/// Files linked to each other, directly or through other files, by
+/// duplicate blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneFamily {
+    /// Member files, sorted by name
+    pub files: Vec<String>,
+    /// Number of duplicate blocks between members
+    pub blocks: usize,
+    /// Lines those blocks span, counted once per block
+    pub duplicate_lines: usize,
+}
+
+impl FileStats {
+    /// Percentage of the file's lines that are duplicated
+    pub fn duplication_percent(&self) -> f64 {
+        if self.total_lines > 0 {
+            (self.duplicate_lines as f64 / self.total_lines as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl GroupStats {
+    /// Percentage of lines in the group that are duplicated
+    pub fn duplication_percent(&self) -> f64 {
+        if self.total_lines > 0 {
+            (self.duplicate_lines as f64 / self.total_lines as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Compute duplication statistics for each file, indexed like `source_files`
+///
+/// A line covered by several blocks is counted once.
+pub fn per_file_stats(result: &DuploResult, source_files: &[SourceFile]) -> Vec<FileStats> {
+    let mut covered: Vec<Vec<bool>> = source_files
+        .iter()
+        .map(|f| vec![false; f.num_lines()])
+        .collect();
+
+    for block in &result.blocks {
+        covered[block.source1_idx][block.line1..block.end1()].fill(true);
+        covered[block.source2_idx][block.line2..block.end2()].fill(true);
+    }
+
+    covered
+        .iter()
+        .map(|lines| FileStats {
+            total_lines: lines.len(),
+            duplicate_lines: lines.iter().filter(|&&c| c).count(),
+        })
+        .collect()
+}
+
+/// Files more than `percent` duplicated, most duplicated first
+pub fn files_over_threshold<'a>(
+    result: &DuploResult,
+    source_files: &'a [SourceFile],
+    percent: f64,
+) -> Vec<(&'a str, FileStats)> {
+    let mut over: Vec<(&str, FileStats)> = source_files
+        .iter()
+        .map(|f| f.filename())
+        .zip(per_file_stats(result, source_files))
+        .filter(|(_, stats)| stats.duplication_percent() > percent)
+        .collect();
+    over.sort_by(|(name_a, a), (name_b, b)| {
+        b.duplication_percent()
+            .total_cmp(&a.duplication_percent())
+            .then(name_a.cmp(name_b))
+    });
+    over
+}
+
+/// Duplication debt: the sum of `line_count ^ exponent` over all blocks
+///
+/// Every block is one pair of copies, so a clone repeated in more places
+/// adds a term per extra copy, and an exponent above 1 makes one large clone
+/// weigh more than several small ones with the same total lines.
+pub fn debt_score(result: &DuploResult, exponent: f64) -> f64 {
+    result
+        .blocks
+        .iter()
+        .map(|b| (b.count as f64).powf(exponent))
+        .sum()
+}
+
+/// Roll up per-file statistics by the first `depth` directory components of
+/// each file, relative to `base` when the file lives under it
+pub fn group_stats(
+    result: &DuploResult,
+    source_files: &[SourceFile],
+    depth: usize,
+    base: &Path,
+) -> Vec<GroupStats> {
+    let mut groups: BTreeMap<String, GroupStats> = BTreeMap::new();
+
+    for (file, stats) in source_files
+        .iter()
+        .zip(per_file_stats(result, source_files))
+    {
+        let key = group_key(file.filename(), depth, base);
+        let group = groups.entry(key.clone()).or_insert_with(|| GroupStats {
+            group: key,
+            files: 0,
+            total_lines: 0,
+            duplicate_lines: 0,
+        });
+        group.files += 1;
+        group.total_lines += stats.total_lines;
+        group.duplicate_lines += stats.duplicate_lines;
+    }
+
+    groups.into_values().collect()
+}
+
+/// Group files into clone families: the connected components of the graph
+/// whose edges are the duplicate blocks
+///
+/// A file duplicating B and B duplicating C puts all three in one family
+/// even when no block pairs the first and last directly (e.g. because the
+/// `files_to_check` limit kept them apart). Files whose duplicates all lie
+/// within themselves form no family. Largest families come first.
+pub fn clone_families(result: &DuploResult, source_files: &[SourceFile]) -> Vec<CloneFamily> {
+    // Union-find over file indices
+    let mut parent: Vec<usize> = (0..source_files.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for block in &result.blocks {
+        let a = root(&mut parent, block.source1_idx);
+        let b = root(&mut parent, block.source2_idx);
+        parent[a.max(b)] = a.min(b);
+    }
+
+    let mut families: BTreeMap<usize, CloneFamily> = BTreeMap::new();
+    for block in &result.blocks {
+        let family = families
+            .entry(root(&mut parent, block.source1_idx))
+            .or_insert_with(|| CloneFamily {
+                files: Vec::new(),
+                blocks: 0,
+                duplicate_lines: 0,
+            });
+        family.blocks += 1;
+        family.duplicate_lines += block.count;
+    }
+    for (idx, file) in source_files.iter().enumerate() {
+        if let Some(family) = families.get_mut(&root(&mut parent, idx)) {
+            family.files.push(file.filename().to_string());
+        }
+    }
+
+    let mut families: Vec<CloneFamily> = families
+        .into_values()
+        .filter(|f| f.files.len() > 1)
+        .map(|mut f| {
+            f.files.sort();
+            f
+        })
+        .collect();
+    families.sort_by(|a, b| {
+        b.files
+            .len()
+            .cmp(&a.files.len())
+            .then_with(|| a.files.cmp(&b.files))
+    });
+    families
+}
+
+/// Lower bounds of the block size buckets of `size_histogram`, after the
+/// first one, which starts at the minimum block size
+const HISTOGRAM_BOUNDS: [usize; 3] = [10, 25, 50];
+
+/// Blocks of one size range, for `--histogram`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBucket {
+    /// Smallest line count in the bucket
+    pub min_lines: usize,
+    /// Largest line count in the bucket; None for the open-ended last one
+    pub max_lines: Option<usize>,
+    /// Number of blocks in the bucket
+    pub blocks: usize,
+    /// Lines those blocks span
+    pub lines: usize,
+}
+
+/// Count the blocks of each size range (4-9, 10-24, 25-49 and 50+ lines
+/// with the default minimum block size)
+///
+/// Ranges that lie entirely below `min_block_size` are left out, since no
+/// block can fall into them.
+pub fn size_histogram(result: &DuploResult, min_block_size: usize) -> Vec<SizeBucket> {
+    let mut bounds = vec![min_block_size.max(1)];
+    bounds.extend(HISTOGRAM_BOUNDS.iter().filter(|&&b| b > min_block_size));
+
+    let mut buckets: Vec<SizeBucket> = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, &min_lines)| SizeBucket {
+            min_lines,
+            max_lines: bounds.get(i + 1).map(|next| next - 1),
+            blocks: 0,
+            lines: 0,
+        })
+        .collect();
+    for block in &result.blocks {
+        // Blocks below the minimum (from a lower --min-lines elsewhere)
+        // count towards the first bucket
+        let i = bounds.partition_point(|&b| b <= block.count).max(1) - 1;
+        buckets[i].blocks += 1;
+        buckets[i].lines += block.count;
+    }
+    buckets
+}
+
+/// Two files sharing most of their lines, in whatever order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSimilarity {
+    pub file1: String,
+    pub file2: String,
+    /// Jaccard similarity of the files' sets of lines, from 0 to 1
+    pub similarity: f64,
+}
+
+/// File pairs whose line sets have a Jaccard similarity of at least
+/// `threshold`, most similar first
+///
+/// Lines are compared by hash, so whitespace changes don't count, and order
+/// is ignored: files with the same functions arranged differently score 1
+/// even though block detection finds only pieces of them.
+pub fn similar_file_pairs(source_files: &[SourceFile], threshold: f64) -> Vec<FileSimilarity> {
+    let line_sets: Vec<HashSet<(u32, u64)>> = source_files
+        .iter()
+        .map(|f| f.lines().map(|l| (l.hash(), l.hash64())).collect())
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, set1) in line_sets.iter().enumerate() {
+        for (j, set2) in line_sets.iter().enumerate().skip(i + 1) {
+            let (smaller, larger) = if set1.len() <= set2.len() {
+                (set1, set2)
+            } else {
+                (set2, set1)
+            };
+            // The intersection can't exceed the smaller set
+            if larger.is_empty() || (smaller.len() as f64) < threshold * larger.len() as f64 {
+                continue;
+            }
+            let shared = smaller.iter().filter(|line| larger.contains(line)).count();
+            let similarity = shared as f64 / (set1.len() + set2.len() - shared) as f64;
+            if similarity >= threshold {
+                pairs.push(FileSimilarity {
+                    file1: source_files[i].filename().to_string(),
+                    file2: source_files[j].filename().to_string(),
+                    similarity,
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| (&a.file1, &a.file2).cmp(&(&b.file1, &b.file2)))
+    });
+    pairs
+}
+
+/// How often distinct lines share a hash (`--collision-report`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CollisionStats {
+    /// Distinct lines, ignoring whitespace as the hashes do
+    pub distinct_lines: usize,
+    /// Primary (32-bit) hash values shared by two or more distinct lines.
+    /// These only cost time: such lines are told apart by the second hash
+    pub primary_collisions: usize,
+    /// Primary and secondary hash pairs shared by two or more distinct
+    /// lines. Each can make different lines match, i.e. false positives
+    pub full_collisions: usize,
+}
+
+/// Count the hash collisions among the lines of `source_files`
+///
+/// Lines are compared by their text with whitespace removed, the form that
+/// is hashed, so lines differing only in spacing aren't collisions.
+pub fn collision_stats(source_files: &[SourceFile]) -> CollisionStats {
+    // Distinct (secondary hash, text) pairs under each primary hash
+    let mut buckets: HashMap<u32, HashSet<(u64, String)>> = HashMap::new();
+    for line in source_files.iter().flat_map(|f| f.lines()) {
+        let text: String = line.line().chars().filter(|&c| c > ' ').collect();
+        buckets
+            .entry(line.hash())
+            .or_default()
+            .insert((line.hash64(), text));
+    }
+
+    let mut stats = CollisionStats::default();
+    for entries in buckets.values() {
+        stats.distinct_lines += entries.len();
+        let texts: HashSet<&str> = entries.iter().map(|(_, t)| t.as_str()).collect();
+        if texts.len() > 1 {
+            stats.primary_collisions += 1;
+        }
+        let mut per_secondary: HashMap<u64, usize> = HashMap::new();
+        for (hash64, _) in entries {
+            *per_secondary.entry(*hash64).or_default() += 1;
+        }
+        stats.full_collisions += per_secondary.values().filter(|&&n| n > 1).count();
+    }
+    stats
+}
+
+/// Identifier of the clone class a block belongs to
+///
+/// Derived from the cleaned content of both sides, sorted so it doesn't
+/// depend on which file came first, and hashed with FNV-1a so it is the same
+/// in every run and build. Blocks duplicating the same code share the id
+/// whichever files they are found in.
+pub fn class_id(block: &Block, source_files: &[SourceFile]) -> u64 {
+    let side_hash = |idx: usize, start: usize| {
+        let bytes: Vec<u8> = source_files[idx].lines_slice()[start..start + block.count]
+            .iter()
+            .flat_map(|l| l.hash64().to_le_bytes())
+            .collect();
+        fnv1a_hash64(&bytes)
+    };
+    let (a, b) = (
+        side_hash(block.source1_idx, block.line1),
+        side_hash(block.source2_idx, block.line2),
+    );
+    let mut bytes = a.min(b).to_le_bytes().to_vec();
+    bytes.extend(a.max(b).to_le_bytes());
+    fnv1a_hash64(&bytes)
+}
+
+/// The alphabetically first file among the blocks of each clone class, by
+/// `class_id`
+pub fn class_representatives<'a>(
+    result: &DuploResult,
+    source_files: &'a [SourceFile],
+) -> HashMap<u64, &'a str> {
+    let mut representatives: HashMap<u64, &str> = HashMap::new();
+    for block in &result.blocks {
+        let first = source_files[block.source1_idx]
+            .filename()
+            .min(source_files[block.source2_idx].filename());
+        representatives
+            .entry(class_id(block, source_files))
+            .and_modify(|r| *r = (*r).min(first))
+            .or_insert(first);
+    }
+    representatives
+}
+
+/// First `depth` directory components of a file's path ("." for top-level files)
+fn group_key(filename: &str, depth: usize, base: &Path) -> String {
+    let path = Path::new(filename);
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    let dir = relative.parent().unwrap_or(Path::new(""));
+
+    let components: Vec<String> = dir
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .take(depth)
+        .collect();
+
+    if components.is_empty() {
+        ".".to_string()
+    } else {
+        components.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, SourceLine};
+
+    fn file(name: &str, lines: usize) -> SourceFile {
+        let lines = (1..=lines)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        SourceFile::from_lines(name.to_string(), lines)
+    }
+
+    fn result(blocks: Vec<Block>) -> DuploResult {
+        DuploResult {
+            files_analyzed: 3,
+            files_provided: 3,
+            total_lines: 30,
+            ..DuploResult::from_blocks(blocks)
+        }
+    }
+
+    #[test]
+    fn test_per_file_stats_counts_overlaps_once() {
+        let files = vec![file("a.c", 10), file("b.c", 10)];
+        let result = result(vec![Block::new(0, 1, 0, 0, 4), Block::new(0, 1, 2, 5, 4)]);
+
+        let stats = per_file_stats(&result, &files);
+        assert_eq!(stats[0].duplicate_lines, 6);
+        assert_eq!(stats[1].duplicate_lines, 8);
+        assert_eq!(stats[0].total_lines, 10);
+    }
+
+    #[test]
+    fn test_files_over_threshold() {
+        let files = vec![file("a.c", 10), file("b.c", 20), file("c.c", 8)];
+        // a.c 40% and b.c 20% duplicated; c.c fully, by repeating itself
+        let result = result(vec![Block::new(0, 1, 0, 0, 4), Block::new(2, 2, 0, 4, 4)]);
+
+        let over: Vec<(&str, f64)> = files_over_threshold(&result, &files, 20.0)
+            .into_iter()
+            .map(|(name, stats)| (name, stats.duplication_percent()))
+            .collect();
+        assert_eq!(over, vec![("c.c", 100.0), ("a.c", 40.0)]);
+        assert!(files_over_threshold(&result, &files, 100.0).is_empty());
+    }
+
+    #[test]
+    fn test_clone_families_are_transitive() {
+        let files = vec![
+            file("a.c", 10),
+            file("b.c", 10),
+            file("c.c", 10),
+            file("d.c", 10),
+            file("e.c", 10),
+            file("f.c", 10),
+        ];
+        // a~b and b~c, but never a~c; d~e apart; f only repeats itself
+        let result = result(vec![
+            Block::new(0, 1, 0, 0, 4),
+            Block::new(1, 2, 5, 0, 3),
+            Block::new(3, 4, 0, 0, 5),
+            Block::new(5, 5, 0, 5, 4),
+        ]);
+
+        let families = clone_families(&result, &files);
+        assert_eq!(
+            families,
+            vec![
+                CloneFamily {
+                    files: vec!["a.c".to_string(), "b.c".to_string(), "c.c".to_string()],
+                    blocks: 2,
+                    duplicate_lines: 7,
+                },
+                CloneFamily {
+                    files: vec!["d.c".to_string(), "e.c".to_string()],
+                    blocks: 1,
+                    duplicate_lines: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_id_follows_content() {
+        let files = vec![file("b.c", 10), file("c.c", 10), file("a.c", 10)];
+        let same = Block::new(0, 1, 0, 0, 4);
+        let elsewhere = Block::new(1, 2, 0, 0, 4);
+        let other_lines = Block::new(0, 1, 4, 4, 4);
+
+        let id = class_id(&same, &files);
+        assert_eq!(class_id(&elsewhere, &files), id);
+        assert_ne!(class_id(&other_lines, &files), id);
+        // The files' order doesn't matter
+        assert_eq!(class_id(&Block::new(1, 0, 0, 0, 4), &files), id);
+
+        let other_id = class_id(&other_lines, &files);
+        let result = result(vec![same, elsewhere, other_lines]);
+        let representatives = class_representatives(&result, &files);
+        assert_eq!(representatives.len(), 2);
+        assert_eq!(representatives[&id], "a.c");
+        assert_eq!(representatives[&other_id], "b.c");
+    }
+
+    #[test]
+    fn test_similar_file_pairs_ignore_order() {
+        let source = |name: &str, lines: &[&str]| {
+            SourceFile::from_lines(
+                name.to_string(),
+                lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, l)| SourceLine::new(l.to_string(), i + 1))
+                    .collect(),
+            )
+        };
+        let files = vec![
+            source("a.c", &["int f() {", "return 1;", "int g() {", "return 2;"]),
+            // Same functions, swapped
+            source("b.c", &["int g() {", "return 2;", "int f() {", "return 1;"]),
+            // Shares two of six distinct lines with each
+            source("c.c", &["int f() {", "return 1;", "int h() {", "return 3;"]),
+        ];
+
+        let pairs = similar_file_pairs(&files, 0.3);
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(
+            (pairs[0].file1.as_str(), pairs[0].file2.as_str()),
+            ("a.c", "b.c")
+        );
+        assert_eq!(pairs[0].similarity, 1.0);
+        assert_eq!(pairs[1].similarity, 2.0 / 6.0);
+
+        assert_eq!(similar_file_pairs(&files, 0.5).len(), 1);
+    }
+
+    #[test]
+    fn test_size_histogram() {
+        let sizes = [4, 9, 10, 24, 25, 60, 200];
+        let result = result(
+            sizes
+                .iter()
+                .map(|&count| Block::new(0, 1, 0, 0, count))
+                .collect(),
+        );
+
+        let counts: Vec<(usize, Option<usize>, usize)> = size_histogram(&result, 4)
+            .into_iter()
+            .map(|b| (b.min_lines, b.max_lines, b.blocks))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (4, Some(9), 2),
+                (10, Some(24), 2),
+                (25, Some(49), 1),
+                (50, None, 2)
+            ]
+        );
+        assert_eq!(size_histogram(&result, 4)[3].lines, 260);
+
+        // A higher minimum narrows the first bucket and drops the ones below
+        let buckets = size_histogram(&result, 30);
+        assert_eq!((buckets[0].min_lines, buckets[0].max_lines), (30, Some(49)));
+        assert_eq!(buckets.len(), 2);
+    }
+
+    #[test]
+    fn test_collision_stats() {
+        let line = |text: &str, hash: u32, hash64: u64| {
+            SourceLine::from_cached(text.to_string(), 1, hash, hash64)
+        };
+        let files = vec![
+            SourceFile::from_lines(
+                "a.c".to_string(),
+                vec![
+                    line("int x = 1;", 7, 70),
+                    // Same text but for spacing, so the same line
+                    line("int x=1;", 7, 70),
+                    // Shares the primary hash only
+                    line("int y = 2;", 7, 71),
+                    line("int z = 3;", 8, 80),
+                ],
+            ),
+            SourceFile::from_lines(
+                "b.c".to_string(),
+                // Contrived: both hashes of a different line
+                vec![line("return total;", 8, 80), line("int x = 1;", 7, 70)],
+            ),
+        ];
+
+        assert_eq!(
+            collision_stats(&files),
+            CollisionStats {
+                distinct_lines: 4,
+                primary_collisions: 2,
+                full_collisions: 1,
+            }
+        );
+        assert_eq!(collision_stats(&files[..1]).full_collisions, 0);
+    }
+
+    #[test]
+    fn test_group_stats_by_directory() {
+        let files = vec![
+            file("/repo/src/a/x.c", 10),
+            file("/repo/src/a/y.c", 10),
+            file("/repo/src/b/z.c", 10),
+        ];
+        let result = result(vec![Block::new(0, 2, 0, 0, 4)]);
+        let base = Path::new("/repo");
+
+        let groups = group_stats(&result, &files, 2, base);
+        assert_eq!(
+            groups,
+            vec![
+                GroupStats {
+                    group: "src/a".to_string(),
+                    files: 2,
+                    total_lines: 20,
+                    duplicate_lines: 4,
+                },
+                GroupStats {
+                    group: "src/b".to_string(),
+                    files: 1,
+                    total_lines: 10,
+                    duplicate_lines: 4,
+                },
+            ]
+        );
+        assert_eq!(groups[0].duplication_percent(), 20.0);
+
+        let groups = group_stats(&result, &files, 1, base);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].group, "src");
+        assert_eq!(groups[0].total_lines, 30);
+        assert_eq!(groups[0].duplicate_lines, 8);
+    }
+
+    #[test]
+    fn test_debt_score_grows_with_size_and_instances() {
+        let one = debt_score(&result(vec![Block::new(0, 1, 0, 0, 4)]), 1.5);
+        assert_eq!(one, 8.0);
+
+        // A larger block outweighs two smaller ones with the same total lines
+        let larger = debt_score(&result(vec![Block::new(0, 1, 0, 0, 8)]), 1.5);
+        let split = debt_score(
+            &result(vec![Block::new(0, 1, 0, 0, 4), Block::new(0, 1, 4, 4, 4)]),
+            1.5,
+        );
+        assert!(larger > split);
+
+        // Each extra copy of a clone adds to the score
+        let more_copies = debt_score(
+            &result(vec![Block::new(0, 1, 0, 0, 4), Block::new(0, 2, 0, 0, 4)]),
+            1.5,
+        );
+        assert!(more_copies > one);
+
+        assert_eq!(debt_score(&result(vec![]), 1.5), 0.0);
+    }
+
+    #[test]
+    fn test_group_key_top_level_file() {
+        assert_eq!(group_key("/repo/main.c", 2, Path::new("/repo")), ".");
+        assert_eq!(group_key("lib/util.c", 3, Path::new("/elsewhere")), "lib");
+    }
+}
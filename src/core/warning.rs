@@ -0,0 +1,93 @@
+//! Structured warnings collected during a run
+//!
+//! Warnings are still printed to stderr as they happen; collecting them lets
+//! the JSON and XML exporters report them in a form CI can act on.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Category of a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A listed file or archive could not be read
+    FileUnreadable,
+    /// A file was skipped for exceeding --max-file-bytes
+    FileTooLarge,
+    /// The cache could not be set up or written
+    Cache,
+    /// The baseline may not be comparable with this run
+    Baseline,
+    /// Detection was interrupted, so the results are partial
+    Interrupted,
+    /// The Ctrl-C handler couldn't be installed, so an interrupt quits
+    /// without reporting partial results
+    SignalHandler,
+}
+
+impl WarningKind {
+    /// Name used in structured output (e.g. "file_unreadable")
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::FileUnreadable => "file_unreadable",
+            WarningKind::FileTooLarge => "file_too_large",
+            WarningKind::Cache => "cache",
+            WarningKind::Baseline => "baseline",
+            WarningKind::Interrupted => "interrupted",
+            WarningKind::SignalHandler => "signal_handler",
+        }
+    }
+}
+
+/// A non-fatal problem encountered during a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// Category of the warning
+    pub kind: WarningKind,
+    /// File the warning is about, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Warning {
+    /// Create a warning not tied to a file
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            path: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attach the file the warning is about
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_serialization() {
+        let warning = Warning::new(WarningKind::FileUnreadable, "gone").with_path("a.c");
+        let json = serde_json::to_value(&warning).unwrap();
+        assert_eq!(json["kind"], WarningKind::FileUnreadable.name());
+        assert_eq!(json["path"], "a.c");
+        assert_eq!(json["message"], "gone");
+
+        let json = serde_json::to_value(Warning::new(WarningKind::Baseline, "stale")).unwrap();
+        assert!(json.get("path").is_none());
+        assert_eq!(warning.to_string(), "gone");
+    }
+}
@@ -0,0 +1,154 @@
+//! Builder for running detection from other programs
+
+use crate::config::{Config, CrossLanguage};
+use crate::core::{process_files_with_list, process_source_files, DuploResult, SourceFile};
+use crate::error::Result;
+
+/// Fluent builder over [`Config`] that runs duplicate detection
+///
+/// Starts from the same defaults as the command line tool.
+///
+/// ```
+/// use lucidshark_duplo::Detector;
+///
+/// let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n";
+/// let (result, files) = Detector::new()
+///     .min_lines(4)
+///     .min_chars(3)
+///     .threads(2)
+///     .ignore_same_name(true)
+///     .detect_sources([("a.c", code), ("b.c", code)])
+///     .unwrap();
+///
+/// assert_eq!(result.duplicate_blocks, 1);
+/// let block = &result.blocks[0];
+/// assert_eq!(files[block.source1_idx].filename(), "a.c");
+/// assert_eq!(block.count, 4);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Detector {
+    config: Config,
+}
+
+impl Detector {
+    /// Create a detector with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a detector from a fully specified configuration
+    pub fn from_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Minimum number of lines in a reported block
+    pub fn min_lines(mut self, n: u32) -> Self {
+        self.config.min_block_size = n;
+        self
+    }
+
+    /// Minimum characters for a line to be compared
+    pub fn min_chars(mut self, n: u32) -> Self {
+        self.config.min_chars = n;
+        self
+    }
+
+    /// Number of worker threads
+    pub fn threads(mut self, n: usize) -> Self {
+        self.config.num_threads = n;
+        self
+    }
+
+    /// Keep the thread pool for later runs instead of building one per run
+    ///
+    /// Worth enabling when detecting repeatedly from a long-lived process.
+    pub fn reuse_thread_pool(mut self, enabled: bool) -> Self {
+        self.config.reuse_thread_pool = enabled;
+        self
+    }
+
+    /// Don't compare files that share a file name
+    pub fn ignore_same_name(mut self, enabled: bool) -> Self {
+        self.config.ignore_same_filename = enabled;
+        self
+    }
+
+    /// Allow up to `n` differing lines inside a block
+    pub fn max_gap(mut self, n: usize) -> Self {
+        self.config.max_gap = n;
+        self
+    }
+
+    /// Whether files of different languages are compared
+    pub fn cross_language(mut self, cross_language: CrossLanguage) -> Self {
+        self.config.cross_language = cross_language;
+        self
+    }
+
+    /// The configuration detection runs with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Detect duplicates among the files at `paths`
+    pub fn detect(&self, paths: &[String]) -> Result<(DuploResult, Vec<SourceFile>)> {
+        self.config.validate()?;
+        process_files_with_list(paths, &self.config, |_| {})
+    }
+
+    /// Detect duplicates among in-memory sources, given as (file name, text)
+    /// pairs; the file name selects the language
+    pub fn detect_sources<N, T>(
+        &self,
+        sources: impl IntoIterator<Item = (N, T)>,
+    ) -> Result<(DuploResult, Vec<SourceFile>)>
+    where
+        N: Into<String>,
+        T: AsRef<str>,
+    {
+        self.config.validate()?;
+        let config = &self.config;
+        let options = config.file_type_options();
+        let source_files = sources
+            .into_iter()
+            .map(|(name, text)| {
+                SourceFile::from_source(
+                    name.into(),
+                    text.as_ref(),
+                    options.clone(),
+                    &config.extension_map,
+                )
+            })
+            .collect();
+        process_source_files(source_files, config, |_| {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_detection_with_reused_pool() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = load(1);\nint b = load(2);\nint c = load(3);\nint d = load(4);\n";
+        let paths: Vec<String> = ["a.c", "b.c", "c.c"]
+            .iter()
+            .map(|name| {
+                let path = temp.path().join(name);
+                std::fs::write(&path, code).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let detector = Detector::new()
+            .min_lines(4)
+            .threads(3)
+            .reuse_thread_pool(true);
+        for _ in 0..50 {
+            let (result, files) = detector.detect(&paths).unwrap();
+            assert_eq!(result.duplicate_blocks, 3);
+            assert_eq!(files.len(), 3);
+        }
+    }
+}
@@ -34,7 +34,9 @@ pub enum DuploError {
     InvalidConfig(String),
 
     /// Conflicting output format options
-    #[error("Output format conflict: specify only one of --json or --xml")]
+    #[error(
+        "Output format conflict: specify only one of --json, --xml, --diff, --markdown or --yaml"
+    )]
     OutputFormatConflict,
 
     /// I/O error during file operations
@@ -61,6 +63,10 @@ pub enum DuploError {
     #[error("Baseline version {found} is not supported (expected {expected})")]
     BaselineVersionMismatch { found: u32, expected: u32 },
 
+    /// Detection results that don't match the files they were found in
+    #[error("Inconsistent results: {0}")]
+    InconsistentResult(String),
+
     /// Generic error for other cases
     #[error("{0}")]
     Other(String),
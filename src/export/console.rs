@@ -1,14 +1,126 @@
 //! Console (human-readable) exporter
 
 use crate::config::Config;
-use crate::core::{DuploResult, SourceFile};
+use crate::core::stats::{
+    clone_families, collision_stats, debt_score, files_over_threshold, group_stats,
+    similar_file_pairs, size_histogram,
+};
+use crate::core::{Block, DuploResult, SourceFile};
 use crate::error::Result;
 use crate::export::Exporter;
+use anstyle::{AnsiColor, Style};
+use std::fmt::Display;
 use std::io::Write;
 
 /// Human-readable console output exporter
 pub struct ConsoleExporter;
 
+const FILE_STYLE: Style = Style::new().bold();
+const RANGE_STYLE: Style = AnsiColor::Cyan.on_default();
+const HEADING_STYLE: Style = Style::new().bold().underline();
+
+/// Duplication at or above this percentage is shown in red
+const HIGH_DUPLICATION: f64 = 10.0;
+/// Duplication at or above this percentage (and below `HIGH_DUPLICATION`)
+/// is shown in yellow; anything lower in green
+const MODERATE_DUPLICATION: f64 = 3.0;
+
+/// `text` wrapped in the escapes for `style` when `color` is set, otherwise
+/// unchanged
+fn paint(text: impl Display, style: Style, color: bool) -> String {
+    if color {
+        format!("{style}{text}{style:#}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Style of a duplication percentage, from green to red
+fn duplication_style(percent: f64) -> Style {
+    let color = if percent >= HIGH_DUPLICATION {
+        AnsiColor::Red
+    } else if percent >= MODERATE_DUPLICATION {
+        AnsiColor::Yellow
+    } else {
+        AnsiColor::Green
+    };
+    color.on_default().bold()
+}
+
+/// Original (1-based) line numbers of the first and last line of a block range
+fn line_range(source: &SourceFile, start: usize, count: usize) -> (usize, usize) {
+    (
+        source.get_line(start).line_number(),
+        source.get_line(start + count - 1).end_line_number(),
+    )
+}
+
+/// Write a block's location as one grep-style `path:line` line per file,
+/// followed by the line range
+fn write_clickable_header(
+    writer: &mut dyn Write,
+    indent: &str,
+    locations: [(&SourceFile, (usize, usize)); 2],
+) -> Result<()> {
+    for (source, (start, end)) in locations {
+        writeln!(
+            writer,
+            "{}{}:{}: lines {}-{}",
+            indent,
+            source.filename(),
+            start,
+            start,
+            end
+        )?;
+    }
+    Ok(())
+}
+
+/// Output blocks under one `file1 <-> file2` header per file pair
+fn write_grouped_by_pair(
+    result: &DuploResult,
+    source_files: &[SourceFile],
+    clickable: bool,
+    color: bool,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    // Blocks are sorted by file pair, so each pair's blocks are contiguous
+    for group in result.blocks.chunk_by(|a: &Block, b: &Block| {
+        (a.source1_idx, a.source2_idx) == (b.source1_idx, b.source2_idx)
+    }) {
+        let source1 = &source_files[group[0].source1_idx];
+        let source2 = &source_files[group[0].source2_idx];
+        writeln!(
+            writer,
+            "{} <-> {}",
+            paint(source1.filename(), FILE_STYLE, color),
+            paint(source2.filename(), FILE_STYLE, color)
+        )?;
+
+        for block in group {
+            let range1 = line_range(source1, block.line1, block.count);
+            let range2 = line_range(source2, block.line2, block.count);
+            if clickable {
+                write_clickable_header(writer, "  ", [(source1, range1), (source2, range2)])?;
+            } else {
+                writeln!(
+                    writer,
+                    "  {} <-> {}",
+                    paint(format!("({}-{})", range1.0, range1.1), RANGE_STYLE, color),
+                    paint(format!("({}-{})", range2.0, range2.1), RANGE_STYLE, color)
+                )?;
+            }
+
+            for line in source1.get_lines(block.line1, block.line1 + block.count) {
+                writeln!(writer, "      {}", line)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
 impl Exporter for ConsoleExporter {
     fn export(
         &self,
@@ -17,42 +129,52 @@ impl Exporter for ConsoleExporter {
         config: &Config,
         writer: &mut dyn Write,
     ) -> Result<()> {
-        // Output each duplicate block
-        for block in &result.blocks {
-            let source1 = &source_files[block.source1_idx];
-            let source2 = &source_files[block.source2_idx];
-
-            // Get original line numbers
-            let start1 = source1.get_line(block.line1).line_number();
-            let end1 = source1
-                .get_line(block.line1 + block.count - 1)
-                .line_number();
-            let start2 = source2.get_line(block.line2).line_number();
-            let end2 = source2
-                .get_line(block.line2 + block.count - 1)
-                .line_number();
-
-            writeln!(
+        result.check_indices(source_files)?;
+        let color = config.color;
+        if config.group_by_pair {
+            write_grouped_by_pair(
+                result,
+                source_files,
+                config.console_clickable,
+                color,
                 writer,
-                "{}({}-{}) <-> {}({}-{})",
-                source1.filename(),
-                start1,
-                end1,
-                source2.filename(),
-                start2,
-                end2
             )?;
+        } else {
+            // Output each duplicate block
+            for block in &result.blocks {
+                let source1 = &source_files[block.source1_idx];
+                let source2 = &source_files[block.source2_idx];
+                let (start1, end1) = line_range(source1, block.line1, block.count);
+                let (start2, end2) = line_range(source2, block.line2, block.count);
 
-            // Output the duplicate lines (indented)
-            let lines = source1.get_lines(block.line1, block.line1 + block.count);
-            for line in lines {
-                writeln!(writer, "    {}", line)?;
+                if config.console_clickable {
+                    write_clickable_header(
+                        writer,
+                        "",
+                        [(source1, (start1, end1)), (source2, (start2, end2))],
+                    )?;
+                } else {
+                    writeln!(
+                        writer,
+                        "{}{} <-> {}{}",
+                        paint(source1.filename(), FILE_STYLE, color),
+                        paint(format!("({}-{})", start1, end1), RANGE_STYLE, color),
+                        paint(source2.filename(), FILE_STYLE, color),
+                        paint(format!("({}-{})", start2, end2), RANGE_STYLE, color)
+                    )?;
+                }
+
+                // Output the duplicate lines (indented)
+                let lines = source1.get_lines(block.line1, block.line1 + block.count);
+                for line in lines {
+                    writeln!(writer, "    {}", line)?;
+                }
+                writeln!(writer)?;
             }
-            writeln!(writer)?;
         }
 
         // Output summary
-        writeln!(writer, "Configuration:")?;
+        writeln!(writer, "{}", paint("Configuration:", HEADING_STYLE, color))?;
         writeln!(
             writer,
             "  Minimum block size: {} lines",
@@ -70,14 +192,174 @@ impl Exporter for ConsoleExporter {
         )?;
         writeln!(writer)?;
 
-        writeln!(writer, "Summary:")?;
+        writeln!(writer, "{}", paint("Summary:", HEADING_STYLE, color))?;
+        writeln!(writer, "  Files provided: {}", result.files_provided)?;
         writeln!(writer, "  Files analyzed: {}", result.files_analyzed)?;
+        if result.is_partial() {
+            writeln!(
+                writer,
+                "  Partial: yes (interrupted before all files were compared)"
+            )?;
+        }
         writeln!(writer, "  Total lines: {}", result.total_lines)?;
         writeln!(writer, "  Duplicate blocks: {}", result.duplicate_blocks)?;
         writeln!(writer, "  Duplicate lines: {}", result.duplicate_lines)?;
         if result.total_lines > 0 {
             let percent = (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0;
-            writeln!(writer, "  Duplication: {:.1}%", percent)?;
+            let shown = paint(
+                format!("{:.1}%", percent),
+                duplication_style(percent),
+                color,
+            );
+            if config.sample.is_some() {
+                writeln!(
+                    writer,
+                    "  Duplication: {} (estimated from a sample of the files)",
+                    shown
+                )?;
+            } else {
+                writeln!(writer, "  Duplication: {}", shown)?;
+            }
+        }
+        writeln!(
+            writer,
+            "  Debt score: {:.1}",
+            debt_score(result, config.debt_exponent)
+        )?;
+
+        if let Some(threshold) = config.file_fail_threshold {
+            let over = files_over_threshold(result, source_files, threshold);
+            if !over.is_empty() {
+                writeln!(writer)?;
+                writeln!(
+                    writer,
+                    "{}",
+                    paint(
+                        format!("Files over {}% duplication:", threshold),
+                        HEADING_STYLE,
+                        color
+                    )
+                )?;
+                for (filename, stats) in over {
+                    let percent = stats.duplication_percent();
+                    writeln!(
+                        writer,
+                        "  {}: {}/{} lines ({})",
+                        paint(filename, FILE_STYLE, color),
+                        stats.duplicate_lines,
+                        stats.total_lines,
+                        paint(
+                            format!("{:.1}%", percent),
+                            duplication_style(percent),
+                            color
+                        )
+                    )?;
+                }
+            }
+        }
+
+        if config.report_skipped && !result.skipped_files.is_empty() {
+            writeln!(writer)?;
+            writeln!(writer, "Skipped (no analyzable lines):")?;
+            for filename in &result.skipped_files {
+                writeln!(writer, "  {}", filename)?;
+            }
+        }
+
+        if config.clone_families {
+            let families = clone_families(result, source_files);
+            writeln!(writer)?;
+            writeln!(writer, "Clone families:")?;
+            if families.is_empty() {
+                writeln!(writer, "  (none)")?;
+            }
+            for (i, family) in families.iter().enumerate() {
+                writeln!(
+                    writer,
+                    "  Family {}: {} files, {} blocks, {} lines",
+                    i + 1,
+                    family.files.len(),
+                    family.blocks,
+                    family.duplicate_lines
+                )?;
+                for filename in &family.files {
+                    writeln!(writer, "    {}", filename)?;
+                }
+            }
+        }
+
+        if config.histogram {
+            writeln!(writer)?;
+            writeln!(writer, "Block sizes:")?;
+            for bucket in size_histogram(result, config.min_block_size as usize) {
+                let range = match bucket.max_lines {
+                    Some(max) => format!("{}-{}", bucket.min_lines, max),
+                    None => format!("{}+", bucket.min_lines),
+                };
+                writeln!(
+                    writer,
+                    "  {:>7} lines: {} blocks ({} lines)",
+                    range, bucket.blocks, bucket.lines
+                )?;
+            }
+        }
+
+        if let Some(threshold) = config.similarity_threshold {
+            writeln!(writer)?;
+            writeln!(
+                writer,
+                "Similar files (line set similarity >= {}):",
+                threshold
+            )?;
+            let pairs = similar_file_pairs(source_files, threshold);
+            if pairs.is_empty() {
+                writeln!(writer, "  (none)")?;
+            }
+            for pair in pairs {
+                writeln!(
+                    writer,
+                    "  {} <-> {}: {:.1}%",
+                    paint(&pair.file1, FILE_STYLE, color),
+                    paint(&pair.file2, FILE_STYLE, color),
+                    pair.similarity * 100.0
+                )?;
+            }
+        }
+
+        if config.collision_report {
+            let stats = collision_stats(source_files);
+            writeln!(writer)?;
+            writeln!(writer, "Hash collisions:")?;
+            writeln!(writer, "  Distinct lines: {}", stats.distinct_lines)?;
+            writeln!(
+                writer,
+                "  Shared 32-bit hashes: {}",
+                stats.primary_collisions
+            )?;
+            writeln!(writer, "  Shared full hashes: {}", stats.full_collisions)?;
+            if stats.full_collisions > 0 {
+                writeln!(
+                    writer,
+                    "  Different lines can match; consider --hash xxhash or --hash fnv64"
+                )?;
+            }
+        }
+
+        if let Some(depth) = config.group_report_depth {
+            let base = std::env::current_dir().unwrap_or_default();
+            writeln!(writer)?;
+            writeln!(writer, "Duplication by directory (depth {}):", depth)?;
+            for group in group_stats(result, source_files, depth, &base) {
+                writeln!(
+                    writer,
+                    "  {}: {}/{} lines ({:.1}%) in {} files",
+                    group.group,
+                    group.duplicate_lines,
+                    group.total_lines,
+                    group.duplication_percent(),
+                    group.files
+                )?;
+            }
         }
 
         Ok(())
@@ -87,7 +369,7 @@ impl Exporter for ConsoleExporter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{Block, SourceLine};
+    use crate::core::SourceLine;
 
     #[test]
     fn test_console_export() {
@@ -103,13 +385,7 @@ mod tests {
         let sf2 = SourceFile::from_lines("b.c".to_string(), lines2);
         let source_files = vec![sf1, sf2];
 
-        let result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 4)],
-            files_analyzed: 2,
-            total_lines: 8,
-            duplicate_lines: 4,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4)]);
 
         let config = Config::default();
         let exporter = ConsoleExporter;
@@ -123,4 +399,125 @@ mod tests {
         assert!(output_str.contains("a.c(1-4) <-> b.c(1-4)"));
         assert!(output_str.contains("Duplicate blocks: 1"));
     }
+
+    #[test]
+    fn test_console_color_only_adds_escapes() {
+        let lines: Vec<SourceLine> = (1..=4)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4)]);
+        let render = |color, group_by_pair| {
+            let config = Config {
+                color,
+                group_by_pair,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            ConsoleExporter
+                .export(&result, &source_files, &config, &mut output)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        for group_by_pair in [false, true] {
+            let plain = render(false, group_by_pair);
+            let colored = render(true, group_by_pair);
+            assert!(!plain.contains('\x1b'));
+            assert!(colored.contains('\x1b'));
+            assert_eq!(strip_escapes(&colored), plain);
+        }
+        // 50% duplication is shown in red
+        assert!(
+            render(true, false).contains(&format!("{}50.0%", AnsiColor::Red.on_default().bold()))
+        );
+    }
+
+    /// Remove ANSI SGR sequences (`ESC [ ... m`)
+    fn strip_escapes(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_console_export_group_by_pair() {
+        let lines: Vec<SourceLine> = (1..=10)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+
+        let result = DuploResult {
+            total_lines: 20,
+            ..DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4), Block::new(0, 1, 6, 6, 4)])
+        };
+
+        let config = Config {
+            group_by_pair: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        ConsoleExporter
+            .export(&result, &source_files, &config, &mut output)
+            .unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(output_str.matches("a.c <-> b.c").count(), 1);
+        assert!(output_str.contains("  (1-4) <-> (1-4)"));
+        assert!(output_str.contains("  (7-10) <-> (7-10)"));
+        assert!(!output_str.contains("a.c(1-4)"));
+    }
+
+    #[test]
+    fn test_console_export_clickable() {
+        let lines: Vec<SourceLine> = (1..=10)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("src/a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("src/b.c".to_string(), lines),
+        ];
+        let result = DuploResult {
+            total_lines: 20,
+            ..DuploResult::from_blocks(vec![Block::new(0, 1, 0, 5, 4)])
+        };
+        let export = |config: &Config| {
+            let mut output = Vec::new();
+            ConsoleExporter
+                .export(&result, &source_files, config, &mut output)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = export(&Config {
+            console_clickable: true,
+            ..Default::default()
+        });
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "src/a.c:1: lines 1-4");
+        assert_eq!(lines[1], "src/b.c:6: lines 6-9");
+        assert_eq!(lines[2], "    line1");
+        assert!(!output.contains("<->"));
+
+        let output = export(&Config {
+            console_clickable: true,
+            group_by_pair: true,
+            ..Default::default()
+        });
+        assert!(output
+            .contains("src/a.c <-> src/b.c\n  src/a.c:1: lines 1-4\n  src/b.c:6: lines 6-9\n"));
+    }
 }
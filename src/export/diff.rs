@@ -0,0 +1,237 @@
+//! Unified diff exporter
+
+use crate::config::Config;
+use crate::core::{DuploResult, SourceFile};
+use crate::error::Result;
+use crate::export::Exporter;
+use std::io::Write;
+
+/// Unified diff output exporter
+///
+/// Renders each duplicate as a unified diff between the two ranges, so
+/// review tooling can highlight where the copies differ.
+///
+/// The diff is over the cleaned lines: comments, blank lines and joined
+/// statements mean they don't map one-to-one onto the physical range in
+/// the `---`/`+++` headers, so the hunk header carries no line counts.
+pub struct DiffExporter;
+
+/// A single line of diff output
+#[derive(Debug, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl DiffExporter {
+    /// Compute a line diff between two slices using longest common subsequence
+    ///
+    /// Uses Hirschberg's divide-and-conquer LCS, so memory stays linear in
+    /// the block length even for long `--max-gap` blocks.
+    fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+        let mut diff = Vec::with_capacity(old.len().max(new.len()));
+        Self::diff_into(old, new, &mut diff);
+        diff
+    }
+
+    fn diff_into<'a>(old: &[&'a str], new: &[&'a str], diff: &mut Vec<DiffLine<'a>>) {
+        // Common prefix and suffix never need the LCS table
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        diff.extend(old[..prefix].iter().map(|l| DiffLine::Same(l)));
+        let old_mid = &old[prefix..old.len() - suffix];
+        let new_mid = &new[prefix..new.len() - suffix];
+
+        if old_mid.is_empty() || new_mid.is_empty() {
+            diff.extend(old_mid.iter().map(|l| DiffLine::Removed(l)));
+            diff.extend(new_mid.iter().map(|l| DiffLine::Added(l)));
+        } else if old_mid.len() == 1 {
+            match new_mid.iter().position(|l| *l == old_mid[0]) {
+                Some(k) => {
+                    diff.extend(new_mid[..k].iter().map(|l| DiffLine::Added(l)));
+                    diff.push(DiffLine::Same(old_mid[0]));
+                    diff.extend(new_mid[k + 1..].iter().map(|l| DiffLine::Added(l)));
+                }
+                None => {
+                    diff.push(DiffLine::Removed(old_mid[0]));
+                    diff.extend(new_mid.iter().map(|l| DiffLine::Added(l)));
+                }
+            }
+        } else {
+            // Split old in half and find where new splits along an optimal path
+            let mid = old_mid.len() / 2;
+            let forward =
+                Self::lcs_lengths(old_mid[..mid].iter().copied(), new_mid.iter().copied());
+            let backward = Self::lcs_lengths(
+                old_mid[mid..].iter().rev().copied(),
+                new_mid.iter().rev().copied(),
+            );
+            let n = new_mid.len();
+            let split = (0..=n)
+                .max_by_key(|&k| (forward[k] + backward[n - k], std::cmp::Reverse(k)))
+                .unwrap_or(0);
+
+            Self::diff_into(&old_mid[..mid], &new_mid[..split], diff);
+            Self::diff_into(&old_mid[mid..], &new_mid[split..], diff);
+        }
+
+        diff.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    }
+
+    /// LCS length of all of `old` against each prefix of `new`, in two rows
+    fn lcs_lengths<'a>(
+        old: impl Iterator<Item = &'a str>,
+        new: impl Iterator<Item = &'a str> + Clone,
+    ) -> Vec<usize> {
+        let mut prev = vec![0usize; new.clone().count() + 1];
+        let mut curr = prev.clone();
+        for a in old {
+            for (j, b) in new.clone().enumerate() {
+                curr[j + 1] = if a == b {
+                    prev[j] + 1
+                } else {
+                    prev[j + 1].max(curr[j])
+                };
+            }
+            std::mem::swap(&mut prev, &mut curr);
+        }
+        prev
+    }
+}
+
+impl Exporter for DiffExporter {
+    fn export(
+        &self,
+        result: &DuploResult,
+        source_files: &[SourceFile],
+        _config: &Config,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        result.check_indices(source_files)?;
+        for block in &result.blocks {
+            let source1 = &source_files[block.source1_idx];
+            let source2 = &source_files[block.source2_idx];
+
+            let start1 = source1.get_line(block.line1).line_number();
+            let end1 = source1
+                .get_line(block.line1 + block.count - 1)
+                .end_line_number();
+            let start2 = source2.get_line(block.line2).line_number();
+            let end2 = source2
+                .get_line(block.line2 + block.count - 1)
+                .end_line_number();
+
+            let lines1 = source1.get_lines(block.line1, block.line1 + block.count);
+            let lines2 = source2.get_lines(block.line2, block.line2 + block.count);
+
+            writeln!(writer, "--- {}:{}-{}", source1.filename(), start1, end1)?;
+            writeln!(writer, "+++ {}:{}-{}", source2.filename(), start2, end2)?;
+            writeln!(writer, "@@ cleaned lines @@")?;
+
+            for line in Self::diff_lines(&lines1, &lines2) {
+                match line {
+                    DiffLine::Same(l) => writeln!(writer, " {}", l)?,
+                    DiffLine::Removed(l) => writeln!(writer, "-{}", l)?,
+                    DiffLine::Added(l) => writeln!(writer, "+{}", l)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, SourceLine};
+
+    fn export_pair(lines1: &[&str], lines2: &[&str]) -> String {
+        let to_source_lines = |lines: &[&str]| {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, l)| SourceLine::new(l.to_string(), i + 1))
+                .collect::<Vec<_>>()
+        };
+
+        let sf1 = SourceFile::from_lines("a.c".to_string(), to_source_lines(lines1));
+        let sf2 = SourceFile::from_lines("b.c".to_string(), to_source_lines(lines2));
+        let source_files = vec![sf1, sf2];
+
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, lines1.len())]);
+
+        let mut output = Vec::new();
+        DiffExporter
+            .export(&result, &source_files, &Config::default(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_diff_export_exact_duplicate() {
+        let lines = ["int x = 1;", "int y = 2;", "int z = 3;"];
+        let output = export_pair(&lines, &lines);
+
+        assert!(output.contains("--- a.c:1-3"));
+        assert!(output.contains("+++ b.c:1-3"));
+        assert!(output.contains("@@ cleaned lines @@"));
+        // No changed lines in the hunk body
+        let body: Vec<&str> = output.lines().skip(3).collect();
+        assert!(body.iter().all(|l| l.starts_with(' ')));
+    }
+
+    #[test]
+    fn test_diff_lines_keeps_longest_common_subsequence() {
+        let old = ["a", "b", "c", "d", "e", "f", "g"];
+        let new = ["x", "b", "c", "y", "e", "z", "g", "h"];
+        let diff = DiffExporter::diff_lines(&old, &new);
+
+        let same: Vec<&str> = diff
+            .iter()
+            .filter_map(|l| match l {
+                DiffLine::Same(l) => Some(*l),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(same, ["b", "c", "e", "g"]);
+
+        // Both sides can be rebuilt from the diff in order
+        let rebuilt_old: Vec<&str> = diff
+            .iter()
+            .filter_map(|l| match l {
+                DiffLine::Same(l) | DiffLine::Removed(l) => Some(*l),
+                DiffLine::Added(_) => None,
+            })
+            .collect();
+        let rebuilt_new: Vec<&str> = diff
+            .iter()
+            .filter_map(|l| match l {
+                DiffLine::Same(l) | DiffLine::Added(l) => Some(*l),
+                DiffLine::Removed(_) => None,
+            })
+            .collect();
+        assert_eq!(rebuilt_old, old);
+        assert_eq!(rebuilt_new, new);
+    }
+
+    #[test]
+    fn test_diff_export_approximate_duplicate() {
+        // Whitespace-only differences hash equally but differ textually
+        let output = export_pair(
+            &["int x = 1;", "int y = 2;", "int z = 3;"],
+            &["int x = 1;", "int  y  = 2;", "int z = 3;"],
+        );
+
+        assert!(output.contains(" int x = 1;"));
+        assert!(output.contains("-int y = 2;"));
+        assert!(output.contains("+int  y  = 2;"));
+        assert!(output.contains(" int z = 3;"));
+    }
+}
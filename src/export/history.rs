@@ -0,0 +1,109 @@
+//! Duplication trend history (`--history`)
+//!
+//! Each run appends one JSON object per line to the history file, so the
+//! duplication of a repository can be charted across commits.
+
+use crate::core::DuploResult;
+use crate::error::{DuploError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One line of the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch when the run finished
+    pub timestamp: u64,
+    /// Checked out commit, when the run happened inside a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Totals of the run
+    pub summary: HistorySummary,
+}
+
+/// Repository-wide totals of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistorySummary {
+    pub files_analyzed: usize,
+    pub total_lines: usize,
+    pub duplicate_blocks: usize,
+    pub duplicate_lines: usize,
+    pub duplication_percent: f64,
+}
+
+impl HistoryEntry {
+    /// Record `result` as of now, at `commit`
+    pub fn new(result: &DuploResult, commit: Option<String>) -> Self {
+        let duplication_percent = if result.total_lines > 0 {
+            (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0
+        } else {
+            0.0
+        };
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            commit,
+            summary: HistorySummary {
+                files_analyzed: result.files_analyzed,
+                total_lines: result.total_lines,
+                duplicate_blocks: result.duplicate_blocks,
+                duplicate_lines: result.duplicate_lines,
+                duplication_percent,
+            },
+        }
+    }
+}
+
+/// Append `entry` as a single line to the history file, creating it if needed
+pub fn append_history(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(DuploError::Io)?;
+
+    // Written in one call so concurrent runs don't interleave within a line
+    let mut line = serde_json::to_vec(entry).map_err(|e| DuploError::Other(e.to_string()))?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_history_adds_one_line_per_run() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("history.jsonl");
+        let result = DuploResult {
+            blocks: Vec::new(),
+            files_provided: 4,
+            files_analyzed: 4,
+            skipped_files: Vec::new(),
+            warnings: Vec::new(),
+            total_lines: 200,
+            duplicate_lines: 50,
+            duplicate_blocks: 3,
+        };
+
+        let first = HistoryEntry::new(&result, Some("abc123".to_string()));
+        append_history(&path, &first).unwrap();
+        let second = HistoryEntry::new(&result, None);
+        append_history(&path, &second).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<HistoryEntry> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(entries, vec![first, second]);
+        assert_eq!(entries[0].summary.duplication_percent, 25.0);
+        assert!(!content.lines().nth(1).unwrap().contains("commit"));
+    }
+}
@@ -1,27 +1,120 @@
 //! JSON exporter
 
-use crate::config::Config;
-use crate::core::{DuploResult, SourceFile};
+use crate::config::{Config, CrossLanguage};
+use crate::core::stats::{
+    class_id, class_representatives, clone_families, collision_stats, debt_score,
+    files_over_threshold, group_stats, similar_file_pairs, size_histogram,
+};
+use crate::core::{DuploResult, MatchKind, SourceFile, Warning};
 use crate::error::Result;
-use crate::export::Exporter;
-use serde::Serialize;
+use crate::export::{snippet_len, Exporter, SnippetSource};
+use crate::git::BlameCache;
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
 use std::io::Write;
 
 /// JSON output exporter
 pub struct JsonExporter;
 
+/// Report shared by the JSON and YAML exporters
 #[derive(Serialize)]
-struct JsonOutput {
-    duplicates: Vec<JsonDuplicate>,
+pub(super) struct JsonOutput<D: Serialize> {
+    duplicates: D,
     summary: JsonSummary,
+    /// Detection settings the results were produced with
+    config: JsonConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<JsonGroup>>,
+    /// Files transitively linked by duplicates (with --clone-families)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clone_families: Option<Vec<JsonCloneFamily>>,
+    /// Files dropped for having no analyzable lines (with --report-skipped)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped_files: Option<Vec<String>>,
+    /// Files more duplicated than --file-fail-threshold allows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files_over_threshold: Option<Vec<JsonFileDuplication>>,
+    /// Number of blocks per size range (with --histogram)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    histogram: Option<Vec<JsonSizeBucket>>,
+    /// File pairs sharing most lines in any order (with --similarity-threshold)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similar_files: Option<Vec<JsonSimilarFiles>>,
+    /// Lines sharing hashes (with --collision-report)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash_collisions: Option<JsonCollisions>,
+    /// Non-fatal problems encountered during the run
+    warnings: Vec<Warning>,
+}
+
+#[derive(Serialize)]
+struct JsonGroup {
+    path: String,
+    files: usize,
+    total_lines: usize,
+    duplicate_lines: usize,
+    duplication_percent: f64,
+}
+
+#[derive(Serialize)]
+struct JsonFileDuplication {
+    path: String,
+    total_lines: usize,
+    duplicate_lines: usize,
+    duplication_percent: f64,
+}
+
+#[derive(Serialize)]
+struct JsonSizeBucket {
+    min_lines: usize,
+    /// Absent for the last, open-ended bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_lines: Option<usize>,
+    blocks: usize,
+    lines: usize,
+}
+
+#[derive(Serialize)]
+struct JsonSimilarFiles {
+    file1: String,
+    file2: String,
+    similarity: f64,
+}
+
+#[derive(Serialize)]
+struct JsonCollisions {
+    distinct_lines: usize,
+    primary_collisions: usize,
+    full_collisions: usize,
+}
+
+#[derive(Serialize)]
+struct JsonCloneFamily {
+    files: Vec<String>,
+    blocks: usize,
+    duplicate_lines: usize,
 }
 
 #[derive(Serialize)]
 struct JsonDuplicate {
+    /// Names the clone class: the same for every duplicate of this code, in
+    /// every run (hex, as dashboards may not hold a u64 exactly)
+    class_id: String,
+    /// Alphabetically first file of the clone class (with --class-representative)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    representative: Option<String>,
     line_count: usize,
+    /// Non-matching lines bridged inside the block (with --max-gap)
+    #[serde(skip_serializing_if = "is_zero")]
+    gap_lines: usize,
+    /// Whether both copies have byte-equal text, not just equal hashes
+    match_kind: MatchKind,
     file1: JsonFileRef,
     file2: JsonFileRef,
     lines: Vec<String>,
+    /// Set when `lines` was cut short by --max-snippet-lines
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
 }
 
 #[derive(Serialize)]
@@ -29,15 +122,231 @@ struct JsonFileRef {
     path: String,
     start_line: usize,
     end_line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authors: Option<Vec<String>>,
 }
 
 #[derive(Serialize)]
 struct JsonSummary {
+    files_provided: usize,
     files_analyzed: usize,
     total_lines: usize,
     duplicate_blocks: usize,
     duplicate_lines: usize,
     duplication_percent: f64,
+    /// Sum of line_count^debt_exponent over all blocks
+    debt_score: f64,
+    /// Set when only a sample of the files was analyzed (--sample), making
+    /// duplication_percent an estimate
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    sampled: bool,
+    /// Set when the run was interrupted and only some files were compared
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    partial: bool,
+}
+
+#[derive(Serialize)]
+struct JsonConfig {
+    min_block_size: u32,
+    min_chars: u32,
+    block_percent_threshold: u8,
+    ignore_same_filename: bool,
+    ignore_same_filename_fuzzy: bool,
+    max_gap: usize,
+    cross_language: CrossLanguage,
+    /// Changes whenever a setting that affects how lines are cleaned does
+    cleaning_config_hash: u64,
+    /// Changes whenever a setting that affects which blocks are found does
+    detection_config_hash: u64,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// A sequence whose elements are produced while it is being written, so
+/// large result sets never have to be held in memory as a whole
+///
+/// The iterator is consumed by the first serialization.
+struct StreamedSeq<I>(RefCell<Option<I>>);
+
+impl<I> StreamedSeq<I> {
+    fn new(iter: I) -> Self {
+        Self(RefCell::new(Some(iter)))
+    }
+}
+
+impl<I> Serialize for StreamedSeq<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let iter = self.0.borrow_mut().take();
+        serializer.collect_seq(iter.into_iter().flatten())
+    }
+}
+
+/// Build the report written by the JSON and YAML exporters
+pub(super) fn build_output<'a>(
+    result: &'a DuploResult,
+    source_files: &'a [SourceFile],
+    config: &'a Config,
+) -> JsonOutput<impl Serialize + 'a> {
+    let mut blame = config.blame.then(BlameCache::new);
+    let snippets = SnippetSource::new(config);
+    let representatives = config
+        .class_representative
+        .then(|| class_representatives(result, source_files));
+
+    // Each duplicate is built (snippet read, blame looked up) just before
+    // it is written
+    let duplicates = result.blocks.iter().map(move |block| {
+        let source1 = &source_files[block.source1_idx];
+        let source2 = &source_files[block.source2_idx];
+
+        let start1 = source1.get_line(block.line1).line_number();
+        let end1 = source1
+            .get_line(block.line1 + block.count - 1)
+            .end_line_number();
+        let start2 = source2.get_line(block.line2).line_number();
+        let end2 = source2
+            .get_line(block.line2 + block.count - 1)
+            .end_line_number();
+
+        let (snippet_len, truncated) = snippet_len(block.count, config);
+        let lines = snippets.lines(source1, block.line1, snippet_len);
+
+        let (authors1, authors2) = match blame.as_mut() {
+            Some(cache) => (
+                cache.authors(source1.filename(), start1, end1),
+                cache.authors(source2.filename(), start2, end2),
+            ),
+            None => (None, None),
+        };
+
+        let class = class_id(block, source_files);
+        JsonDuplicate {
+            class_id: format!("{:016x}", class),
+            representative: representatives.as_ref().map(|r| r[&class].to_string()),
+            line_count: block.count,
+            gap_lines: block.gaps,
+            match_kind: block.match_kind(source_files),
+            file1: JsonFileRef {
+                path: source1.filename().to_string(),
+                start_line: start1,
+                end_line: end1,
+                authors: authors1,
+            },
+            file2: JsonFileRef {
+                path: source2.filename().to_string(),
+                start_line: start2,
+                end_line: end2,
+                authors: authors2,
+            },
+            lines,
+            truncated,
+        }
+    });
+
+    let duplication_percent = if result.total_lines > 0 {
+        (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let groups = config.group_report_depth.map(|depth| {
+        let base = std::env::current_dir().unwrap_or_default();
+        group_stats(result, source_files, depth, &base)
+            .into_iter()
+            .map(|g| JsonGroup {
+                duplication_percent: g.duplication_percent(),
+                path: g.group,
+                files: g.files,
+                total_lines: g.total_lines,
+                duplicate_lines: g.duplicate_lines,
+            })
+            .collect()
+    });
+
+    JsonOutput {
+        duplicates: StreamedSeq::new(duplicates),
+        summary: JsonSummary {
+            files_provided: result.files_provided,
+            files_analyzed: result.files_analyzed,
+            total_lines: result.total_lines,
+            duplicate_blocks: result.duplicate_blocks,
+            duplicate_lines: result.duplicate_lines,
+            duplication_percent,
+            debt_score: debt_score(result, config.debt_exponent),
+            sampled: config.sample.is_some(),
+            partial: result.is_partial(),
+        },
+        config: JsonConfig {
+            min_block_size: config.min_block_size,
+            min_chars: config.min_chars,
+            block_percent_threshold: config.block_percent_threshold,
+            ignore_same_filename: config.ignore_same_filename,
+            ignore_same_filename_fuzzy: config.ignore_same_filename_fuzzy,
+            max_gap: config.max_gap,
+            cross_language: config.cross_language,
+            cleaning_config_hash: config.cleaning_config_hash(),
+            detection_config_hash: config.detection_config_hash(),
+        },
+        groups,
+        clone_families: config.clone_families.then(|| {
+            clone_families(result, source_files)
+                .into_iter()
+                .map(|f| JsonCloneFamily {
+                    files: f.files,
+                    blocks: f.blocks,
+                    duplicate_lines: f.duplicate_lines,
+                })
+                .collect()
+        }),
+        skipped_files: config.report_skipped.then(|| result.skipped_files.clone()),
+        files_over_threshold: config.file_fail_threshold.map(|threshold| {
+            files_over_threshold(result, source_files, threshold)
+                .into_iter()
+                .map(|(path, stats)| JsonFileDuplication {
+                    path: path.to_string(),
+                    total_lines: stats.total_lines,
+                    duplicate_lines: stats.duplicate_lines,
+                    duplication_percent: stats.duplication_percent(),
+                })
+                .collect()
+        }),
+        histogram: config.histogram.then(|| {
+            size_histogram(result, config.min_block_size as usize)
+                .into_iter()
+                .map(|b| JsonSizeBucket {
+                    min_lines: b.min_lines,
+                    max_lines: b.max_lines,
+                    blocks: b.blocks,
+                    lines: b.lines,
+                })
+                .collect()
+        }),
+        similar_files: config.similarity_threshold.map(|threshold| {
+            similar_file_pairs(source_files, threshold)
+                .into_iter()
+                .map(|pair| JsonSimilarFiles {
+                    file1: pair.file1,
+                    file2: pair.file2,
+                    similarity: pair.similarity,
+                })
+                .collect()
+        }),
+        hash_collisions: config.collision_report.then(|| {
+            let stats = collision_stats(source_files);
+            JsonCollisions {
+                distinct_lines: stats.distinct_lines,
+                primary_collisions: stats.primary_collisions,
+                full_collisions: stats.full_collisions,
+            }
+        }),
+        warnings: result.warnings.clone(),
+    }
 }
 
 impl Exporter for JsonExporter {
@@ -45,68 +354,14 @@ impl Exporter for JsonExporter {
         &self,
         result: &DuploResult,
         source_files: &[SourceFile],
-        _config: &Config,
+        config: &Config,
         writer: &mut dyn Write,
     ) -> Result<()> {
-        let duplicates: Vec<JsonDuplicate> = result
-            .blocks
-            .iter()
-            .map(|block| {
-                let source1 = &source_files[block.source1_idx];
-                let source2 = &source_files[block.source2_idx];
-
-                let start1 = source1.get_line(block.line1).line_number();
-                let end1 = source1
-                    .get_line(block.line1 + block.count - 1)
-                    .line_number();
-                let start2 = source2.get_line(block.line2).line_number();
-                let end2 = source2
-                    .get_line(block.line2 + block.count - 1)
-                    .line_number();
-
-                let lines: Vec<String> = source1
-                    .get_lines(block.line1, block.line1 + block.count)
-                    .into_iter()
-                    .map(|s| s.to_string())
-                    .collect();
-
-                JsonDuplicate {
-                    line_count: block.count,
-                    file1: JsonFileRef {
-                        path: source1.filename().to_string(),
-                        start_line: start1,
-                        end_line: end1,
-                    },
-                    file2: JsonFileRef {
-                        path: source2.filename().to_string(),
-                        start_line: start2,
-                        end_line: end2,
-                    },
-                    lines,
-                }
-            })
-            .collect();
-
-        let duplication_percent = if result.total_lines > 0 {
-            (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        let output = JsonOutput {
-            duplicates,
-            summary: JsonSummary {
-                files_analyzed: result.files_analyzed,
-                total_lines: result.total_lines,
-                duplicate_blocks: result.duplicate_blocks,
-                duplicate_lines: result.duplicate_lines,
-                duplication_percent,
-            },
-        };
-
-        let json = serde_json::to_string_pretty(&output)
+        result.check_indices(source_files)?;
+        let output = build_output(result, source_files, config);
+        serde_json::to_writer_pretty(&mut *writer, &output)
             .map_err(|e| crate::error::DuploError::Other(e.to_string()))?;
-        writeln!(writer, "{}", json)?;
+        writeln!(writer)?;
 
         Ok(())
     }
@@ -115,7 +370,7 @@ impl Exporter for JsonExporter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{Block, SourceLine};
+    use crate::core::{Block, SourceLine, Warning, WarningKind};
 
     #[test]
     fn test_json_export() {
@@ -129,13 +384,7 @@ mod tests {
         let sf2 = SourceFile::from_lines("b.c".to_string(), lines2);
         let source_files = vec![sf1, sf2];
 
-        let result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 2)],
-            files_analyzed: 2,
-            total_lines: 4,
-            duplicate_lines: 2,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 2)]);
 
         let config = Config::default();
         let exporter = JsonExporter;
@@ -150,5 +399,158 @@ mod tests {
 
         assert_eq!(parsed["summary"]["files_analyzed"], 2);
         assert_eq!(parsed["duplicates"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            parsed["summary"]["debt_score"].as_f64().unwrap(),
+            2f64.powf(1.5)
+        );
+    }
+
+    #[test]
+    fn test_json_export_embeds_config() {
+        let lines: Vec<SourceLine> = (1..=4)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4)]);
+        let config = Config {
+            min_block_size: 7,
+            min_chars: 5,
+            block_percent_threshold: 80,
+            ignore_same_filename: true,
+            ignore_same_filename_fuzzy: true,
+            max_gap: 2,
+            cross_language: CrossLanguage::Allow,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        JsonExporter
+            .export(&result, &source_files, &config, &mut output)
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        let embedded = &parsed["config"];
+        assert_eq!(embedded["min_block_size"], 7);
+        assert_eq!(embedded["min_chars"], 5);
+        assert_eq!(embedded["block_percent_threshold"], 80);
+        assert_eq!(embedded["ignore_same_filename"], true);
+        assert_eq!(embedded["ignore_same_filename_fuzzy"], true);
+        assert_eq!(embedded["max_gap"], 2);
+        assert_eq!(embedded["cross_language"], "allow");
+        assert_eq!(
+            embedded["cleaning_config_hash"].as_u64().unwrap(),
+            config.cleaning_config_hash()
+        );
+        assert_eq!(
+            embedded["detection_config_hash"].as_u64().unwrap(),
+            config.detection_config_hash()
+        );
+    }
+
+    #[test]
+    fn test_json_marks_interrupted_run_partial() {
+        let result = DuploResult {
+            warnings: vec![Warning::new(WarningKind::Interrupted, "stopped")],
+            ..DuploResult::from_blocks(Vec::new())
+        };
+
+        let mut output = Vec::new();
+        JsonExporter
+            .export(&result, &[], &Config::default(), &mut output)
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(parsed["summary"]["partial"], true);
+        assert_eq!(parsed["warnings"][0]["kind"], "interrupted");
+    }
+
+    #[test]
+    fn test_json_snippet_truncated() {
+        let lines: Vec<SourceLine> = (1..=10)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 10)]);
+        let export = |config: &Config| {
+            let mut output = Vec::new();
+            JsonExporter
+                .export(&result, &source_files, config, &mut output)
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&output).unwrap()
+        };
+
+        let parsed = export(&Config {
+            max_snippet_lines: Some(3),
+            ..Default::default()
+        });
+        let dup = &parsed["duplicates"][0];
+        assert_eq!(dup["lines"].as_array().unwrap().len(), 3);
+        assert_eq!(dup["lines"][2], "line3");
+        assert_eq!(dup["truncated"], true);
+        assert_eq!(dup["line_count"], 10);
+        assert_eq!(dup["file1"]["end_line"], 10);
+
+        // Blocks within the limit are emitted whole, without the marker
+        let parsed = export(&Config {
+            max_snippet_lines: Some(10),
+            ..Default::default()
+        });
+        let dup = &parsed["duplicates"][0];
+        assert_eq!(dup["lines"].as_array().unwrap().len(), 10);
+        assert!(dup.get("truncated").is_none());
+    }
+
+    #[test]
+    fn test_json_streamed_multi_block() {
+        let lines: Vec<SourceLine> = (1..=6)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines.clone()),
+            SourceFile::from_lines("c.c".to_string(), lines),
+        ];
+        let result = DuploResult {
+            total_lines: 18,
+            ..DuploResult::from_blocks(vec![
+                Block::new(0, 1, 0, 0, 6),
+                Block::new(0, 2, 1, 1, 4),
+                Block::new(1, 2, 2, 2, 3),
+            ])
+        };
+
+        let mut output = Vec::new();
+        JsonExporter
+            .export(&result, &source_files, &Config::default(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        let duplicates = parsed["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 3);
+        let counts: Vec<u64> = duplicates
+            .iter()
+            .map(|d| d["line_count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(counts, vec![6, 4, 3]);
+        assert_eq!(duplicates[1]["file2"]["path"], "c.c");
+        assert_eq!(duplicates[1]["file2"]["start_line"], 2);
+        assert_eq!(
+            duplicates[2]["lines"],
+            serde_json::json!(["line3", "line4", "line5"])
+        );
+        assert_eq!(parsed["summary"]["duplicate_blocks"], 3);
+        assert_eq!(parsed["warnings"], serde_json::json!([]));
+
+        // Same layout as serializing a fully built document: the summary
+        // follows the duplicates, pretty-printed, with a trailing newline
+        assert!(output.starts_with("{\n  \"duplicates\": [\n    {\n"));
+        assert!(output.find("\"duplicates\"").unwrap() < output.find("\"summary\"").unwrap());
+        assert!(output.ends_with("}\n"));
     }
 }
@@ -0,0 +1,91 @@
+//! Manifest of the analyzed files (`--manifest`)
+//!
+//! Lists every file that took part in a run with the content hash the cache
+//! keys on, so downstream tools know exactly which inputs a report covers.
+
+use crate::cache::FileCache;
+use crate::core::SourceFile;
+use crate::error::{DuploError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The analyzed files of a run, keyed by path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, ManifestFile>,
+}
+
+/// What was analyzed of one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Hash of the file's bytes on disk; absent for files that were not read
+    /// from disk (archive members, old versions from git)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<u64>,
+    /// Number of lines left after cleaning
+    pub lines: usize,
+}
+
+impl Manifest {
+    /// Describe the analyzed `source_files`
+    pub fn new(source_files: &[SourceFile]) -> Self {
+        let files = source_files
+            .iter()
+            .map(|file| {
+                (
+                    file.filename().to_string(),
+                    ManifestFile {
+                        content_hash: FileCache::compute_content_hash(file.filename()).ok(),
+                        lines: file.num_lines(),
+                    },
+                )
+            })
+            .collect();
+        Self { files }
+    }
+}
+
+/// Write `manifest` as pretty-printed JSON, replacing any existing file
+pub fn write_manifest(path: &Path, manifest: &Manifest) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, manifest)
+        .map_err(|e| DuploError::Other(e.to_string()))?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_lists_files_with_stable_hashes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("a.c");
+        std::fs::write(&path, "int x = 1;\n// note\nint y = 2;\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        let files = vec![
+            SourceFile::load(&path, 3, &BTreeMap::new()).unwrap(),
+            SourceFile::from_source("HEAD:b.c".to_string(), "int z;\n", 3, &BTreeMap::new()),
+        ];
+
+        let manifest = Manifest::new(&files);
+        assert_eq!(manifest, Manifest::new(&files));
+        let entry = &manifest.files[&path];
+        assert_eq!(entry.lines, 2);
+        assert_eq!(
+            entry.content_hash,
+            Some(FileCache::compute_content_hash(&path).unwrap())
+        );
+        assert_eq!(manifest.files["HEAD:b.c"].content_hash, None);
+
+        let out = temp.path().join("manifest.json");
+        write_manifest(&out, &manifest).unwrap();
+        let read: Manifest = serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(read, manifest);
+    }
+}
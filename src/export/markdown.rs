@@ -0,0 +1,224 @@
+//! Markdown exporter
+
+use crate::config::Config;
+use crate::core::{DuploResult, SourceFile};
+use crate::error::Result;
+use crate::export::Exporter;
+use std::io::Write;
+use std::path::Path;
+
+/// Number of duplicates listed in the `<details>` section
+const TOP_DUPLICATES: usize = 20;
+
+/// GitHub-flavored Markdown summary exporter, suited to PR comments
+///
+/// Writes a stats table followed by a collapsible list of the largest
+/// duplicates, each linking to its line range in both files.
+pub struct MarkdownExporter;
+
+impl MarkdownExporter {
+    /// Escape characters that Markdown would otherwise interpret
+    fn escape_markdown(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(
+                c,
+                '\\' | '`'
+                    | '*'
+                    | '_'
+                    | '{'
+                    | '}'
+                    | '['
+                    | ']'
+                    | '<'
+                    | '>'
+                    | '('
+                    | ')'
+                    | '#'
+                    | '+'
+                    | '!'
+                    | '|'
+            ) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Encode characters that would end or break a link target
+    fn escape_link(s: &str) -> String {
+        s.replace('%', "%25")
+            .replace(' ', "%20")
+            .replace('(', "%28")
+            .replace(')', "%29")
+            .replace('|', "%7C")
+    }
+
+    /// Link to a line range, with the path relative to `base` when under it
+    fn file_link(filename: &str, start: usize, end: usize, base: &Path) -> String {
+        let path = Path::new(filename);
+        let relative = path.strip_prefix(base).unwrap_or(path).to_string_lossy();
+        format!(
+            "[{}:{}-{}]({}#L{}-L{})",
+            Self::escape_markdown(&relative),
+            start,
+            end,
+            Self::escape_link(&relative),
+            start,
+            end
+        )
+    }
+}
+
+impl Exporter for MarkdownExporter {
+    fn export(
+        &self,
+        result: &DuploResult,
+        source_files: &[SourceFile],
+        config: &Config,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        result.check_indices(source_files)?;
+        let duplication_percent = if result.total_lines > 0 {
+            (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        writeln!(writer, "## Code duplication")?;
+        writeln!(writer)?;
+        writeln!(writer, "| Metric | Value |")?;
+        writeln!(writer, "|--------|------:|")?;
+        writeln!(writer, "| Files analyzed | {} |", result.files_analyzed)?;
+        writeln!(writer, "| Total lines | {} |", result.total_lines)?;
+        writeln!(writer, "| Duplicate blocks | {} |", result.duplicate_blocks)?;
+        writeln!(writer, "| Duplicate lines | {} |", result.duplicate_lines)?;
+        let label = if config.sample.is_some() {
+            "Duplication (estimated from a sample)"
+        } else {
+            "Duplication"
+        };
+        writeln!(writer, "| {} | {:.2}% |", label, duplication_percent)?;
+
+        if result.blocks.is_empty() {
+            return Ok(());
+        }
+
+        // Largest first (the canonical `Block` ordering)
+        let mut blocks: Vec<_> = result.blocks.iter().collect();
+        blocks.sort();
+        let shown = blocks.len().min(TOP_DUPLICATES);
+        let base = std::env::current_dir().unwrap_or_default();
+
+        writeln!(writer)?;
+        writeln!(writer, "<details>")?;
+        writeln!(
+            writer,
+            "<summary>Top {} of {} duplicates</summary>",
+            shown,
+            blocks.len()
+        )?;
+        writeln!(writer)?;
+        writeln!(writer, "| Lines | First copy | Second copy |")?;
+        writeln!(writer, "|------:|------------|-------------|")?;
+        for block in &blocks[..shown] {
+            let source1 = &source_files[block.source1_idx];
+            let source2 = &source_files[block.source2_idx];
+            let start1 = source1.get_line(block.line1).line_number();
+            let end1 = source1
+                .get_line(block.line1 + block.count - 1)
+                .end_line_number();
+            let start2 = source2.get_line(block.line2).line_number();
+            let end2 = source2
+                .get_line(block.line2 + block.count - 1)
+                .end_line_number();
+
+            writeln!(
+                writer,
+                "| {} | {} | {} |",
+                block.count,
+                Self::file_link(source1.filename(), start1, end1, &base),
+                Self::file_link(source2.filename(), start2, end2, &base)
+            )?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "</details>")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, SourceLine};
+
+    fn file(name: &str, lines: usize) -> SourceFile {
+        let lines = (1..=lines)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        SourceFile::from_lines(name.to_string(), lines)
+    }
+
+    #[test]
+    fn test_markdown_export() {
+        let source_files = vec![file("src/a.c", 10), file("src/b_test|x.c", 10)];
+        let result = DuploResult {
+            total_lines: 20,
+            ..DuploResult::from_blocks(vec![Block::new(0, 1, 0, 2, 3), Block::new(0, 1, 4, 6, 4)])
+        };
+
+        let mut output = Vec::new();
+        MarkdownExporter
+            .export(&result, &source_files, &Config::default(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("| Metric | Value |"));
+        assert!(output.contains("| Duplicate blocks | 2 |"));
+        assert!(output.contains("| Duplication | 35.00% |"));
+        assert!(output.contains("<details>"));
+
+        // Largest duplicate first, with escaped paths and line anchors
+        let rows: Vec<&str> = output
+            .lines()
+            .filter(|l| l.starts_with("| 3 ") || l.starts_with("| 4 "))
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].starts_with("| 4 | [src/a.c:5-8](src/a.c#L5-L8)"));
+        assert!(rows[0].contains(r"[src/b\_test\|x.c:7-10](src/b_test%7Cx.c#L7-L10)"));
+    }
+
+    #[test]
+    fn test_markdown_export_no_duplicates() {
+        let source_files = vec![file("a.c", 3)];
+        let result = DuploResult {
+            files_provided: 1,
+            files_analyzed: 1,
+            total_lines: 3,
+            ..DuploResult::from_blocks(Vec::new())
+        };
+
+        let mut output = Vec::new();
+        MarkdownExporter
+            .export(&result, &source_files, &Config::default(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("| Duplicate blocks | 0 |"));
+        assert!(!output.contains("<details>"));
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(
+            MarkdownExporter::escape_markdown("a_b*c[1].rs"),
+            r"a\_b\*c\[1\].rs"
+        );
+        assert_eq!(
+            MarkdownExporter::escape_markdown("plain/path.c"),
+            "plain/path.c"
+        );
+    }
+}
@@ -1,18 +1,35 @@
 //! Export system for duplicate detection results
 
 mod console;
+mod diff;
+mod history;
 mod json;
+mod manifest;
+mod markdown;
+mod snippet;
 mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
 
 use crate::config::{Config, OutputFormat};
 use crate::core::{DuploResult, SourceFile};
 use crate::error::{DuploError, Result};
+use crate::git;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub use console::ConsoleExporter;
+pub use diff::DiffExporter;
+pub use history::{append_history, HistoryEntry, HistorySummary};
 pub use json::JsonExporter;
+pub use manifest::{write_manifest, Manifest, ManifestFile};
+pub use markdown::MarkdownExporter;
 pub use xml::XmlExporter;
+#[cfg(feature = "yaml")]
+pub use yaml::YamlExporter;
+
+use snippet::SnippetSource;
 
 /// Trait for output formatting
 pub trait Exporter {
@@ -26,21 +43,139 @@ pub trait Exporter {
     ) -> Result<()>;
 }
 
+/// Number of a block's lines to include in a snippet, and whether that
+/// truncates the block (see `--max-snippet-lines`)
+fn snippet_len(count: usize, config: &Config) -> (usize, bool) {
+    match config.max_snippet_lines {
+        Some(max) if count > max => (max, true),
+        _ => (count, false),
+    }
+}
+
 /// Create an appropriate exporter based on configuration
 pub fn create_exporter(format: OutputFormat) -> Box<dyn Exporter> {
     match format {
         OutputFormat::Console => Box::new(ConsoleExporter),
         OutputFormat::Json => Box::new(JsonExporter),
         OutputFormat::Xml => Box::new(XmlExporter),
+        OutputFormat::Diff => Box::new(DiffExporter),
+        OutputFormat::Markdown => Box::new(MarkdownExporter),
+        #[cfg(feature = "yaml")]
+        OutputFormat::Yaml => Box::new(YamlExporter),
+    }
+}
+
+/// Values substituted for placeholders in the output path
+#[derive(Debug, Clone)]
+pub struct OutputContext {
+    /// Replaces `{format}` (e.g. "json")
+    pub format: &'static str,
+    /// Replaces `{timestamp}`: seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Replaces `{branch}`, with `/` turned into `-` ("unknown" outside git)
+    pub branch: Option<String>,
+}
+
+impl OutputContext {
+    /// Build the context for a run, asking git for the branch only when
+    /// the output path uses it
+    pub fn from_config(config: &Config) -> Self {
+        let branch = if config.output_filename.contains("{branch}") {
+            git::get_current_branch().ok()
+        } else {
+            None
+        };
+        Self {
+            format: config.output_format.name(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            branch,
+        }
+    }
+
+    /// Expand `{format}`, `{timestamp}` and `{branch}` in an output path
+    pub fn expand(&self, path: &str) -> String {
+        let branch = self
+            .branch
+            .as_deref()
+            .unwrap_or("unknown")
+            .replace(['/', '\\'], "-");
+        path.replace("{format}", self.format)
+            .replace("{timestamp}", &self.timestamp.to_string())
+            .replace("{branch}", &branch)
     }
 }
 
-/// Get a writer for the output (file or stdout)
-pub fn get_output_writer(path: &str) -> Result<Box<dyn Write>> {
+/// Get a writer for the output (file or stdout), expanding placeholders in
+/// `path` with `context`
+pub fn get_output_writer(path: &str, context: &OutputContext) -> Result<Box<dyn Write>> {
     if path == "-" {
         Ok(Box::new(BufWriter::new(io::stdout())))
     } else {
-        let file = File::create(path).map_err(DuploError::Io)?;
+        let file = File::create(context.expand(path)).map_err(DuploError::Io)?;
         Ok(Box::new(BufWriter::new(file)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path_expansion() {
+        let context = OutputContext {
+            format: "json",
+            timestamp: 1700000000,
+            branch: Some("feature/login".to_string()),
+        };
+        assert_eq!(context.expand("report-{format}.out"), "report-json.out");
+        assert_eq!(
+            context.expand("out/{branch}-{timestamp}.{format}"),
+            "out/feature-login-1700000000.json"
+        );
+        assert_eq!(context.expand("plain.txt"), "plain.txt");
+
+        let context = OutputContext {
+            branch: None,
+            ..context
+        };
+        assert_eq!(context.expand("{branch}.json"), "unknown.json");
+    }
+
+    #[test]
+    fn test_exporters_reject_out_of_range_blocks() {
+        use crate::core::{Block, SourceLine};
+
+        let lines: Vec<SourceLine> = (1..=4)
+            .map(|i| SourceLine::new(format!("int v{} = {};", i, i), i))
+            .collect();
+        let source_files = vec![SourceFile::from_lines("a.c".to_string(), lines)];
+        // The second file was dropped without remapping the block
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4)]);
+
+        for format in [
+            OutputFormat::Console,
+            OutputFormat::Json,
+            OutputFormat::Xml,
+            OutputFormat::Diff,
+            OutputFormat::Markdown,
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml,
+        ] {
+            let mut output = Vec::new();
+            let exported = create_exporter(format).export(
+                &result,
+                &source_files,
+                &Config::default(),
+                &mut output,
+            );
+            assert!(
+                matches!(exported, Err(DuploError::InconsistentResult(_))),
+                "{}",
+                format.name()
+            );
+        }
+    }
+}
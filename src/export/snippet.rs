@@ -0,0 +1,128 @@
+//! Text of the lines emitted in duplicate snippets
+
+use crate::config::Config;
+use crate::core::SourceFile;
+
+/// Produces a block's snippet lines: the cleaned lines, or with
+/// `--preserve-indentation` the original lines kept when each file was loaded
+pub(crate) struct SnippetSource {
+    preserve_indentation: bool,
+}
+
+impl SnippetSource {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            preserve_indentation: config.preserve_indentation,
+        }
+    }
+
+    /// Get the snippet lines of the `len` cleaned lines of `file` starting at `start`
+    ///
+    /// Original lines keep their indentation but lose trailing whitespace.
+    /// A statement joined from several physical lines is shown as all of
+    /// them, and a physical line split into several statements only once.
+    /// Files loaded without their original text yield the cleaned text.
+    pub fn lines(&self, file: &SourceFile, start: usize, len: usize) -> Vec<String> {
+        let cleaned = file.get_lines(start, start + len);
+        let originals = match file.original_lines() {
+            Some(originals) if self.preserve_indentation => originals,
+            _ => return cleaned.into_iter().map(|l| l.to_string()).collect(),
+        };
+
+        let mut lines = Vec::with_capacity(len);
+        // First physical line not emitted yet
+        let mut next = 1;
+        for line in &file.lines_slice()[start..start + len] {
+            let first = line.line_number().max(next);
+            let last = line.end_line_number();
+            if first <= last {
+                lines.extend(originals[first - 1..last].iter().cloned());
+                next = last + 1;
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Granularity;
+    use std::collections::BTreeMap;
+
+    fn preserving() -> Config {
+        Config {
+            preserve_indentation: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_original_lines_keep_indentation() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("a.c");
+        std::fs::write(
+            &path,
+            "int f() {\n    // note\n    int x = 1;   \n\treturn x;\n}\n",
+        )
+        .unwrap();
+        let config = preserving();
+        let file = SourceFile::load(
+            path.to_str().unwrap(),
+            config.file_type_options(),
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        // Snippets come from the text kept at load time, not the file on disk
+        std::fs::write(&path, "changed\n").unwrap();
+
+        let plain = SnippetSource::new(&Config::default());
+        assert_eq!(plain.lines(&file, 1, 2), vec!["int x = 1;", "return x;"]);
+
+        let original = SnippetSource::new(&config);
+        assert_eq!(
+            original.lines(&file, 1, 2),
+            vec!["    int x = 1;", "\treturn x;"]
+        );
+    }
+
+    #[test]
+    fn test_in_memory_source_keeps_indentation() {
+        let config = preserving();
+        let file = SourceFile::from_source(
+            "archive.tar.gz:a.c".to_string(),
+            "    int x = 1;\n",
+            config.file_type_options(),
+            &BTreeMap::new(),
+        );
+        let original = SnippetSource::new(&config);
+        assert_eq!(original.lines(&file, 0, 1), vec!["    int x = 1;"]);
+    }
+
+    #[test]
+    fn test_joined_statement_shows_every_physical_line() {
+        let config = Config {
+            granularity: Granularity::Statement,
+            ..preserving()
+        };
+        let file = SourceFile::from_source(
+            "a.c".to_string(),
+            "int total =\n    a + b;\nint other = total;\n",
+            config.file_type_options(),
+            &BTreeMap::new(),
+        );
+        let original = SnippetSource::new(&config);
+        assert_eq!(
+            original.lines(&file, 0, 2),
+            vec!["int total =", "    a + b;", "int other = total;"]
+        );
+    }
+
+    #[test]
+    fn test_file_without_original_text_uses_cleaned() {
+        let file =
+            SourceFile::from_source("a.c".to_string(), "    int x = 1;\n", 3, &BTreeMap::new());
+        let original = SnippetSource::new(&preserving());
+        assert_eq!(original.lines(&file, 0, 1), vec!["int x = 1;"]);
+    }
+}
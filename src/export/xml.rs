@@ -1,9 +1,9 @@
 //! XML exporter
 
 use crate::config::Config;
-use crate::core::{DuploResult, SourceFile};
+use crate::core::{DuploResult, MatchKind, SourceFile};
 use crate::error::Result;
-use crate::export::Exporter;
+use crate::export::{snippet_len, Exporter, SnippetSource};
 use std::io::Write;
 
 /// XML output exporter
@@ -25,13 +25,15 @@ impl Exporter for XmlExporter {
         &self,
         result: &DuploResult,
         source_files: &[SourceFile],
-        _config: &Config,
+        config: &Config,
         writer: &mut dyn Write,
     ) -> Result<()> {
+        result.check_indices(source_files)?;
         writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
         writeln!(writer, "<duplo>")?;
 
         // Output each duplicate block as a set
+        let snippets = SnippetSource::new(config);
         for block in &result.blocks {
             let source1 = &source_files[block.source1_idx];
             let source2 = &source_files[block.source2_idx];
@@ -39,13 +41,21 @@ impl Exporter for XmlExporter {
             let start1 = source1.get_line(block.line1).line_number();
             let end1 = source1
                 .get_line(block.line1 + block.count - 1)
-                .line_number();
+                .end_line_number();
             let start2 = source2.get_line(block.line2).line_number();
             let end2 = source2
                 .get_line(block.line2 + block.count - 1)
-                .line_number();
+                .end_line_number();
 
-            writeln!(writer, r#"  <set LineCount="{}">"#, block.count)?;
+            let match_kind = match block.match_kind(source_files) {
+                MatchKind::Exact => "exact",
+                MatchKind::Approximate => "approximate",
+            };
+            writeln!(
+                writer,
+                r#"  <set LineCount="{}" MatchKind="{}">"#,
+                block.count, match_kind
+            )?;
             writeln!(
                 writer,
                 r#"    <block SourceFile="{}" StartLineNumber="{}" EndLineNumber="{}"/>"#,
@@ -61,10 +71,22 @@ impl Exporter for XmlExporter {
                 end2
             )?;
 
-            writeln!(writer, r#"    <lines xml:space="preserve">"#)?;
-            let lines = source1.get_lines(block.line1, block.line1 + block.count);
+            let (snippet_len, truncated) = snippet_len(block.count, config);
+            if truncated {
+                writeln!(
+                    writer,
+                    r#"    <lines xml:space="preserve" Truncated="true">"#
+                )?;
+            } else {
+                writeln!(writer, r#"    <lines xml:space="preserve">"#)?;
+            }
+            let lines = snippets.lines(source1, block.line1, snippet_len);
             for line in lines {
-                writeln!(writer, r#"      <line Text="{}"/>"#, Self::escape_xml(line))?;
+                writeln!(
+                    writer,
+                    r#"      <line Text="{}"/>"#,
+                    Self::escape_xml(&line)
+                )?;
             }
             writeln!(writer, "    </lines>")?;
             writeln!(writer, "  </set>")?;
@@ -72,6 +94,7 @@ impl Exporter for XmlExporter {
 
         // Summary element
         writeln!(writer, "  <summary")?;
+        writeln!(writer, r#"    FilesProvided="{}""#, result.files_provided)?;
         writeln!(writer, r#"    FilesAnalyzed="{}""#, result.files_analyzed)?;
         writeln!(writer, r#"    TotalLines="{}""#, result.total_lines)?;
         writeln!(
@@ -84,8 +107,30 @@ impl Exporter for XmlExporter {
             let percent = (result.duplicate_lines as f64 / result.total_lines as f64) * 100.0;
             writeln!(writer, r#"    DuplicationPercent="{:.1}""#, percent)?;
         }
+        if config.sample.is_some() {
+            writeln!(writer, r#"    Sampled="true""#)?;
+        }
+        if result.is_partial() {
+            writeln!(writer, r#"    Partial="true""#)?;
+        }
         writeln!(writer, "  />")?;
 
+        if !result.warnings.is_empty() {
+            writeln!(writer, "  <warnings>")?;
+            for warning in &result.warnings {
+                write!(writer, r#"    <warning Kind="{}""#, warning.kind.name())?;
+                if let Some(ref path) = warning.path {
+                    write!(writer, r#" Path="{}""#, Self::escape_xml(path))?;
+                }
+                writeln!(
+                    writer,
+                    r#" Message="{}"/>"#,
+                    Self::escape_xml(&warning.message)
+                )?;
+            }
+            writeln!(writer, "  </warnings>")?;
+        }
+
         writeln!(writer, "</duplo>")?;
 
         Ok(())
@@ -95,7 +140,7 @@ impl Exporter for XmlExporter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{Block, SourceLine};
+    use crate::core::{Block, SourceLine, Warning, WarningKind};
 
     #[test]
     fn test_xml_export() {
@@ -109,13 +154,7 @@ mod tests {
         let sf2 = SourceFile::from_lines("b.c".to_string(), lines2);
         let source_files = vec![sf1, sf2];
 
-        let result = DuploResult {
-            blocks: vec![Block::new(0, 1, 0, 0, 2)],
-            files_analyzed: 2,
-            total_lines: 4,
-            duplicate_lines: 2,
-            duplicate_blocks: 1,
-        };
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 2)]);
 
         let config = Config::default();
         let exporter = XmlExporter;
@@ -132,6 +171,56 @@ mod tests {
         assert!(output_str.contains(r#"LineCount="2""#));
     }
 
+    #[test]
+    fn test_xml_marks_interrupted_run_partial() {
+        let result = |warnings| DuploResult {
+            warnings,
+            ..DuploResult::from_blocks(Vec::new())
+        };
+        let export = |result: DuploResult| {
+            let mut output = Vec::new();
+            XmlExporter
+                .export(&result, &[], &Config::default(), &mut output)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let interrupted = export(result(vec![Warning::new(
+            WarningKind::Interrupted,
+            "stopped",
+        )]));
+        assert!(interrupted.contains(r#"Partial="true""#));
+        assert!(interrupted.contains(r#"<warning Kind="interrupted""#));
+        assert!(!export(result(Vec::new())).contains("Partial"));
+    }
+
+    #[test]
+    fn test_xml_snippet_truncated() {
+        let lines: Vec<SourceLine> = (1..=10)
+            .map(|i| SourceLine::new(format!("line{}", i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 10)]);
+        let config = Config {
+            max_snippet_lines: Some(3),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        XmlExporter
+            .export(&result, &source_files, &config, &mut output)
+            .unwrap();
+        let output_str = String::from_utf8(output).unwrap();
+
+        assert!(output_str.contains(r#"LineCount="10""#));
+        assert!(output_str.contains(r#"Truncated="true""#));
+        assert_eq!(output_str.matches("<line Text=").count(), 3);
+        assert!(!output_str.contains("line4"));
+    }
+
     #[test]
     fn test_xml_escape() {
         assert_eq!(XmlExporter::escape_xml("a < b"), "a &lt; b");
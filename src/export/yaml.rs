@@ -0,0 +1,55 @@
+//! YAML exporter
+
+use crate::config::Config;
+use crate::core::{DuploResult, SourceFile};
+use crate::error::{DuploError, Result};
+use crate::export::json::build_output;
+use crate::export::Exporter;
+use std::io::Write;
+
+/// YAML output exporter, with the same structure as the JSON output
+pub struct YamlExporter;
+
+impl Exporter for YamlExporter {
+    fn export(
+        &self,
+        result: &DuploResult,
+        source_files: &[SourceFile],
+        config: &Config,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        result.check_indices(source_files)?;
+        let output = build_output(result, source_files, config);
+        serde_yaml::to_writer(&mut *writer, &output).map_err(|e| DuploError::Other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, SourceLine};
+
+    #[test]
+    fn test_yaml_export_mirrors_json() {
+        let lines: Vec<SourceLine> = (1..=4)
+            .map(|i| SourceLine::new(format!("int v{} = {};", i, i), i))
+            .collect();
+        let source_files = vec![
+            SourceFile::from_lines("a.c".to_string(), lines.clone()),
+            SourceFile::from_lines("b.c".to_string(), lines),
+        ];
+        let result = DuploResult::from_blocks(vec![Block::new(0, 1, 0, 0, 4)]);
+
+        let mut output = Vec::new();
+        YamlExporter
+            .export(&result, &source_files, &Config::default(), &mut output)
+            .unwrap();
+
+        let parsed: serde_yaml::Value = serde_yaml::from_slice(&output).unwrap();
+        assert_eq!(parsed["summary"]["files_analyzed"], 2);
+        let duplicates = parsed["duplicates"].as_sequence().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0]["file2"]["path"], "b.c");
+        assert_eq!(duplicates[0]["lines"][3], "int v4 = 4;");
+    }
+}
@@ -1,22 +1,75 @@
 //! C/C++ file type implementation
 
+use crate::config::Granularity;
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, strip_c_style_comments, FileType};
+use crate::filetype::{
+    clean_whitespace, emit_line, flush_statement, strip_c_style_comments, FileType, LineFilter,
+    StatementCoalescer,
+};
 
 /// C/C++ file type processor
 pub struct CFileType {
-    min_chars: u32,
+    filter: LineFilter,
+    statement_mode: bool,
+    skip_disabled_code: bool,
 }
 
 impl CFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+            statement_mode: false,
+            skip_disabled_code: false,
+        }
+    }
+
+    /// Join continued lines into statements when `granularity` is `Statement`
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.statement_mode = granularity == Granularity::Statement;
+        self
+    }
+
+    /// Skip the bodies of `#if 0` / `#ifdef NEVER` regions
+    pub fn with_skip_disabled_code(mut self, enabled: bool) -> Self {
+        self.skip_disabled_code = enabled;
+        self
     }
 
     /// Check if a line is a preprocessor directive
     fn is_preprocessor_directive(line: &str) -> bool {
         line.trim_start().starts_with('#')
     }
+
+    /// Split a directive line into its keyword and argument
+    /// (e.g. "#  if 0" -> ("if", "0"))
+    fn parse_directive(line: &str) -> (&str, &str) {
+        let body = line.trim_start().trim_start_matches('#').trim_start();
+        match body.split_once(char::is_whitespace) {
+            Some((keyword, arg)) => (keyword, arg.trim()),
+            None => (body, ""),
+        }
+    }
+
+    /// Update the disabled-region depth for a directive line.
+    /// A depth of 0 means code is active.
+    fn track_disabled_region(line: &str, disabled_depth: &mut usize) {
+        let (keyword, arg) = Self::parse_directive(line);
+        if *disabled_depth == 0 {
+            let never = (keyword == "if" && arg == "0") || (keyword == "ifdef" && arg == "NEVER");
+            if never {
+                *disabled_depth = 1;
+            }
+            return;
+        }
+
+        match keyword {
+            "if" | "ifdef" | "ifndef" => *disabled_depth += 1,
+            "endif" => *disabled_depth -= 1,
+            // The alternative branch of the outermost disabled conditional is live
+            "else" | "elif" if *disabled_depth == 1 => *disabled_depth = 0,
+            _ => {}
+        }
+    }
 }
 
 impl FileType for CFileType {
@@ -26,7 +79,9 @@ impl FileType for CFileType {
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
         let mut result = Vec::new();
+        let mut statements = self.statement_mode.then(StatementCoalescer::new);
         let mut in_block_comment = false;
+        let mut disabled_depth = 0usize;
 
         for (line_num, line) in lines.iter().enumerate() {
             let cleaned = strip_c_style_comments(line, &mut in_block_comment);
@@ -37,17 +92,34 @@ impl FileType for CFileType {
 
             // Skip preprocessor directives
             if Self::is_preprocessor_directive(&cleaned) {
+                if self.skip_disabled_code {
+                    Self::track_disabled_region(&cleaned, &mut disabled_depth);
+                }
                 continue;
             }
 
-            // Validate and add line
-            if is_valid_line(&cleaned, self.min_chars) {
-                result.push(SourceLine::new(cleaned, line_num + 1));
+            // Skip code compiled out by #if 0
+            if disabled_depth > 0 {
+                continue;
             }
+
+            // Validate and add line
+            emit_line(
+                &mut result,
+                &mut statements,
+                cleaned,
+                line_num + 1,
+                &self.filter,
+            );
         }
 
+        flush_statement(&mut result, &mut statements, &self.filter);
         result
     }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -116,4 +188,44 @@ mod tests {
         let result = ft.get_cleaned_source_lines(&lines);
         assert_eq!(result.len(), 2);
     }
+
+    #[test]
+    fn test_disabled_code_skipped() {
+        let ft = CFileType::new(3).with_skip_disabled_code(true);
+        let lines = vec![
+            "#if 0".to_string(),
+            "int dead = 1;".to_string(),
+            "#ifdef INNER".to_string(),
+            "int nested_dead = 2;".to_string(),
+            "#endif".to_string(),
+            "#else".to_string(),
+            "int alive_else = 3;".to_string(),
+            "#endif".to_string(),
+            "#if 1".to_string(),
+            "int alive = 4;".to_string(),
+            "#endif".to_string(),
+            "#ifdef NEVER".to_string(),
+            "int never = 5;".to_string(),
+            "#endif".to_string(),
+            "int after = 6;".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        let texts: Vec<&str> = result.iter().map(|l| l.line()).collect();
+        assert_eq!(
+            texts,
+            vec!["int alive_else = 3;", "int alive = 4;", "int after = 6;"]
+        );
+    }
+
+    #[test]
+    fn test_disabled_code_kept_by_default() {
+        let ft = CFileType::new(3);
+        let lines = vec![
+            "#if 0".to_string(),
+            "int dead = 1;".to_string(),
+            "#endif".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        assert_eq!(result.len(), 1);
+    }
 }
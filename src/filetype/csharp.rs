@@ -1,16 +1,30 @@
 //! C# file type implementation
 
+use crate::config::Granularity;
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, strip_c_style_comments, FileType};
+use crate::filetype::{
+    clean_whitespace, emit_line, flush_statement, strip_c_style_comments, FileType, LineFilter,
+    StatementCoalescer,
+};
 
 /// C# file type processor
 pub struct CSharpFileType {
-    min_chars: u32,
+    filter: LineFilter,
+    statement_mode: bool,
 }
 
 impl CSharpFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+            statement_mode: false,
+        }
+    }
+
+    /// Join continued lines into statements when `granularity` is `Statement`
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.statement_mode = granularity == Granularity::Statement;
+        self
     }
 
     /// Check if a line is a C# preprocessor directive
@@ -26,6 +40,7 @@ impl FileType for CSharpFileType {
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
         let mut result = Vec::new();
+        let mut statements = self.statement_mode.then(StatementCoalescer::new);
         let mut in_block_comment = false;
 
         for (line_num, line) in lines.iter().enumerate() {
@@ -39,13 +54,22 @@ impl FileType for CSharpFileType {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
-                result.push(SourceLine::new(cleaned, line_num + 1));
-            }
+            emit_line(
+                &mut result,
+                &mut statements,
+                cleaned,
+                line_num + 1,
+                &self.filter,
+            );
         }
 
+        flush_statement(&mut result, &mut statements, &self.filter);
         result
     }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -1,16 +1,18 @@
 //! CSS file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// CSS file type processor
 pub struct CssFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl CssFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a CSS "preprocessor" directive (@import)
@@ -29,6 +31,7 @@ impl FileType for CssFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
 
@@ -55,17 +58,22 @@ impl FileType for CssFileType {
                 continue;
             }
 
-            if Self::is_preprocessor_directive(&cleaned) {
+            if skip.imports && Self::is_preprocessor_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    /// Quoted `url()` targets, `content` values and font names
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'']
+    }
 }
 
 #[cfg(test)]
@@ -1,16 +1,18 @@
 //! Erlang file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// Erlang file type processor
 pub struct ErlangFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl ErlangFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is an Erlang preprocessor directive
@@ -53,7 +55,7 @@ impl FileType for ErlangFileType {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
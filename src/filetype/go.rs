@@ -2,17 +2,19 @@
 
 use crate::core::SourceLine;
 use crate::filetype::{
-    clean_whitespace, is_valid_line, strip_c_style_comments, FileType, SignatureTracker,
+    clean_whitespace, is_valid_line, strip_c_style_comments, FileType, LineFilter, SignatureTracker,
 };
 
 /// Go file type processor
 pub struct GoFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl GoFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a Go import or package declaration
@@ -24,6 +26,14 @@ impl GoFileType {
             || trimmed == ")"
     }
 
+    /// Check if a line opens a grouped `import (` block that continues on
+    /// the following lines
+    fn starts_import_block(line: &str) -> bool {
+        line.strip_prefix("import")
+            .map(str::trim_start)
+            .is_some_and(|rest| rest.starts_with('(') && !rest.contains(')'))
+    }
+
     /// Check if a line starts a function/method signature
     fn starts_signature(line: &str) -> bool {
         let trimmed = line.trim_start();
@@ -88,6 +98,7 @@ impl FileType for GoFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
         let mut in_import_block = false;
@@ -101,12 +112,12 @@ impl FileType for GoFileType {
             }
 
             // Track import blocks
-            if cleaned == "import (" {
+            if skip.imports && Self::starts_import_block(&cleaned) {
                 in_import_block = true;
                 continue;
             }
             if in_import_block {
-                if cleaned == ")" {
+                if cleaned.contains(')') {
                     in_import_block = false;
                 }
                 continue;
@@ -118,23 +129,32 @@ impl FileType for GoFileType {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace) = Self::analyze_line(&cleaned);
                 sig.start(balance, has_brace);
                 continue;
             }
 
-            if Self::is_directive(&cleaned) {
+            if skip.imports && Self::is_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    /// Interpreted (`"`) and raw (backtick) strings; `'` is a rune
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '`']
+    }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -198,6 +218,23 @@ mod tests {
         assert_eq!(result[0].line(), "fmt.Println(\"Hello\")");
     }
 
+    #[test]
+    fn test_import_block_variants_filtering() {
+        let ft = GoFileType::new(3);
+        let lines = vec![
+            "import(".to_string(),
+            "    str \"strings\"".to_string(),
+            "    _ \"net/http/pprof\"".to_string(),
+            ")".to_string(),
+            "import ( \"fmt\"".to_string(),
+            "    \"os\" )".to_string(),
+            "var config = loadConfig()".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].line(), "var config = loadConfig()");
+    }
+
     #[test]
     fn test_single_import_filtering() {
         let ft = GoFileType::new(3);
@@ -1,16 +1,18 @@
 //! HTML file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// HTML file type processor
 pub struct HtmlFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl HtmlFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 }
 
@@ -63,7 +65,7 @@ impl FileType for HtmlFileType {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
@@ -87,7 +89,7 @@ mod tests {
             "</html>".to_string(),
         ];
         let result = ft.get_cleaned_source_lines(&lines);
-        assert!(result.len() >= 1);
+        assert!(!result.is_empty());
     }
 
     #[test]
@@ -1,19 +1,30 @@
 //! Java file type implementation
 
+use crate::config::Granularity;
 use crate::core::SourceLine;
 use crate::filetype::{
-    analyze_line_basic, clean_whitespace, is_valid_line, strip_c_style_comments, FileType,
-    SignatureTracker,
+    analyze_line_basic, clean_whitespace, emit_line, flush_statement, strip_c_style_comments,
+    FileType, LineFilter, SignatureTracker, StatementCoalescer,
 };
 
 /// Java file type processor
 pub struct JavaFileType {
-    min_chars: u32,
+    filter: LineFilter,
+    statement_mode: bool,
 }
 
 impl JavaFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+            statement_mode: false,
+        }
+    }
+
+    /// Join continued lines into statements when `granularity` is `Statement`
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.statement_mode = granularity == Granularity::Statement;
+        self
     }
 
     /// Check if a line is a Java "preprocessor" directive (package, import)
@@ -22,6 +33,12 @@ impl JavaFileType {
         trimmed.starts_with("package ") || trimmed.starts_with("import ")
     }
 
+    /// Check if a directive line continues on the next line (an import or
+    /// package name wrapped before its `;`)
+    fn continues_directive(line: &str) -> bool {
+        !line.contains(';')
+    }
+
     /// Check if a line is an annotation (@Something)
     fn is_annotation(line: &str) -> bool {
         line.trim_start().starts_with('@')
@@ -121,9 +138,12 @@ impl FileType for JavaFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
+        let mut statements = self.statement_mode.then(StatementCoalescer::new);
         let mut in_block_comment = false;
         let mut sig = SignatureTracker::new();
+        let mut in_directive = false;
 
         for (line_num, line) in lines.iter().enumerate() {
             let cleaned = strip_c_style_comments(line, &mut in_block_comment);
@@ -132,38 +152,55 @@ impl FileType for JavaFileType {
                 continue;
             }
 
+            // Skip the rest of a directive wrapped over several lines
+            if in_directive {
+                in_directive = Self::continues_directive(&cleaned);
+                continue;
+            }
+
             if sig.in_signature {
                 let (balance, has_brace) = analyze_line_basic(&cleaned);
                 sig.update(balance, has_brace);
                 continue;
             }
 
-            if Self::is_annotation(&cleaned) {
+            if skip.annotations && Self::is_annotation(&cleaned) {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace) = analyze_line_basic(&cleaned);
                 sig.start(balance, has_brace);
                 continue;
             }
 
-            if Self::is_preprocessor_directive(&cleaned) {
+            if skip.imports && Self::is_preprocessor_directive(&cleaned) {
+                in_directive = Self::continues_directive(&cleaned);
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
-                result.push(SourceLine::new(cleaned, line_num + 1));
-            }
+            emit_line(
+                &mut result,
+                &mut statements,
+                cleaned,
+                line_num + 1,
+                &self.filter,
+            );
         }
 
+        flush_statement(&mut result, &mut statements, &self.filter);
         result
     }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filetype::SkipRules;
 
     #[test]
     fn test_basic_java() {
@@ -193,6 +230,24 @@ mod tests {
         assert!(result.iter().all(|l| !l.line().starts_with("import")));
     }
 
+    #[test]
+    fn test_wrapped_import_filtering() {
+        let ft = JavaFileType::new(3);
+        let lines = vec![
+            "import static org.junit.jupiter.api.".to_string(),
+            "    Assertions.assertEquals;".to_string(),
+            "import com.example.very.long.package".to_string(),
+            "    .name".to_string(),
+            "    .SomeClass;".to_string(),
+            "public class Test {".to_string(),
+            "    int x = 5;".to_string(),
+            "}".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        let lines: Vec<&str> = result.iter().map(|l| l.line()).collect();
+        assert_eq!(lines, vec!["public class Test {", "int x = 5;"]);
+    }
+
     #[test]
     fn test_javadoc_comment() {
         let ft = JavaFileType::new(3);
@@ -283,6 +338,54 @@ mod tests {
         let result = ft.get_cleaned_source_lines(&lines);
         // Interface declaration stays, abstract methods are filtered
         // (they end with ; not {, but they still match signature pattern)
-        assert!(result.len() >= 1);
+        assert!(!result.is_empty());
+    }
+
+    #[test]
+    fn test_skip_rules_toggle_independently() {
+        let lines: Vec<String> = [
+            "import java.util.List;",
+            "@Override",
+            "public int size(List<String> items) {",
+            "    return items.size();",
+            "}",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let cleaned = |skip: SkipRules| -> Vec<String> {
+            JavaFileType::new(LineFilter { skip, ..3.into() })
+                .get_cleaned_source_lines(&lines)
+                .iter()
+                .map(|l| l.line().to_string())
+                .collect()
+        };
+        let all = SkipRules::default();
+
+        assert_eq!(cleaned(all), vec!["return items.size();"]);
+        assert_eq!(
+            cleaned(SkipRules {
+                imports: false,
+                ..all
+            }),
+            vec!["import java.util.List;", "return items.size();"]
+        );
+        assert_eq!(
+            cleaned(SkipRules {
+                annotations: false,
+                ..all
+            }),
+            vec!["@Override", "return items.size();"]
+        );
+        assert_eq!(
+            cleaned(SkipRules {
+                signatures: false,
+                ..all
+            }),
+            vec![
+                "public int size(List<String> items) {",
+                "return items.size();"
+            ]
+        );
     }
 }
@@ -1,18 +1,30 @@
 //! JavaScript/TypeScript file type implementation
 
+use crate::config::Granularity;
 use crate::core::SourceLine;
 use crate::filetype::{
-    clean_whitespace, is_valid_line, strip_c_style_comments, FileType, SignatureTracker,
+    clean_whitespace, emit_line, flush_statement, strip_c_style_comments, FileType, LineFilter,
+    SignatureTracker, StatementCoalescer,
 };
 
 /// JavaScript/TypeScript file type processor
 pub struct JavaScriptFileType {
-    min_chars: u32,
+    filter: LineFilter,
+    statement_mode: bool,
 }
 
 impl JavaScriptFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+            statement_mode: false,
+        }
+    }
+
+    /// Join continued lines into statements when `granularity` is `Statement`
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.statement_mode = granularity == Granularity::Statement;
+        self
     }
 
     /// Check if a line is a JS/TS "preprocessor" directive (import/export)
@@ -24,6 +36,16 @@ impl JavaScriptFileType {
             || trimmed.starts_with("const ") && trimmed.contains("require(")
     }
 
+    /// Check if a directive line opens a braced import/export list that
+    /// continues on the following lines (`import {`, `export type {`, ...)
+    fn starts_import_block(line: &str) -> bool {
+        let trimmed = line.trim_start();
+        let is_list = trimmed.starts_with("import ")
+            || trimmed.starts_with("export {")
+            || trimmed.starts_with("export type {");
+        is_list && trimmed.matches('{').count() > trimmed.matches('}').count()
+    }
+
     /// Check if a line is a TypeScript decorator (@something)
     fn is_decorator(line: &str) -> bool {
         line.trim_start().starts_with('@')
@@ -179,9 +201,12 @@ impl FileType for JavaScriptFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
+        let mut statements = self.statement_mode.then(StatementCoalescer::new);
         let mut in_block_comment = false;
         let mut sig = SignatureTracker::new();
+        let mut in_import_block = false;
 
         for (line_num, line) in lines.iter().enumerate() {
             let cleaned = strip_c_style_comments(line, &mut in_block_comment);
@@ -190,33 +215,56 @@ impl FileType for JavaScriptFileType {
                 continue;
             }
 
+            // Skip the names of a multi-line import/export list up to its `}`
+            if in_import_block {
+                if cleaned.contains('}') {
+                    in_import_block = false;
+                }
+                continue;
+            }
+
             if sig.in_signature {
                 let (balance, has_brace, has_arrow) = Self::analyze_line(&cleaned);
                 sig.update(balance, has_brace || has_arrow);
                 continue;
             }
 
-            if Self::is_decorator(&cleaned) {
+            if skip.annotations && Self::is_decorator(&cleaned) {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace, has_arrow) = Self::analyze_line(&cleaned);
                 sig.start(balance, has_brace || has_arrow);
                 continue;
             }
 
-            if Self::is_preprocessor_directive(&cleaned) {
+            if skip.imports && Self::is_preprocessor_directive(&cleaned) {
+                in_import_block = Self::starts_import_block(&cleaned);
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
-                result.push(SourceLine::new(cleaned, line_num + 1));
-            }
+            emit_line(
+                &mut result,
+                &mut statements,
+                cleaned,
+                line_num + 1,
+                &self.filter,
+            );
         }
 
+        flush_statement(&mut result, &mut statements, &self.filter);
         result
     }
+
+    /// Single, double and template (backtick) strings
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'', '`']
+    }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -265,6 +313,41 @@ mod tests {
         assert_eq!(result[0].line(), "return 'world';");
     }
 
+    #[test]
+    fn test_import_block_filtering() {
+        let ft = JavaScriptFileType::new(3);
+        let lines = vec![
+            "import {".to_string(),
+            "    useState,".to_string(),
+            "    useEffect,".to_string(),
+            "} from 'react';".to_string(),
+            "import type {".to_string(),
+            "    Props, State".to_string(),
+            "} from './types';".to_string(),
+            "export {".to_string(),
+            "    helperOne,".to_string(),
+            "    helperTwo".to_string(),
+            "};".to_string(),
+            "const value = compute();".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].line(), "const value = compute();");
+    }
+
+    #[test]
+    fn test_export_object_not_treated_as_import_block() {
+        let ft = JavaScriptFileType::new(3);
+        let lines = vec![
+            "export const settings = {".to_string(),
+            "    retries: 3,".to_string(),
+            "};".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].line(), "retries: 3,");
+    }
+
     #[test]
     fn test_jsdoc_filtering() {
         let ft = JavaScriptFileType::new(3);
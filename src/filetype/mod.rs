@@ -15,6 +15,7 @@ mod html;
 mod java;
 mod javascript;
 mod kotlin;
+mod perl;
 mod php;
 mod python;
 mod ruby;
@@ -24,7 +25,12 @@ mod swift;
 mod unknown;
 mod vb;
 
+use crate::config::{Granularity, HashAlgorithm};
 use crate::core::SourceLine;
+use regex::RegexSet;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 pub use c::CFileType;
 pub use csharp::CSharpFileType;
@@ -35,6 +41,7 @@ pub use html::HtmlFileType;
 pub use java::JavaFileType;
 pub use javascript::JavaScriptFileType;
 pub use kotlin::KotlinFileType;
+pub use perl::PerlFileType;
 pub use php::PhpFileType;
 pub use python::PythonFileType;
 pub use ruby::RubyFileType;
@@ -49,8 +56,7 @@ pub use vb::VbFileType;
 /// Implementations handle comment removal, preprocessor filtering,
 /// and line validation specific to each programming language.
 pub trait FileType: Send + Sync {
-    /// Get the name of this file type (used in tests and for debugging)
-    #[allow(dead_code)]
+    /// Get the name of this file type (e.g. "C/C++"), which identifies its language
     fn name(&self) -> &'static str;
 
     /// Process raw file lines and return cleaned source lines
@@ -62,74 +68,453 @@ pub trait FileType: Send + Sync {
     /// 4. Filters out lines that are too short or have no alphabetic chars
     /// 5. Creates SourceLine objects with hashes for remaining lines
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine>;
+
+    /// Characters that delimit string literals in this language
+    ///
+    /// Only `"` by default, since `'` often delimits a character literal or
+    /// a lifetime rather than a string.
+    fn string_quotes(&self) -> &'static [char] {
+        &['"']
+    }
+
+    /// Whether function bodies are delimited by braces, so [`find_functions`]
+    /// can locate them (`--function-granularity`)
+    fn has_brace_functions(&self) -> bool {
+        false
+    }
 }
 
 /// Create a FileType implementation based on file extension
 ///
 /// # Arguments
 /// * `filename` - The filename to determine type from
-/// * `min_chars` - Minimum characters required for a line to be included
+/// * `options` - How lines are cleaned and kept (a bare `u32` is the minimum
+///   characters per line, with every other option at its default)
+/// * `extension_map` - Extra extensions mapped to built-in ones (e.g. `ino` -> `c`),
+///   consulted before the built-in table
 ///
 /// # Returns
 /// A boxed FileType implementation appropriate for the file extension
-pub fn create_file_type(filename: &str, min_chars: u32) -> Box<dyn FileType> {
+pub fn create_file_type(
+    filename: &str,
+    options: impl Into<FileTypeOptions>,
+    extension_map: &BTreeMap<String, String>,
+) -> Box<dyn FileType> {
+    let options = options.into();
+    match builtin_language(&mapped_extension(filename, extension_map)) {
+        Some(language) => (language.create)(&options),
+        None => Box::new(UnknownFileType::new(options.filter)),
+    }
+}
+
+/// Lowercase extension of a file, after the config file's mappings
+fn mapped_extension(filename: &str, extension_map: &BTreeMap<String, String>) -> String {
     let extension = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+    extension_map
+        .get(&extension)
+        .map(|mapped| mapped.to_lowercase())
+        .unwrap_or(extension)
+}
+
+/// Language (file type name) of a file, e.g. "C/C++" for `main.c`
+///
+/// Files without a dedicated file type report "Unknown".
+pub fn language_of(filename: &str) -> &'static str {
+    file_type_name(filename, &BTreeMap::new())
+}
+
+/// Language of a file, honoring extensions mapped by the config file
+pub fn file_type_name(filename: &str, extension_map: &BTreeMap<String, String>) -> &'static str {
+    builtin_language(&mapped_extension(filename, extension_map))
+        .map_or(UNKNOWN_LANGUAGE, |language| language.name)
+}
+
+/// Check whether an extension (without the dot) has a built-in file type
+pub fn is_builtin_extension(extension: &str) -> bool {
+    builtin_language(&extension.to_lowercase()).is_some()
+}
+
+/// Built-in language handling a lowercase extension
+fn builtin_language(extension: &str) -> Option<&'static Language> {
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension))
+}
+
+/// Name reported for files without a dedicated file type
+const UNKNOWN_LANGUAGE: &str = "Unknown";
+
+/// A built-in language: its name, the extensions it handles and how its
+/// file type is built
+pub struct Language {
+    /// Name its file type reports (e.g. "C/C++")
+    pub name: &'static str,
+    /// Extensions, lowercase and without the dot
+    pub extensions: &'static [&'static str],
+    create: fn(&FileTypeOptions) -> Box<dyn FileType>,
+}
+
+/// Every built-in language
+///
+/// This is the one list of supported extensions: file type selection, git
+/// and directory discovery, and `--list-languages` all read it.
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "C/C++",
+        extensions: &["c", "cpp", "cxx", "cc", "h", "hpp", "hxx", "hh"],
+        create: |o| {
+            Box::new(
+                CFileType::new(o.filter.clone())
+                    .with_granularity(o.granularity)
+                    .with_skip_disabled_code(o.skip_disabled_code),
+            )
+        },
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        create: |o| Box::new(JavaFileType::new(o.filter.clone()).with_granularity(o.granularity)),
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        create: |o| Box::new(CSharpFileType::new(o.filter.clone()).with_granularity(o.granularity)),
+    },
+    Language {
+        name: "VB.NET",
+        extensions: &["vb"],
+        create: |o| Box::new(VbFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Erlang",
+        extensions: &["erl", "hrl"],
+        create: |o| Box::new(ErlangFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        create: |o| Box::new(PythonFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        create: |o| Box::new(RustFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "JavaScript/TypeScript",
+        extensions: &["js", "jsx", "ts", "tsx", "mjs", "cjs"],
+        create: |o| {
+            Box::new(JavaScriptFileType::new(o.filter.clone()).with_granularity(o.granularity))
+        },
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        create: |o| Box::new(GoFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        create: |o| Box::new(KotlinFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        create: |o| Box::new(RubyFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php", "phtml", "php3", "php4", "php5", "phps"],
+        create: |o| Box::new(PhpFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        create: |o| Box::new(PerlFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        create: |o| Box::new(SwiftFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "Scala",
+        extensions: &["scala", "sc"],
+        create: |o| Box::new(ScalaFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xhtml"],
+        create: |o| Box::new(HtmlFileType::new(o.filter.clone())),
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        create: |o| Box::new(CssFileType::new(o.filter.clone())),
+    },
+];
+
+/// Rules deciding which cleaned lines are kept for comparison
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+    /// Minimum characters per line
+    pub min_chars: u32,
+    /// Keep structural delimiter lines (`{`, `});`, `end`) regardless of
+    /// `min_chars`, so they don't split otherwise contiguous blocks
+    pub keep_structural: bool,
+    /// Keep lines without any alphabetic character (`255, 0, 17,` in a
+    /// lookup table), which are dropped by default
+    pub include_nonalphabetic: bool,
+    /// Replace the contents of string literals with a placeholder, so lines
+    /// differing only in their strings compare equal
+    pub normalize_strings: bool,
+    /// Hash function applied to the kept lines
+    pub hash: HashAlgorithm,
+    /// Cleaned lines matching any of these patterns are dropped
+    pub ignore_patterns: Option<Arc<RegexSet>>,
+    /// Declaration lines dropped by the languages that recognize them
+    pub skip: SkipRules,
+}
+
+/// Kinds of declaration lines a file type drops, each toggled on its own
+///
+/// All are dropped by default. C-family preprocessor lines (`#include`,
+/// `#define`) are always dropped and aren't covered here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkipRules {
+    /// Import, package and module directives (`import`, `use`, `require`)
+    pub imports: bool,
+    /// Annotations, attributes and decorators (`@Override`, `#[derive(..)]`)
+    pub annotations: bool,
+    /// Function and method signatures, including ones spanning several lines
+    pub signatures: bool,
+}
+
+impl Default for SkipRules {
+    fn default() -> Self {
+        Self {
+            imports: true,
+            annotations: true,
+            signatures: true,
+        }
+    }
+}
 
-    match extension.as_str() {
-        // C/C++
-        "c" | "cpp" | "cxx" | "cc" | "h" | "hpp" | "hxx" | "hh" => {
-            Box::new(CFileType::new(min_chars))
+impl From<u32> for LineFilter {
+    fn from(min_chars: u32) -> Self {
+        Self {
+            min_chars,
+            keep_structural: false,
+            include_nonalphabetic: false,
+            normalize_strings: false,
+            hash: HashAlgorithm::default(),
+            ignore_patterns: None,
+            skip: SkipRules::default(),
         }
-        // Java
-        "java" => Box::new(JavaFileType::new(min_chars)),
-        // C#
-        "cs" => Box::new(CSharpFileType::new(min_chars)),
-        // VB.NET
-        "vb" => Box::new(VbFileType::new(min_chars)),
-        // Erlang
-        "erl" | "hrl" => Box::new(ErlangFileType::new(min_chars)),
-        // Python
-        "py" | "pyw" | "pyi" => Box::new(PythonFileType::new(min_chars)),
-        // Rust
-        "rs" => Box::new(RustFileType::new(min_chars)),
-        // JavaScript/TypeScript
-        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => Box::new(JavaScriptFileType::new(min_chars)),
-        // Go
-        "go" => Box::new(GoFileType::new(min_chars)),
-        // Kotlin
-        "kt" | "kts" => Box::new(KotlinFileType::new(min_chars)),
-        // Ruby
-        "rb" | "rake" | "gemspec" => Box::new(RubyFileType::new(min_chars)),
-        // PHP
-        "php" | "phtml" | "php3" | "php4" | "php5" | "phps" => {
-            Box::new(PhpFileType::new(min_chars))
+    }
+}
+
+/// Everything that decides how a file's raw lines become comparable lines
+///
+/// Passed whole to [`create_file_type`] and `SourceFile`, so adding an option
+/// doesn't change their signatures. Options a file type doesn't support
+/// (e.g. statement granularity for Python) are ignored.
+#[derive(Debug, Clone)]
+pub struct FileTypeOptions {
+    /// Which cleaned lines are kept
+    pub filter: LineFilter,
+    /// Unit of comparison (statements apply to C-family languages)
+    pub granularity: Granularity,
+    /// Skip `#if 0` regions (C/C++ only)
+    pub skip_disabled_code: bool,
+    /// Split the lines of minified files into statements before cleaning
+    pub split_minified: bool,
+    /// Record where the functions of brace languages lie
+    /// (`--function-granularity`)
+    pub functions: bool,
+    /// Keep each file's original text for snippets (`--preserve-indentation`)
+    pub keep_original: bool,
+}
+
+impl From<LineFilter> for FileTypeOptions {
+    fn from(filter: LineFilter) -> Self {
+        Self {
+            filter,
+            granularity: Granularity::Line,
+            skip_disabled_code: false,
+            split_minified: false,
+            functions: false,
+            keep_original: false,
         }
-        // Swift
-        "swift" => Box::new(SwiftFileType::new(min_chars)),
-        // Scala
-        "scala" | "sc" => Box::new(ScalaFileType::new(min_chars)),
-        // HTML
-        "html" | "htm" | "xhtml" => Box::new(HtmlFileType::new(min_chars)),
-        // CSS
-        "css" | "scss" | "less" => Box::new(CssFileType::new(min_chars)),
-        // Unknown/fallback
-        _ => Box::new(UnknownFileType::new(min_chars)),
     }
 }
 
+impl From<u32> for FileTypeOptions {
+    fn from(min_chars: u32) -> Self {
+        LineFilter::from(min_chars).into()
+    }
+}
+
+/// Placeholder the contents of string literals are replaced with
+pub(crate) const STRING_PLACEHOLDER: &str = "STR";
+
+/// Replace the contents of every string literal delimited by one of `quotes`
+/// with [`STRING_PLACEHOLDER`], e.g. `log("saving user")` -> `log("STR")`
+///
+/// Backslash escapes are honored; an unterminated literal runs to the end of
+/// the line.
+pub(crate) fn normalize_string_literals(line: &str, quotes: &[char]) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if !quotes.contains(&c) {
+            continue;
+        }
+
+        result.push_str(STRING_PLACEHOLDER);
+        while let Some(inner) = chars.next() {
+            if inner == '\\' {
+                chars.next();
+            } else if inner == c {
+                result.push(c);
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+/// Check if a line is only a structural delimiter: braces (with closing
+/// parentheses, brackets and separators, e.g. `});`) or `begin` / `end`
+pub(crate) fn is_structural_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    let braces_only =
+        trimmed.contains(['{', '}']) && trimmed.chars().all(|c| "{}()[];, ".contains(c));
+    let keyword = trimmed.trim_end_matches([';', ',', '.']);
+    braces_only || matches!(keyword, "begin" | "end")
+}
+
 /// Common line validation logic shared by all file types
-pub(crate) fn is_valid_line(line: &str, min_chars: u32) -> bool {
+pub(crate) fn is_valid_line(line: &str, filter: &LineFilter) -> bool {
     let trimmed = line.trim();
+    if filter
+        .ignore_patterns
+        .as_ref()
+        .is_some_and(|patterns| patterns.is_match(trimmed))
+    {
+        return false;
+    }
+
+    if filter.keep_structural && is_structural_line(line) {
+        return true;
+    }
+
+    let min_chars = filter.min_chars;
 
-    // Must have at least min_chars non-whitespace characters
-    if trimmed.len() < min_chars as usize {
+    // Must have at least min_chars characters (not bytes, so multibyte
+    // text is measured by its visible length)
+    if trimmed.chars().count() < min_chars as usize {
         return false;
     }
 
+    if filter.include_nonalphabetic {
+        return !trimmed.is_empty();
+    }
+
     // Must contain at least one alphabetic character
     trimmed.chars().any(|c| c.is_alphabetic())
 }
 
+/// Joins continued physical lines into logical statements.
+///
+/// Used by C-family file types in `Granularity::Statement` mode. A statement
+/// ends at a line whose last character is `;`, `{` or `}`; the joined
+/// statement spans the line numbers of its first and last lines.
+pub(crate) struct StatementCoalescer {
+    pending: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl StatementCoalescer {
+    pub fn new() -> Self {
+        Self {
+            pending: String::new(),
+            start_line: 0,
+            end_line: 0,
+        }
+    }
+
+    /// Append a cleaned line. Returns the statement with its first and last
+    /// line numbers once the line terminates it.
+    pub fn push(&mut self, line: &str, line_number: usize) -> Option<(String, usize, usize)> {
+        if self.pending.is_empty() {
+            self.start_line = line_number;
+        } else {
+            self.pending.push(' ');
+        }
+        self.pending.push_str(line);
+        self.end_line = line_number;
+
+        if line.ends_with([';', '{', '}']) {
+            self.finish()
+        } else {
+            None
+        }
+    }
+
+    /// Take any unterminated statement left at end of file
+    pub fn finish(&mut self) -> Option<(String, usize, usize)> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some((
+                std::mem::take(&mut self.pending),
+                self.start_line,
+                self.end_line,
+            ))
+        }
+    }
+}
+
+/// Emit a cleaned line, first routing it through `statements` when
+/// statement granularity is enabled.
+pub(crate) fn emit_line(
+    result: &mut Vec<SourceLine>,
+    statements: &mut Option<StatementCoalescer>,
+    line: String,
+    line_number: usize,
+    filter: &LineFilter,
+) {
+    let (line, line_number, end_line) = match statements {
+        Some(s) => match s.push(&line, line_number) {
+            Some(statement) => statement,
+            None => return,
+        },
+        None => (line, line_number, line_number),
+    };
+
+    if is_valid_line(&line, filter) {
+        result.push(SourceLine::new(line, line_number).with_end_line(end_line));
+    }
+}
+
+/// Emit any statement left unterminated at end of file
+pub(crate) fn flush_statement(
+    result: &mut Vec<SourceLine>,
+    statements: &mut Option<StatementCoalescer>,
+    filter: &LineFilter,
+) {
+    if let Some((line, line_number, end_line)) = statements.as_mut().and_then(|s| s.finish()) {
+        if is_valid_line(&line, filter) {
+            result.push(SourceLine::new(line, line_number).with_end_line(end_line));
+        }
+    }
+}
+
 /// Remove leading and trailing whitespace while preserving the line
 pub(crate) fn clean_whitespace(line: &str) -> String {
     line.trim().to_string()
@@ -268,84 +653,603 @@ impl SignatureTracker {
     }
 }
 
+/// Words that open a control-flow block rather than a function body
+const CONTROL_KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "switch",
+    "match",
+    "when",
+    "try",
+    "catch",
+    "finally",
+    "synchronized",
+    "using",
+    "lock",
+    "with",
+    "return",
+];
+
+/// Words declaring a type or namespace, whose braces contain functions
+const CONTAINER_KEYWORDS: &[&str] = &[
+    "class",
+    "interface",
+    "object",
+    "struct",
+    "enum",
+    "trait",
+    "impl",
+    "namespace",
+    "extension",
+    "protocol",
+];
+
+/// Whether the text between the last statement boundary and a `{` is a
+/// function signature: it closes a parameter list and isn't a control
+/// statement or type declaration (e.g. Kotlin's `class Point(val x: Int)`)
+fn is_function_header(header: &str) -> bool {
+    let Some(params) = header.find('(') else {
+        return false;
+    };
+    if !header[params..].contains(')') {
+        return false;
+    }
+    let mut words = header[..params]
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty());
+    match words.next() {
+        Some(first) if CONTROL_KEYWORDS.contains(&first) => false,
+        Some(first) => {
+            !CONTAINER_KEYWORDS.contains(&first) && words.all(|w| !CONTAINER_KEYWORDS.contains(&w))
+        }
+        // An anonymous function such as `() => {`
+        None => true,
+    }
+}
+
+/// Outermost function bodies of a brace language, as ranges of 1-based line
+/// numbers from the first line of the signature to the closing brace
+///
+/// Works on raw lines, since cleaning drops the signatures and braces.
+/// Comments, string literals and character literals are skipped; braces of
+/// types and namespaces are descended into, while functions nested in a
+/// function belong to the outer one.
+pub fn find_functions(lines: &[String]) -> Vec<RangeInclusive<usize>> {
+    let mut functions = Vec::new();
+    let mut depth = 0usize;
+    // Depth of the brace that opened the current function, and its first line
+    let mut open: Option<(usize, usize)> = None;
+    // Code since the last `;`, `{` or `}`, and the line it started on
+    let mut header = String::new();
+    let mut header_start = None;
+    let mut in_block_comment = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        let number = index + 1;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_block_comment {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    in_block_comment = false;
+                }
+                continue;
+            }
+            match c {
+                '/' if chars.peek() == Some(&'/') => break,
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    in_block_comment = true;
+                }
+                '"' | '`' => {
+                    while let Some(s) = chars.next() {
+                        if s == '\\' {
+                            chars.next();
+                        } else if s == c {
+                            break;
+                        }
+                    }
+                    header.push_str("\"\"");
+                }
+                // A character literal; otherwise a lifetime or label (Rust)
+                '\'' => {
+                    let mut ahead = chars.clone();
+                    let literal_len = match (ahead.next(), ahead.next()) {
+                        (Some('\\'), _) => ahead.position(|c| c == '\'').map(|p| p + 3),
+                        (Some(_), Some('\'')) => Some(2),
+                        _ => None,
+                    };
+                    for _ in 0..literal_len.unwrap_or(0) {
+                        chars.next();
+                    }
+                }
+                '{' => {
+                    depth += 1;
+                    if open.is_none() && is_function_header(header.trim()) {
+                        open = Some((depth, header_start.unwrap_or(number)));
+                    }
+                    header.clear();
+                    header_start = None;
+                }
+                '}' => {
+                    if let Some((function_depth, start)) = open {
+                        if function_depth == depth {
+                            functions.push(start..=number);
+                            open = None;
+                        }
+                    }
+                    depth = depth.saturating_sub(1);
+                    header.clear();
+                    header_start = None;
+                }
+                ';' => {
+                    header.clear();
+                    header_start = None;
+                }
+                _ => {
+                    if !c.is_whitespace() {
+                        header_start.get_or_insert(number);
+                    }
+                    header.push(c);
+                }
+            }
+        }
+        header.push(' ');
+    }
+
+    functions
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_is_valid_line() {
-        assert!(is_valid_line("int x = 5;", 3));
-        assert!(is_valid_line("abc", 3));
-        assert!(!is_valid_line("ab", 3)); // too short
-        assert!(!is_valid_line("123", 3)); // no alphabetic
-        assert!(!is_valid_line("   ", 3)); // only whitespace
+        assert!(is_valid_line("int x = 5;", &3.into()));
+        assert!(is_valid_line("abc", &3.into()));
+        assert!(!is_valid_line("ab", &3.into())); // too short
+        assert!(!is_valid_line("123", &3.into())); // no alphabetic
+        assert!(!is_valid_line("   ", &3.into())); // only whitespace
+    }
+
+    #[test]
+    fn test_structural_lines() {
+        for line in ["{", "}", "});", "},", "} }", "end", "end;", "begin"] {
+            assert!(is_structural_line(line), "{}", line);
+        }
+        for line in [");", "x }", "ending", "", "[]"] {
+            assert!(!is_structural_line(line), "{}", line);
+        }
+
+        let keep = LineFilter {
+            keep_structural: true,
+            ..3.into()
+        };
+        assert!(!is_valid_line("}", &3.into()));
+        assert!(is_valid_line("}", &keep));
+        assert!(is_valid_line("end", &keep));
+        assert!(!is_valid_line(");", &keep));
+    }
+
+    #[test]
+    fn test_include_nonalphabetic() {
+        let tables = LineFilter {
+            include_nonalphabetic: true,
+            ..3.into()
+        };
+        assert!(!is_valid_line("255, 0, 17,", &3.into()));
+        assert!(is_valid_line("255, 0, 17,", &tables));
+        assert!(is_valid_line("123", &tables));
+        assert!(!is_valid_line("12", &tables)); // min_chars still applies
+
+        let everything = LineFilter {
+            include_nonalphabetic: true,
+            ..0.into()
+        };
+        assert!(is_valid_line("1,", &everything));
+        assert!(!is_valid_line("   ", &everything));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let filter = LineFilter {
+            keep_structural: true,
+            ignore_patterns: Some(Arc::new(RegexSet::new([r"^return;?$", r"^\}$"]).unwrap())),
+            ..3.into()
+        };
+        assert!(!is_valid_line("  return;", &filter));
+        assert!(!is_valid_line("}", &filter)); // patterns win over keep_structural
+        assert!(is_valid_line("return x;", &filter));
+        assert!(is_valid_line("});", &filter));
+    }
+
+    #[test]
+    fn test_normalize_string_literals() {
+        assert_eq!(
+            normalize_string_literals(r#"log("saving user", 'x');"#, &['"']),
+            r#"log("STR", 'x');"#
+        );
+        assert_eq!(
+            normalize_string_literals(r#"log("saving user", 'x');"#, &['"', '\'']),
+            r#"log("STR", 'STR');"#
+        );
+        // Escaped quotes stay inside the literal
+        assert_eq!(
+            normalize_string_literals(r#"say("a \"quoted\" word") + x"#, &['"']),
+            r#"say("STR") + x"#
+        );
+        assert_eq!(
+            normalize_string_literals(r#"s = """#, &['"']),
+            r#"s = "STR""#
+        );
+        assert_eq!(
+            normalize_string_literals(r#"s = "open"#, &['"']),
+            r#"s = "STR"#
+        );
+        assert_eq!(
+            normalize_string_literals("no strings", &['"']),
+            "no strings"
+        );
+    }
+
+    #[test]
+    fn test_is_valid_line_counts_chars_not_bytes() {
+        // Three two-byte characters are three characters, not six
+        assert_eq!("äöü".len(), 6);
+        assert!(is_valid_line("äöü", &3.into()));
+        assert!(!is_valid_line("äöü", &4.into()));
+        assert!(!is_valid_line("äö", &3.into()));
     }
 
     #[test]
     fn test_create_file_type_c() {
-        let ft = create_file_type("test.cpp", 3);
+        let ft = create_file_type("test.cpp", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "C/C++");
     }
 
     #[test]
     fn test_create_file_type_java() {
-        let ft = create_file_type("Test.java", 3);
+        let ft = create_file_type("Test.java", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Java");
     }
 
+    #[test]
+    fn test_languages_table_matches_file_types() {
+        for language in LANGUAGES {
+            for extension in language.extensions {
+                let filename = format!("f.{}", extension);
+                let ft = create_file_type(&filename, 3, &BTreeMap::new());
+                assert_eq!(ft.name(), language.name, "extension '{}'", extension);
+                assert_eq!(language_of(&filename), language.name);
+            }
+        }
+        assert_eq!(language_of("notes.txt"), UnknownFileType::new(3u32).name());
+        let mut all: Vec<&str> = LANGUAGES
+            .iter()
+            .flat_map(|l| l.extensions.iter().copied())
+            .collect();
+        let count = all.len();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), count, "an extension is listed twice");
+    }
+
     #[test]
     fn test_create_file_type_unknown() {
-        let ft = create_file_type("test.xyz", 3);
+        let ft = create_file_type("test.xyz", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Unknown");
     }
 
     #[test]
     fn test_create_file_type_go() {
-        let ft = create_file_type("main.go", 3);
+        let ft = create_file_type("main.go", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Go");
     }
 
     #[test]
     fn test_create_file_type_kotlin() {
-        let ft = create_file_type("Main.kt", 3);
+        let ft = create_file_type("Main.kt", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Kotlin");
-        let ft2 = create_file_type("build.kts", 3);
+        let ft2 = create_file_type("build.kts", 3, &BTreeMap::new());
         assert_eq!(ft2.name(), "Kotlin");
     }
 
     #[test]
     fn test_create_file_type_ruby() {
-        let ft = create_file_type("app.rb", 3);
+        let ft = create_file_type("app.rb", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Ruby");
-        let ft2 = create_file_type("Rakefile.rake", 3);
+        let ft2 = create_file_type("Rakefile.rake", 3, &BTreeMap::new());
         assert_eq!(ft2.name(), "Ruby");
     }
 
     #[test]
     fn test_create_file_type_php() {
-        let ft = create_file_type("index.php", 3);
+        let ft = create_file_type("index.php", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "PHP");
     }
 
+    #[test]
+    fn test_create_file_type_perl() {
+        let ft = create_file_type("script.pl", 3, &BTreeMap::new());
+        assert_eq!(ft.name(), "Perl");
+        let ft2 = create_file_type("Module.pm", 3, &BTreeMap::new());
+        assert_eq!(ft2.name(), "Perl");
+    }
+
     #[test]
     fn test_create_file_type_swift() {
-        let ft = create_file_type("ViewController.swift", 3);
+        let ft = create_file_type("ViewController.swift", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Swift");
     }
 
     #[test]
     fn test_create_file_type_scala() {
-        let ft = create_file_type("Main.scala", 3);
+        let ft = create_file_type("Main.scala", 3, &BTreeMap::new());
         assert_eq!(ft.name(), "Scala");
-        let ft2 = create_file_type("script.sc", 3);
+        let ft2 = create_file_type("script.sc", 3, &BTreeMap::new());
         assert_eq!(ft2.name(), "Scala");
     }
 
+    #[test]
+    fn test_statement_coalescer() {
+        let mut coalescer = StatementCoalescer::new();
+        assert_eq!(coalescer.push("int total = compute(alpha,", 3), None);
+        assert_eq!(
+            coalescer.push("beta);", 4),
+            Some(("int total = compute(alpha, beta);".to_string(), 3, 4))
+        );
+        assert_eq!(
+            coalescer.push("if (ready) {", 5),
+            Some(("if (ready) {".to_string(), 5, 5))
+        );
+        assert_eq!(coalescer.push("return x", 6), None);
+        assert_eq!(coalescer.finish(), Some(("return x".to_string(), 6, 6)));
+        assert_eq!(coalescer.finish(), None);
+    }
+
     #[test]
     fn test_create_file_type_case_insensitive() {
-        let ft1 = create_file_type("test.CPP", 3);
-        let ft2 = create_file_type("test.Cpp", 3);
+        let ft1 = create_file_type("test.CPP", 3, &BTreeMap::new());
+        let ft2 = create_file_type("test.Cpp", 3, &BTreeMap::new());
         assert_eq!(ft1.name(), "C/C++");
         assert_eq!(ft2.name(), "C/C++");
     }
+
+    #[test]
+    fn test_create_file_type_extension_map() {
+        let map: BTreeMap<String, String> = [("ino".to_string(), "c".to_string())].into();
+
+        // Unmapped, .ino is unknown and comments survive
+        let ft = create_file_type("sketch.ino", 3, &BTreeMap::new());
+        assert_eq!(ft.name(), "Unknown");
+
+        let ft = create_file_type("sketch.INO", 3, &map);
+        assert_eq!(ft.name(), "C/C++");
+        let lines = vec![
+            "// setup the board".to_string(),
+            "int led = 13; /* pin */".to_string(),
+        ];
+        let result = ft.get_cleaned_source_lines(&lines);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].line(), "int led = 13;");
+    }
+
+    #[test]
+    fn test_options_match_direct_construction() {
+        // Per-language samples cleaned through the options and through each
+        // file type's own constructor must agree
+        let samples: &[(&str, Box<dyn FileType>, &str)] = &[
+            (
+                "a.c",
+                Box::new(CFileType::new(5)),
+                "#include <x.h>\nint value = 1; // c\n",
+            ),
+            (
+                "A.java",
+                Box::new(JavaFileType::new(5)),
+                "import x;\n@Override\nint value = 1;\n",
+            ),
+            (
+                "a.cs",
+                Box::new(CSharpFileType::new(5)),
+                "using X;\nint value = 1;\n",
+            ),
+            (
+                "a.vb",
+                Box::new(VbFileType::new(5)),
+                "' note\nDim value = 1\n",
+            ),
+            (
+                "a.erl",
+                Box::new(ErlangFileType::new(5)),
+                "-module(a).\nValue = 1.\n",
+            ),
+            (
+                "a.py",
+                Box::new(PythonFileType::new(5)),
+                "import os\nvalue = 1  # py\n",
+            ),
+            (
+                "a.rs",
+                Box::new(RustFileType::new(5)),
+                "use x;\n#[inline]\nlet value = 1;\n",
+            ),
+            (
+                "a.ts",
+                Box::new(JavaScriptFileType::new(5)),
+                "import x from 'x';\nlet value = 1;\n",
+            ),
+            (
+                "a.go",
+                Box::new(GoFileType::new(5)),
+                "package a\nvalue := 1\n",
+            ),
+            (
+                "a.kt",
+                Box::new(KotlinFileType::new(5)),
+                "import x\nval value = 1\n",
+            ),
+            (
+                "a.rb",
+                Box::new(RubyFileType::new(5)),
+                "require 'x'\nvalue = 1\n",
+            ),
+            (
+                "a.php",
+                Box::new(PhpFileType::new(5)),
+                "<?php\nuse X;\n$value = 1;\n",
+            ),
+            (
+                "a.pl",
+                Box::new(PerlFileType::new(5)),
+                "use strict;\nmy $value = 1;\n",
+            ),
+            (
+                "a.swift",
+                Box::new(SwiftFileType::new(5)),
+                "import UIKit\nlet value = 1\n",
+            ),
+            (
+                "a.scala",
+                Box::new(ScalaFileType::new(5)),
+                "import x._\nval value = 1\n",
+            ),
+            (
+                "a.html",
+                Box::new(HtmlFileType::new(5)),
+                "<!-- c -->\n<div>value</div>\n",
+            ),
+            (
+                "a.css",
+                Box::new(CssFileType::new(5)),
+                "@import 'x';\n.value { color: red; }\n",
+            ),
+            ("a.txt", Box::new(UnknownFileType::new(5)), "plain value\n"),
+        ];
+
+        let cleaned = |ft: &dyn FileType, lines: &[String]| -> Vec<(String, usize)> {
+            ft.get_cleaned_source_lines(lines)
+                .iter()
+                .map(|l| (l.line().to_string(), l.line_number()))
+                .collect()
+        };
+        for (filename, direct, source) in samples {
+            let lines: Vec<String> = source.lines().map(String::from).collect();
+            let via_options = create_file_type(filename, 5, &BTreeMap::new());
+            assert_eq!(via_options.name(), direct.name(), "{}", filename);
+            let expected = cleaned(direct.as_ref(), &lines);
+            assert!(!expected.is_empty(), "{}", filename);
+            assert_eq!(
+                cleaned(via_options.as_ref(), &lines),
+                expected,
+                "{}",
+                filename
+            );
+        }
+    }
+
+    #[test]
+    fn test_options_granularity_and_disabled_code() {
+        let lines: Vec<String> = [
+            "#if 0",
+            "int dead = 0;",
+            "#endif",
+            "int total =",
+            "    a + b;",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let options = FileTypeOptions {
+            granularity: Granularity::Statement,
+            skip_disabled_code: true,
+            ..3.into()
+        };
+
+        let via_options = create_file_type("a.c", options, &BTreeMap::new());
+        let direct = CFileType::new(3)
+            .with_granularity(Granularity::Statement)
+            .with_skip_disabled_code(true);
+        let result = via_options.get_cleaned_source_lines(&lines);
+        assert_eq!(result, direct.get_cleaned_source_lines(&lines));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].line(), "int total = a + b;");
+
+        // Languages without statement mode ignore it
+        let plain = create_file_type("a.py", FileTypeOptions::from(3), &BTreeMap::new());
+        let statement = create_file_type(
+            "a.py",
+            FileTypeOptions {
+                granularity: Granularity::Statement,
+                ..3.into()
+            },
+            &BTreeMap::new(),
+        );
+        assert_eq!(
+            plain.get_cleaned_source_lines(&lines),
+            statement.get_cleaned_source_lines(&lines)
+        );
+    }
+
+    #[test]
+    fn test_find_functions() {
+        let source = r#"package demo;
+
+public class Totals {
+    private int count = 0;
+
+    @Override
+    public int sum(int a,
+                   int b) {
+        if (a > b) {
+            return a + b; // }
+        }
+        String s = "{";
+        char c = '}';
+        return b;
+    }
+
+    /* void fake() { */
+    void run() { Runnable r = () -> { go(); }; }
+}
+
+class Point(val x: Int) {
+    fun <'a> len(s: &'a str) { }
+}
+"#;
+        let lines: Vec<String> = source.lines().map(String::from).collect();
+        assert_eq!(find_functions(&lines), vec![6..=15, 18..=18, 22..=22]);
+    }
+
+    #[test]
+    fn test_language_of() {
+        assert_eq!(language_of("src/main.c"), "C/C++");
+        assert_eq!(language_of("Widget.HPP"), "C/C++");
+        assert_eq!(language_of("App.java"), "Java");
+        assert_eq!(language_of("script.py"), "Python");
+        assert_eq!(language_of("lib.rs"), "Rust");
+        assert_eq!(language_of("index.tsx"), "JavaScript/TypeScript");
+        assert_eq!(language_of("notes.txt"), "Unknown");
+        assert_eq!(language_of("Makefile"), "Unknown");
+    }
+
+    #[test]
+    fn test_file_type_name_extension_map() {
+        let map: BTreeMap<String, String> = [("ino".to_string(), "c".to_string())].into();
+        assert_eq!(file_type_name("sketch.ino", &BTreeMap::new()), "Unknown");
+        assert_eq!(file_type_name("sketch.ino", &map), "C/C++");
+        assert_eq!(file_type_name("main.py", &map), "Python");
+    }
+
+    #[test]
+    fn test_is_builtin_extension() {
+        assert!(is_builtin_extension("c"));
+        assert!(is_builtin_extension("PY"));
+        assert!(!is_builtin_extension("ino"));
+    }
 }
@@ -0,0 +1,271 @@
+//! Perl file type implementation
+
+use crate::core::SourceLine;
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
+
+/// Perl file type processor
+pub struct PerlFileType {
+    filter: LineFilter,
+}
+
+impl PerlFileType {
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
+    }
+
+    /// Check if a line is a Perl directive (use, no, require, package)
+    fn is_directive(line: &str) -> bool {
+        let trimmed = line.trim_start();
+        trimmed.starts_with("use ")
+            || trimmed.starts_with("no ")
+            || trimmed.starts_with("require ")
+            || trimmed.starts_with("package ")
+    }
+
+    /// Check if a line starts a POD region (`=pod`, `=head1`, `=begin`, ...)
+    ///
+    /// POD commands must start in the first column.
+    fn starts_pod(line: &str) -> bool {
+        line.strip_prefix('=')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    /// Check if a line ends a POD region
+    fn ends_pod(line: &str) -> bool {
+        line.strip_prefix("=cut")
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    }
+
+    /// Check if a line ends the code, leaving only data
+    fn ends_code(line: &str) -> bool {
+        matches!(line.trim_end(), "__END__" | "__DATA__")
+    }
+
+    /// Find a heredoc marker (`<<EOF`, `<<"EOF"`, `<<'EOF'`, `<<~EOF`),
+    /// returning its byte position and terminator
+    ///
+    /// `<<` followed by anything else (e.g. `1 << 2`) is a shift.
+    fn find_heredoc(line: &str) -> Option<(usize, String)> {
+        let mut search = 0;
+        while let Some(offset) = line[search..].find("<<") {
+            let pos = search + offset;
+            let after = line[pos + 2..].trim_start_matches('~');
+            let (quoted, after) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => (Some(q), &after[1..]),
+                _ => (None, after),
+            };
+            let delim: String = after
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            let closed = match quoted {
+                Some(q) => after[delim.len()..].starts_with(q),
+                None => true,
+            };
+            if closed && delim.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                return Some((pos, delim));
+            }
+            search = pos + 2;
+        }
+        None
+    }
+
+    /// Remove Perl single-line comments (# style)
+    ///
+    /// `#` inside quoted strings and in `$#array` / `$#{expr}` (last index)
+    /// doesn't start a comment.
+    fn remove_comment(line: &str) -> &str {
+        let mut in_single_string = false;
+        let mut in_double_string = false;
+        let bytes = line.as_bytes();
+
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            if in_double_string {
+                if c == b'"' {
+                    in_double_string = false;
+                } else if c == b'\\' {
+                    i += 1; // skip escaped char
+                }
+            } else if in_single_string {
+                if c == b'\'' {
+                    in_single_string = false;
+                } else if c == b'\\' {
+                    i += 1;
+                }
+            } else if c == b'"' {
+                in_double_string = true;
+            } else if c == b'\'' {
+                in_single_string = true;
+            } else if c == b'#' && (i == 0 || bytes[i - 1] != b'$') {
+                return &line[..i];
+            }
+            i += 1;
+        }
+
+        line
+    }
+
+    /// Clean a line of code and add it if it's worth comparing
+    fn push_line(&self, result: &mut Vec<SourceLine>, line: &str, line_number: usize) {
+        let cleaned = clean_whitespace(Self::remove_comment(line));
+        if !cleaned.is_empty()
+            && is_valid_line(&cleaned, &self.filter)
+            && !(self.filter.skip.imports && Self::is_directive(&cleaned))
+        {
+            result.push(SourceLine::new(cleaned, line_number));
+        }
+    }
+}
+
+impl FileType for PerlFileType {
+    fn name(&self) -> &'static str {
+        "Perl"
+    }
+
+    fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let mut result = Vec::new();
+        let mut in_pod = false;
+        let mut heredoc_delimiter: Option<String> = None;
+
+        for (line_num, line) in lines.iter().enumerate() {
+            // Skip POD documentation (any =command up to =cut)
+            if in_pod {
+                if Self::ends_pod(line) {
+                    in_pod = false;
+                }
+                continue;
+            }
+
+            // Skip heredoc bodies up to the terminator line
+            if let Some(ref delim) = heredoc_delimiter {
+                if line.trim() == delim.as_str() {
+                    heredoc_delimiter = None;
+                }
+                continue;
+            }
+
+            if Self::starts_pod(line) {
+                in_pod = !Self::ends_pod(line);
+                continue;
+            }
+
+            if Self::ends_code(line) {
+                break;
+            }
+
+            // Keep the code before a heredoc marker
+            if let Some((pos, delim)) = Self::find_heredoc(Self::remove_comment(line)) {
+                heredoc_delimiter = Some(delim);
+                self.push_line(&mut result, &line[..pos], line_num + 1);
+                continue;
+            }
+
+            self.push_line(&mut result, line, line_num + 1);
+        }
+
+        result
+    }
+
+    /// Non-interpolating `'...'` and interpolating `"..."` strings
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'']
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleaned(lines: &[&str]) -> Vec<String> {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        PerlFileType::new(3)
+            .get_cleaned_source_lines(&lines)
+            .iter()
+            .map(|l| l.line().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_comment_removal() {
+        let result = cleaned(&[
+            "my $x = 5; # comment",
+            "# full line comment",
+            "my $last = $#items;",
+            "my $top = $#{$stack};",
+            "print \"total # $x\\n\";",
+        ]);
+        assert_eq!(
+            result,
+            vec![
+                "my $x = 5;",
+                "my $last = $#items;",
+                "my $top = $#{$stack};",
+                "print \"total # $x\\n\";",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directive_filtering() {
+        let result = cleaned(&[
+            "package My::Module;",
+            "use strict;",
+            "use warnings;",
+            "no warnings 'redefine';",
+            "require Exporter;",
+            "my $value = compute();",
+        ]);
+        assert_eq!(result, vec!["my $value = compute();"]);
+    }
+
+    #[test]
+    fn test_pod_block_skipped() {
+        let result = cleaned(&[
+            "my $before = 1;",
+            "=head1 NAME",
+            "",
+            "# not a comment, just POD text",
+            "my $example = 'also POD';",
+            "=cut",
+            "my $after = 2;",
+            "=pod",
+            "# more docs",
+            "=cut",
+        ]);
+        assert_eq!(result, vec!["my $before = 1;", "my $after = 2;"]);
+    }
+
+    #[test]
+    fn test_heredoc_skipped() {
+        let result = cleaned(&[
+            "my $text = <<\"END_TEXT\";",
+            "# heading in the text",
+            "my $not_code = 1;",
+            "END_TEXT",
+            "my $shifted = $flags << 2;",
+            "print <<~EOT;",
+            "    indented text",
+            "    EOT",
+            "my $done = 1;",
+        ]);
+        assert_eq!(
+            result,
+            vec![
+                "my $text =",
+                "my $shifted = $flags << 2;",
+                "print",
+                "my $done = 1;",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_section_ignored() {
+        let result = cleaned(&["my $x = <DATA>;", "__END__", "some data line"]);
+        assert_eq!(result, vec!["my $x = <DATA>;"]);
+    }
+}
@@ -1,16 +1,18 @@
 //! PHP file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType, SignatureTracker};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter, SignatureTracker};
 
 /// PHP file type processor
 pub struct PhpFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl PhpFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a PHP directive (use, namespace, require, include)
@@ -152,6 +154,7 @@ impl FileType for PhpFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
         let mut sig = SignatureTracker::new();
@@ -195,27 +198,36 @@ impl FileType for PhpFileType {
                 continue;
             }
 
-            if Self::is_attribute(&cleaned) {
+            if skip.annotations && Self::is_attribute(&cleaned) {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace) = Self::analyze_line(&cleaned);
                 sig.start(balance, has_brace);
                 continue;
             }
 
-            if Self::is_directive(&cleaned) {
+            if skip.imports && Self::is_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    /// Single-quoted and double-quoted (interpolating) strings
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'']
+    }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -1,16 +1,18 @@
 //! Python file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// Python file type processor
 pub struct PythonFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl PythonFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a Python "preprocessor" directive (import/from)
@@ -100,6 +102,7 @@ impl FileType for PythonFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_multiline_string = false;
         let mut multiline_delimiter: Option<&str> = None;
@@ -134,12 +137,12 @@ impl FileType for PythonFileType {
             }
 
             // Skip decorators
-            if Self::is_decorator(line) {
+            if skip.annotations && Self::is_decorator(line) {
                 continue;
             }
 
             // Check for start of function signature
-            if Self::starts_signature(line) {
+            if skip.signatures && Self::starts_signature(line) {
                 let (open, close) = Self::count_parens(line);
                 paren_depth = open as i32 - close as i32;
 
@@ -203,8 +206,8 @@ impl FileType for PythonFileType {
                     let cleaned = clean_whitespace(without_comment);
 
                     if !cleaned.is_empty()
-                        && is_valid_line(&cleaned, self.min_chars)
-                        && !Self::is_preprocessor_directive(&cleaned)
+                        && is_valid_line(&cleaned, &self.filter)
+                        && !(skip.imports && Self::is_preprocessor_directive(&cleaned))
                     {
                         result.push(SourceLine::new(cleaned, line_num + 1));
                     }
@@ -220,8 +223,8 @@ impl FileType for PythonFileType {
                     let cleaned = clean_whitespace(without_comment);
 
                     if !cleaned.is_empty()
-                        && is_valid_line(&cleaned, self.min_chars)
-                        && !Self::is_preprocessor_directive(&cleaned)
+                        && is_valid_line(&cleaned, &self.filter)
+                        && !(skip.imports && Self::is_preprocessor_directive(&cleaned))
                     {
                         result.push(SourceLine::new(cleaned, line_num + 1));
                     }
@@ -237,17 +240,22 @@ impl FileType for PythonFileType {
                 continue;
             }
 
-            if Self::is_preprocessor_directive(&cleaned) {
+            if skip.imports && Self::is_preprocessor_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    /// `'` and `"` both delimit strings (including triple-quoted ones)
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'']
+    }
 }
 
 #[cfg(test)]
@@ -1,16 +1,18 @@
 //! Ruby file type implementation
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// Ruby file type processor
 pub struct RubyFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl RubyFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a Ruby directive (require, require_relative, load, include)
@@ -71,6 +73,7 @@ impl FileType for RubyFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
         let mut in_heredoc = false;
@@ -116,11 +119,9 @@ impl FileType for RubyFileType {
                     let before = &line[..pos];
                     let without_comment = Self::remove_comment(before);
                     let cleaned = clean_whitespace(without_comment);
-                    if !cleaned.is_empty()
-                        && !Self::is_directive(&cleaned)
-                        && !Self::starts_signature(&cleaned)
-                        && is_valid_line(&cleaned, self.min_chars)
-                    {
+                    let skipped = (skip.imports && Self::is_directive(&cleaned))
+                        || (skip.signatures && Self::starts_signature(&cleaned));
+                    if !cleaned.is_empty() && !skipped && is_valid_line(&cleaned, &self.filter) {
                         result.push(SourceLine::new(cleaned, line_num + 1));
                     }
                     continue;
@@ -135,21 +136,26 @@ impl FileType for RubyFileType {
             }
 
             // Skip method signatures
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 continue;
             }
 
-            if Self::is_directive(&cleaned) {
+            if skip.imports && Self::is_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    /// Single-quoted (literal) and double-quoted (interpolated) strings
+    fn string_quotes(&self) -> &'static [char] {
+        &['"', '\'']
+    }
 }
 
 #[cfg(test)]
@@ -2,17 +2,19 @@
 
 use crate::core::SourceLine;
 use crate::filetype::{
-    clean_whitespace, is_valid_line, strip_nested_comments, FileType, SignatureTracker,
+    clean_whitespace, is_valid_line, strip_nested_comments, FileType, LineFilter, SignatureTracker,
 };
 
 /// Rust file type processor
 pub struct RustFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl RustFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a Rust "preprocessor" directive
@@ -122,6 +124,7 @@ impl FileType for RustFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
         let mut comment_depth = 0;
@@ -140,32 +143,37 @@ impl FileType for RustFileType {
                 continue;
             }
 
-            if Self::is_attribute(&cleaned) {
+            if skip.annotations && Self::is_attribute(&cleaned) {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace) = Self::analyze_line(&cleaned);
                 sig.start(balance, has_brace);
                 continue;
             }
 
-            if Self::is_preprocessor_directive(&cleaned) {
+            if skip.imports && Self::is_preprocessor_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filetype::SkipRules;
 
     #[test]
     fn test_basic_rust() {
@@ -302,4 +310,49 @@ mod tests {
         assert!(result.iter().any(|l| l.line().starts_with("if")));
         assert!(result.iter().any(|l| l.line().starts_with("for")));
     }
+
+    #[test]
+    fn test_skip_rules_toggle_independently() {
+        let lines: Vec<String> = [
+            "use std::io;",
+            "#[inline]",
+            "fn total(items: &[u32]) -> u32 {",
+            "    items.iter().sum()",
+            "}",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let cleaned = |skip: SkipRules| -> Vec<String> {
+            RustFileType::new(LineFilter { skip, ..3.into() })
+                .get_cleaned_source_lines(&lines)
+                .iter()
+                .map(|l| l.line().to_string())
+                .collect()
+        };
+        let all = SkipRules::default();
+
+        assert_eq!(cleaned(all), vec!["items.iter().sum()"]);
+        assert_eq!(
+            cleaned(SkipRules {
+                imports: false,
+                ..all
+            }),
+            vec!["use std::io;", "items.iter().sum()"]
+        );
+        assert_eq!(
+            cleaned(SkipRules {
+                annotations: false,
+                ..all
+            }),
+            vec!["#[inline]", "items.iter().sum()"]
+        );
+        assert_eq!(
+            cleaned(SkipRules {
+                signatures: false,
+                ..all
+            }),
+            vec!["fn total(items: &[u32]) -> u32 {", "items.iter().sum()"]
+        );
+    }
 }
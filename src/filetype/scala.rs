@@ -3,17 +3,19 @@
 use crate::core::SourceLine;
 use crate::filetype::{
     analyze_line_basic, clean_whitespace, is_valid_line, strip_nested_comments, FileType,
-    SignatureTracker,
+    LineFilter, SignatureTracker,
 };
 
 /// Scala file type processor
 pub struct ScalaFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl ScalaFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 
     /// Check if a line is a Scala directive (package, import)
@@ -104,6 +106,7 @@ impl FileType for ScalaFileType {
     }
 
     fn get_cleaned_source_lines(&self, lines: &[String]) -> Vec<SourceLine> {
+        let skip = self.filter.skip;
         let mut result = Vec::new();
         let mut in_block_comment = false;
         let mut comment_depth = 0;
@@ -122,27 +125,31 @@ impl FileType for ScalaFileType {
                 continue;
             }
 
-            if Self::is_annotation(&cleaned) {
+            if skip.annotations && Self::is_annotation(&cleaned) {
                 continue;
             }
 
-            if Self::starts_signature(&cleaned) {
+            if skip.signatures && Self::starts_signature(&cleaned) {
                 let (balance, has_brace) = analyze_line_basic(&cleaned);
                 sig.start(balance, has_brace);
                 continue;
             }
 
-            if Self::is_directive(&cleaned) {
+            if skip.imports && Self::is_directive(&cleaned) {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
 
         result
     }
+
+    fn has_brace_functions(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -5,16 +5,18 @@
 //! and alphabetic character presence.
 
 use crate::core::SourceLine;
-use crate::filetype::{clean_whitespace, is_valid_line, FileType};
+use crate::filetype::{clean_whitespace, is_valid_line, FileType, LineFilter};
 
 /// Unknown file type processor (fallback)
 pub struct UnknownFileType {
-    min_chars: u32,
+    filter: LineFilter,
 }
 
 impl UnknownFileType {
-    pub fn new(min_chars: u32) -> Self {
-        Self { min_chars }
+    pub fn new(filter: impl Into<LineFilter>) -> Self {
+        Self {
+            filter: filter.into(),
+        }
     }
 }
 
@@ -33,7 +35,7 @@ impl FileType for UnknownFileType {
                 continue;
             }
 
-            if is_valid_line(&cleaned, self.min_chars) {
+            if is_valid_line(&cleaned, &self.filter) {
                 result.push(SourceLine::new(cleaned, line_num + 1));
             }
         }
@@ -0,0 +1,104 @@
+//! Git blame attribution for duplicate blocks
+//!
+//! Runs `git blame --line-porcelain` once per file and caches the
+//! per-line authors so every block touching that file can be attributed
+//! without re-invoking git.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::process::Command;
+
+/// Per-file cache of blame results
+#[derive(Default)]
+pub struct BlameCache {
+    /// Author of each line (index 0 = line 1), or None if blame failed
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl BlameCache {
+    /// Create an empty blame cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the distinct authors of lines `start..=end` (1-indexed) in a file
+    ///
+    /// Returns None if the file could not be blamed (e.g. untracked or
+    /// outside a git repository).
+    pub fn authors(&mut self, path: &str, start: usize, end: usize) -> Option<Vec<String>> {
+        let line_authors = self
+            .files
+            .entry(path.to_string())
+            .or_insert_with(|| blame_file(path))
+            .as_ref()?;
+
+        let first = start.saturating_sub(1);
+        let last = end.min(line_authors.len());
+        if first >= last {
+            return None;
+        }
+
+        let authors: BTreeSet<&String> = line_authors[first..last].iter().collect();
+        Some(authors.into_iter().cloned().collect())
+    }
+}
+
+/// Run git blame on a whole file and return the author of each line
+fn blame_file(path: &str) -> Option<Vec<String>> {
+    let file = Path::new(path);
+    let name = file.file_name()?;
+
+    // Run from the file's directory so blame resolves the right repository
+    let mut cmd = Command::new("git");
+    cmd.args(["blame", "--line-porcelain", "--"]).arg(name);
+    if let Some(dir) = file.parent().filter(|d| !d.as_os_str().is_empty()) {
+        cmd.current_dir(dir);
+    }
+
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_line_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Extract the author of each line from `--line-porcelain` output
+fn parse_line_porcelain(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.strip_prefix("author "))
+        .map(|a| a.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_porcelain() {
+        let output = "\
+abc123 1 1 2
+author Alice
+author-mail <alice@example.com>
+summary first
+filename a.c
+\tint x = 1;
+def456 2 2
+author Bob
+author-mail <bob@example.com>
+filename a.c
+\tint y = 2;
+";
+        assert_eq!(parse_line_porcelain(output), vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn test_authors_missing_file() {
+        let mut cache = BlameCache::new();
+        assert!(cache.authors("/nonexistent/dir/file.c", 1, 5).is_none());
+    }
+}
@@ -5,8 +5,11 @@
 
 use crate::config::Config;
 use crate::error::{DuploError, Result};
-use std::path::PathBuf;
-use std::process::Command;
+use crate::filetype::is_builtin_extension;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 /// Check if the current directory is inside a git repository
 pub fn is_git_repo() -> bool {
@@ -19,8 +22,39 @@ pub fn is_git_repo() -> bool {
         .unwrap_or(false)
 }
 
-/// Get the root directory of the git repository
+/// Get the root directory of the working tree git runs in
+///
+/// In a linked worktree (`git worktree add`) this is the worktree's own
+/// directory rather than the main checkout's. A bare repository, or the
+/// inside of a `.git` directory, has no working tree to analyze.
 pub fn get_repo_root() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--is-bare-repository", "--is-inside-work-tree"])
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(DuploError::NotGitRepo);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut flags = stdout.lines().map(|l| l.trim() == "true");
+    let (is_bare, in_work_tree) = (flags.next().unwrap_or(false), flags.next().unwrap_or(false));
+    if is_bare {
+        return Err(DuploError::GitError(
+            "this is a bare repository, which has no working tree to analyze; \
+             run from a clone or a worktree created with 'git worktree add'"
+                .to_string(),
+        ));
+    }
+    if !in_work_tree {
+        return Err(DuploError::GitError(
+            "not inside a working tree (e.g. inside the .git directory); \
+             run from the repository's checkout"
+                .to_string(),
+        ));
+    }
+
     let output = Command::new("git")
         .args(["rev-parse", "--show-toplevel"])
         .output()
@@ -34,6 +68,34 @@ pub fn get_repo_root() -> Result<PathBuf> {
     Ok(PathBuf::from(root))
 }
 
+/// Get the name of the currently checked out branch ("HEAD" when detached)
+pub fn get_current_branch() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(DuploError::NotGitRepo);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the full SHA of the checked out commit
+pub fn get_head_commit() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(DuploError::NotGitRepo);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Get all tracked files in the repository
 pub fn get_tracked_files() -> Result<Vec<String>> {
     let output = Command::new("git")
@@ -58,20 +120,104 @@ pub fn get_tracked_files() -> Result<Vec<String>> {
     Ok(files)
 }
 
-/// Detect the default base branch (tries main, master, develop in order)
-pub fn detect_base_branch() -> Result<String> {
-    // Try common default branches in order of preference
-    for branch in &["main", "master", "develop"] {
-        let output = Command::new("git")
-            .args(["rev-parse", "--verify", &format!("refs/heads/{}", branch)])
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status();
-
-        if let Ok(status) = output {
-            if status.success() {
-                return Ok(branch.to_string());
-            }
+/// `.gitattributes` attributes marking files GitHub's linguist leaves out
+/// of language statistics, and duplo leaves out of analysis
+const LINGUIST_ATTRIBUTES: &[&str] = &["linguist-generated", "linguist-vendored"];
+
+/// Get the files marked `linguist-generated` or `linguist-vendored`
+///
+/// Asks `git check-attr`, so every `.gitattributes` file (and
+/// `.git/info/attributes`) is honored with git's own matching rules.
+pub fn get_linguist_excluded(files: &[String]) -> Result<HashSet<String>> {
+    if files.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let mut child = Command::new("git")
+        .args(["check-attr", "-z", "--stdin"])
+        .args(LINGUIST_ATTRIBUTES)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git check-attr: {}", e)))?;
+
+    // Feed the paths from another thread so a full stdout pipe can't block us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input: Vec<u8> = files
+        .iter()
+        .flat_map(|f| f.bytes().chain(std::iter::once(0)))
+        .collect();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git check-attr: {}", e)))?;
+    writer
+        .join()
+        .expect("check-attr writer panicked")
+        .map_err(|e| DuploError::GitError(format!("Failed to write to git check-attr: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DuploError::GitError(format!(
+            "git check-attr failed: {}",
+            stderr
+        )));
+    }
+
+    Ok(parse_check_attr(&output.stdout))
+}
+
+/// Collect the paths with any attribute set from `git check-attr -z` output
+/// (NUL-separated `path`, `attribute`, `value` triples)
+fn parse_check_attr(output: &[u8]) -> HashSet<String> {
+    let fields: Vec<String> = output
+        .split(|&b| b == 0)
+        .map(|f| String::from_utf8_lossy(f).to_string())
+        .collect();
+
+    fields
+        .chunks_exact(3)
+        .filter(|triple| !matches!(triple[2].as_str(), "unspecified" | "unset" | "false"))
+        .map(|triple| triple[0].clone())
+        .collect()
+}
+
+/// Default candidates tried by `detect_base_branch`, in order of preference
+pub const DEFAULT_BASE_BRANCH_CANDIDATES: &[&str] = &["main", "master", "develop"];
+
+/// Check whether a fully-qualified ref exists
+fn ref_exists(refname: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", refname])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Detect the default base branch
+///
+/// Tries, in order:
+/// 1. Local branches `refs/heads/<candidate>`
+/// 2. Remote-tracking branches `refs/remotes/origin/<candidate>` (returned as
+///    `origin/<candidate>`, for CI checkouts without a local default branch)
+/// 3. The branch `refs/remotes/origin/HEAD` points to, as a remote-tracking
+///    name (`origin/<branch>`) like in 2
+pub fn detect_base_branch(candidates: &[String]) -> Result<String> {
+    // Try local branches in order of preference
+    for branch in candidates {
+        if ref_exists(&format!("refs/heads/{}", branch)) {
+            return Ok(branch.to_string());
+        }
+    }
+
+    // Try remote-tracking branches
+    for branch in candidates {
+        if ref_exists(&format!("refs/remotes/origin/{}", branch)) {
+            return Ok(format!("origin/{}", branch));
         }
     }
 
@@ -82,10 +228,11 @@ pub fn detect_base_branch() -> Result<String> {
 
     if let Ok(o) = output {
         if o.status.success() {
+            // Like "origin/main"; kept whole, since a bare "main" needn't
+            // exist locally
             let remote = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            // remote is like "origin/main", extract "main"
-            if let Some(branch) = remote.split('/').next_back() {
-                return Ok(branch.to_string());
+            if !remote.is_empty() {
+                return Ok(remote);
             }
         }
     }
@@ -95,29 +242,33 @@ pub fn detect_base_branch() -> Result<String> {
     ))
 }
 
-/// Get files changed compared to a base branch
-pub fn get_changed_files(base_branch: &str) -> Result<Vec<String>> {
-    // Get merge base commit
-    let merge_base_output = Command::new("git")
+/// Get the commit where HEAD branched off `base_branch`
+pub fn get_merge_base(base_branch: &str) -> Result<String> {
+    let output = Command::new("git")
         .args(["merge-base", "HEAD", base_branch])
         .output()
         .map_err(|e| DuploError::GitError(format!("Failed to run git merge-base: {}", e)))?;
 
-    if !merge_base_output.status.success() {
-        let stderr = String::from_utf8_lossy(&merge_base_output.stderr);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(DuploError::GitError(format!(
             "Failed to find merge base with '{}': {}. Is it a valid branch?",
             base_branch, stderr
         )));
     }
 
-    let base_commit = String::from_utf8_lossy(&merge_base_output.stdout)
-        .trim()
-        .to_string();
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get files changed compared to a base branch
+pub fn get_changed_files(base_branch: &str) -> Result<Vec<String>> {
+    get_files_changed_from(&get_merge_base(base_branch)?)
+}
 
-    // Get changed files between merge base and HEAD
+/// Get files changed between `base_commit` and HEAD
+fn get_files_changed_from(base_commit: &str) -> Result<Vec<String>> {
     let output = Command::new("git")
-        .args(["diff", "--name-only", &base_commit, "HEAD"])
+        .args(["diff", "--name-only", base_commit, "HEAD"])
         .output()
         .map_err(|e| DuploError::GitError(format!("Failed to run git diff: {}", e)))?;
 
@@ -138,25 +289,109 @@ pub fn get_changed_files(base_branch: &str) -> Result<Vec<String>> {
     Ok(files)
 }
 
-/// Check if a file has a supported source code extension
-fn is_supported_file(path: &str) -> bool {
-    let supported_extensions = [
-        // C/C++
-        ".c", ".cpp", ".cxx", ".cc", ".h", ".hpp", ".hxx", ".hh",   // Java
-        ".java", // C#
-        ".cs",   // Python
-        ".py",   // Rust
-        ".rs",   // JavaScript/TypeScript
-        ".js", ".ts", ".jsx", ".tsx", // HTML/CSS
-        ".html", ".htm", ".css", // Visual Basic
-        ".vb",  // Erlang
-        ".erl",
-    ];
-
-    let path_lower = path.to_lowercase();
-    supported_extensions
-        .iter()
-        .any(|ext| path_lower.ends_with(ext))
+/// Get files touched by commits after a date or revision
+///
+/// If `since` resolves to a commit, the files changed in `<since>..HEAD` are
+/// returned; otherwise it is passed to `git log --since` as a date
+/// (e.g. "2024-01-01" or "3 months ago").
+pub fn get_files_changed_since(since: &str) -> Result<Vec<String>> {
+    let is_rev = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", since))
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    let mut cmd = Command::new("git");
+    cmd.args(["log", "--name-only", "--pretty=format:"]);
+    if is_rev {
+        cmd.arg(format!("{}..HEAD", since));
+    } else {
+        cmd.arg(format!("--since={}", since));
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git log: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DuploError::GitError(format!(
+            "git log --name-only failed: {}",
+            stderr
+        )));
+    }
+
+    // Union of touched files across all commits
+    let mut seen = HashSet::new();
+    let files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .filter(|l| seen.insert(l.to_string()))
+        .map(|l| l.to_string())
+        .collect();
+
+    Ok(files)
+}
+
+/// Check if a file has an extension with a built-in file type
+pub(crate) fn is_supported_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| is_builtin_extension(&ext.to_string_lossy()))
+}
+
+/// Check if a file's extension is mapped to a built-in file type by the config file
+fn has_mapped_extension(path: &str, extension_map: &BTreeMap<String, String>) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .is_some_and(|ext| extension_map.contains_key(&ext.to_string_lossy().to_lowercase()))
+}
+
+/// Check whether discovery should pick up a file
+///
+/// `--only-extensions` replaces the built-in allowlist (and config-file
+/// mappings) with exactly the given extensions.
+pub(crate) fn is_discoverable(path: &str, config: &Config) -> bool {
+    match config.only_extensions {
+        Some(ref extensions) => std::path::Path::new(path)
+            .extension()
+            .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase())),
+        None => is_supported_file(path) || has_mapped_extension(path, &config.extension_map),
+    }
+}
+
+/// Recursively list the discoverable files under `dir`, without git
+///
+/// Hidden files and directories (`.git`, `.venv`, ...) are skipped. Paths
+/// are joined onto `dir` as given and returned sorted, so two snapshots of
+/// the same tree list their files in the same order.
+pub fn discover_directory(dir: &Path, config: &Config) -> Result<Vec<String>> {
+    let entries = std::fs::read_dir(dir).map_err(|e| DuploError::FileNotFound {
+        path: dir.display().to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            files.extend(discover_directory(&path, config)?);
+        } else {
+            let path = path.to_string_lossy().to_string();
+            if is_discoverable(&path, config) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
 }
 
 /// Result of git file discovery for --changed-only mode
@@ -164,7 +399,24 @@ pub struct GitDiscoveryResult {
     /// All files to analyze
     pub files: Vec<String>,
     /// Files that are changed (subset of files, only populated when changed_only is true)
-    pub changed_files: Option<std::collections::HashSet<String>>,
+    pub changed_files: Option<HashSet<String>>,
+    /// Merge base the changed files were found against (changed_only only)
+    pub base_commit: Option<String>,
+}
+
+/// Convert repo-relative changed paths to absolute paths, keeping only files
+/// that are also being analyzed (deleted files can't contribute duplicates)
+fn to_changed_set(
+    changed: Vec<String>,
+    repo_root: &std::path::Path,
+    files: &[String],
+) -> HashSet<String> {
+    let files: HashSet<&str> = files.iter().map(|f| f.as_str()).collect();
+    changed
+        .into_iter()
+        .map(|f| repo_root.join(&f).to_string_lossy().to_string())
+        .filter(|f| files.contains(f.as_str()))
+        .collect()
 }
 
 /// Main entry point for git file discovery
@@ -197,34 +449,47 @@ pub fn discover_files_with_changed_set(
     progress("Finding git-tracked files...");
     let all_files = get_tracked_files()?;
 
-    // Convert to absolute paths and filter by supported extensions
+    // Skip what GitHub considers generated or vendored
+    let excluded = get_linguist_excluded(&all_files)?;
+    if !excluded.is_empty() {
+        progress(&format!(
+            "Skipped {} linguist-generated or linguist-vendored files",
+            excluded.len()
+        ));
+    }
+
+    // Convert to absolute paths and filter by supported (or config-mapped) extensions
     let absolute_files: Vec<String> = all_files
         .into_iter()
-        .filter(|f| is_supported_file(f))
+        .filter(|f| !excluded.contains(f) && is_discoverable(f, config))
         .map(|f| repo_root.join(&f).to_string_lossy().to_string())
         .filter(|f| std::path::Path::new(f).exists())
         .collect();
 
-    // If changed_only, also get the changed file set
-    let changed_files = if config.changed_only {
+    // If changed_only or since, also get the changed file set
+    let mut base_commit = None;
+    let changed_files = if let Some(ref since) = config.since {
+        progress(&format!("Finding files changed since '{}'...", since));
+        let changed = get_files_changed_since(since)?;
+        let changed_set = to_changed_set(changed, &repo_root, &absolute_files);
+
+        progress(&format!("Found {} changed files", changed_set.len()));
+        Some(changed_set)
+    } else if config.changed_only {
         let base_branch = config
             .base_branch
             .clone()
             .map(Ok)
-            .unwrap_or_else(detect_base_branch)?;
+            .unwrap_or_else(|| detect_base_branch(&config.base_branch_candidates))?;
 
         progress(&format!(
             "Finding files changed vs '{}' branch...",
             base_branch
         ));
-        let changed = get_changed_files(&base_branch)?;
-
-        // Convert to absolute paths and create set
-        let changed_set: std::collections::HashSet<String> = changed
-            .into_iter()
-            .filter(|f| is_supported_file(f))
-            .map(|f| repo_root.join(&f).to_string_lossy().to_string())
-            .collect();
+        let merge_base = get_merge_base(&base_branch)?;
+        let changed = get_files_changed_from(&merge_base)?;
+        let changed_set = to_changed_set(changed, &repo_root, &absolute_files);
+        base_commit = Some(merge_base);
 
         progress(&format!("Found {} changed files", changed_set.len()));
         Some(changed_set)
@@ -236,6 +501,7 @@ pub fn discover_files_with_changed_set(
     Ok(GitDiscoveryResult {
         files: absolute_files,
         changed_files,
+        base_commit,
     })
 }
 
@@ -243,6 +509,32 @@ pub fn discover_files_with_changed_set(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_discover_directory() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        for file in ["src/b.c", "src/nested/a.py", "README.md", ".git/hook.py"] {
+            std::fs::write(root.join(file), "x = 1\n").unwrap();
+        }
+
+        let files = discover_directory(root, &Config::default()).unwrap();
+        let relative: Vec<String> = files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        assert_eq!(relative, vec!["src/b.c", "src/nested/a.py"]);
+
+        assert!(discover_directory(&root.join("missing"), &Config::default()).is_err());
+    }
+
     #[test]
     fn test_is_supported_file_rust() {
         assert!(is_supported_file("main.rs"));
@@ -268,6 +560,16 @@ mod tests {
         assert!(is_supported_file("Component.tsx"));
     }
 
+    #[test]
+    fn test_is_supported_file_follows_file_types() {
+        // Every extension with a file type is discovered
+        assert!(is_supported_file("style.scss"));
+        assert!(is_supported_file("theme.less"));
+        assert!(is_supported_file("main.go"));
+        assert!(is_supported_file("Main.KT"));
+        assert!(!is_supported_file("archive.c.orig"));
+    }
+
     #[test]
     fn test_is_supported_file_python() {
         assert!(is_supported_file("script.py"));
@@ -309,5 +611,33 @@ mod tests {
         assert!(is_supported_file("Program.cs"));
         assert!(is_supported_file("Module.vb"));
         assert!(is_supported_file("server.erl"));
+        assert!(is_supported_file("script.pl"));
+        assert!(is_supported_file("Module.pm"));
+    }
+
+    #[test]
+    fn test_parse_check_attr() {
+        let output = b"gen.c\0linguist-generated\0true\0gen.c\0linguist-vendored\0unspecified\0\
+            lib.c\0linguist-generated\0unspecified\0lib.c\0linguist-vendored\0set\0\
+            src.c\0linguist-generated\0false\0src.c\0linguist-vendored\0unset\0";
+        let excluded = parse_check_attr(output);
+        assert_eq!(
+            excluded,
+            ["gen.c", "lib.c"].iter().map(|s| s.to_string()).collect()
+        );
+    }
+
+    #[test]
+    fn test_is_discoverable_only_extensions() {
+        let mut config = Config::default();
+        assert!(is_discoverable("src/a.c", &config));
+        assert!(is_discoverable("include/a.h", &config));
+        assert!(!is_discoverable("notes.txt", &config));
+
+        config.only_extensions = Some(vec!["h".to_string(), "txt".to_string()]);
+        assert!(!is_discoverable("src/a.c", &config));
+        assert!(is_discoverable("include/a.H", &config));
+        assert!(is_discoverable("notes.txt", &config));
+        assert!(!is_discoverable("Makefile", &config));
     }
 }
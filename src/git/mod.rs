@@ -3,11 +3,22 @@
 //! This module provides functionality to discover source files using git,
 //! including tracking files and detecting changed files for PR workflows.
 
+mod blame;
 mod discovery;
+mod self_history;
+mod snapshot;
 
+pub use blame::BlameCache;
+#[allow(unused_imports)]
+pub(crate) use discovery::{is_discoverable, is_supported_file};
 // Keep all discovery functions in public API even if not all are used in main
 #[allow(unused_imports)]
 pub use discovery::{
-    detect_base_branch, discover_files, discover_files_with_changed_set, get_changed_files,
-    get_repo_root, get_tracked_files, is_git_repo, GitDiscoveryResult,
+    detect_base_branch, discover_directory, discover_files, discover_files_with_changed_set,
+    get_changed_files, get_current_branch, get_files_changed_since, get_head_commit,
+    get_merge_base, get_repo_root, get_tracked_files, is_git_repo, GitDiscoveryResult,
+    DEFAULT_BASE_BRANCH_CANDIDATES,
 };
+pub use self_history::{detect_self_history, get_added_lines};
+#[allow(unused_imports)]
+pub use snapshot::{get_files_at_ref, load_files_at_ref, read_file_at_ref, read_files_at_ref};
@@ -0,0 +1,168 @@
+//! Comparing changed files against their own past (`--self-history`)
+//!
+//! Each changed file is compared with its version at the merge base. Matches
+//! in lines the working tree adds on top of that version are code the file
+//! once had, deleted and then pasted back, rather than code left untouched.
+
+use super::discovery::get_repo_root;
+use super::snapshot::read_file_at_ref;
+use crate::config::Config;
+use crate::core::{compare_source_files, DuploResult, SourceFile};
+use crate::error::{DuploError, Result};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+use std::process::Command;
+
+/// Number of hex digits of the base commit in the names of old versions
+const SHORT_COMMIT_LEN: usize = 10;
+
+/// Line ranges (1-based, end exclusive) the working tree adds to `path`
+/// relative to `base_commit`
+pub fn get_added_lines(base_commit: &str, path: &str) -> Result<Vec<Range<usize>>> {
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            base_commit,
+            "--",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git diff: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DuploError::GitError(format!(
+            "git diff of '{}' against '{}' failed: {}",
+            path,
+            base_commit,
+            stderr.trim()
+        )));
+    }
+
+    Ok(parse_added_ranges(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Collect the `+start,count` side of each `@@` hunk header
+fn parse_added_ranges(diff: &str) -> Vec<Range<usize>> {
+    diff.lines()
+        .filter(|line| line.starts_with("@@ "))
+        .filter_map(|line| line.split(' ').find_map(|part| part.strip_prefix('+')))
+        .filter_map(|added| {
+            let (start, count) = match added.split_once(',') {
+                Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+                None => (added.parse().ok()?, 1),
+            };
+            Some(start..start + count)
+        })
+        .filter(|range: &Range<usize>| !range.is_empty())
+        .collect()
+}
+
+/// Find blocks that changed files reintroduce from their version at
+/// `base_commit`
+///
+/// The old version of each file is named `<commit>:<path>`, and is always
+/// the first file of its blocks. Files that didn't exist at the base are
+/// skipped.
+pub fn detect_self_history(
+    changed_files: &HashSet<String>,
+    base_commit: &str,
+    config: &Config,
+    progress: &impl Fn(&str),
+) -> Result<(DuploResult, Vec<SourceFile>)> {
+    let repo_root = get_repo_root()?;
+    let short_commit = &base_commit[..base_commit.len().min(SHORT_COMMIT_LEN)];
+    let options = config.file_type_options();
+
+    let mut changed: Vec<&String> = changed_files.iter().collect();
+    changed.sort();
+    progress(&format!(
+        "Comparing {} changed files with their versions at {}...",
+        changed.len(),
+        short_commit
+    ));
+
+    let mut result = DuploResult {
+        blocks: Vec::new(),
+        files_provided: changed.len(),
+        files_analyzed: 0,
+        skipped_files: Vec::new(),
+        warnings: Vec::new(),
+        total_lines: 0,
+        duplicate_lines: 0,
+        duplicate_blocks: 0,
+    };
+    let mut source_files = Vec::new();
+
+    for path in changed {
+        let relative = Path::new(path)
+            .strip_prefix(&repo_root)
+            .unwrap_or(Path::new(path))
+            .to_string_lossy()
+            .replace('\\', "/");
+        // New files have no past to reintroduce
+        let Ok(old_content) = read_file_at_ref(base_commit, &relative) else {
+            continue;
+        };
+
+        let old = SourceFile::from_source(
+            format!("{}:{}", short_commit, relative),
+            &old_content,
+            options.clone(),
+            &config.extension_map,
+        );
+        let current = SourceFile::load(path, options.clone(), &config.extension_map)?;
+        let added = get_added_lines(base_commit, path)?;
+
+        let (pair, files) = compare_source_files(old, current, config)?;
+        let offset = source_files.len();
+        let current = &files[1];
+        result.blocks.extend(
+            pair.blocks
+                .into_iter()
+                .filter(|b| {
+                    (b.line2..b.end2()).all(|i| {
+                        let line = current.get_line(i).line_number();
+                        added.iter().any(|range| range.contains(&line))
+                    })
+                })
+                .map(|mut b| {
+                    b.source1_idx += offset;
+                    b.source2_idx += offset;
+                    b
+                }),
+        );
+        result.files_analyzed += 1;
+        result.total_lines += current.num_lines();
+        source_files.extend(files);
+    }
+
+    result.duplicate_blocks = result.blocks.len();
+    result.duplicate_lines = result.blocks.iter().map(|b| b.count).sum();
+    Ok((result, source_files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_added_ranges() {
+        let diff = "\
+diff --git a/a.c b/a.c
+--- a/a.c
++++ b/a.c
+@@ -1,6 +0,0 @@
+-int gone = 1;
+@@ -9 +4 @@ int keep()
++int changed = 2;
+@@ -12,0 +8,3 @@
++int back = 3;
+";
+        assert_eq!(parse_added_ranges(diff), vec![4..5, 8..11]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Reading source files as they exist at another git ref
+//!
+//! Files are read straight from the object database (`git show`, or one
+//! `git cat-file --batch` for a whole tree), so the working tree is never
+//! touched.
+
+use super::discovery::{get_repo_root, is_discoverable, is_git_repo};
+use crate::config::Config;
+use crate::core::generated::has_generated_marker;
+use crate::core::{SourceFile, Warning, WarningKind};
+use crate::error::{DuploError, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+
+/// List the files tracked at `refname`, relative to the repository root
+pub fn get_files_at_ref(refname: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "--full-tree", "--name-only", refname])
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git ls-tree: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DuploError::GitError(format!(
+            "Cannot list files at '{}': {}",
+            refname,
+            stderr.trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// Read a file's contents at `refname` (`path` is relative to the repository root)
+pub fn read_file_at_ref(refname: &str, path: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["show", &format!("{}:{}", refname, path)])
+        .output()
+        .map_err(|e| DuploError::GitError(format!("Failed to run git show: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DuploError::GitError(format!(
+            "Cannot read '{}' at '{}': {}",
+            path,
+            refname,
+            stderr.trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Read many files at `refname` through a single `git cat-file --batch`
+///
+/// Returns, in the order of `paths`, each file's contents or why it
+/// couldn't be read (missing at the ref, or not a regular file).
+pub fn read_files_at_ref(
+    refname: &str,
+    paths: &[String],
+) -> Result<Vec<std::result::Result<String, String>>> {
+    let git_error =
+        |e: std::io::Error| DuploError::GitError(format!("Failed to run git cat-file: {}", e));
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(git_error)?;
+
+    // Requests are written from another thread so a full stdout pipe can't
+    // block git while we are still writing; closing stdin ends the batch
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let requests: String = paths
+        .iter()
+        .map(|path| format!("{}:{}\n", refname, path))
+        .collect();
+    let writer = std::thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut contents = Vec::with_capacity(paths.len());
+    for path in paths {
+        // "<oid> <type> <size>", or "<name> missing"
+        let mut header = String::new();
+        if stdout.read_line(&mut header).map_err(git_error)? == 0 {
+            return Err(DuploError::GitError(
+                "git cat-file stopped before reading every file".to_string(),
+            ));
+        }
+        let fields: Vec<&str> = header.trim_end().split(' ').collect();
+        let (kind, size) = match fields.as_slice() {
+            [_, kind, size] => match size.parse::<usize>() {
+                Ok(size) => (*kind, size),
+                Err(_) => {
+                    return Err(DuploError::GitError(format!(
+                        "Unexpected git cat-file output: {}",
+                        header.trim_end()
+                    )))
+                }
+            },
+            _ => {
+                contents.push(Err(format!(
+                    "Cannot read '{}' at '{}': {}",
+                    path,
+                    refname,
+                    header.trim_end()
+                )));
+                continue;
+            }
+        };
+
+        // The object is followed by a newline
+        let mut object = vec![0u8; size + 1];
+        stdout.read_exact(&mut object).map_err(git_error)?;
+        object.truncate(size);
+        contents.push(if kind == "blob" {
+            Ok(String::from_utf8_lossy(&object).into_owned())
+        } else {
+            Err(format!(
+                "Cannot read '{}' at '{}': it is a {}, not a file",
+                path, refname, kind
+            ))
+        });
+    }
+
+    let written = writer.join().expect("request writer panicked");
+    let status = child.wait().map_err(git_error)?;
+    if written.is_err() || !status.success() {
+        return Err(DuploError::GitError(format!(
+            "git cat-file failed reading files at '{}'",
+            refname
+        )));
+    }
+    Ok(contents)
+}
+
+/// Load the supported source files tracked at `refname` into memory
+///
+/// Files are named by the absolute path they have in the working tree, so
+/// results line up with a `--git` run on the current checkout. Files that
+/// can't be read are skipped and returned as warnings.
+pub fn load_files_at_ref(
+    refname: &str,
+    config: &Config,
+    progress: &impl Fn(&str),
+) -> Result<(Vec<SourceFile>, Vec<Warning>)> {
+    if !is_git_repo() {
+        return Err(DuploError::NotGitRepo);
+    }
+
+    let repo_root = get_repo_root()?;
+    let paths: Vec<String> = get_files_at_ref(refname)?
+        .into_iter()
+        .filter(|f| is_discoverable(f, config))
+        .collect();
+    progress(&format!(
+        "Reading {} files at '{}'...",
+        paths.len(),
+        refname
+    ));
+
+    let mut source_files = Vec::with_capacity(paths.len());
+    let mut warnings = Vec::new();
+    let options = config.file_type_options();
+    for (path, content) in paths.iter().zip(read_files_at_ref(refname, &paths)?) {
+        let content = match content {
+            Ok(content) => content,
+            Err(message) => {
+                warnings.push(
+                    Warning::new(WarningKind::FileUnreadable, message).with_path(path.as_str()),
+                );
+                continue;
+            }
+        };
+        if config.ignore_generated
+            && has_generated_marker(content.lines(), &config.generated_markers)
+        {
+            continue;
+        }
+        source_files.push(SourceFile::from_source(
+            repo_root.join(path).to_string_lossy().to_string(),
+            &content,
+            options.clone(),
+            &config.extension_map,
+        ));
+    }
+
+    Ok((source_files, warnings))
+}
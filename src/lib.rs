@@ -0,0 +1,21 @@
+//! lucidshark-duplo - Code duplication detection library
+//!
+//! The detection engine behind the `lucidshark-duplo` command line tool.
+//! [`Detector`] is the simplest way to embed it; [`Config`] exposes every
+//! option for advanced use.
+
+pub mod baseline;
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod core;
+mod detector;
+pub mod error;
+pub mod export;
+pub mod filetype;
+pub mod git;
+
+pub use config::Config;
+pub use core::{Block, DuploResult, SourceFile};
+pub use detector::Detector;
+pub use error::{DuploError, Result};
@@ -3,30 +3,54 @@
 //! A fast, feature-rich code duplication detector with git integration,
 //! incremental caching, baseline comparison, and multi-language support.
 
-mod baseline;
-mod cache;
-mod cli;
-mod config;
-mod core;
-mod error;
-mod export;
-mod filetype;
-mod git;
-
-use baseline::{load_baseline, save_baseline, Baseline};
-use cache::{clear_cache, FileCache};
 use clap::Parser;
-use cli::Cli;
-use core::{load_file_list, process_files_with_cache, DuploResult, SourceFile};
-use export::{create_exporter, get_output_writer};
+use lucidshark_duplo::baseline::{load_baseline, load_baseline_dir, save_baseline, Baseline};
+use lucidshark_duplo::cache::{clear_cache, FileCache};
+use lucidshark_duplo::cli::Cli;
+use lucidshark_duplo::core::hash::fnv1a_hash64;
+use lucidshark_duplo::core::stats::files_over_threshold;
+use lucidshark_duplo::core::{
+    compare_two_files, file_list_iter, load_file_list, process_files_with_cache,
+    process_source_files, warm_cache, Block, DuploResult, SourceFile, Warning, WarningKind,
+};
+use lucidshark_duplo::export::{
+    append_history, create_exporter, get_output_writer, write_manifest, HistoryEntry, Manifest,
+    OutputContext,
+};
+use lucidshark_duplo::{config, error, filetype, git};
 use std::collections::HashSet;
 use std::io::Write;
 use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Seed mixed into the path hashes that pick the files for --sample
+const SAMPLE_SEED: u64 = 0x0064_7570_6c6f;
+
+/// Exit code of a run interrupted with Ctrl-C (128 + SIGINT, as shells report)
+const EXIT_INTERRUPTED: u8 = 130;
 
 fn main() -> ExitCode {
     // Parse command line arguments
     let cli = Cli::parse();
 
+    // Needs no file list, so it's answered before the config is validated
+    if cli.list_languages {
+        if write_languages(&mut std::io::stdout().lock()).is_err() {
+            return ExitCode::from(2);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let print_config = cli.print_config;
+    let config_hash_only = cli.config_hash_only;
+    let list_files = cli.list_files;
+    let warm_cache_only = cli.warm_cache;
+    let compare_two = cli.compare_two.clone();
+    let compare_dirs = cli.compare_dirs.clone();
+    let mut profile = Profile::new(cli.profile);
+
     // Convert to config
     let config = match cli.into_config() {
         Ok(c) => c,
@@ -36,23 +60,97 @@ fn main() -> ExitCode {
         }
     };
 
+    if print_config {
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        if let Err(e) = serde_json::to_writer_pretty(&mut out, &config) {
+            eprintln!("Error: {}", e);
+            return ExitCode::from(2);
+        }
+        let _ = writeln!(out);
+        return ExitCode::SUCCESS;
+    }
+
+    if config_hash_only {
+        let stdout = std::io::stdout();
+        if write_config_hashes(&config, &mut stdout.lock()).is_err() {
+            return ExitCode::from(2);
+        }
+        return ExitCode::SUCCESS;
+    }
+
     // Progress callback for logging
     let progress = |msg: &str| {
         eprintln!("{}", msg);
     };
 
+    // With --baseline-auto, compare against the baseline when it exists and
+    // create it otherwise
+    let creating_baseline = config.baseline_auto.as_ref().is_some_and(|p| !p.exists());
+    let config = match config.baseline_auto.clone() {
+        Some(path) if creating_baseline => config::Config {
+            save_baseline_path: Some(path),
+            ..config
+        },
+        Some(path) => config::Config {
+            baseline_path: Some(path),
+            ..config
+        },
+        None => config,
+    };
+
+    // Warnings raised here rather than while loading files
+    let mut warnings = Vec::new();
+
     // === Phase 0: Handle --clear-cache ===
     if config.clear_cache {
         progress("Clearing cache...");
         if let Err(e) = clear_cache(&config) {
-            eprintln!("Warning: Failed to clear cache: {}", e);
+            warn(
+                &mut warnings,
+                Warning::new(WarningKind::Cache, format!("Failed to clear cache: {}", e)),
+            );
         }
     }
 
     // === Phase 1: File Discovery ===
-    let (file_list, changed_files) = if config.git_mode {
+    // With --compare-dirs, the files found under the first directory
+    let mut first_dir_files = None;
+    // A file list is streamed straight into loading unless a phase before
+    // detection needs all of its entries
+    let streamed_list = config.list_filename.clone().filter(|_| {
+        !config.git_mode
+            && compare_two.is_none()
+            && compare_dirs.is_none()
+            && config.exclude_list_filename.is_none()
+            && config.sample.is_none()
+            && !list_files
+    });
+    let (file_list, changed_files, base_commit) = if let Some(paths) = compare_two.clone() {
+        (paths, None, None)
+    } else if let Some(ref dirs) = compare_dirs {
+        let discovered = git::discover_directory(&dirs[0], &config)
+            .and_then(|a| git::discover_directory(&dirs[1], &config).map(|b| (a, b)));
+        match discovered {
+            Ok((a, b)) => {
+                progress(&format!(
+                    "Found {} source files in '{}' and {} in '{}'",
+                    a.len(),
+                    dirs[0].display(),
+                    b.len(),
+                    dirs[1].display()
+                ));
+                first_dir_files = Some(a.iter().cloned().collect::<HashSet<_>>());
+                ([a, b].concat(), None, None)
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else if config.git_mode {
         match git::discover_files_with_changed_set(&config, &progress) {
-            Ok(result) => (result.files, result.changed_files),
+            Ok(result) => (result.files, result.changed_files, result.base_commit),
             Err(e) => {
                 eprintln!("Error: {}", e);
                 return ExitCode::from(2);
@@ -60,8 +158,9 @@ fn main() -> ExitCode {
         }
     } else {
         match &config.list_filename {
+            Some(_) if streamed_list.is_some() => (Vec::new(), None, None),
             Some(path) => match load_file_list(path) {
-                Ok(files) => (files, None),
+                Ok(files) => (files, None, None),
                 Err(e) => {
                     eprintln!("Error: {}", e);
                     return ExitCode::from(2);
@@ -74,6 +173,62 @@ fn main() -> ExitCode {
         }
     };
 
+    // === Phase 1.05: Remove Excluded Files (--exclude-file-list) ===
+    let file_list = match config.exclude_list_filename {
+        Some(ref path) => match std::fs::read_to_string(path) {
+            Ok(content) => {
+                let excluded = parse_exclude_list(&content);
+                let total = file_list.len();
+                let kept = exclude_listed_files(file_list, &excluded);
+                progress(&format!(
+                    "Excluded {} of {} files listed in '{}'",
+                    total - kept.len(),
+                    total,
+                    path
+                ));
+                kept
+            }
+            Err(e) => {
+                eprintln!("Error reading exclude list: {}", e);
+                return ExitCode::from(2);
+            }
+        },
+        None => file_list,
+    };
+
+    // === Phase 1.1: Sample Files (--sample) ===
+    let file_list = match config.sample {
+        Some(sample) => {
+            let total = file_list.len();
+            let sampled = sample_files(file_list, sample);
+            progress(&format!("Sampling {} of {} files", sampled.len(), total));
+            sampled
+        }
+        None => file_list,
+    };
+    profile.mark("Discovery");
+
+    // Entries of the file list, read from it afresh when it is streamed
+    let list_entries = || -> Box<dyn Iterator<Item = error::Result<String>> + '_> {
+        match streamed_list {
+            Some(ref path) => Box::new(file_list_iter(path)),
+            None => Box::new(file_list.iter().cloned().map(Ok)),
+        }
+    };
+
+    // === Phase 1.25: Dry Run (--list-files) ===
+    if list_files {
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        for path in resolve_listed_files(&file_list) {
+            let language = filetype::file_type_name(&path, &config.extension_map);
+            if writeln!(out, "{}\t{}", path, language).is_err() {
+                return ExitCode::from(2);
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
     // === Phase 1.5: Setup Cache ===
     let cache = if config.cache_enabled {
         match FileCache::new(&config) {
@@ -82,40 +237,204 @@ fn main() -> ExitCode {
                 Some(c)
             }
             Err(e) => {
-                eprintln!("Warning: Failed to initialize cache: {}", e);
+                warn(
+                    &mut warnings,
+                    Warning::new(
+                        WarningKind::Cache,
+                        format!("Failed to initialize cache: {}", e),
+                    ),
+                );
                 None
             }
         }
     } else {
         None
     };
+    profile.mark("Cache setup");
 
-    // === Phase 2: Process Files ===
-    let (result, source_files) =
-        match process_files_with_cache(&file_list, &config, cache.as_ref(), progress) {
-            Ok(r) => r,
+    // === Phase 1.75: Populate the Cache Only (--warm-cache) ===
+    if warm_cache_only {
+        let Some(ref cache) = cache else {
+            eprintln!("Error: --warm-cache needs a working cache");
+            return ExitCode::from(2);
+        };
+        return match warm_cache(list_entries(), &config, cache, progress) {
+            Ok(files) => {
+                progress(&format!("Warmed cache with {} files", files));
+                ExitCode::SUCCESS
+            }
             Err(e) => {
                 eprintln!("Error: {}", e);
-                return ExitCode::from(2);
+                ExitCode::from(2)
             }
         };
+    }
 
-    // === Phase 3: Filter Results (for --changed-only) ===
-    let result = if let Some(changed_set) = changed_files {
-        filter_to_changed_files(result, &source_files, &changed_set)
+    // The first Ctrl-C stops detection and reports what was found so far;
+    // a second one quits immediately
+    let cancel = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&cancel);
+    let handler = ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED.into());
+        }
+        eprintln!("Interrupted: reporting partial results (press Ctrl-C again to quit)");
+    });
+    if let Err(e) = handler {
+        warn(
+            &mut warnings,
+            Warning::new(
+                WarningKind::SignalHandler,
+                format!("Failed to install Ctrl-C handler: {}", e),
+            ),
+        );
+    }
+    let config = config::Config {
+        cancel: Some(cancel),
+        ..config
+    };
+
+    // With --changed-scope changed, pairs of unchanged files are skipped
+    let config = match changed_files {
+        Some(ref changed) if config.changed_scope == config::ChangedScope::Changed => {
+            config::Config {
+                focus_files: Some(Arc::new(changed.clone())),
+                ..config
+            }
+        }
+        _ => config,
+    };
+
+    // === Phase 2: Process Files ===
+    let processed = if compare_two.is_some() {
+        compare_two_files(&file_list[0], &file_list[1], &config)
+    } else if let (true, Some(changed), Some(base)) =
+        (config.self_history, &changed_files, &base_commit)
+    {
+        git::detect_self_history(changed, base, &config, &progress)
+    } else {
+        process_files_with_cache(list_entries(), &config, cache.as_ref(), progress)
+    };
+    let (result, source_files) = match processed {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    profile.mark("Loading and detection");
+
+    // Every later phase indexes source_files by the blocks' file indices
+    if let Err(e) = result.check_indices(&source_files) {
+        eprintln!("Error: {}", e);
+        return ExitCode::from(2);
+    }
+
+    // Make a run that analyzed nothing distinguishable from a clean one
+    if result.files_analyzed == 0 {
+        if config.fail_on_empty {
+            eprintln!("Error: No analyzable files found (--fail-on-empty)");
+            return ExitCode::from(2);
+        }
+        progress("No analyzable files found");
+    }
+
+    // === Phase 2.25: Keep Only Clones Across the Two Trees (--compare-dirs) ===
+    let result = match first_dir_files {
+        Some(ref first) => filter_to_cross_directory(result, &source_files, first),
+        None => result,
+    };
+
+    // === Phase 2.5: Drop Boilerplate Blocks (--min-block-entropy) ===
+    let result = if config.min_block_entropy > 0.0 {
+        filter_low_entropy_blocks(result, &source_files, config.min_block_entropy)
     } else {
         result
     };
 
+    // The trend follows the whole repository, before any filtering
+    let history_entry = config
+        .history_path
+        .as_ref()
+        .map(|_| HistoryEntry::new(&result, git::get_head_commit().ok()));
+
+    // === Phase 3: Filter Results (for --changed-only / --since) ===
+    // With --fail-on-new every duplicate is reported and the changed set
+    // only decides the exit code (Phase 5)
+    let changed_files = changed_files.map(|set| retain_loaded_files(set, &source_files, &progress));
+    let result = match changed_files {
+        Some(ref changed_set) if !config.fail_on_new => {
+            filter_to_changed_files(result, &source_files, changed_set)
+        }
+        _ => result,
+    };
+    profile.mark("Filtering");
+
     // === Phase 3.5: Load and Apply Baseline ===
-    let baseline = if let Some(ref baseline_path) = config.baseline_path {
-        match load_baseline(baseline_path) {
+    let baseline = if let Some(ref refname) = config.baseline_against_ref {
+        match baseline_from_ref(refname, &config, &progress) {
+            Ok((b, ref_warnings)) => {
+                for warning in ref_warnings {
+                    warn(&mut warnings, warning);
+                }
+                progress(&format!(
+                    "Built baseline with {} known duplicates from '{}'",
+                    b.entries.len(),
+                    refname
+                ));
+                Some(b)
+            }
+            Err(e) => {
+                eprintln!("Error building baseline from '{}': {}", refname, e);
+                return ExitCode::from(2);
+            }
+        }
+    } else if let Some(ref baseline_path) = config.baseline_path {
+        // A directory holds one baseline per team or subsystem
+        let loaded = if baseline_path.is_dir() {
+            load_baseline_dir(baseline_path)
+        } else {
+            load_baseline(baseline_path)
+        };
+        match loaded {
             Ok(b) => {
-                // Warn if config hash differs
+                // Warn if config hash differs (fail with --baseline-strict)
                 if b.config_hash != config.detection_config_hash() {
-                    eprintln!(
-                        "Warning: Baseline was created with different detection settings. \
-                         Results may not be comparable."
+                    if config.baseline_strict {
+                        eprintln!(
+                            "Error: Baseline '{}' was created with different detection \
+                             settings (config hash {}, current {}). Recreate it with \
+                             --save-baseline or drop --baseline-strict.",
+                            baseline_path.display(),
+                            b.config_hash,
+                            config.detection_config_hash()
+                        );
+                        return ExitCode::from(2);
+                    }
+                    warn(
+                        &mut warnings,
+                        Warning::new(
+                            WarningKind::Baseline,
+                            "Baseline was created with different detection settings. \
+                             Results may not be comparable.",
+                        ),
+                    );
+                }
+                if b.order_insensitive != config.baseline_order_insensitive {
+                    warn(
+                        &mut warnings,
+                        Warning::new(
+                            WarningKind::Baseline,
+                            format!(
+                                "Baseline was saved {} --baseline-order-insensitive; \
+                                 matching uses the baseline's setting.",
+                                if b.order_insensitive {
+                                    "with"
+                                } else {
+                                    "without"
+                                }
+                            ),
+                        ),
                     );
                 }
                 progress(&format!(
@@ -133,21 +452,37 @@ fn main() -> ExitCode {
         None
     };
 
-    // Filter to only new duplicates if baseline is provided
+    // Filter to only new duplicates if baseline is provided, or to the
+    // known ones that still exist with --report-unchanged-duplicates
     let result = if let Some(ref baseline) = baseline {
-        let filtered = baseline.filter_new_duplicates(result, &source_files);
-        progress(&format!(
-            "Found {} NEW duplicate blocks (filtered from baseline)",
-            filtered.duplicate_blocks
-        ));
-        filtered
+        if config.report_unchanged_duplicates {
+            let present = baseline.still_present(result, &source_files);
+            progress(&format!(
+                "Found {} baseline duplicate blocks still present",
+                present.duplicate_blocks
+            ));
+            present
+        } else {
+            let filtered = baseline.filter_new_duplicates(result, &source_files);
+            progress(&format!(
+                "Found {} NEW duplicate blocks (filtered from baseline)",
+                filtered.duplicate_blocks
+            ));
+            filtered
+        }
     } else {
         result
     };
+    profile.mark("Baseline");
 
     // === Phase 4: Export Results ===
+    let result = DuploResult {
+        warnings: [warnings, result.warnings].concat(),
+        ..result
+    };
     let exporter = create_exporter(config.output_format);
-    let mut writer = match get_output_writer(&config.output_filename) {
+    let output_context = OutputContext::from_config(&config);
+    let mut writer = match get_output_writer(&config.output_filename, &output_context) {
         Ok(w) => w,
         Err(e) => {
             eprintln!("Error creating output: {}", e);
@@ -164,11 +499,37 @@ fn main() -> ExitCode {
         eprintln!("Error flushing output: {}", e);
         return ExitCode::from(2);
     }
+    profile.mark("Export");
+
+    // === Phase 4.1: Write the Manifest (--manifest) ===
+    if let Some(ref path) = config.manifest_path {
+        if let Err(e) = write_manifest(path, &Manifest::new(&source_files)) {
+            eprintln!("Error writing manifest: {}", e);
+            return ExitCode::from(2);
+        }
+    }
+
+    // Partial results must not end up in the history or a baseline
+    if result.is_partial() {
+        return ExitCode::from(EXIT_INTERRUPTED);
+    }
+
+    // === Phase 4.25: Append to the History File (--history) ===
+    if let (Some(path), Some(entry)) = (&config.history_path, &history_entry) {
+        if let Err(e) = append_history(path, entry) {
+            eprintln!("Error appending to history file: {}", e);
+            return ExitCode::from(2);
+        }
+    }
 
     // === Phase 4.5: Save Baseline ===
     if let Some(ref save_path) = config.save_baseline_path {
-        let new_baseline =
-            Baseline::from_results(&result, &source_files, config.detection_config_hash());
+        let new_baseline = Baseline::from_results(
+            &result,
+            &source_files,
+            config.detection_config_hash(),
+            config.baseline_order_insensitive,
+        );
         if let Err(e) = save_baseline(&new_baseline, save_path) {
             eprintln!("Error saving baseline: {}", e);
             return ExitCode::from(2);
@@ -178,40 +539,446 @@ fn main() -> ExitCode {
             new_baseline.entries.len(),
             save_path.display()
         ));
+        profile.mark("Baseline save");
+    }
+
+    if profile.enabled {
+        let _ = profile.report(&mut std::io::stderr().lock());
+    }
+
+    // The first --baseline-auto run accepts the existing duplicates
+    if creating_baseline {
+        return ExitCode::SUCCESS;
     }
 
     // === Phase 5: Exit Code ===
-    if result.duplicate_blocks > 0 {
-        ExitCode::from(1) // Duplicates found
+    // Under --fail-on-new only duplicates involving changed files can fail
+    // the run, both as blocks and toward --file-fail-threshold
+    let result = match changed_files {
+        Some(ref changed_set) if config.fail_on_new => {
+            filter_to_changed_files(result, &source_files, changed_set)
+        }
+        _ => result,
+    };
+    let over = config
+        .file_fail_threshold
+        .map(|threshold| files_over_threshold(&result, &source_files, threshold))
+        .unwrap_or_default();
+    if !over.is_empty() || result.duplicate_blocks > 0 {
+        ExitCode::from(1) // Duplicates found, or some file too duplicated
     } else {
         ExitCode::SUCCESS // No duplicates
     }
 }
 
+/// Wall-clock time spent in each phase of a run, for --profile
+struct Profile {
+    enabled: bool,
+    /// End of the previous phase (the start of the run for the first)
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Profile {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record the time since the previous mark as phase `name`
+    fn mark(&mut self, name: &'static str) {
+        let now = Instant::now();
+        if self.enabled {
+            self.phases.push((name, now - self.last));
+        }
+        self.last = now;
+    }
+
+    /// Write one line per phase followed by the total
+    fn report(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(out, "Profile:")?;
+        for (name, duration) in &self.phases {
+            writeln!(out, "  {}: {:.3} ms", name, ms(*duration))?;
+        }
+        let total: Duration = self.phases.iter().map(|(_, d)| *d).sum();
+        writeln!(out, "  Total: {:.3} ms", ms(total))
+    }
+}
+
+/// Write the hashes that key the cache (cleaning) and that baselines are
+/// checked against (detection), as stored in those files
+fn write_config_hashes(config: &config::Config, out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "cleaning_config_hash: {}",
+        config.cleaning_config_hash()
+    )?;
+    writeln!(
+        out,
+        "detection_config_hash: {}",
+        config.detection_config_hash()
+    )
+}
+
+/// Write each built-in language and its extensions, tab-separated
+fn write_languages(out: &mut impl Write) -> std::io::Result<()> {
+    for language in filetype::LANGUAGES {
+        let extensions: Vec<String> = language
+            .extensions
+            .iter()
+            .map(|e| format!(".{}", e))
+            .collect();
+        writeln!(out, "{}\t{}", language.name, extensions.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Print a warning and keep it for the structured (JSON/XML) output
+fn warn(warnings: &mut Vec<Warning>, warning: Warning) {
+    eprintln!("Warning: {}", warning);
+    warnings.push(warning);
+}
+
+/// Deterministically pick `sample.size(n)` of `n` files, keeping list order
+///
+/// Files are ranked by an FNV-1a hash of their path, so the same files are
+/// picked on every run over the same list, whatever the build.
+fn sample_files(files: Vec<String>, sample: config::Sample) -> Vec<String> {
+    let keep = sample.size(files.len());
+    let rank = |path: &str| {
+        let mut bytes = SAMPLE_SEED.to_le_bytes().to_vec();
+        bytes.extend_from_slice(path.as_bytes());
+        fnv1a_hash64(&bytes)
+    };
+
+    let mut ranked: Vec<(u64, usize)> = files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| (rank(path), i))
+        .collect();
+    ranked.sort_unstable();
+    let chosen: HashSet<usize> = ranked[..keep].iter().map(|&(_, i)| i).collect();
+
+    files
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| chosen.contains(i))
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Detect duplicates in the files tracked at `refname` to use as a baseline,
+/// along with warnings about files that couldn't be read there
+fn baseline_from_ref(
+    refname: &str,
+    config: &config::Config,
+    progress: &(impl Fn(&str) + Send + Sync),
+) -> error::Result<(Baseline, Vec<Warning>)> {
+    let (files, warnings) = git::load_files_at_ref(refname, config, progress)?;
+    let (result, files) = process_source_files(files, config, progress)?;
+    let baseline = Baseline::from_results(
+        &result,
+        &files,
+        config.detection_config_hash(),
+        config.baseline_order_insensitive,
+    );
+    Ok((baseline, warnings))
+}
+
+/// Resolve discovered files to absolute paths, dropping any that don't exist
+fn resolve_listed_files(file_list: &[String]) -> Vec<String> {
+    file_list
+        .iter()
+        .filter_map(|f| std::fs::canonicalize(f).ok())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Paths of an exclude list, one per line
+///
+/// Unlike FILE_LIST, short entries (`a.c`) are kept; blank lines and lines
+/// starting with `#` are skipped.
+fn parse_exclude_list(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Remove the files in `excluded` from `file_list`
+///
+/// Paths are compared after resolving them, so a relative entry excludes the
+/// absolute path git discovery produces for the same file.
+fn exclude_listed_files(file_list: Vec<String>, excluded: &[String]) -> Vec<String> {
+    let resolve =
+        |f: &str| std::fs::canonicalize(f).unwrap_or_else(|_| std::path::PathBuf::from(f));
+    let excluded: HashSet<_> = excluded.iter().map(|f| resolve(f)).collect();
+    file_list
+        .into_iter()
+        .filter(|f| !excluded.contains(&resolve(f)))
+        .collect()
+}
+
+/// Drop changed files that could not be loaded (e.g. deleted after discovery)
+/// so the changed set only refers to files present in `source_files`
+fn retain_loaded_files(
+    mut changed_files: HashSet<String>,
+    source_files: &[SourceFile],
+    progress: &impl Fn(&str),
+) -> HashSet<String> {
+    let loaded: HashSet<&str> = source_files.iter().map(|f| f.filename()).collect();
+    let before = changed_files.len();
+    changed_files.retain(|f| loaded.contains(f.as_str()));
+
+    let missing = before - changed_files.len();
+    if missing > 0 {
+        progress(&format!(
+            "{} changed files could not be loaded and were skipped",
+            missing
+        ));
+    }
+    changed_files
+}
+
+/// Check whether either side of a block is in the changed set
+fn involves_changed_file(
+    block: &Block,
+    source_files: &[SourceFile],
+    changed_files: &HashSet<String>,
+) -> bool {
+    changed_files.contains(source_files[block.source1_idx].filename())
+        || changed_files.contains(source_files[block.source2_idx].filename())
+}
+
 /// Filter duplicate results to only include blocks where at least one file is in the changed set
 fn filter_to_changed_files(
-    result: DuploResult,
+    mut result: DuploResult,
     source_files: &[SourceFile],
     changed_files: &HashSet<String>,
 ) -> DuploResult {
-    let filtered_blocks: Vec<_> = result
-        .blocks
-        .into_iter()
-        .filter(|block| {
-            let file1 = source_files[block.source1_idx].filename();
-            let file2 = source_files[block.source2_idx].filename();
-            changed_files.contains(file1) || changed_files.contains(file2)
-        })
-        .collect();
+    result.retain_blocks(|block| involves_changed_file(block, source_files, changed_files));
+    result
+}
+
+/// Keep only the blocks with one side among `first_dir_files` and the other
+/// outside it
+fn filter_to_cross_directory(
+    mut result: DuploResult,
+    source_files: &[SourceFile],
+    first_dir_files: &HashSet<String>,
+) -> DuploResult {
+    let in_first = |idx: usize| first_dir_files.contains(source_files[idx].filename());
+    result.retain_blocks(|block| in_first(block.source1_idx) != in_first(block.source2_idx));
+    result
+}
+
+/// Drop blocks whose ratio of distinct lines to total lines is below `min_entropy`
+///
+/// Blocks made of the same few trivial lines repeated (`});`, `end`) are
+/// duplicates in name only.
+fn filter_low_entropy_blocks(
+    mut result: DuploResult,
+    source_files: &[SourceFile],
+    min_entropy: f64,
+) -> DuploResult {
+    result.retain_blocks(|block| {
+        let distinct: HashSet<u32> = source_files[block.source1_idx]
+            .line_hashes(block.line1, block.count)
+            .collect();
+        distinct.len() as f64 / block.count as f64 >= min_entropy
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use lucidshark_duplo::core::process_files_with_list;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const DUP_CODE: &str = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\nint e = 5;\n";
+
+    #[test]
+    fn test_sample_files_is_deterministic() {
+        let files: Vec<String> = (0..100).map(|i| format!("src/file{}.c", i)).collect();
+
+        let half = sample_files(files.clone(), config::Sample::Fraction(0.5));
+        assert_eq!(half.len(), 50);
+        assert_eq!(
+            half,
+            sample_files(files.clone(), config::Sample::Fraction(0.5))
+        );
+        // List order is kept
+        let mut sorted = half.clone();
+        sorted.sort_by_key(|f| files.iter().position(|g| g == f));
+        assert_eq!(half, sorted);
+
+        assert_eq!(
+            sample_files(files.clone(), config::Sample::Count(7)).len(),
+            7
+        );
+        assert_eq!(sample_files(files, config::Sample::Count(500)).len(), 100);
+    }
+
+    #[test]
+    fn test_exclude_listed_files() {
+        let temp = TempDir::new().unwrap();
+        for name in ["a.c", "b.c", "c.c"] {
+            fs::write(temp.path().join(name), DUP_CODE).unwrap();
+        }
+        let path = |name: &str| temp.path().join(name).to_string_lossy().to_string();
+        let list = "# generated copies\n\n  b.c  \nmissing.c\n".replace("b.c", &path("b.c"));
+
+        let excluded = parse_exclude_list(&list);
+        assert_eq!(excluded, vec![path("b.c"), "missing.c".to_string()]);
+        let kept = exclude_listed_files(vec![path("a.c"), path("b.c"), path("c.c")], &excluded);
+        assert_eq!(kept, vec![path("a.c"), path("c.c")]);
+    }
 
-    let duplicate_lines: usize = filtered_blocks.iter().map(|b| b.count).sum();
-    let duplicate_blocks = filtered_blocks.len();
+    #[test]
+    fn test_changed_file_vanishing_before_load() {
+        let temp = TempDir::new().unwrap();
+        let path = |name: &str| temp.path().join(name).to_string_lossy().to_string();
+        fs::write(path("a.c"), DUP_CODE).unwrap();
+        fs::write(path("b.c"), DUP_CODE).unwrap();
+        fs::write(path("c.c"), DUP_CODE).unwrap();
+
+        // Discovery saw all three files; b.c and c.c were changed
+        let file_list = vec![path("a.c"), path("b.c"), path("c.c")];
+        let changed: HashSet<String> = [path("b.c"), path("c.c")].into_iter().collect();
+
+        // b.c disappears between discovery and loading
+        fs::remove_file(path("b.c")).unwrap();
+
+        let config = Config {
+            num_threads: 1,
+            ..Default::default()
+        };
+        let (result, source_files) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert_eq!(source_files.len(), 2);
+
+        let changed = retain_loaded_files(changed, &source_files, &|_| {});
+        assert_eq!(changed, [path("c.c")].into_iter().collect());
+
+        // Only the a.c <-> c.c duplicate remains, referencing loaded files
+        let filtered = filter_to_changed_files(result, &source_files, &changed);
+        assert_eq!(filtered.duplicate_blocks, 1);
+        let block = &filtered.blocks[0];
+        assert_eq!(source_files[block.source1_idx].filename(), path("a.c"));
+        assert_eq!(source_files[block.source2_idx].filename(), path("c.c"));
+    }
+
+    #[test]
+    fn test_filters_keep_block_indices_valid() {
+        let temp = TempDir::new().unwrap();
+        let path = |name: &str| temp.path().join(name).to_string_lossy().to_string();
+        for name in ["a.c", "b.c", "c.c", "d.c"] {
+            fs::write(path(name), DUP_CODE).unwrap();
+        }
+        fs::write(path("b.c"), format!("int only_b = 0;\n{}", DUP_CODE)).unwrap();
+
+        let file_list: Vec<String> = ["a.c", "b.c", "c.c", "d.c"].map(path).to_vec();
+        let config = Config {
+            num_threads: 1,
+            ..Default::default()
+        };
+        let (result, source_files) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        let loaded = source_files.len();
+        assert!(result.duplicate_blocks >= 3);
+        result.check_indices(&source_files).unwrap();
+
+        let first_dir: HashSet<String> = [path("a.c"), path("b.c")].into_iter().collect();
+        let changed: HashSet<String> = [path("c.c")].into_iter().collect();
+        let baseline = Baseline::from_results(&result, &source_files, 0, false);
+
+        // Every filter main applies, in order; each only drops blocks
+        let result = filter_to_cross_directory(result, &source_files, &first_dir);
+        result.check_indices(&source_files).unwrap();
+        let result = filter_low_entropy_blocks(result, &source_files, 0.5);
+        result.check_indices(&source_files).unwrap();
+        let result = filter_to_changed_files(result, &source_files, &changed);
+        result.check_indices(&source_files).unwrap();
+        assert!(result.duplicate_blocks > 0);
+        let present = baseline.still_present(result, &source_files);
+        present.check_indices(&source_files).unwrap();
+        let new = baseline.filter_new_duplicates(present, &source_files);
+        new.check_indices(&source_files).unwrap();
+        assert_eq!(new.duplicate_blocks, 0);
+        assert_eq!(source_files.len(), loaded);
+    }
+
+    #[test]
+    fn test_low_entropy_blocks_dropped() {
+        let temp = TempDir::new().unwrap();
+        let path = |name: &str| temp.path().join(name).to_string_lossy().to_string();
+        let boilerplate = "} else {\n} else {\n} else {\n} else {\n} else {\n";
+        fs::write(path("a.js"), boilerplate).unwrap();
+        fs::write(path("b.js"), boilerplate).unwrap();
+        fs::write(path("a.c"), DUP_CODE).unwrap();
+        fs::write(path("b.c"), DUP_CODE).unwrap();
+
+        let file_list = vec![path("a.js"), path("b.js"), path("a.c"), path("b.c")];
+        let config = Config {
+            num_threads: 1,
+            ..Default::default()
+        };
+        let (result, source_files) = process_files_with_list(&file_list, &config, |_| {}).unwrap();
+        assert!(result.duplicate_blocks >= 2);
+
+        // The varied block survives, the repeated `} else {` block is dropped
+        let filtered = filter_low_entropy_blocks(result, &source_files, 0.5);
+        assert_eq!(filtered.duplicate_blocks, 1);
+        assert_eq!(filtered.duplicate_lines, 5);
+        let block = &filtered.blocks[0];
+        assert!(source_files[block.source1_idx].filename().ends_with(".c"));
+    }
+
+    #[test]
+    fn test_profile_report() {
+        let mut profile = Profile::new(true);
+        profile.mark("Discovery");
+        profile.mark("Export");
+        let mut out = Vec::new();
+        profile.report(&mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let labels: Vec<&str> = out
+            .lines()
+            .map(|l| l.trim().split(':').next().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["Profile", "Discovery", "Export", "Total"]);
+        assert!(out.lines().skip(1).all(|l| l.ends_with(" ms")));
+
+        // Nothing is recorded when the flag is off
+        let mut profile = Profile::new(false);
+        profile.mark("Discovery");
+        assert!(profile.phases.is_empty());
+    }
+
+    #[test]
+    fn test_write_config_hashes() {
+        let config = Config {
+            min_block_size: 7,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_config_hashes(&config, &mut out).unwrap();
 
-    DuploResult {
-        blocks: filtered_blocks,
-        files_analyzed: result.files_analyzed,
-        total_lines: result.total_lines,
-        duplicate_lines,
-        duplicate_blocks,
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "cleaning_config_hash: {}\ndetection_config_hash: {}\n",
+                config.cleaning_config_hash(),
+                config.detection_config_hash()
+            )
+        );
     }
 }
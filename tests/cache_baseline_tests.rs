@@ -52,7 +52,7 @@ int duplicate_function() {
         let cache_files: Vec<_> = fs::read_dir(&cache_dir)
             .unwrap()
             .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "cache"))
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "cache"))
             .collect();
 
         assert_eq!(
@@ -121,6 +121,60 @@ int duplicate_function() {
         );
     }
 
+    #[test]
+    fn test_warm_cache_serves_next_run_from_cache() {
+        let temp = TempDir::new().unwrap();
+
+        let code = r#"
+int duplicate_function() {
+    int x = 1;
+    int y = 2;
+    int z = 3;
+    return x + y + z;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+        let cache_dir = temp.path().join("cache");
+        let output_file = temp.path().join("out.json");
+
+        // Warming loads the files but neither compares nor exports
+        let warm = Command::new(common::binary_path())
+            .args([
+                "--cache",
+                "--cache-dir",
+                cache_dir.to_str().unwrap(),
+                "--warm-cache",
+                "--json",
+                file_list.to_str().unwrap(),
+                output_file.to_str().unwrap(),
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&warm.stderr);
+        assert_eq!(warm.status.code(), Some(0), "stderr: {}", stderr);
+        assert!(stderr.contains("Warmed cache with 2 files"), "{}", stderr);
+        assert!(!output_file.exists(), "No output should be written");
+
+        let run = Command::new(common::binary_path())
+            .args([
+                "--cache",
+                "--cache-dir",
+                cache_dir.to_str().unwrap(),
+                file_list.to_str().unwrap(),
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&run.stderr);
+        assert_eq!(run.status.code(), Some(1));
+        assert!(stderr.contains("Cache: 2 hits, 0 misses"), "{}", stderr);
+    }
+
     #[test]
     fn test_clear_cache_removes_cache_files() {
         let temp = TempDir::new().unwrap();
@@ -147,7 +201,7 @@ int duplicate_function() {
         let cache_files: Vec<_> = fs::read_dir(&cache_dir)
             .unwrap()
             .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "cache"))
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "cache"))
             .collect();
         assert!(!cache_files.is_empty(), "Cache files should exist");
 
@@ -221,6 +275,80 @@ int duplicate_function() {
 mod baseline {
     use super::*;
 
+    #[test]
+    fn test_baseline_auto_creates_then_compares() {
+        let temp = TempDir::new().unwrap();
+
+        let code = r#"
+int duplicate_function() {
+    int x = 1;
+    int y = 2;
+    int z = 3;
+    return x + y + z;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+        let baseline_path = temp.path().join("baseline.json");
+
+        let run = || {
+            Command::new(common::binary_path())
+                .args([
+                    "--baseline-auto",
+                    baseline_path.to_str().unwrap(),
+                    file_list.to_str().unwrap(),
+                ])
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run binary")
+        };
+
+        // First run: the baseline is created and the existing duplicate accepted
+        let first = run();
+        let stderr = String::from_utf8_lossy(&first.stderr);
+        assert_eq!(first.status.code(), Some(0), "stderr: {}", stderr);
+        assert!(baseline_path.exists(), "Baseline file should be created");
+        assert!(
+            stderr.contains("Saved baseline with 1 duplicates"),
+            "{}",
+            stderr
+        );
+
+        // Second run: compared against it, nothing new
+        let second = run();
+        let stderr = String::from_utf8_lossy(&second.stderr);
+        assert_eq!(second.status.code(), Some(0), "stderr: {}", stderr);
+        assert!(
+            stderr.contains("Loaded baseline with 1 known duplicates"),
+            "{}",
+            stderr
+        );
+
+        // A new duplicate fails the next run
+        common::create_source_file(
+            temp.path(),
+            "c.c",
+            "int other_function() {\n    int p = 7;\n    int q = 8;\n    int r = 9;\n    return p * q * r;\n}\n",
+        );
+        common::create_source_file(
+            temp.path(),
+            "d.c",
+            "int other_function() {\n    int p = 7;\n    int q = 8;\n    int r = 9;\n    return p * q * r;\n}\n",
+        );
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c", "c.c", "d.c"]);
+        let third = Command::new(common::binary_path())
+            .args([
+                "--baseline-auto",
+                baseline_path.to_str().unwrap(),
+                file_list.to_str().unwrap(),
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(third.status.code(), Some(1));
+    }
+
     #[test]
     fn test_save_baseline_creates_file() {
         let temp = TempDir::new().unwrap();
@@ -444,4 +572,102 @@ int duplicate_function() {
         // New baseline should be created
         assert!(new_baseline.exists(), "New baseline should be created");
     }
+
+    #[test]
+    fn test_baseline_strict_rejects_different_settings() {
+        let temp = TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\nint e = 5;\nint f = 6;\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+        let baseline_path = temp.path().join("baseline.json");
+
+        Command::new(common::binary_path())
+            .args([
+                "--save-baseline",
+                baseline_path.to_str().unwrap(),
+                "-m",
+                "5",
+            ])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+
+        let run = |extra: &[&str]| {
+            Command::new(common::binary_path())
+                .args(["--baseline", baseline_path.to_str().unwrap()])
+                .args(extra)
+                .arg(&file_list)
+                .output()
+                .expect("Failed to run binary")
+        };
+
+        // Without --baseline-strict the mismatch is only a warning
+        let output = run(&["-m", "4"]);
+        assert_eq!(output.status.code(), Some(0));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: "));
+
+        let output = run(&["-m", "4", "--baseline-strict"]);
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("different detection settings"),
+            "stderr: {}",
+            stderr
+        );
+
+        // Matching settings pass in strict mode
+        let output = run(&["-m", "5", "--baseline-strict"]);
+        assert_eq!(output.status.code(), Some(0));
+    }
+
+    #[test]
+    fn test_baseline_directory_merges_team_baselines() {
+        let temp = TempDir::new().unwrap();
+        let parser = "int parse_header() {\n    int magic = read_word();\n    int size = read_word();\n    int flags = read_word();\n    return magic + size + flags;\n}\n";
+        let render = "void render_frame() {\n    clear_screen(color);\n    draw_sprites(list);\n    draw_overlay(hud);\n    swap_buffers(window);\n}\n";
+        common::create_source_file(temp.path(), "parse_a.c", parser);
+        common::create_source_file(temp.path(), "parse_b.c", parser);
+        common::create_source_file(temp.path(), "render_a.c", render);
+        common::create_source_file(temp.path(), "render_b.c", render);
+        let baselines = temp.path().join("baselines");
+        fs::create_dir(&baselines).unwrap();
+
+        let run = |args: &[&str], files: &[&str]| {
+            let file_list = common::create_file_list_in_dir(temp.path(), files);
+            Command::new(common::binary_path())
+                .args(args)
+                .arg(&file_list)
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run binary")
+        };
+
+        // Each team saves a baseline of its own files
+        for (team, files) in [
+            ("parsing", ["parse_a.c", "parse_b.c"]),
+            ("rendering", ["render_a.c", "render_b.c"]),
+        ] {
+            let path = baselines.join(format!("{}.json", team));
+            run(&["--save-baseline", path.to_str().unwrap()], &files);
+        }
+
+        let all = ["parse_a.c", "parse_b.c", "render_a.c", "render_b.c"];
+        let output = run(&["--json", "--baseline", baselines.to_str().unwrap()], &all);
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["summary"]["duplicate_blocks"], 0);
+
+        // Either baseline alone leaves the other team's duplicate new
+        let single = baselines.join("parsing.json");
+        let output = run(&["--json", "--baseline", single.to_str().unwrap()], &all);
+        assert_eq!(output.status.code(), Some(1));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["summary"]["duplicate_blocks"], 1);
+    }
 }
@@ -109,4 +109,769 @@ mod exit_codes {
         // Exit code 0 means no duplicates
         assert_eq!(output.status.code(), Some(0));
     }
+
+    #[test]
+    fn test_empty_file_list_noted() {
+        let file_list = common::create_fixture_file_list(&[]);
+
+        let output = Command::new(common::binary_path())
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+
+        assert_eq!(output.status.code(), Some(0));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("No analyzable files found"));
+    }
+
+    #[test]
+    fn test_fail_on_empty() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "notes.c", "// only a comment\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["notes.c"]);
+
+        let output = Command::new(common::binary_path())
+            .arg("--fail-on-empty")
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("No analyzable files found"));
+
+        // Files with analyzable lines pass, even without duplicates
+        let file_list = common::create_fixture_file_list(&["unique_a.c", "unique_b.c"]);
+        let output = Command::new(common::binary_path())
+            .arg("--fail-on-empty")
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+    }
+}
+
+mod threads {
+    use super::*;
+
+    fn run_with_env(args: &[&str], rayon_threads: &str) -> String {
+        let file_list = common::create_fixture_file_list(&["unique_a.c", "unique_b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(args)
+            .arg(file_list.path())
+            .env("RAYON_NUM_THREADS", rayon_threads)
+            .output()
+            .expect("Failed to run binary");
+
+        String::from_utf8_lossy(&output.stderr).to_string()
+    }
+
+    #[test]
+    fn test_rayon_num_threads_used_without_j() {
+        let stderr = run_with_env(&[], "3");
+        assert!(stderr.contains("Using 3 threads"), "stderr: {}", stderr);
+
+        let stderr = run_with_env(&["--threads", "auto"], "3");
+        assert!(stderr.contains("Using 3 threads"), "stderr: {}", stderr);
+    }
+
+    #[test]
+    fn test_j_overrides_rayon_num_threads() {
+        let stderr = run_with_env(&["-j", "2"], "3");
+        assert!(stderr.contains("Using 2 threads"), "stderr: {}", stderr);
+    }
+}
+
+mod print_config {
+    use super::*;
+
+    #[test]
+    fn test_print_config_reflects_flags() {
+        let output = Command::new(common::binary_path())
+            .args([
+                "--print-config",
+                "-m",
+                "7",
+                "-c",
+                "5",
+                "-j",
+                "2",
+                "--json",
+                "--granularity",
+                "statement",
+                "files.txt",
+                "out.json",
+            ])
+            .output()
+            .expect("Failed to run binary");
+
+        // Exits before reading the (nonexistent) file list
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let config: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse config JSON");
+        assert_eq!(config["min_block_size"], 7);
+        assert_eq!(config["min_chars"], 5);
+        assert_eq!(config["num_threads"], 2);
+        assert_eq!(config["output_format"], "json");
+        assert_eq!(config["granularity"], "statement");
+        assert_eq!(config["list_filename"], "files.txt");
+        assert_eq!(config["output_filename"], "out.json");
+        assert!(config["baseline_path"].is_null());
+    }
+
+    fn config_hashes(args: &[&str]) -> (String, String) {
+        let output = Command::new(common::binary_path())
+            .arg("--config-hash-only")
+            .args(args)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let value = |key: &str| {
+            stdout
+                .lines()
+                .find_map(|l| l.strip_prefix(key))
+                .unwrap_or_else(|| panic!("missing {} in: {}", key, stdout))
+                .trim()
+                .to_string()
+        };
+        (
+            value("cleaning_config_hash:"),
+            value("detection_config_hash:"),
+        )
+    }
+
+    #[test]
+    fn test_config_hash_only() {
+        let (cleaning, detection) = config_hashes(&["files.txt"]);
+        let (cleaning_m7, detection_m7) = config_hashes(&["-m", "7", "files.txt"]);
+
+        // --min-lines only affects detection, so the cache stays valid
+        assert_eq!(cleaning, cleaning_m7);
+        assert_ne!(detection, detection_m7);
+
+        let (cleaning_c5, _) = config_hashes(&["-c", "5", "files.txt"]);
+        assert_ne!(cleaning, cleaning_c5);
+    }
+
+    #[test]
+    fn test_config_hash_matches_saved_baseline() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+        let baseline_path = temp.path().join("baseline.json");
+
+        Command::new(common::binary_path())
+            .arg("--save-baseline")
+            .arg(&baseline_path)
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+        let baseline: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&baseline_path).unwrap()).unwrap();
+
+        let (_, detection) = config_hashes(&[file_list.path().to_str().unwrap()]);
+        assert_eq!(baseline["config_hash"].to_string(), detection);
+    }
+}
+
+mod list_languages {
+    use super::*;
+
+    #[test]
+    fn test_list_languages_without_file_list() {
+        let output = Command::new(common::binary_path())
+            .arg("--list-languages")
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let languages: Vec<(&str, Vec<&str>)> = stdout
+            .lines()
+            .map(|l| {
+                let (name, extensions) = l.split_once('\t').expect("language<TAB>extensions");
+                (name, extensions.split(' ').collect())
+            })
+            .collect();
+        let extensions_of = |name: &str| {
+            languages
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, e)| e.clone())
+                .unwrap_or_else(|| panic!("{} not listed", name))
+        };
+
+        assert!(extensions_of("C/C++").contains(&".cpp"));
+        assert_eq!(extensions_of("Rust"), vec![".rs"]);
+        assert!(extensions_of("Python").contains(&".py"));
+        assert!(extensions_of("JavaScript/TypeScript").contains(&".tsx"));
+        assert!(extensions_of("CSS").contains(&".scss"));
+    }
+}
+
+mod list_files {
+    use super::*;
+
+    #[test]
+    fn test_list_files_matches_analyzed_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let files = ["a.c", "b.c", "c.py"];
+        for name in files {
+            common::create_source_file(temp.path(), name, "int x = 1;\nint y = 2;\n");
+        }
+        let mut entries: Vec<&str> = files.to_vec();
+        entries.push("missing.c");
+        let file_list = common::create_file_list_in_dir(temp.path(), &entries);
+
+        let output = Command::new(common::binary_path())
+            .args(["--list-files", file_list.to_str().unwrap()])
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let listed: Vec<(&str, &str)> = stdout
+            .lines()
+            .map(|l| l.split_once('\t').expect("path<TAB>language"))
+            .collect();
+        assert_eq!(listed.len(), 3);
+        assert!(listed
+            .iter()
+            .all(|(p, _)| std::path::Path::new(p).is_absolute()));
+
+        // Each path is annotated with its language
+        for (path, language) in &listed {
+            let expected = if path.ends_with(".py") {
+                "Python"
+            } else {
+                "C/C++"
+            };
+            assert_eq!(language, &expected, "{}", path);
+        }
+
+        // The same discovery feeds a real run
+        let output = Command::new(common::binary_path())
+            .args(["--json", file_list.to_str().unwrap()])
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(
+            json["summary"]["files_analyzed"].as_u64().unwrap(),
+            listed.len() as u64
+        );
+    }
+}
+
+mod compare_two {
+    use super::*;
+
+    #[test]
+    fn test_compare_two_fixture_files() {
+        let a = common::fixtures_dir().join("identical_a.c");
+        let b = common::fixtures_dir().join("identical_b.c");
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--compare-two"])
+            .arg(&a)
+            .arg(&b)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(json["summary"]["files_analyzed"], 2);
+        assert_eq!(json["summary"]["duplicate_blocks"], 1);
+        let dup = &json["duplicates"][0];
+        assert!(dup["file1"]["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("identical_a.c"));
+        assert!(dup["file2"]["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("identical_b.c"));
+    }
+
+    #[test]
+    fn test_compare_two_missing_file() {
+        let a = common::fixtures_dir().join("identical_a.c");
+
+        let output = Command::new(common::binary_path())
+            .args(["--compare-two"])
+            .arg(&a)
+            .arg("/nonexistent/b.c")
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(2));
+    }
+}
+
+mod algorithm_stats {
+    use super::*;
+
+    fn stat(stderr: &str, label: &str) -> usize {
+        stderr
+            .lines()
+            .find_map(|l| l.trim().strip_prefix(label))
+            .and_then(|rest| rest.trim_start_matches(':').trim().parse().ok())
+            .unwrap_or_else(|| panic!("missing '{}' in stderr: {}", label, stderr))
+    }
+
+    #[test]
+    fn test_algorithm_stats_account_for_all_pairs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n";
+        std::fs::create_dir(temp.path().join("other")).unwrap();
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        common::create_source_file(temp.path(), "other/a.c", code);
+        common::create_source_file(temp.path(), "unique.c", "char *s = \"x\";\nlong n = 0;\n");
+        common::create_source_file(temp.path(), "x.py", "int a = 1;\nint b = 2;\n");
+        let file_list = common::create_file_list_in_dir(
+            temp.path(),
+            &["a.c", "b.c", "other/a.c", "unique.c", "x.py"],
+        );
+
+        let output = Command::new(common::binary_path())
+            .args(["--algorithm-stats", "-d", "-j", "2"])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        let candidates = stat(&stderr, "Candidate pairs");
+        let compared = stat(&stderr, "Pairs compared");
+        let no_shared = stat(&stderr, "Pairs skipped (no shared lines)");
+        let same_name = stat(&stderr, "Pairs skipped (same filename)");
+        let cross_language = stat(&stderr, "Pairs skipped (cross-language)");
+
+        assert_eq!(candidates, 10);
+        assert_eq!(
+            compared + no_shared + same_name + cross_language,
+            candidates
+        );
+        assert_eq!(same_name, 1);
+        assert_eq!(cross_language, 4);
+        assert!(no_shared > 0);
+        assert!(stat(&stderr, "Matrix cells computed") > 0);
+        assert!(stderr.contains("Load time:"));
+        assert!(stderr.contains("Detection time:"));
+    }
+
+    #[test]
+    fn test_algorithm_stats_off_by_default() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+        assert!(!String::from_utf8_lossy(&output.stderr).contains("Algorithm stats"));
+    }
+}
+
+mod profile {
+    use super::*;
+
+    #[test]
+    fn test_profile_reports_phases_without_changing_output() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+        let run = |extra: &[&str]| {
+            Command::new(common::binary_path())
+                .args(extra)
+                .arg("--json")
+                .arg(file_list.path())
+                .output()
+                .expect("Failed to run binary")
+        };
+
+        let plain = run(&[]);
+        let profiled = run(&["--profile"]);
+        assert_eq!(plain.status.code(), profiled.status.code());
+        assert_eq!(plain.stdout, profiled.stdout);
+
+        let stderr = String::from_utf8_lossy(&profiled.stderr);
+        for phase in ["Discovery", "Loading and detection", "Export", "Total"] {
+            assert!(
+                stderr
+                    .lines()
+                    .any(|l| l.trim().starts_with(&format!("{}:", phase)) && l.ends_with(" ms")),
+                "missing '{}' timing in stderr: {}",
+                phase,
+                stderr
+            );
+        }
+        assert!(!String::from_utf8_lossy(&plain.stderr).contains("Profile:"));
+    }
+}
+
+mod sample {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sample_half_marks_summary() {
+        let temp = TempDir::new().unwrap();
+        let names: Vec<String> = (0..20).map(|i| format!("f{}.c", i)).collect();
+        for (i, name) in names.iter().enumerate() {
+            let code =
+                format!("int first_{i} = {i};\nint second_{i} = {i};\nint third_{i} = {i};\n");
+            common::create_source_file(temp.path(), name, &code);
+        }
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let file_list = common::create_file_list_in_dir(temp.path(), &names);
+
+        let run = |extra: &[&str]| {
+            let output = Command::new(common::binary_path())
+                .args(extra)
+                .args(["--json", file_list.to_str().unwrap()])
+                .output()
+                .expect("Failed to run binary");
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            (json, String::from_utf8_lossy(&output.stderr).to_string())
+        };
+
+        let (full, _) = run(&[]);
+        assert_eq!(full["summary"]["files_analyzed"], 20);
+        assert!(full["summary"].get("sampled").is_none());
+
+        let (sampled, stderr) = run(&["--sample", "0.5"]);
+        assert_eq!(sampled["summary"]["files_analyzed"], 10);
+        assert_eq!(sampled["summary"]["sampled"], true);
+        assert!(stderr.contains("Sampling 10 of 20 files"), "{}", stderr);
+
+        // The same files are picked again
+        let (again, _) = run(&["--sample", "0.5"]);
+        assert_eq!(sampled, again);
+    }
+}
+
+mod history {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_history_appends_one_line_per_run() {
+        let temp = TempDir::new().unwrap();
+        let code = "int first = compute(1);\nint second = compute(2);\nint third = compute(3);\nint fourth = compute(4);\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+        let history = temp.path().join("history.jsonl");
+
+        for _ in 0..2 {
+            let output = Command::new(common::binary_path())
+                .args(["--history", history.to_str().unwrap()])
+                .arg(&file_list)
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run binary");
+            assert_eq!(
+                output.status.code(),
+                Some(1),
+                "stderr: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let content = std::fs::read_to_string(&history).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(entry["timestamp"].as_u64().unwrap() > 0);
+            assert_eq!(entry["summary"]["files_analyzed"], 2);
+            assert_eq!(entry["summary"]["duplicate_blocks"], 1);
+            assert_eq!(entry["summary"]["duplicate_lines"], 4);
+            assert_eq!(entry["summary"]["duplication_percent"], 50.0);
+        }
+    }
+}
+
+mod clone_families {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_transitive_clones_form_one_family() {
+        let temp = TempDir::new().unwrap();
+        let first = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        let second = "float north = measure(5);\nfloat south = measure(6);\nfloat east = measure(7);\nfloat west = measure(8);\n";
+        // b.c shares one block with a.c and another with c.c; a.c and c.c
+        // share nothing
+        common::create_source_file(temp.path(), "a.c", first);
+        common::create_source_file(temp.path(), "b.c", &format!("{}{}", first, second));
+        common::create_source_file(temp.path(), "c.c", second);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c", "c.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--clone-families"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        assert_eq!(json["duplicates"].as_array().unwrap().len(), 2);
+        let families = json["clone_families"].as_array().unwrap();
+        assert_eq!(families.len(), 1);
+        let files: Vec<&str> = families[0]["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f.as_str().unwrap().rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(files, vec!["a.c", "b.c", "c.c"]);
+        assert_eq!(families[0]["blocks"], 2);
+    }
+
+    #[test]
+    fn test_families_absent_without_flag() {
+        let temp = TempDir::new().unwrap();
+        let code = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .arg("--json")
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value =
+            serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).unwrap();
+        assert!(json.get("clone_families").is_none());
+    }
+}
+
+mod compare_dirs {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_only_clones_across_directories_reported() {
+        let temp = TempDir::new().unwrap();
+        let shared = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        let local = "float north = measure(5);\nfloat south = measure(6);\nfloat east = measure(7);\nfloat west = measure(8);\n";
+        let upstream = temp.path().join("upstream");
+        let vendor = temp.path().join("vendor");
+        std::fs::create_dir_all(upstream.join("src")).unwrap();
+        std::fs::create_dir_all(&vendor).unwrap();
+        common::create_source_file(&upstream.join("src"), "core.c", shared);
+        common::create_source_file(&vendor, "core.c", shared);
+        // Clones within vendor/ alone are not reported
+        common::create_source_file(&vendor, "local_a.c", local);
+        common::create_source_file(&vendor, "local_b.c", local);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--compare-dirs"])
+            .arg(&upstream)
+            .arg(&vendor)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(json["summary"]["files_analyzed"], 4);
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        let path = |file: &str| duplicates[0][file]["path"].as_str().unwrap().to_string();
+        assert!(path("file1").contains("upstream"));
+        assert!(path("file2").contains("vendor"));
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        let temp = TempDir::new().unwrap();
+        let output = Command::new(common::binary_path())
+            .arg("--compare-dirs")
+            .arg(temp.path())
+            .arg(temp.path().join("missing"))
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(2));
+    }
+}
+
+mod exclude_file_list {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_excluded_copy_leaves_no_duplicate() {
+        let temp = TempDir::new().unwrap();
+        let code = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+        // Relative to the working directory, while the file list is absolute
+        std::fs::write(temp.path().join("exclude.txt"), "b.c\n").unwrap();
+
+        let run = |extra: &[&str]| {
+            Command::new(common::binary_path())
+                .arg("--json")
+                .args(extra)
+                .arg(&file_list)
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run binary")
+        };
+
+        let output = run(&[]);
+        assert_eq!(output.status.code(), Some(1));
+
+        let output = run(&["--exclude-file-list", "exclude.txt"]);
+        assert_eq!(output.status.code(), Some(0));
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(json["summary"]["files_analyzed"], 1);
+        assert_eq!(json["summary"]["duplicate_blocks"], 0);
+    }
+
+    #[test]
+    fn test_missing_exclude_list() {
+        let temp = TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "a.c", "int x = 1;\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--exclude-file-list", "missing.txt"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(2));
+    }
+}
+
+mod file_fail_threshold {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_only_heavily_duplicated_file_trips_gate() {
+        let temp = TempDir::new().unwrap();
+        let block = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        // Repeats itself entirely
+        common::create_source_file(temp.path(), "copied.c", &block.repeat(2));
+        common::create_source_file(
+            temp.path(),
+            "clean.c",
+            "int one = first();\nint two = second();\nint three = third();\nint four = fourth();\n",
+        );
+        let file_list = common::create_file_list_in_dir(temp.path(), &["copied.c", "clean.c"]);
+        let run = |threshold: &str| {
+            let output = Command::new(common::binary_path())
+                .args(["--json", "--file-fail-threshold", threshold])
+                .arg(&file_list)
+                .output()
+                .expect("Failed to run binary");
+            let json: serde_json::Value =
+                serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+            (output.status.code(), json)
+        };
+
+        // Overall duplication stays below the threshold, copied.c's doesn't
+        let (code, json) = run("60");
+        assert_eq!(code, Some(1));
+        assert!(json["summary"]["duplication_percent"].as_f64().unwrap() < 60.0);
+        let over = json["files_over_threshold"].as_array().unwrap();
+        assert_eq!(over.len(), 1);
+        assert!(over[0]["path"].as_str().unwrap().ends_with("copied.c"));
+        assert_eq!(over[0]["duplication_percent"], 100.0);
+
+        // No file exceeds it, but the duplicate block still fails the run
+        let (code, json) = run("100");
+        assert_eq!(code, Some(1));
+        assert_eq!(json["summary"]["duplicate_blocks"], 1);
+        assert!(json["files_over_threshold"].as_array().unwrap().is_empty());
+    }
+}
+
+mod streamed_file_list {
+    use super::*;
+    use std::io::Write;
+    use std::process::Stdio;
+
+    fn duplicates(output: &std::process::Output) -> serde_json::Value {
+        assert_eq!(
+            output.status.code(),
+            Some(1),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        json["duplicates"].clone()
+    }
+
+    #[test]
+    fn test_streamed_list_matches_collected_list() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        for name in ["a.c", "b.c", "c.c"] {
+            common::create_source_file(temp.path(), name, code);
+        }
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c", "c.c"]);
+
+        // Streamed from the list file
+        let streamed = Command::new(common::binary_path())
+            .arg("--json")
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+
+        // Streamed from stdin
+        let mut child = Command::new(common::binary_path())
+            .args(["--json", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run binary");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(&std::fs::read(&file_list).unwrap())
+            .unwrap();
+        let from_stdin = child.wait_with_output().unwrap();
+
+        // An exclude list needs the whole list up front
+        let empty = temp.path().join("exclude.txt");
+        std::fs::write(&empty, "").unwrap();
+        let collected = Command::new(common::binary_path())
+            .arg("--json")
+            .arg("--exclude-file-list")
+            .arg(&empty)
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+
+        let expected = duplicates(&collected);
+        assert_eq!(expected.as_array().unwrap().len(), 3);
+        assert_eq!(duplicates(&streamed), expected);
+        assert_eq!(duplicates(&from_stdin), expected);
+    }
+
+    #[test]
+    fn test_missing_streamed_list_is_an_error() {
+        let output = Command::new(common::binary_path())
+            .arg("/nonexistent/files.txt")
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("/nonexistent/files.txt"));
+    }
 }
@@ -6,11 +6,14 @@ pub fn binary_path() -> PathBuf {
     PathBuf::from(env!("CARGO_BIN_EXE_lucidshark-duplo"))
 }
 
+// Not every test binary uses the fixture helpers
+#[allow(dead_code)]
 pub fn fixtures_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
 }
 
 /// Create a temp file list referencing fixture files
+#[allow(dead_code)]
 pub fn create_fixture_file_list(files: &[&str]) -> tempfile::NamedTempFile {
     use std::io::Write;
     let mut file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
@@ -22,6 +25,7 @@ pub fn create_fixture_file_list(files: &[&str]) -> tempfile::NamedTempFile {
 }
 
 /// Create a file list in a directory referencing files within that directory
+#[allow(dead_code)]
 pub fn create_file_list_in_dir(dir: &std::path::Path, files: &[&str]) -> PathBuf {
     use std::io::Write;
     let file_list_path = dir.join("files.txt");
@@ -229,3 +229,227 @@ mod language_specific {
         );
     }
 }
+
+mod determinism {
+    use super::*;
+
+    fn run_json_with_threads(file_list_path: &std::path::Path, threads: &str) -> Vec<u8> {
+        let output = Command::new(common::binary_path())
+            .args(["--json", "-j", threads])
+            .arg(file_list_path)
+            .output()
+            .expect("Failed to run binary");
+        output.stdout
+    }
+
+    #[test]
+    fn test_json_identical_across_thread_counts() {
+        let file_list = common::create_fixture_file_list(&[
+            "identical_a.c",
+            "identical_b.c",
+            "partial_a.c",
+            "partial_b.c",
+            "with_comments.c",
+            "without_comments.c",
+        ]);
+
+        let single = run_json_with_threads(file_list.path(), "1");
+        let parallel = run_json_with_threads(file_list.path(), "4");
+
+        assert!(!single.is_empty());
+        assert_eq!(single, parallel, "JSON output should be byte-identical");
+    }
+}
+
+mod granularity {
+    use super::*;
+
+    fn run_json_with_args(file_list_path: &std::path::Path, args: &[&str]) -> serde_json::Value {
+        let output = Command::new(common::binary_path())
+            .arg("--json")
+            .args(args)
+            .arg(file_list_path)
+            .output()
+            .expect("Failed to run binary");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        serde_json::from_str(&stdout).expect("Failed to parse JSON output")
+    }
+
+    #[test]
+    fn test_split_statements_not_matched_in_line_mode() {
+        let file_list =
+            common::create_fixture_file_list(&["statement_split_a.c", "statement_split_b.c"]);
+        let json = run_with_json(file_list.path());
+
+        assert!(
+            json["duplicates"].as_array().unwrap().is_empty(),
+            "Differently split statements should not match line-by-line"
+        );
+    }
+
+    #[test]
+    fn test_split_statements_matched_in_statement_mode() {
+        let file_list =
+            common::create_fixture_file_list(&["statement_split_a.c", "statement_split_b.c"]);
+        let json = run_json_with_args(file_list.path(), &["--granularity", "statement"]);
+
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1, "Should match the whole function");
+
+        let dup = &duplicates[0];
+        assert_eq!(dup["line_count"], 6);
+        assert_eq!(dup["file1"]["start_line"], 1);
+        assert_eq!(dup["file1"]["end_line"], 10);
+    }
+
+    #[test]
+    fn test_statement_block_ends_at_last_physical_line() {
+        let file_list = common::create_fixture_file_list(&[
+            "statement_split_tail_a.c",
+            "statement_split_tail_b.c",
+        ]);
+        let json = run_json_with_args(file_list.path(), &["--granularity", "statement"]);
+
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1);
+
+        // The block's last statement is `finalize(...)` on lines 8-9
+        let dup = &duplicates[0];
+        assert_eq!(dup["line_count"], 5);
+        for file in ["file1", "file2"] {
+            assert_eq!(dup[file]["start_line"], 1);
+            assert_eq!(dup[file]["end_line"], 9);
+        }
+    }
+
+    #[test]
+    fn test_identical_functions_reported_as_function_clone() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let checksum = "static unsigned checksum(const char *data, int len) {\n    unsigned sum = 0;\n    for (int i = 0; i < len; i++) {\n        sum = sum * 31 + data[i];\n    }\n    return sum;\n}\n";
+        common::create_source_file(
+            temp.path(),
+            "a.c",
+            &format!(
+                "#include <stdio.h>\n\n{}\nint main(void) {{\n    printf(\"%u\\n\", checksum(\"abc\", 3));\n    return 0;\n}}\n",
+                checksum
+            ),
+        );
+        common::create_source_file(
+            temp.path(),
+            "b.c",
+            &format!(
+                "#include <string.h>\n\nint debug_level = 0;\n\n{}",
+                checksum
+            ),
+        );
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let json = run_json_with_args(&file_list, &["--function-granularity"]);
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1, "Should report the function once");
+
+        // From the signature to the last cleaned line of the body
+        let dup = &duplicates[0];
+        assert_eq!(dup["line_count"], 5);
+        assert_eq!(dup["file1"]["start_line"], 3);
+        assert_eq!(dup["file1"]["end_line"], 8);
+        assert_eq!(dup["file2"]["start_line"], 5);
+        assert_eq!(dup["file2"]["end_line"], 10);
+    }
+
+    #[test]
+    fn test_numeric_table_matched_with_include_nonalphabetic() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let table = "    0, 27, 54, 81,\n    108, 135, 162, 189,\n    216, 243, 14, 41,\n    68, 95, 122, 149,\n    176, 203, 230, 1,\n";
+        common::create_source_file(
+            temp.path(),
+            "crc.c",
+            &format!(
+                "static const unsigned char crc_table[] = {{\n{}}};\n",
+                table
+            ),
+        );
+        common::create_source_file(
+            temp.path(),
+            "copy.c",
+            &format!("const unsigned char lookup[] = {{\n{}}};\n", table),
+        );
+        let file_list = common::create_file_list_in_dir(temp.path(), &["crc.c", "copy.c"]);
+
+        let json = run_with_json(&file_list);
+        assert!(json["duplicates"].as_array().unwrap().is_empty());
+
+        let json = run_json_with_args(&file_list, &["--include-nonalphabetic"]);
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0]["line_count"], 5);
+        assert_eq!(duplicates[0]["file1"]["start_line"], 2);
+    }
+}
+
+mod end_of_file {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SHARED: &str =
+        "int total = compute(items);\nint count = size(items);\nint mean = total / count;\nreturn report(mean, count);";
+
+    #[test]
+    fn test_block_ending_at_eof_without_trailing_newline() {
+        let temp = TempDir::new().unwrap();
+        // Neither file ends in a newline, and the shared lines sit at
+        // different offsets so the end lines differ per file
+        let a = format!("int setup_a = prepare();\n{}", SHARED);
+        let b = format!(
+            "int setup_b = prepare();\nint extra_b = warm_up();\n{}",
+            SHARED
+        );
+        std::fs::write(temp.path().join("a.c"), a).unwrap();
+        std::fs::write(temp.path().join("b.c"), b).unwrap();
+        // The file list lacks a trailing newline as well
+        let file_list = temp.path().join("files.txt");
+        let entries = ["a.c", "b.c"].map(|n| temp.path().join(n).to_string_lossy().to_string());
+        std::fs::write(&file_list, entries.join("\n")).unwrap();
+
+        let json = run_with_json(&file_list);
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1, "{}", json);
+
+        let dup = &duplicates[0];
+        assert_eq!(dup["line_count"], 4);
+        let (start_a, end_a, start_b, end_b) =
+            if dup["file1"]["path"].as_str().unwrap().ends_with("a.c") {
+                (2, 5, 3, 6)
+            } else {
+                (3, 6, 2, 5)
+            };
+        assert_eq!(dup["file1"]["start_line"], start_a);
+        assert_eq!(dup["file1"]["end_line"], end_a);
+        assert_eq!(dup["file2"]["start_line"], start_b);
+        assert_eq!(dup["file2"]["end_line"], end_b);
+        assert_eq!(
+            dup["lines"].as_array().unwrap().last().unwrap(),
+            "return report(mean, count);"
+        );
+
+        // Every other output format indexes the last line the same way
+        for format in [
+            &[][..],
+            &["--xml"],
+            &["--markdown"],
+            &["--diff"],
+            #[cfg(feature = "yaml")]
+            &["--yaml"],
+        ] {
+            let output = Command::new(common::binary_path())
+                .args(format)
+                .arg(&file_list)
+                .output()
+                .expect("Failed to run binary");
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert_eq!(output.status.code(), Some(1), "{:?}: {}", format, stderr);
+            assert!(!stderr.contains("panicked"), "{:?}: {}", format, stderr);
+        }
+    }
+}
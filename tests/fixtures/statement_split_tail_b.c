@@ -0,0 +1,11 @@
+int process(int alpha, int beta, int gamma) {
+    int total = compute(
+        alpha, beta, gamma);
+    int scaled = scale(total, factor,
+        offset);
+    int clamped = clamp(
+        scaled, lower, upper);
+    int result = finalize(clamped, mode,
+        flags);
+    return report(result, secondary);
+}
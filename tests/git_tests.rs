@@ -54,6 +54,26 @@ fn git_commit(dir: &std::path::Path, message: &str) {
         .expect("Failed to git commit");
 }
 
+/// Git commit with an explicit author
+fn git_commit_as(dir: &std::path::Path, message: &str, author: &str) {
+    Command::new("git")
+        .args(["commit", "-m", message, "--author", author])
+        .current_dir(dir)
+        .output()
+        .expect("Failed to git commit");
+}
+
+/// Git commit with backdated author and committer timestamps
+fn git_commit_at(dir: &std::path::Path, message: &str, date: &str) {
+    Command::new("git")
+        .args(["commit", "-m", message])
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to git commit");
+}
+
 /// Create a branch
 fn git_branch(dir: &std::path::Path, branch: &str) {
     Command::new("git")
@@ -188,6 +208,70 @@ int tracked() {
         );
     }
 
+    #[test]
+    fn test_only_extensions_restricts_discovery() {
+        let temp = setup_git_repo();
+
+        let code = "int shared() {\n    int a = 1;\n    int b = 2;\n    return a + b;\n}\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        common::create_source_file(temp.path(), "a.h", "int shared();\n");
+        common::create_source_file(temp.path(), "b.h", "int other();\n");
+        git_add(temp.path(), &["a.c", "b.c", "a.h", "b.h"]);
+        git_commit(temp.path(), "add sources and headers");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--only-extensions", "h", "--list-files"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let listed: Vec<&str> = stdout
+            .lines()
+            .map(|l| l.split('\t').next().unwrap())
+            .collect();
+        assert_eq!(listed.len(), 2, "stdout: {}", stdout);
+        assert!(listed.iter().all(|p| p.ends_with(".h")));
+    }
+
+    #[test]
+    fn test_linguist_attributes_exclude_files() {
+        let temp = setup_git_repo();
+
+        let code = "int shared() {\n    int a = 1;\n    int b = 2;\n    return a + b;\n}\n";
+        fs::create_dir(temp.path().join("vendor")).unwrap();
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "parser.c", code);
+        common::create_source_file(temp.path(), "vendor/lib.c", code);
+        common::create_source_file(
+            temp.path(),
+            ".gitattributes",
+            "parser.c linguist-generated\nvendor/** linguist-vendored=true\n",
+        );
+        git_add(
+            temp.path(),
+            &["a.c", "parser.c", "vendor/lib.c", ".gitattributes"],
+        );
+        git_commit(temp.path(), "add sources");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--list-files"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let listed: Vec<&str> = stdout
+            .lines()
+            .map(|l| l.split('\t').next().unwrap())
+            .collect();
+        assert_eq!(listed.len(), 1, "stdout: {}", stdout);
+        assert!(listed[0].ends_with("a.c"));
+    }
+
     #[test]
     fn test_git_flag_fails_outside_repo() {
         let temp = TempDir::new().unwrap(); // Not a git repo
@@ -212,6 +296,86 @@ int tracked() {
     }
 }
 
+mod worktrees {
+    use super::*;
+
+    fn dup_code() -> &'static str {
+        "int shared() {\n    int a = 1;\n    int b = 2;\n    int c = 3;\n    return a + b + c;\n}\n"
+    }
+
+    #[test]
+    fn test_linked_worktree_resolves_its_own_files() {
+        let repo = setup_git_repo();
+        common::create_source_file(repo.path(), "a.c", dup_code());
+        git_add(repo.path(), &["a.c"]);
+        git_commit(repo.path(), "Initial commit");
+
+        // The worktree has a second copy committed on its own branch
+        let parent = TempDir::new().unwrap();
+        let worktree = parent.path().join("wt");
+        let status = Command::new("git")
+            .args(["worktree", "add", "-b", "feature"])
+            .arg(&worktree)
+            .current_dir(repo.path())
+            .output()
+            .expect("Failed to add worktree");
+        assert!(status.status.success(), "{:?}", status);
+        common::create_source_file(&worktree, "b.c", dup_code());
+        git_add(&worktree, &["b.c"]);
+        git_commit(&worktree, "Add copy");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--list-files"])
+            .current_dir(&worktree)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let worktree = fs::canonicalize(&worktree).unwrap();
+        let listed: Vec<&str> = stdout
+            .lines()
+            .filter_map(|l| l.split('\t').next())
+            .collect();
+        assert_eq!(listed.len(), 2, "stdout: {}", stdout);
+        for path in &listed {
+            let path = fs::canonicalize(path).unwrap();
+            assert!(
+                path.starts_with(&worktree),
+                "{} outside worktree",
+                path.display()
+            );
+        }
+
+        let output = Command::new(common::binary_path())
+            .args(["--git"])
+            .current_dir(&worktree)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1), "duplicate in the worktree");
+    }
+
+    #[test]
+    fn test_bare_repo_is_a_clear_error() {
+        let temp = TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init", "--bare"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to init bare repo");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("bare repository"), "stderr: {}", stderr);
+    }
+}
+
 mod changed_only {
     use super::*;
 
@@ -307,6 +471,76 @@ int original() {
         );
     }
 
+    #[test]
+    fn test_changed_scope_skips_unchanged_pairs() {
+        let temp = setup_git_repo();
+
+        let code = r#"
+int shared() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "first.c", code);
+        common::create_source_file(temp.path(), "second.c", code);
+        git_add(temp.path(), &["first.c", "second.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "third.c", code);
+        git_add(temp.path(), &["third.c"]);
+        git_commit(temp.path(), "add copy");
+
+        let run = |scope: &str| {
+            let output = Command::new(common::binary_path())
+                .args([
+                    "--git",
+                    "--changed-only",
+                    "--base-branch",
+                    "main",
+                    "--json",
+                    "--algorithm-stats",
+                    "--changed-scope",
+                    scope,
+                ])
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run binary");
+            assert_eq!(
+                output.status.code(),
+                Some(1),
+                "stderr: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let json: serde_json::Value =
+                serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let compared: usize = stderr
+                .lines()
+                .find_map(|l| l.trim().strip_prefix("Pairs compared: "))
+                .expect("algorithm stats should be printed")
+                .parse()
+                .unwrap();
+            (json["summary"]["duplicate_blocks"].clone(), compared)
+        };
+
+        let (all_blocks, all_compared) = run("all");
+        let (changed_blocks, changed_compared) = run("changed");
+
+        // first.c and second.c both predate the branch, so their pair is
+        // skipped without losing any block involving third.c
+        assert_eq!(changed_blocks, all_blocks);
+        assert_eq!(all_blocks.as_u64(), Some(2));
+        assert!(
+            changed_compared < all_compared,
+            "expected fewer pairs: {} vs {}",
+            changed_compared,
+            all_compared
+        );
+    }
+
     #[test]
     fn test_base_branch_auto_detection() {
         let temp = setup_git_repo();
@@ -339,6 +573,136 @@ int original() {
         );
     }
 
+    #[test]
+    fn test_base_branch_detection_from_remote_tracking_ref() {
+        let temp = setup_git_repo();
+
+        let code = r#"
+int shared() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "original.c", code);
+        git_add(temp.path(), &["original.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        // Simulate a CI checkout: only origin/main exists, no local main
+        Command::new("git")
+            .args(["update-ref", "refs/remotes/origin/main", "HEAD"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to create remote-tracking ref");
+        git_branch(temp.path(), "feature");
+        Command::new("git")
+            .args(["branch", "-D", "main"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to delete local main");
+
+        common::create_source_file(temp.path(), "copy.c", code);
+        git_add(temp.path(), &["copy.c"]);
+        git_commit(temp.path(), "add duplicate");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--changed-only", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(
+            output.status.code(),
+            Some(1),
+            "Expected duplicates vs origin/main, stderr: {}",
+            stderr
+        );
+        assert!(stderr.contains("'origin/main'"), "stderr: {}", stderr);
+    }
+
+    #[test]
+    fn test_base_branch_detection_from_origin_head() {
+        let temp = setup_git_repo();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run git");
+            assert!(output.status.success(), "git {:?}", args);
+        };
+
+        let code = r#"
+int shared() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "original.c", code);
+        git_add(temp.path(), &["original.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        // The remote's default branch isn't a candidate and has no local copy
+        git(&["update-ref", "refs/remotes/origin/trunk", "HEAD"]);
+        git(&[
+            "symbolic-ref",
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/trunk",
+        ]);
+        git_branch(temp.path(), "feature");
+        git(&["branch", "-D", "main"]);
+
+        common::create_source_file(temp.path(), "copy.c", code);
+        git_add(temp.path(), &["copy.c"]);
+        git_commit(temp.path(), "add duplicate");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--changed-only", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(
+            output.status.code(),
+            Some(1),
+            "Expected duplicates vs origin/trunk, stderr: {}",
+            stderr
+        );
+        assert!(stderr.contains("'origin/trunk'"), "stderr: {}", stderr);
+    }
+
+    #[test]
+    fn test_base_branch_candidates_override() {
+        let temp = setup_git_repo();
+
+        common::create_source_file(temp.path(), "file.c", "int main() { return 0; }");
+        git_add(temp.path(), &["file.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "trunk");
+        git_branch(temp.path(), "feature");
+
+        let output = Command::new(common::binary_path())
+            .args([
+                "--git",
+                "--changed-only",
+                "--base-branch-candidates",
+                "nonexistent,trunk",
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+        assert!(stderr.contains("'trunk'"), "stderr: {}", stderr);
+    }
+
     #[test]
     fn test_invalid_base_branch_error() {
         let temp = setup_git_repo();
@@ -371,6 +735,175 @@ int original() {
             "Error should mention the issue with the branch"
         );
     }
+
+    #[test]
+    fn test_base_branch_alone_implies_changed_only() {
+        let temp = setup_git_repo();
+
+        // Pre-existing duplicate on main
+        let dup_code = r#"
+int compute() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", dup_code);
+        common::create_source_file(temp.path(), "b.c", dup_code);
+        git_add(temp.path(), &["a.c", "b.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        // Feature branch only touches an unrelated file
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "other.c", "int other() {\n    return 42;\n}\n");
+        git_add(temp.path(), &["other.c"]);
+        git_commit(temp.path(), "add unrelated file");
+
+        // Full analysis reports the old duplicate
+        let full = Command::new(common::binary_path())
+            .args(["--git", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(full.status.code(), Some(1));
+
+        // --base-branch alone behaves like --changed-only --base-branch
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--base-branch", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "Duplicate between unchanged files should not be reported, stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let changed_only = Command::new(common::binary_path())
+            .args(["--git", "--changed-only", "--base-branch", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.stdout, changed_only.stdout);
+    }
+
+    #[test]
+    fn test_fail_on_new_passes_with_only_unchanged_duplicates() {
+        let temp = setup_git_repo();
+
+        // Pre-existing duplicate on main
+        let dup_code = r#"
+int compute() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", dup_code);
+        common::create_source_file(temp.path(), "b.c", dup_code);
+        git_add(temp.path(), &["a.c", "b.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "other.c", "int other() {\n    return 42;\n}\n");
+        git_add(temp.path(), &["other.c"]);
+        git_commit(temp.path(), "add unrelated file");
+
+        let output = Command::new(common::binary_path())
+            .args([
+                "--git",
+                "--changed-only",
+                "--base-branch",
+                "main",
+                "--fail-on-new",
+                "--json",
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        // The old duplicate is still reported, but doesn't fail the build
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert!(json["summary"]["duplicate_blocks"].as_u64().unwrap() > 0);
+
+        // A duplicate involving a changed file fails it
+        common::create_source_file(temp.path(), "c.c", dup_code);
+        git_add(temp.path(), &["c.c"]);
+        git_commit(temp.path(), "add new duplicate");
+
+        let output = Command::new(common::binary_path())
+            .args([
+                "--git",
+                "--changed-only",
+                "--base-branch",
+                "main",
+                "--fail-on-new",
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_fail_on_new_ignores_unchanged_files_over_threshold() {
+        let temp = setup_git_repo();
+
+        // Both files entirely duplicated before the branch
+        let dup_code = r#"
+int compute() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", dup_code);
+        common::create_source_file(temp.path(), "b.c", dup_code);
+        git_add(temp.path(), &["a.c", "b.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "other.c", "int other() {\n    return 42;\n}\n");
+        git_add(temp.path(), &["other.c"]);
+        git_commit(temp.path(), "add unrelated file");
+
+        let output = Command::new(common::binary_path())
+            .args([
+                "--git",
+                "--changed-only",
+                "--base-branch",
+                "main",
+                "--fail-on-new",
+                "--file-fail-threshold",
+                "50",
+                "--json",
+            ])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        // a.c and b.c are over the threshold, but neither changed
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(json["files_over_threshold"].as_array().unwrap().len(), 2);
+    }
 }
 
 mod git_with_file_list {
@@ -519,4 +1052,430 @@ int deep_function() {
             "Should analyze files in subdirectories"
         );
     }
+
+    #[test]
+    fn test_default_cache_dir_at_repo_root() {
+        let temp = setup_git_repo();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        common::create_source_file(temp.path(), "src/a.c", "int a = 1;\nint b = 2;\n");
+        git_add(temp.path(), &["src/a.c"]);
+        git_commit(temp.path(), "add file");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--cache"])
+            .current_dir(temp.path().join("src"))
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        assert!(temp.path().join(".duplo-cache").is_dir());
+        assert!(!temp.path().join("src/.duplo-cache").exists());
+    }
+}
+
+mod blame {
+    use super::*;
+
+    #[test]
+    fn test_blame_attaches_authors_to_json() {
+        let temp = setup_git_repo();
+
+        let code = r#"
+int duplicate_function() {
+    int x = 1;
+    int y = 2;
+    int z = 3;
+    return x + y + z;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        git_add(temp.path(), &["a.c", "b.c"]);
+        git_commit_as(
+            temp.path(),
+            "add duplicated code",
+            "Alice Example <alice@example.com>",
+        );
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--json", "--blame"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json: serde_json::Value = serde_json::from_str(&stdout).expect("Failed to parse JSON");
+
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert!(!duplicates.is_empty(), "Should find duplicates");
+
+        for dup in duplicates {
+            for file in ["file1", "file2"] {
+                let authors = dup[file]["authors"]
+                    .as_array()
+                    .expect("Each file ref should carry authors");
+                assert_eq!(authors, &vec![serde_json::json!("Alice Example")]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_authors_without_blame_flag() {
+        let temp = setup_git_repo();
+
+        let code = r#"
+int duplicate_function() {
+    int x = 1;
+    int y = 2;
+    int z = 3;
+    return x + y + z;
+}
+"#;
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        git_add(temp.path(), &["a.c", "b.c"]);
+        git_commit(temp.path(), "add duplicated code");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json: serde_json::Value = serde_json::from_str(&stdout).expect("Failed to parse JSON");
+
+        let dup = &json["duplicates"][0];
+        assert!(dup["file1"].get("authors").is_none());
+    }
+}
+
+mod since {
+    use super::*;
+
+    const SHARED_CODE: &str = r#"
+int shared() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+
+    /// Two old duplicate files, plus a recent commit adding `recent_content` as new.c
+    fn setup_backdated_repo(recent_content: &str) -> TempDir {
+        let temp = setup_git_repo();
+
+        common::create_source_file(temp.path(), "old_a.c", SHARED_CODE);
+        common::create_source_file(temp.path(), "old_b.c", SHARED_CODE);
+        git_add(temp.path(), &["old_a.c", "old_b.c"]);
+        git_commit_at(temp.path(), "old commit", "2020-01-01T12:00:00");
+
+        common::create_source_file(temp.path(), "new.c", recent_content);
+        git_add(temp.path(), &["new.c"]);
+        git_commit_at(temp.path(), "recent commit", "2024-06-01T12:00:00");
+
+        temp
+    }
+
+    #[test]
+    fn test_since_date_only_includes_recent_files() {
+        let temp = setup_backdated_repo("int unique_new() { return 42; }\n");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--since", "2024-01-01", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Found 1 changed files"),
+            "Only new.c should be in the changed set, stderr: {}",
+            stderr
+        );
+
+        // old_a.c <-> old_b.c predates --since, so it is filtered out
+        assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    }
+
+    #[test]
+    fn test_since_reports_recent_duplicates_of_old_code() {
+        let temp = setup_backdated_repo(SHARED_CODE);
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--since", "2024-01-01", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        assert_eq!(
+            output.status.code(),
+            Some(1),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json: serde_json::Value = serde_json::from_str(&stdout).expect("Failed to parse JSON");
+
+        for dup in json["duplicates"].as_array().unwrap() {
+            let involves_new = ["file1", "file2"]
+                .iter()
+                .any(|f| dup[f]["path"].as_str().unwrap().ends_with("new.c"));
+            assert!(involves_new, "Every block should involve new.c: {}", dup);
+        }
+    }
+
+    #[test]
+    fn test_since_accepts_revision() {
+        let temp = setup_backdated_repo("int unique_new() { return 42; }\n");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--since", "HEAD~1", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Found 1 changed files"),
+            "stderr: {}",
+            stderr
+        );
+        assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    }
+}
+
+mod baseline_against_ref {
+    use super::*;
+
+    const OLD_CODE: &str = r#"
+int old_shared() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    return a + b + c;
+}
+"#;
+
+    const NEW_CODE: &str = r#"
+int new_shared() {
+    int x = 10;
+    int y = 20;
+    int z = 30;
+    return x * y * z;
+}
+"#;
+
+    #[test]
+    fn test_duplicates_on_both_refs_are_suppressed() {
+        let temp = setup_git_repo();
+
+        common::create_source_file(temp.path(), "old_a.c", OLD_CODE);
+        common::create_source_file(temp.path(), "old_b.c", OLD_CODE);
+        git_add(temp.path(), &["old_a.c", "old_b.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "new_a.c", NEW_CODE);
+        common::create_source_file(temp.path(), "new_b.c", NEW_CODE);
+        git_add(temp.path(), &["new_a.c", "new_b.c"]);
+        git_commit(temp.path(), "add new duplicate");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--baseline-against-ref", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert!(!duplicates.is_empty());
+        for dup in duplicates {
+            for file in ["file1", "file2"] {
+                let path = dup[file]["path"].as_str().unwrap();
+                assert!(path.contains("new_"), "Old duplicate reported: {}", dup);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unreadable_file_at_ref_is_a_warning() {
+        let temp = setup_git_repo();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(temp.path())
+                .output()
+                .expect("Failed to run git");
+            assert!(output.status.success(), "git {:?}", args);
+        };
+
+        common::create_source_file(temp.path(), "old_a.c", OLD_CODE);
+        common::create_source_file(temp.path(), "old_b.c", OLD_CODE);
+        git_add(temp.path(), &["old_a.c", "old_b.c"]);
+        // A submodule entry: listed at the ref, but with no blob to read
+        git(&[
+            "update-index",
+            "--add",
+            "--cacheinfo",
+            "160000,1111111111111111111111111111111111111111,vendor.c",
+        ]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        git(&["rm", "--cached", "--quiet", "vendor.c"]);
+        common::create_source_file(temp.path(), "new_a.c", NEW_CODE);
+        common::create_source_file(temp.path(), "new_b.c", NEW_CODE);
+        git_add(temp.path(), &["new_a.c", "new_b.c"]);
+        git_commit(temp.path(), "add new duplicate");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--baseline-against-ref", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        // The readable files still form the baseline
+        let duplicates = json["duplicates"].as_array().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates[0]["file1"]["path"]
+            .as_str()
+            .unwrap()
+            .contains("new_"));
+
+        let warnings = json["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert_eq!(warnings[0]["kind"], "file_unreadable");
+        assert_eq!(warnings[0]["path"], "vendor.c");
+    }
+
+    #[test]
+    fn test_unknown_ref_is_an_error() {
+        let temp = setup_git_repo();
+        common::create_source_file(temp.path(), "a.c", OLD_CODE);
+        git_add(temp.path(), &["a.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--baseline-against-ref", "no-such-ref"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("no-such-ref"));
+    }
+}
+
+mod self_history {
+    use super::*;
+
+    const LEGACY: &str = "int legacy_total(int *items, int n) {
+    int total = 0;
+    for (int i = 0; i < n; i++) total += items[i];
+    int scaled = total * SCALE;
+    return scaled / n;
+}
+";
+
+    const KEEP: &str = "int keep_running(struct state *s) {
+    int ready = prepare(s);
+    int steps = count_steps(s);
+    int budget = allowance(s, steps);
+    int spent = consume(s, budget);
+    int left = budget - spent;
+    report_progress(s, spent, left);
+    return ready && left > 0;
+}
+";
+
+    const HELPER: &str = "int fresh_helper(int x) {
+    int doubled = x * 2;
+    return doubled + 1;
+}
+";
+
+    #[test]
+    fn test_deleted_then_reintroduced_code_is_flagged() {
+        let temp = setup_git_repo();
+
+        common::create_source_file(temp.path(), "calc.c", &format!("{}{}", LEGACY, KEEP));
+        git_add(temp.path(), &["calc.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "calc.c", KEEP);
+        git_add(temp.path(), &["calc.c"]);
+        git_commit(temp.path(), "drop legacy_total");
+
+        common::create_source_file(
+            temp.path(),
+            "calc.c",
+            &format!("{}{}{}", KEEP, HELPER, LEGACY),
+        );
+        git_add(temp.path(), &["calc.c"]);
+        git_commit(temp.path(), "paste legacy_total back");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--self-history", "--base-branch", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        let duplicates = json["duplicates"].as_array().unwrap();
+        // keep_running() never changed, so only the pasted function is reported
+        assert_eq!(duplicates.len(), 1, "{}", json);
+        let dup = &duplicates[0];
+        let old = dup["file1"]["path"].as_str().unwrap();
+        assert!(old.ends_with(":calc.c"), "{}", old);
+        assert_eq!(dup["file1"]["start_line"], 1);
+        assert!(dup["file2"]["path"].as_str().unwrap().ends_with("calc.c"));
+        assert_eq!(dup["file2"]["start_line"], 14);
+        assert_eq!(dup["line_count"], 5);
+    }
+
+    #[test]
+    fn test_untouched_and_new_files_are_not_flagged() {
+        let temp = setup_git_repo();
+
+        common::create_source_file(temp.path(), "calc.c", KEEP);
+        git_add(temp.path(), &["calc.c"]);
+        git_commit(temp.path(), "initial commit");
+
+        git_branch(temp.path(), "feature");
+        common::create_source_file(temp.path(), "calc.c", &format!("{}{}", KEEP, HELPER));
+        // A new file duplicating existing code is a cross-file clone, not history
+        common::create_source_file(temp.path(), "copy.c", KEEP);
+        git_add(temp.path(), &["calc.c", "copy.c"]);
+        git_commit(temp.path(), "add helper");
+
+        let output = Command::new(common::binary_path())
+            .args(["--git", "--self-history", "--base-branch", "main", "--json"])
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    }
 }
@@ -81,6 +81,236 @@ mod json_output {
         assert!(summary.get("duplicate_lines").is_some());
         assert!(summary.get("duplication_percent").is_some());
     }
+
+    #[test]
+    fn test_json_config_matches_flags() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+        let run = |flags: &[&str]| {
+            let output = Command::new(common::binary_path())
+                .arg("--json")
+                .args(flags)
+                .arg(file_list.path())
+                .output()
+                .expect("Failed to run binary");
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            json["config"].clone()
+        };
+
+        let defaults = run(&[]);
+        assert_eq!(defaults["min_block_size"], 4);
+        assert_eq!(defaults["min_chars"], 3);
+        assert_eq!(defaults["block_percent_threshold"], 100);
+        assert_eq!(defaults["ignore_same_filename"], false);
+
+        let config = run(&["-m", "6", "-c", "5", "-p", "40", "-d"]);
+        assert_eq!(config["min_block_size"], 6);
+        assert_eq!(config["min_chars"], 5);
+        assert_eq!(config["block_percent_threshold"], 40);
+        assert_eq!(config["ignore_same_filename"], true);
+        assert!(config["detection_config_hash"].is_u64());
+        assert_ne!(
+            config["detection_config_hash"],
+            defaults["detection_config_hash"]
+        );
+    }
+
+    #[test]
+    fn test_class_id_stable_across_runs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        common::create_source_file(temp.path(), "c.c", &format!("// copy\n{}", code));
+        let run = |files: &[&str]| {
+            let file_list = common::create_file_list_in_dir(temp.path(), files);
+            let output = Command::new(common::binary_path())
+                .args(["--json", "--class-representative"])
+                .arg(&file_list)
+                .output()
+                .expect("Failed to run binary");
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            json["duplicates"].as_array().unwrap().clone()
+        };
+
+        // Every pair duplicates the same code, so they form one class
+        let first = run(&["a.c", "b.c", "c.c"]);
+        assert_eq!(first.len(), 3);
+        let class = first[0]["class_id"].as_str().unwrap().to_string();
+        assert_eq!(class.len(), 16);
+        for duplicate in &first {
+            assert_eq!(duplicate["class_id"], class.as_str());
+            assert!(duplicate["representative"]
+                .as_str()
+                .unwrap()
+                .ends_with("a.c"));
+        }
+
+        // Listing the files differently, or dropping a member, keeps the id
+        let second = run(&["c.c", "b.c"]);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0]["class_id"], class.as_str());
+        assert!(second[0]["representative"]
+            .as_str()
+            .unwrap()
+            .ends_with("b.c"));
+    }
+
+    #[test]
+    fn test_json_collision_report() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int alpha = compute(1);\nint beta = compute(2);\nint gamma  =  compute(3);\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", &code.replace("  ", " "));
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--collision-report"])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+        // Lines differing only in spacing count once
+        let collisions = &json["hash_collisions"];
+        assert_eq!(collisions["distinct_lines"], 3);
+        assert_eq!(collisions["primary_collisions"], 0);
+        assert_eq!(collisions["full_collisions"], 0);
+    }
+
+    #[test]
+    fn test_json_and_xml_report_match_kind() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code: String = (0..6)
+            .map(|i| format!("int value_{} = compute({});\n", i, i))
+            .collect();
+        common::create_source_file(temp.path(), "a.c", &code);
+        common::create_source_file(temp.path(), "b.c", &code);
+        // Same tokens, different spacing: the hashes match, the text doesn't
+        common::create_source_file(temp.path(), "c.c", &code.replace(" = ", "="));
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c", "c.c"]);
+
+        let output = Command::new(common::binary_path())
+            .arg("--json")
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+        let mut kinds: Vec<(String, String, String)> = json["duplicates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| {
+                let name = |f: &str| {
+                    let path = d[f]["path"].as_str().unwrap();
+                    path.rsplit('/').next().unwrap().to_string()
+                };
+                (
+                    name("file1"),
+                    name("file2"),
+                    d["match_kind"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        kinds.sort();
+        let kind = |a: &str, b: &str, k: &str| (a.to_string(), b.to_string(), k.to_string());
+        assert_eq!(
+            kinds,
+            vec![
+                kind("a.c", "b.c", "exact"),
+                kind("a.c", "c.c", "approximate"),
+                kind("b.c", "c.c", "approximate"),
+            ]
+        );
+
+        let output = Command::new(common::binary_path())
+            .arg("--xml")
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let xml = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(xml.matches(r#"MatchKind="exact""#).count(), 1);
+        assert_eq!(xml.matches(r#"MatchKind="approximate""#).count(), 2);
+    }
+
+    #[test]
+    fn test_json_histogram_buckets_block_sizes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = |name: &str, count: usize| -> String {
+            (0..count)
+                .map(|i| format!("int {}_{} = compute({});\n", name, i, i))
+                .collect()
+        };
+        for (pair, count) in [("small", 5), ("medium", 12), ("large", 30)] {
+            for side in ["a", "b"] {
+                common::create_source_file(
+                    temp.path(),
+                    &format!("{}_{}.c", pair, side),
+                    &code(pair, count),
+                );
+            }
+        }
+        let names: Vec<String> = ["small", "medium", "large"]
+            .iter()
+            .flat_map(|p| [format!("{}_a.c", p), format!("{}_b.c", p)])
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let file_list = common::create_file_list_in_dir(temp.path(), &names);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--histogram"])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+        let buckets: Vec<(u64, u64)> = json["histogram"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| {
+                (
+                    b["min_lines"].as_u64().unwrap(),
+                    b["blocks"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(buckets, vec![(4, 1), (10, 1), (25, 1), (50, 0)]);
+        assert_eq!(json["histogram"][2]["lines"], 30);
+        assert!(json["histogram"][3].get("max_lines").is_none());
+    }
+
+    #[test]
+    fn test_json_reports_reordered_files_as_similar() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let parse = "int parse(char *s) {\n    int n = atoi(s);\n    return n * 2;\n}\n";
+        let print = "void print(int n) {\n    printf(\"%d\\n\", n);\n    fflush(stdout);\n}\n";
+        let check = "int check(int n) {\n    assert(n > 0);\n    return n % 7;\n}\n";
+        common::create_source_file(temp.path(), "a.c", &[parse, print, check].concat());
+        common::create_source_file(temp.path(), "b.c", &[check, print, parse].concat());
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args([
+                "--json",
+                "--min-lines",
+                "6",
+                "--similarity-threshold",
+                "0.9",
+            ])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+        // No 6-line block survives the reordering, but the files match as sets
+        assert_eq!(json["summary"]["duplicate_blocks"], 0);
+        let similar = json["similar_files"].as_array().unwrap();
+        assert_eq!(similar.len(), 1);
+        assert!(similar[0]["file1"].as_str().unwrap().ends_with("a.c"));
+        assert!(similar[0]["file2"].as_str().unwrap().ends_with("b.c"));
+        assert_eq!(similar[0]["similarity"], 1.0);
+    }
 }
 
 mod xml_output {
@@ -222,3 +452,355 @@ mod console_output {
         );
     }
 }
+
+mod color {
+    use super::*;
+
+    fn run(flags: &[&str], env: &[(&str, &str)]) -> String {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+        let output = Command::new(common::binary_path())
+            .args(flags)
+            .arg(file_list.path())
+            .env_remove("NO_COLOR")
+            .env_remove("CLICOLOR_FORCE")
+            .envs(env.iter().copied())
+            .output()
+            .expect("Failed to run binary");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn test_no_color_output_has_no_escapes() {
+        // Forcing color proves the check would notice escapes
+        let forced = run(&[], &[("CLICOLOR_FORCE", "1")]);
+        assert!(forced.contains('\x1b'));
+
+        let no_color = run(&["--no-color"], &[("CLICOLOR_FORCE", "1")]);
+        assert!(!no_color.contains('\x1b'));
+        assert!(no_color.contains("Summary:"));
+
+        // Piped output is plain, and identical to --no-color output
+        let piped = run(&[], &[]);
+        assert!(!piped.contains('\x1b'));
+        assert_eq!(piped, no_color);
+    }
+
+    #[test]
+    fn test_no_color_env_disables_color() {
+        let output = run(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]);
+        assert!(!output.contains('\x1b'));
+    }
+}
+
+mod group_report {
+    use super::*;
+
+    #[test]
+    fn test_json_group_report_rolls_up_directories() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dup = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n";
+        let unique = "int w = 9;\nint v = 8;\nint u = 7;\nint t = 6;\n";
+        std::fs::create_dir_all(temp.path().join("src/a")).unwrap();
+        std::fs::create_dir_all(temp.path().join("src/b")).unwrap();
+        common::create_source_file(temp.path(), "src/a/x.c", dup);
+        common::create_source_file(temp.path(), "src/a/y.c", unique);
+        common::create_source_file(temp.path(), "src/b/z.c", dup);
+        let file_list =
+            common::create_file_list_in_dir(temp.path(), &["src/a/x.c", "src/a/y.c", "src/b/z.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--group-report", "2"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        let groups = json["groups"].as_array().expect("groups array");
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0]["path"], "src/a");
+        assert_eq!(groups[0]["files"], 2);
+        assert_eq!(groups[0]["total_lines"], 8);
+        assert_eq!(groups[0]["duplicate_lines"], 4);
+        assert_eq!(groups[0]["duplication_percent"], 50.0);
+
+        assert_eq!(groups[1]["path"], "src/b");
+        assert_eq!(groups[1]["total_lines"], 4);
+        assert_eq!(groups[1]["duplicate_lines"], 4);
+    }
+}
+
+mod report_skipped {
+    use super::*;
+
+    #[test]
+    fn test_json_lists_comment_only_file_as_skipped() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "code.c", "int a = 1;\nint b = 2;\n");
+        common::create_source_file(temp.path(), "notes.c", "// just\n// comments\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["code.c", "notes.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json", "--report-skipped"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        assert_eq!(json["summary"]["files_provided"], 2);
+        assert_eq!(json["summary"]["files_analyzed"], 1);
+        let skipped = json["skipped_files"]
+            .as_array()
+            .expect("skipped_files array");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].as_str().unwrap().ends_with("notes.c"));
+    }
+
+    #[test]
+    fn test_console_lists_skipped_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "code.c", "int a = 1;\nint b = 2;\n");
+        common::create_source_file(temp.path(), "notes.c", "/* nothing here */\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["code.c", "notes.c"]);
+
+        let output = Command::new(common::binary_path())
+            .arg("--report-skipped")
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Files provided: 2"));
+        assert!(stdout.contains("Files analyzed: 1"));
+        assert!(stdout.contains("Skipped (no analyzable lines):"));
+        assert!(stdout.contains("notes.c"));
+    }
+}
+
+#[cfg(feature = "yaml")]
+mod yaml_output {
+    use super::*;
+
+    #[test]
+    fn test_yaml_summary_matches_json() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+        let run = |format: &str| {
+            Command::new(common::binary_path())
+                .arg(format)
+                .arg(file_list.path())
+                .output()
+                .expect("Failed to run binary")
+                .stdout
+        };
+
+        let yaml: serde_yaml::Value = serde_yaml::from_slice(&run("--yaml")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&run("--json")).unwrap();
+        assert_eq!(yaml["summary"]["files_analyzed"], 2);
+        assert_eq!(
+            yaml["duplicates"].as_sequence().unwrap().len(),
+            json["duplicates"].as_array().unwrap().len()
+        );
+    }
+}
+
+mod markdown_output {
+    use super::*;
+
+    #[test]
+    fn test_markdown_has_table_and_duplicate_row() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--markdown"])
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("| Metric | Value |"));
+        assert!(stdout.contains("| Lines | First copy | Second copy |"));
+        let rows = stdout
+            .lines()
+            .filter(|l| l.starts_with("| ") && l.contains("identical_a.c") && l.contains("#L"))
+            .count();
+        assert!(rows >= 1, "expected a duplicate row: {}", stdout);
+    }
+}
+
+mod diff_output {
+    use super::*;
+
+    #[test]
+    fn test_diff_shows_line_bridged_by_max_gap() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = |middle: &str| {
+            format!(
+                "int alpha = compute(1);\nint beta = compute(2);\nint gamma = compute(3);\nint delta = compute(4);\n{}\nint epsilon = compute(5);\nint zeta = compute(6);\nint eta = compute(7);\nint theta = compute(8);\n",
+                middle
+            )
+        };
+        common::create_source_file(temp.path(), "a.c", &code("int left_only = 10;"));
+        common::create_source_file(temp.path(), "b.c", &code("int right_only = 20;"));
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--diff", "--max-gap", "1"])
+            .arg(&file_list)
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let hunk: Vec<&str> = stdout
+            .lines()
+            .skip_while(|l| !l.starts_with("@@"))
+            .collect();
+        assert_eq!(hunk[0], "@@ cleaned lines @@", "{}", stdout);
+        assert_eq!(hunk.len(), 11, "{}", stdout);
+        assert_eq!(hunk[5], "-int left_only = 10;");
+        assert_eq!(hunk[6], "+int right_only = 20;");
+        let changed = hunk[1..].iter().filter(|l| !l.starts_with(' ')).count();
+        assert_eq!(changed, 2, "{}", stdout);
+    }
+}
+
+mod warnings {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_reported_as_json_warning() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "a.c", "int a = 1;\nint b = 2;\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "missing.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        // Still printed to stderr as before
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Warning:"));
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
+        let warnings = json["warnings"].as_array().expect("warnings array");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0]["kind"], "file_unreadable");
+        assert!(warnings[0]["path"].as_str().unwrap().ends_with("missing.c"));
+        assert!(!warnings[0]["message"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_file_reported_as_xml_warning() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(temp.path(), "a.c", "int a = 1;\nint b = 2;\n");
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "missing.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--xml"])
+            .arg(&file_list)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("<warnings>"));
+        assert!(stdout.contains(r#"<warning Kind="file_unreadable""#));
+    }
+
+    #[test]
+    fn test_no_warnings_gives_empty_json_array() {
+        let file_list = common::create_fixture_file_list(&["identical_a.c", "identical_b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json"])
+            .arg(file_list.path())
+            .output()
+            .expect("Failed to run binary");
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["warnings"], serde_json::json!([]));
+    }
+}
+
+mod output_path_template {
+    use super::*;
+
+    #[test]
+    fn test_format_placeholder_expanded() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let code = "int a = 1;\nint b = 2;\nint c = 3;\nint d = 4;\n";
+        common::create_source_file(temp.path(), "a.c", code);
+        common::create_source_file(temp.path(), "b.c", code);
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let output = Command::new(common::binary_path())
+            .args(["--json"])
+            .arg(&file_list)
+            .arg("report-{format}.out")
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run binary");
+        assert_eq!(output.status.code(), Some(1));
+
+        let report = temp.path().join("report-json.out");
+        assert!(report.exists(), "expanded report file should exist");
+        assert!(!temp.path().join("report-{format}.out").exists());
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+        assert_eq!(json["summary"]["duplicate_blocks"], 1);
+    }
+}
+
+mod preserve_indentation {
+    use super::*;
+
+    #[test]
+    fn test_snippet_keeps_indentation_while_matching_ignores_it() {
+        let temp = tempfile::TempDir::new().unwrap();
+        common::create_source_file(
+            temp.path(),
+            "a.c",
+            "int sum(int *v) {\n    int total = 0;\n    for (int i = 0; i < 4; i++) {\n        total += v[i];\n    }\n    return total;\n}\n",
+        );
+        // Same code, indented with tabs
+        common::create_source_file(
+            temp.path(),
+            "b.c",
+            "int sum(int *v) {\n\tint total = 0;\n\tfor (int i = 0; i < 4; i++) {\n\t\ttotal += v[i];\n\t}\n\treturn total;\n}\n",
+        );
+        let file_list = common::create_file_list_in_dir(temp.path(), &["a.c", "b.c"]);
+
+        let run = |extra: &[&str]| {
+            let output = Command::new(common::binary_path())
+                .arg("--json")
+                .args(extra)
+                .arg(&file_list)
+                .output()
+                .expect("Failed to run binary");
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            json["duplicates"][0]["lines"]
+                .as_array()
+                .expect("one duplicate")
+                .iter()
+                .map(|l| l.as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let cleaned = run(&[]);
+        assert!(cleaned.contains(&"total += v[i];".to_string()));
+
+        let original = run(&["--preserve-indentation"]);
+        assert_eq!(original.len(), cleaned.len());
+        assert!(original.contains(&"        total += v[i];".to_string()));
+        assert!(original.contains(&"    return total;".to_string()));
+    }
+}